@@ -12,6 +12,7 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::range_plus_one))]
 
 use anyhow::{bail, Context as _, Error};
+use config::keyassignment::{PaneDirection, RotationDirection};
 use mux::client::{ClientId, ClientInfo};
 use mux::pane::PaneId;
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
@@ -32,7 +33,7 @@ use termwiz::image::{ImageData, TextureCoordinate};
 use termwiz::surface::{Line, SequenceNo};
 use thiserror::Error;
 use wezterm_term::color::ColorPalette;
-use wezterm_term::{Alert, ClipboardSelection, StableRowIndex};
+use wezterm_term::{Alert, ClipboardSelection, CommandRecord, StableRowIndex};
 
 #[derive(Error, Debug)]
 #[error("Corrupt Response")]
@@ -416,7 +417,7 @@ macro_rules! pdu {
 /// The overall version of the codec.
 /// This must be bumped when backwards incompatible changes
 /// are made to the types and protocol.
-pub const CODEC_VERSION: usize = 22;
+pub const CODEC_VERSION: usize = 31;
 
 // Defines the Pdu enum.
 // Each struct has an explicit identifying number.
@@ -462,6 +463,21 @@ pdu! {
     SetFocusedPane: 45,
     GetImageCell: 46,
     GetImageCellResponse: 47,
+    ActivatePane: 48,
+    SetTabTitle: 49,
+    GetPaneRenderableDimensions: 50,
+    GetPaneRenderableDimensionsResponse: 51,
+    SaveSession: 52,
+    SwapActivePaneDirection: 53,
+    RotatePanes: 54,
+    MovePaneToNewTab: 55,
+    MovePaneToNewTabResponse: 56,
+    MovePaneToTab: 57,
+    GetMetrics: 58,
+    GetMetricsResponse: 59,
+    GetPaneCommands: 60,
+    GetPaneCommandsResponse: 61,
+    TabTitleChanged: 62,
 }
 
 impl Pdu {
@@ -587,6 +603,8 @@ pub struct ListPanes {}
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct ListPanesResponse {
     pub tabs: Vec<PaneNode>,
+    /// The title assigned to each of `tabs`
+    pub tab_titles: Vec<String>,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
@@ -727,6 +745,88 @@ pub struct SetFocusedPane {
     pub pane_id: PaneId,
 }
 
+/// Makes the pane the active pane in its tab, and that tab
+/// the active tab in its window.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct ActivatePane {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SetTabTitle {
+    pub tab_id: TabId,
+    pub title: String,
+}
+
+/// Sent to clients when the title assigned to a tab changes
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct TabTitleChanged {
+    pub tab_id: TabId,
+    pub title: String,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneRenderableDimensions {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneRenderableDimensionsResponse {
+    pub pane_id: PaneId,
+    pub cursor_position: StableCursorPosition,
+    pub dimensions: RenderableDimensions,
+}
+
+/// The rearranging operations act relative to the active pane.
+/// The client tracks which pane is active in its own view of the
+/// tab, so it passes that along as `active_pane_id`.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SwapActivePaneDirection {
+    pub tab_id: TabId,
+    pub direction: PaneDirection,
+    pub active_pane_id: Option<PaneId>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct RotatePanes {
+    pub tab_id: TabId,
+    pub direction: RotationDirection,
+    pub active_pane_id: Option<PaneId>,
+}
+
+/// Removes the pane from its tab and places it into a new tab
+/// in window_id, or a new window if window_id is None.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MovePaneToNewTab {
+    pub pane_id: PaneId,
+    pub window_id: Option<WindowId>,
+    pub workspace_for_new_window: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MovePaneToNewTabResponse {
+    pub tab_id: TabId,
+    pub window_id: WindowId,
+}
+
+/// Removes the pane from its tab and inserts it into tab_id,
+/// splitting the active pane of that tab
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct MovePaneToTab {
+    pub pane_id: PaneId,
+    pub tab_id: TabId,
+    pub active_pane_id: Option<PaneId>,
+}
+
+/// Saves the layout of the mux so that it can be restored when
+/// the server is restarted.  If `path` is None, the configured
+/// `mux_session_file` is used.
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct SaveSession {
+    pub path: Option<PathBuf>,
+    pub include_scrollback: bool,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetClientList;
 
@@ -735,6 +835,74 @@ pub struct GetClientListResponse {
     pub clients: Vec<ClientInfo>,
 }
 
+/// Requests a snapshot of the metrics that have been collected
+/// by the server process
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetMetrics {}
+
+/// Identifies a metric by its name and labels
+#[derive(Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord, Debug, Clone)]
+pub struct MetricKey {
+    pub name: String,
+    pub labels: Vec<(String, String)>,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum MetricUnit {
+    Seconds,
+    Bytes,
+}
+
+/// The distribution of the values that were recorded for a metric
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct HistogramMetric {
+    pub key: MetricKey,
+    pub unit: MetricUnit,
+    pub count: u64,
+    pub sum: f64,
+    pub p50: f64,
+    pub p75: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+/// An event that is counted, along with the distribution of the
+/// number of events per second
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct RateMetric {
+    pub key: MetricKey,
+    pub total: u64,
+    /// The count in the current one second window
+    pub current: u64,
+    pub p50: u64,
+    pub p75: u64,
+    pub p95: u64,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct CounterMetric {
+    pub key: MetricKey,
+    pub value: u64,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+pub struct GaugeMetric {
+    pub key: MetricKey,
+    pub value: f64,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone, Default)]
+pub struct GetMetricsResponse {
+    /// The name of the process that collected the metrics,
+    /// eg: `wezterm-gui` or `wezterm-mux-server`
+    pub process: String,
+    pub counters: Vec<CounterMetric>,
+    pub gauges: Vec<GaugeMetric>,
+    pub histograms: Vec<HistogramMetric>,
+    pub rates: Vec<RateMetric>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct Resize {
     pub containing_tab_id: TabId,
@@ -970,6 +1138,16 @@ pub struct SearchScrollbackResponse {
     pub results: Vec<mux::pane::SearchResult>,
 }
 
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneCommands {
+    pub pane_id: PaneId,
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Debug)]
+pub struct GetPaneCommandsResponse {
+    pub commands: Vec<CommandRecord>,
+}
+
 #[derive(Deserialize, Serialize, PartialEq, Debug)]
 pub struct GetImageCell {
     pub pane_id: PaneId,
@@ -1092,6 +1270,48 @@ mod test {
         );
     }
 
+    fn round_trip(pdu: Pdu) {
+        let mut encoded = Vec::new();
+        pdu.encode(&mut encoded, 0x42).unwrap();
+        assert_eq!(
+            Pdu::decode(encoded.as_slice()).unwrap(),
+            DecodedPdu { serial: 0x42, pdu }
+        );
+    }
+
+    #[test]
+    fn test_pdu_pane_and_tab_commands() {
+        round_trip(Pdu::ActivatePane(ActivatePane { pane_id: 3 }));
+        round_trip(Pdu::SetTabTitle(SetTabTitle {
+            tab_id: 2,
+            title: "editor \u{1f600}".to_string(),
+        }));
+        round_trip(Pdu::TabTitleChanged(TabTitleChanged {
+            tab_id: 2,
+            title: String::new(),
+        }));
+        round_trip(Pdu::GetPaneRenderableDimensions(
+            GetPaneRenderableDimensions { pane_id: 3 },
+        ));
+        round_trip(Pdu::GetPaneRenderableDimensionsResponse(
+            GetPaneRenderableDimensionsResponse {
+                pane_id: 3,
+                cursor_position: StableCursorPosition {
+                    x: 4,
+                    y: 120,
+                    ..Default::default()
+                },
+                dimensions: RenderableDimensions {
+                    cols: 80,
+                    viewport_rows: 24,
+                    scrollback_rows: 124,
+                    physical_top: 100,
+                    scrollback_top: 0,
+                },
+            },
+        ));
+    }
+
     #[test]
     fn test_bogus_pdu() {
         let mut encoded = Vec::new();
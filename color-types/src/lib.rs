@@ -553,6 +553,19 @@ impl LinearRgba {
         Self(self.0, self.1, self.2, self.3 * alpha)
     }
 
+    /// Convert to an SRGB tuple
+    pub fn to_srgb(self) -> SrgbaTuple {
+        fn to_srgb(v: f32) -> f32 {
+            if v <= 0.0031308 {
+                v * 12.92
+            } else {
+                v.powf(1.0 / 2.4) * 1.055 - 0.055
+            }
+        }
+        // Note that alpha is always linear
+        SrgbaTuple(to_srgb(self.0), to_srgb(self.1), to_srgb(self.2), self.3)
+    }
+
     /// Convert to an SRGB u32 pixel
     pub fn srgba_pixel(self) -> SrgbaPixel {
         SrgbaPixel::rgba(
@@ -567,6 +580,152 @@ impl LinearRgba {
     pub fn tuple(self) -> (f32, f32, f32, f32) {
         (self.0, self.1, self.2, self.3)
     }
+
+    /// Returns the relative luminance of the color, as defined by WCAG 2.x
+    /// <https://www.w3.org/TR/WCAG21/#dfn-relative-luminance>
+    pub fn relative_luminance(self) -> f32 {
+        0.2126 * self.0 + 0.7152 * self.1 + 0.0722 * self.2
+    }
+
+    /// Returns the WCAG contrast ratio between self and other, which
+    /// ranges from 1.0 for identical luminance up to 21.0 for black
+    /// against white.  Alpha is ignored.
+    /// <https://www.w3.org/TR/WCAG21/#dfn-contrast-ratio>
+    pub fn contrast_ratio(self, other: Self) -> f32 {
+        let a = self.relative_luminance() + 0.05;
+        let b = other.relative_luminance() + 0.05;
+        if a > b {
+            a / b
+        } else {
+            b / a
+        }
+    }
+
+    /// Returns self with its components clamped to the range 0.0-1.0
+    fn clamped(self) -> Self {
+        Self(
+            self.0.clamp(0., 1.),
+            self.1.clamp(0., 1.),
+            self.2.clamp(0., 1.),
+            self.3.clamp(0., 1.),
+        )
+    }
+
+    /// Convert to the OKLab perceptual color space
+    #[allow(clippy::excessive_precision)]
+    pub fn to_oklaba(self) -> OkLaba {
+        // See https://bottosson.github.io/posts/oklab/
+        let l = 0.4122214708 * self.0 + 0.5363325363 * self.1 + 0.0514459929 * self.2;
+        let m = 0.2119034982 * self.0 + 0.6806995451 * self.1 + 0.1073969566 * self.2;
+        let s = 0.0883024619 * self.0 + 0.2817188376 * self.1 + 0.6299787005 * self.2;
+
+        let l = l.cbrt();
+        let m = m.cbrt();
+        let s = s.cbrt();
+
+        OkLaba(
+            0.2104542553 * l + 0.7936177850 * m - 0.0040720468 * s,
+            1.9779984951 * l - 2.4285922050 * m + 0.4505937099 * s,
+            0.0259040371 * l + 0.7827717662 * m - 0.8086757660 * s,
+            self.3,
+        )
+    }
+
+    /// Returns a color with the same hue as self whose contrast ratio
+    /// against `background` is at least `min_ratio`, or as close to it
+    /// as is possible.
+    /// The OKLab lightness is moved towards white or black, preferring
+    /// the direction that self already has relative to the background,
+    /// and the chroma is reduced in proportion so that the extremes are
+    /// pure white and black.  The smallest adjustment that reaches the
+    /// ratio is used, so colors that already meet it are unchanged.
+    pub fn ensure_contrast_ratio(self, background: Self, min_ratio: f32) -> Self {
+        if self.contrast_ratio(background) >= min_ratio {
+            return self;
+        }
+
+        let lab = self.to_oklaba();
+        let adjust = |target: f32, amount: f32| {
+            OkLaba(
+                lab.0 + (target - lab.0) * amount,
+                lab.1 * (1. - amount),
+                lab.2 * (1. - amount),
+                lab.3,
+            )
+            .to_linear()
+            .clamped()
+        };
+
+        // Binary search for the smallest adjustment towards target
+        let search = |target: f32| {
+            let extreme = adjust(target, 1.);
+            if extreme.contrast_ratio(background) < min_ratio {
+                return Err(extreme);
+            }
+            let mut low = 0.;
+            let mut high = 1.;
+            for _ in 0..16 {
+                let mid = (low + high) / 2.;
+                if adjust(target, mid).contrast_ratio(background) >= min_ratio {
+                    high = mid;
+                } else {
+                    low = mid;
+                }
+            }
+            Ok(adjust(target, high))
+        };
+
+        let (first, second) = if self.relative_luminance() >= background.relative_luminance() {
+            (1., 0.)
+        } else {
+            (0., 1.)
+        };
+
+        match search(first) {
+            Ok(color) => color,
+            Err(first_extreme) => match search(second) {
+                Ok(color) => color,
+                Err(second_extreme) => {
+                    if first_extreme.contrast_ratio(background)
+                        >= second_extreme.contrast_ratio(background)
+                    {
+                        first_extreme
+                    } else {
+                        second_extreme
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// A color in the OKLab perceptual color space, with linear alpha.
+/// The components are lightness (0.0-1.0), followed by the a (green-red)
+/// and b (blue-yellow) axes.
+/// <https://bottosson.github.io/posts/oklab/>
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct OkLaba(pub f32, pub f32, pub f32, pub f32);
+
+impl OkLaba {
+    /// Convert to linear RGBA.  The result may lie outside of
+    /// the 0.0-1.0 range if the color is not representable in sRGB.
+    #[allow(clippy::excessive_precision)]
+    pub fn to_linear(self) -> LinearRgba {
+        let l = self.0 + 0.3963377774 * self.1 + 0.2158037573 * self.2;
+        let m = self.0 - 0.1055613458 * self.1 - 0.0638541728 * self.2;
+        let s = self.0 - 0.0894841775 * self.1 - 1.2914855480 * self.2;
+
+        let l = l * l * l;
+        let m = m * m * m;
+        let s = s * s * s;
+
+        LinearRgba(
+            4.0767416621 * l - 3.3077115913 * m + 0.2309699292 * s,
+            -1.2684380046 * l + 2.6097574011 * m - 0.3413193965 * s,
+            -0.0041960863 * l - 0.7034186147 * m + 1.7076147010 * s,
+            self.3,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -614,6 +773,70 @@ mod tests {
         );
     }
 
+    #[test]
+    fn linear_to_srgb() {
+        let color = SrgbaTuple::from_str("rgba(200,120,30,0.5)").unwrap();
+        let back = color.to_linear().to_srgb();
+        assert!((color.0 - back.0).abs() < 0.0001, "{:?} {:?}", color, back);
+        assert!((color.1 - back.1).abs() < 0.0001, "{:?} {:?}", color, back);
+        assert!((color.2 - back.2).abs() < 0.0001, "{:?} {:?}", color, back);
+        assert_eq!(color.3, back.3);
+    }
+
+    #[test]
+    fn contrast_ratio() {
+        let black = LinearRgba::with_srgba(0, 0, 0, 255);
+        let white = LinearRgba::with_srgba(255, 255, 255, 255);
+        assert!((black.contrast_ratio(white) - 21.).abs() < 0.01);
+        assert!((white.contrast_ratio(black) - 21.).abs() < 0.01);
+        assert_eq!(white.contrast_ratio(white), 1.);
+    }
+
+    #[test]
+    fn oklab_round_trip() {
+        for &(r, g, b) in &[(0, 0, 0), (255, 255, 255), (0, 0, 205), (200, 120, 30)] {
+            let color = LinearRgba::with_srgba(r, g, b, 255);
+            let lab = color.to_oklaba();
+            let back = lab.to_linear();
+            assert!((color.0 - back.0).abs() < 0.001, "{:?} {:?}", color, back);
+            assert!((color.1 - back.1).abs() < 0.001, "{:?} {:?}", color, back);
+            assert!((color.2 - back.2).abs() < 0.001, "{:?} {:?}", color, back);
+        }
+        let white = LinearRgba::with_srgba(255, 255, 255, 255).to_oklaba();
+        assert!((white.0 - 1.).abs() < 0.001);
+        assert!(white.1.abs() < 0.001 && white.2.abs() < 0.001);
+    }
+
+    #[test]
+    fn ensure_contrast_ratio() {
+        let black = LinearRgba::with_srgba(0, 0, 0, 255);
+        let white = LinearRgba::with_srgba(255, 255, 255, 255);
+        let dark_blue = LinearRgba::with_srgba(0, 0, 205, 255);
+
+        // Already sufficient; left alone
+        assert_eq!(dark_blue.ensure_contrast_ratio(white, 4.5), dark_blue);
+
+        let adjusted = dark_blue.ensure_contrast_ratio(black, 4.5);
+        let ratio = adjusted.contrast_ratio(black);
+        assert!(ratio >= 4.5, "ratio {} for {:?}", ratio, adjusted);
+        // It should be the smallest adjustment, rather than white
+        assert!(ratio < 4.6, "ratio {} for {:?}", ratio, adjusted);
+        // and still recognizably blue
+        assert!(adjusted.2 > adjusted.0 && adjusted.2 > adjusted.1);
+        assert_eq!(adjusted.3, 1.);
+
+        // A light color on a light background is darkened
+        let yellow = LinearRgba::with_srgba(255, 255, 0, 255);
+        let adjusted = yellow.ensure_contrast_ratio(white, 3.);
+        assert!(adjusted.contrast_ratio(white) >= 3.);
+        assert!(adjusted.relative_luminance() < yellow.relative_luminance());
+
+        // Unreachable ratios produce the best possible contrast
+        let grey = LinearRgba::with_srgba(128, 128, 128, 255);
+        let adjusted = grey.ensure_contrast_ratio(grey, 21.);
+        assert!((adjusted.contrast_ratio(grey) - black.contrast_ratio(grey)).abs() < 0.01);
+    }
+
     #[test]
     fn from_rgb() {
         assert!(SrgbaTuple::from_str("").is_err());
@@ -1,6 +1,6 @@
 use crate::background::Gradient;
 use crate::bell::{AudibleBell, EasingFunction, VisualBell};
-use crate::color::{ColorSchemeFile, HsbTransform, Palette, TabBarStyle, WindowFrameConfig};
+use crate::color::{HsbTransform, Palette, TabBarStyle, WindowFrameConfig};
 use crate::daemon::DaemonOptions;
 use crate::font::{
     AllowSquareGlyphOverflow, FontLocatorSelection, FontRasterizerSelection, FontShaperSelection,
@@ -11,6 +11,7 @@ use crate::keyassignment::{
     KeyAssignment, KeyTable, KeyTableEntry, KeyTables, MouseEventTrigger, SpawnCommand,
 };
 use crate::keys::{Key, LeaderKey, Mouse};
+use crate::scheme_import::{load_color_scheme_file, ColorSchemeFormat};
 use crate::ssh::{SshBackend, SshDomain};
 use crate::tls::{TlsDomainClient, TlsDomainServer};
 use crate::units::{de_pixels, Dimension};
@@ -19,7 +20,7 @@ use crate::wsl::WslDomain;
 use crate::{
     de_number, de_vec_table, default_config_with_overrides_applied, default_one_point_oh,
     default_one_point_oh_f64, default_true, make_lua_context, KeyMapPreference, LoadedConfig,
-    CONFIG_DIR, CONFIG_FILE_OVERRIDE, CONFIG_OVERRIDES, CONFIG_SKIP, HOME_DIR,
+    CONFIG_DIR, CONFIG_FILE_OVERRIDE, CONFIG_OVERRIDES, CONFIG_SKIP, DATA_DIR, HOME_DIR,
 };
 use anyhow::Context;
 use luahelper::impl_lua_conversion;
@@ -79,6 +80,12 @@ pub struct Config {
     #[serde(default = "default_true")]
     pub bold_brightens_ansi_colors: bool,
 
+    /// When greater than 1.0, the foreground color of text is adjusted
+    /// so that its contrast ratio against the background is at least
+    /// this value.
+    #[serde(default = "default_one_point_oh")]
+    pub minimum_contrast: f32,
+
     /// The color palette
     pub colors: Option<Palette>,
 
@@ -103,6 +110,16 @@ pub struct Config {
     #[serde(default = "default_scrollback_lines")]
     pub scrollback_lines: usize,
 
+    /// How many of the most recent lines of scrollback are kept
+    /// uncompressed; older lines are compacted to save memory
+    #[serde(default = "default_scrollback_hot_lines")]
+    pub scrollback_hot_lines: usize,
+
+    /// If set, compacted scrollback older than this many lines
+    /// is moved out of memory into a temporary file
+    #[serde(default)]
+    pub scrollback_spill_after_lines: Option<usize>,
+
     /// If no `prog` is specified on the command line, use this
     /// instead of running the user's shell.
     /// For example, to have `wezterm` always run `top` by default,
@@ -254,6 +271,27 @@ pub struct Config {
     #[serde(default = "default_mux_env_remove", deserialize_with = "de_vec_table")]
     pub mux_env_remove: Vec<String>,
 
+    /// Where `wezterm-mux-server` saves its session (windows, tabs,
+    /// splits and the command/cwd of each pane) and restores it
+    /// from on startup.  Defaults to `session.json` in the data dir.
+    #[serde(default)]
+    pub mux_session_file: Option<PathBuf>,
+
+    /// Whether `wezterm-mux-server` should rebuild the session saved
+    /// in `mux_session_file` when it starts up
+    #[serde(default = "default_true")]
+    pub mux_session_restore_on_startup: bool,
+
+    /// If non-zero, `wezterm-mux-server` will save its session to
+    /// `mux_session_file` this often
+    #[serde(default)]
+    pub mux_session_autosave_interval_seconds: u64,
+
+    /// Whether the periodic autosave should include the text of
+    /// the scrollback of each pane
+    #[serde(default)]
+    pub mux_session_autosave_scrollback: bool,
+
     #[serde(default, deserialize_with = "de_vec_table")]
     pub keys: Vec<Key>,
     #[serde(default)]
@@ -940,52 +978,46 @@ impl Config {
     }
 
     fn load_color_schemes(&mut self, paths: &[PathBuf]) -> anyhow::Result<()> {
-        fn extract_scheme_name(name: &str) -> Option<&str> {
-            if name.ends_with(".toml") {
-                let len = name.len();
-                Some(&name[..len - 5])
-            } else {
-                None
-            }
-        }
-
-        fn load_scheme(path: &Path) -> anyhow::Result<ColorSchemeFile> {
-            let s = std::fs::read_to_string(path)?;
-            let scheme: ColorSchemeFile = toml::from_str(&s).context("parsing TOML")?;
-            Ok(scheme)
-        }
-
         for colors_dir in paths {
             if let Ok(dir) = std::fs::read_dir(colors_dir) {
                 for entry in dir {
                     if let Ok(entry) = entry {
-                        if let Some(name) = entry.file_name().to_str() {
-                            if let Some(scheme_name) = extract_scheme_name(name) {
-                                if self.color_schemes.contains_key(scheme_name) {
-                                    // This scheme has already been defined
-                                    continue;
-                                }
+                        let path = entry.path();
+                        if ColorSchemeFormat::from_path(&path).is_none() {
+                            continue;
+                        }
 
-                                let path = entry.path();
-                                match load_scheme(&path) {
-                                    Ok(scheme) => {
-                                        log::trace!(
-                                            "Loaded color scheme `{}` from {}",
-                                            scheme_name,
-                                            path.display()
-                                        );
-                                        self.color_schemes
-                                            .insert(scheme_name.to_string(), scheme.colors);
+                        match load_color_scheme_file(&path) {
+                            Ok(schemes) => {
+                                for (scheme_name, scheme) in schemes {
+                                    if self.color_schemes.contains_key(&scheme_name) {
+                                        // This scheme has already been defined
+                                        continue;
                                     }
-                                    Err(err) => {
-                                        log::error!(
-                                            "Color scheme in `{}` failed to load: {:#}",
+                                    log::trace!(
+                                        "Loaded color scheme `{}` from {}",
+                                        scheme_name,
+                                        path.display()
+                                    );
+                                    if !scheme.unmapped.is_empty() {
+                                        log::debug!(
+                                            "Color scheme `{}` in {} has colors that \
+                                             cannot be represented: {}",
+                                            scheme_name,
                                             path.display(),
-                                            err
+                                            scheme.unmapped.join(", ")
                                         );
                                     }
+                                    self.color_schemes.insert(scheme_name, scheme.palette);
                                 }
                             }
+                            Err(err) => {
+                                log::error!(
+                                    "Color scheme in `{}` failed to load: {:#}",
+                                    path.display(),
+                                    err
+                                );
+                            }
                         }
                     }
                 }
@@ -1005,6 +1037,12 @@ impl Config {
         }
     }
 
+    pub fn mux_session_file(&self) -> PathBuf {
+        self.mux_session_file
+            .clone()
+            .unwrap_or_else(|| DATA_DIR.join("session.json"))
+    }
+
     pub fn initial_size(&self) -> PtySize {
         PtySize {
             rows: self.initial_rows,
@@ -1137,6 +1175,10 @@ fn default_scrollback_lines() -> usize {
     3500
 }
 
+fn default_scrollback_hot_lines() -> usize {
+    10_000
+}
+
 fn default_initial_rows() -> u16 {
     24
 }
@@ -1179,6 +1221,15 @@ pub(crate) fn compute_runtime_dir() -> anyhow::Result<PathBuf> {
     Ok(crate::HOME_DIR.join(".local/share/wezterm"))
 }
 
+/// Returns the directory for data that should persist across
+/// logins and reboots, unlike the runtime dir
+pub(crate) fn compute_data_dir() -> PathBuf {
+    match dirs_next::data_dir() {
+        Some(data) => data.join("wezterm"),
+        None => crate::HOME_DIR.join(".local/share/wezterm"),
+    }
+}
+
 pub fn pki_dir() -> anyhow::Result<PathBuf> {
     compute_runtime_dir().map(|d| d.join("pki"))
 }
@@ -356,6 +356,10 @@ pub struct FontAttributes {
 
     #[serde(default)]
     pub harfbuzz_features: Option<Vec<String>>,
+    /// Explicit values for the design axes of a variable font,
+    /// such as `wght=450` or `opsz=12`
+    #[serde(default)]
+    pub variation_axes: Option<Vec<String>>,
     #[serde(default)]
     pub freetype_load_target: Option<FreeTypeLoadTarget>,
     #[serde(default)]
@@ -387,6 +391,7 @@ impl FontAttributes {
             is_fallback: false,
             is_synthetic: false,
             harfbuzz_features: None,
+            variation_axes: None,
             freetype_load_target: None,
             freetype_render_target: None,
             freetype_load_flags: None,
@@ -403,6 +408,7 @@ impl FontAttributes {
             is_fallback: true,
             is_synthetic: false,
             harfbuzz_features: None,
+            variation_axes: None,
             freetype_load_target: None,
             freetype_render_target: None,
             freetype_load_flags: None,
@@ -421,6 +427,7 @@ impl Default for FontAttributes {
             is_fallback: false,
             is_synthetic: false,
             harfbuzz_features: None,
+            variation_axes: None,
             freetype_load_target: None,
             freetype_render_target: None,
             freetype_load_flags: None,
@@ -95,6 +95,7 @@ pub enum SelectionMode {
     Word,
     Line,
     SemanticZone,
+    Block,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
@@ -223,6 +224,12 @@ pub enum PaneDirection {
     Prev,
 }
 
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum RotationDirection {
+    Clockwise,
+    CounterClockwise,
+}
+
 #[derive(Debug, Copy, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub enum ScrollbackEraseMode {
     ScrollbackOnly,
@@ -260,6 +267,57 @@ impl Default for ClipboardPasteSource {
     }
 }
 
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+pub enum CopyModeAssignment {
+    Close,
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    MoveForwardWord,
+    MoveBackwardWord,
+    MoveToStartOfLine,
+    MoveToStartOfNextLine,
+    MoveToStartOfLineContent,
+    MoveToEndOfLineContent,
+    MoveToScrollbackTop,
+    MoveToScrollbackBottom,
+    MoveToViewportTop,
+    MoveToViewportMiddle,
+    MoveToViewportBottom,
+    PageUp,
+    PageDown,
+    /// Starts, changes or stops selecting text.
+    /// Passing the current mode toggles the selection off.
+    SetSelectionMode(Option<SelectionMode>),
+    /// Prompts for a pattern and searches forwards for it
+    SearchForward,
+    /// Prompts for a pattern and searches backwards for it
+    SearchBackward,
+    /// Moves to the next match of the most recent search,
+    /// in the same direction as that search
+    NextMatch,
+    /// Moves to the next match of the most recent search,
+    /// in the opposite direction to that search
+    PriorMatch,
+    /// Waits for a character and then moves forwards to it on the
+    /// current line.  If `till` is true, stops just before it.
+    JumpForward {
+        #[serde(default)]
+        till: bool,
+    },
+    /// Waits for a character and then moves backwards to it on the
+    /// current line.  If `till` is true, stops just after it.
+    JumpBackward {
+        #[serde(default)]
+        till: bool,
+    },
+    /// Repeats the most recent jump
+    JumpAgain,
+    /// Repeats the most recent jump in the opposite direction
+    JumpReverse,
+}
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct QuickSelectArguments {
     /// Overrides the main quick_select_alphabet config
@@ -275,6 +333,43 @@ pub struct QuickSelectArguments {
     pub label: String,
 }
 
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InputSelectorEntry {
+    /// The text shown for this choice, and matched by the fuzzy filter
+    pub label: String,
+    /// An identifier that is passed to the callback when this choice
+    /// is selected.  Defaults to the label when not specified.
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Additional text shown alongside the label, which is also
+    /// considered by the fuzzy filter
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InputSelector {
+    /// The callback to invoke with the selection; this is
+    /// typically created using `wezterm.action_callback`
+    pub action: Box<KeyAssignment>,
+    #[serde(default)]
+    pub title: String,
+    pub choices: Vec<InputSelectorEntry>,
+    /// Start with the fuzzy filter active
+    #[serde(default)]
+    pub fuzzy: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PromptInputLine {
+    /// The callback to invoke with the line of text; this is
+    /// typically created using `wezterm.action_callback`
+    pub action: Box<KeyAssignment>,
+    /// Text shown above the input line
+    #[serde(default)]
+    pub description: String,
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub enum KeyAssignment {
     SpawnTab(SpawnTabDomain),
@@ -309,6 +404,7 @@ pub enum KeyAssignment {
     ScrollByPage(NotNan<f64>),
     ScrollByLine(isize),
     ScrollToPrompt(isize),
+    ScrollToFailedCommand(isize),
     ScrollToTop,
     ScrollToBottom,
     ShowTabNavigator,
@@ -324,6 +420,7 @@ pub enum KeyAssignment {
     ClearScrollback(ScrollbackEraseMode),
     Search(Pattern),
     ActivateCopyMode,
+    CopyMode(CopyModeAssignment),
 
     SelectTextAtMouseCursor(SelectionMode),
     ExtendSelectionToMouseCursor(Option<SelectionMode>),
@@ -340,6 +437,10 @@ pub enum KeyAssignment {
     CloseCurrentPane {
         confirm: bool,
     },
+    SwapActivePaneDirection(PaneDirection),
+    RotatePanes(RotationDirection),
+    MovePaneToNewTab,
+    MovePaneToTab(isize),
     EmitEvent(String),
     QuickSelect,
     QuickSelectArgs(QuickSelectArguments),
@@ -365,6 +466,8 @@ pub enum KeyAssignment {
     ClearKeyTableStack,
     DetachDomain(SpawnTabDomain),
     AttachDomain(String),
+    InputSelector(InputSelector),
+    PromptInputLine(PromptInputLine),
 }
 impl_lua_conversion!(KeyAssignment);
 
@@ -31,6 +31,7 @@ mod frontend;
 pub mod keyassignment;
 mod keys;
 pub mod lua;
+mod scheme_import;
 mod ssh;
 mod terminal;
 mod tls;
@@ -47,6 +48,7 @@ pub use daemon::*;
 pub use font::*;
 pub use frontend::*;
 pub use keys::*;
+pub use scheme_import::*;
 pub use ssh::*;
 pub use terminal::*;
 pub use tls::*;
@@ -62,6 +64,7 @@ lazy_static! {
     pub static ref HOME_DIR: PathBuf = dirs_next::home_dir().expect("can't find HOME dir");
     pub static ref CONFIG_DIR: PathBuf = xdg_config_home();
     pub static ref RUNTIME_DIR: PathBuf = compute_runtime_dir().unwrap();
+    pub static ref DATA_DIR: PathBuf = compute_data_dir();
     static ref CONFIG: Configuration = Configuration::new();
     static ref CONFIG_FILE_OVERRIDE: Mutex<Option<PathBuf>> = Mutex::new(None);
     static ref CONFIG_SKIP: AtomicBool = AtomicBool::new(false);
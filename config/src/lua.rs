@@ -499,6 +499,8 @@ struct LuaFontAttributes {
     #[serde(default)]
     pub harfbuzz_features: Option<Vec<String>>,
     #[serde(default)]
+    pub variation_axes: Option<Vec<String>>,
+    #[serde(default)]
     pub freetype_load_target: Option<FreeTypeLoadTarget>,
     #[serde(default)]
     pub freetype_render_target: Option<FreeTypeLoadTarget>,
@@ -585,6 +587,7 @@ fn font<'lua>(
             is_fallback: false,
             is_synthetic: false,
             harfbuzz_features: attrs.harfbuzz_features,
+            variation_axes: attrs.variation_axes,
             freetype_load_target: attrs.freetype_load_target,
             freetype_render_target: attrs.freetype_render_target,
             freetype_load_flags: match attrs.freetype_load_flags {
@@ -635,6 +638,7 @@ fn font_with_fallback<'lua>(
                 is_fallback: idx != 0,
                 is_synthetic: false,
                 harfbuzz_features: attrs.harfbuzz_features,
+                variation_axes: attrs.variation_axes,
                 freetype_load_target: attrs.freetype_load_target,
                 freetype_render_target: attrs.freetype_render_target,
                 freetype_load_flags: match attrs.freetype_load_flags {
@@ -0,0 +1,793 @@
+//! Imports color schemes from the formats used by other terminal
+//! emulators and theme collections, mapping them onto `Palette`.
+use crate::color::{ColorSchemeFile, Palette, RgbaColor};
+use anyhow::{anyhow, bail, Context};
+use std::convert::TryFrom;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::str::FromStr;
+use termwiz::color::SrgbaTuple;
+use wezterm_term::color::ColorPalette;
+
+/// The color scheme file formats that we know how to load
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSchemeFormat {
+    /// wezterm's own TOML format
+    Wezterm,
+    /// iTerm2 `.itermcolors` property lists
+    ITerm2,
+    /// Windows Terminal JSON; either a single scheme object, a list
+    /// of schemes, or a `settings.json` with a `schemes` list
+    WindowsTerminal,
+    /// base16 YAML scheme files
+    Base16,
+    /// kitty `.conf` theme files
+    Kitty,
+}
+
+impl ColorSchemeFormat {
+    /// Determines the format of a file from its extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_ascii_lowercase();
+        match ext.as_str() {
+            "toml" => Some(Self::Wezterm),
+            "itermcolors" => Some(Self::ITerm2),
+            "json" => Some(Self::WindowsTerminal),
+            "yaml" | "yml" => Some(Self::Base16),
+            "conf" => Some(Self::Kitty),
+            _ => None,
+        }
+    }
+}
+
+impl FromStr for ColorSchemeFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "wezterm" => Ok(Self::Wezterm),
+            "iterm2" => Ok(Self::ITerm2),
+            "windows-terminal" => Ok(Self::WindowsTerminal),
+            "base16" => Ok(Self::Base16),
+            "kitty" => Ok(Self::Kitty),
+            _ => bail!(
+                "invalid format {}; expected wezterm, iterm2, \
+                 windows-terminal, base16 or kitty",
+                s
+            ),
+        }
+    }
+}
+
+/// A color scheme that was loaded from a file
+#[derive(Debug, Clone)]
+pub struct ImportedColorScheme {
+    /// The name that the file gave to the scheme, if any
+    pub name: Option<String>,
+    pub palette: Palette,
+    /// The names of the colors in the source that have no
+    /// equivalent in a wezterm `Palette`
+    pub unmapped: Vec<String>,
+}
+
+/// The parts of a Palette that the imported formats can express
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Channel {
+    Foreground,
+    Background,
+    /// A single cursor color, used for both its background and border
+    Cursor,
+    CursorText,
+    SelectionFg,
+    SelectionBg,
+    Split,
+    VisualBell,
+    /// An entry in the 256 color palette
+    Indexed(u8),
+}
+
+#[derive(Default)]
+struct PaletteBuilder {
+    palette: Palette,
+    colors: Vec<(u8, RgbaColor)>,
+    unmapped: Vec<String>,
+}
+
+impl PaletteBuilder {
+    fn set(&mut self, channel: Channel, color: RgbaColor) {
+        let p = &mut self.palette;
+        match channel {
+            Channel::Foreground => p.foreground = Some(color),
+            Channel::Background => p.background = Some(color),
+            Channel::Cursor => {
+                p.cursor_bg = Some(color);
+                p.cursor_border = Some(color);
+            }
+            Channel::CursorText => p.cursor_fg = Some(color),
+            Channel::SelectionFg => p.selection_fg = Some(color),
+            Channel::SelectionBg => p.selection_bg = Some(color),
+            Channel::Split => p.split = Some(color),
+            Channel::VisualBell => p.visual_bell = Some(color),
+            Channel::Indexed(idx) => self.colors.push((idx, color)),
+        }
+    }
+
+    fn unmapped(&mut self, name: &str) {
+        self.unmapped.push(name.to_string());
+    }
+
+    fn build(mut self, name: Option<String>) -> ImportedColorScheme {
+        // Schemes that define only some of the 16 ANSI colors have
+        // the remainder filled in from the default palette
+        let defaults = ColorPalette::default();
+        let mut ansi: [RgbaColor; 16] = [RgbaColor::default(); 16];
+        let mut have_ansi = false;
+        let mut have_brights = false;
+        for (idx, color) in ansi.iter_mut().enumerate() {
+            *color = defaults.colors.0[idx].into();
+        }
+
+        for (idx, color) in self.colors {
+            match idx {
+                0..=7 => have_ansi = true,
+                8..=15 => have_brights = true,
+                _ => {
+                    self.palette.indexed.insert(idx, color);
+                    continue;
+                }
+            }
+            ansi[idx as usize] = color;
+        }
+
+        if have_ansi {
+            let mut colors = [RgbaColor::default(); 8];
+            colors.copy_from_slice(&ansi[0..8]);
+            self.palette.ansi = Some(colors);
+        }
+        if have_brights {
+            let mut colors = [RgbaColor::default(); 8];
+            colors.copy_from_slice(&ansi[8..16]);
+            self.palette.brights = Some(colors);
+        }
+
+        ImportedColorScheme {
+            name,
+            palette: self.palette,
+            unmapped: self.unmapped,
+        }
+    }
+}
+
+fn parse_color(value: &str) -> anyhow::Result<RgbaColor> {
+    RgbaColor::try_from(value.to_string())
+}
+
+/// Parses the color schemes from `data`.
+/// Most formats hold a single scheme, but a Windows Terminal
+/// settings file may hold several.
+pub fn import_color_schemes(
+    data: &str,
+    format: ColorSchemeFormat,
+) -> anyhow::Result<Vec<ImportedColorScheme>> {
+    match format {
+        ColorSchemeFormat::Wezterm => {
+            let scheme: ColorSchemeFile = toml::from_str(data).context("parsing TOML")?;
+            Ok(vec![ImportedColorScheme {
+                name: None,
+                palette: scheme.colors,
+                unmapped: vec![],
+            }])
+        }
+        ColorSchemeFormat::ITerm2 => Ok(vec![import_iterm2(data)?]),
+        ColorSchemeFormat::WindowsTerminal => import_windows_terminal(data),
+        ColorSchemeFormat::Base16 => Ok(vec![import_base16(data)?]),
+        ColorSchemeFormat::Kitty => Ok(vec![import_kitty(data)?]),
+    }
+}
+
+/// Loads the color schemes from the file at `path`, returning each
+/// of them along with its name.  Schemes are named after the file,
+/// except for Windows Terminal schemes which carry their own names.
+pub fn load_color_scheme_file(path: &Path) -> anyhow::Result<Vec<(String, ImportedColorScheme)>> {
+    let format = ColorSchemeFormat::from_path(path)
+        .ok_or_else(|| anyhow!("{} is not a known color scheme format", path.display()))?;
+    let data = std::fs::read_to_string(path)?;
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("{} has an invalid file name", path.display()))?;
+
+    Ok(import_color_schemes(&data, format)?
+        .into_iter()
+        .map(|scheme| {
+            let name = match (format, &scheme.name) {
+                (ColorSchemeFormat::WindowsTerminal, Some(name)) => name.to_string(),
+                _ => stem.to_string(),
+            };
+            (name, scheme)
+        })
+        .collect())
+}
+
+/// Renders a palette in wezterm's TOML color scheme format
+pub fn color_scheme_to_toml(name: &str, palette: &Palette) -> String {
+    fn quoted(color: &RgbaColor) -> String {
+        format!("\"{}\"", color.to_rgb_string())
+    }
+
+    let mut output = String::new();
+    writeln!(output, "# {}", name.replace('\n', " ")).ok();
+    writeln!(output, "[colors]").ok();
+    for (key, color) in [
+        ("foreground", &palette.foreground),
+        ("background", &palette.background),
+        ("cursor_bg", &palette.cursor_bg),
+        ("cursor_border", &palette.cursor_border),
+        ("cursor_fg", &palette.cursor_fg),
+        ("selection_bg", &palette.selection_bg),
+        ("selection_fg", &palette.selection_fg),
+        ("scrollbar_thumb", &palette.scrollbar_thumb),
+        ("split", &palette.split),
+        ("visual_bell", &palette.visual_bell),
+        ("compose_cursor", &palette.compose_cursor),
+    ] {
+        if let Some(color) = color {
+            writeln!(output, "{} = {}", key, quoted(color)).ok();
+        }
+    }
+
+    let mut blank_line = true;
+    for (key, colors) in [("ansi", &palette.ansi), ("brights", &palette.brights)] {
+        if let Some(colors) = colors {
+            if blank_line {
+                output.push('\n');
+                blank_line = false;
+            }
+            let colors: Vec<String> = colors.iter().map(quoted).collect();
+            writeln!(output, "{} = [{}]", key, colors.join(",")).ok();
+        }
+    }
+
+    if !palette.indexed.is_empty() {
+        let mut indexed: Vec<_> = palette.indexed.iter().collect();
+        indexed.sort_by_key(|(idx, _)| **idx);
+        writeln!(output, "\n[colors.indexed]").ok();
+        for (idx, color) in indexed {
+            writeln!(output, "{} = {}", idx, quoted(color)).ok();
+        }
+    }
+
+    output
+}
+
+/// A minimal representation of the property list values that
+/// appear in `.itermcolors` files
+#[derive(Debug)]
+enum PlistValue {
+    Dict(Vec<(String, PlistValue)>),
+    Number(f64),
+    Other,
+}
+
+struct PlistReader<'a> {
+    events: xml::reader::EventReader<&'a [u8]>,
+}
+
+impl<'a> PlistReader<'a> {
+    /// Returns the name of the next element, or None if the
+    /// enclosing element ended
+    fn next_element(&mut self) -> anyhow::Result<Option<String>> {
+        use xml::reader::XmlEvent;
+        loop {
+            match self.events.next()? {
+                XmlEvent::StartElement { name, .. } => return Ok(Some(name.local_name)),
+                XmlEvent::EndElement { .. } | XmlEvent::EndDocument => return Ok(None),
+                XmlEvent::Characters(text) if !text.trim().is_empty() => {
+                    bail!("unexpected text {:?}", text)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Returns the text content of the current element
+    fn text(&mut self) -> anyhow::Result<String> {
+        use xml::reader::XmlEvent;
+        let mut result = String::new();
+        loop {
+            match self.events.next()? {
+                XmlEvent::Characters(text) | XmlEvent::CData(text) | XmlEvent::Whitespace(text) => {
+                    result.push_str(&text)
+                }
+                XmlEvent::EndElement { .. } => return Ok(result),
+                XmlEvent::StartElement { name, .. } => {
+                    bail!("unexpected element {} in text", name.local_name)
+                }
+                XmlEvent::EndDocument => bail!("unexpected end of document"),
+                _ => {}
+            }
+        }
+    }
+
+    fn skip_element(&mut self) -> anyhow::Result<()> {
+        use xml::reader::XmlEvent;
+        let mut depth = 1;
+        while depth > 0 {
+            match self.events.next()? {
+                XmlEvent::StartElement { .. } => depth += 1,
+                XmlEvent::EndElement { .. } => depth -= 1,
+                XmlEvent::EndDocument => bail!("unexpected end of document"),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, element: &str) -> anyhow::Result<PlistValue> {
+        match element {
+            "dict" => {
+                let mut entries = vec![];
+                while let Some(element) = self.next_element()? {
+                    if element != "key" {
+                        bail!("expected key in dict but found {}", element);
+                    }
+                    let key = self.text()?;
+                    let element = self
+                        .next_element()?
+                        .ok_or_else(|| anyhow!("missing value for key {}", key))?;
+                    entries.push((key, self.value(&element)?));
+                }
+                Ok(PlistValue::Dict(entries))
+            }
+            "real" | "integer" => {
+                let text = self.text()?;
+                Ok(PlistValue::Number(text.trim().parse().with_context(
+                    || format!("parsing {:?} as a number", text),
+                )?))
+            }
+            _ => {
+                self.skip_element()?;
+                Ok(PlistValue::Other)
+            }
+        }
+    }
+
+    fn parse(data: &'a str) -> anyhow::Result<PlistValue> {
+        let mut reader = Self {
+            events: xml::reader::EventReader::new(data.as_bytes()),
+        };
+        match reader.next_element()?.as_deref() {
+            Some("plist") => {}
+            _ => bail!("not a property list"),
+        }
+        let element = reader
+            .next_element()?
+            .ok_or_else(|| anyhow!("empty property list"))?;
+        reader.value(&element)
+    }
+}
+
+fn iterm2_channel(key: &str) -> Option<Channel> {
+    if let Some(idx) = key
+        .strip_prefix("Ansi ")
+        .and_then(|key| key.strip_suffix(" Color"))
+    {
+        return match idx.parse::<u8>() {
+            Ok(idx) if idx < 16 => Some(Channel::Indexed(idx)),
+            _ => None,
+        };
+    }
+    match key {
+        "Foreground Color" => Some(Channel::Foreground),
+        "Background Color" => Some(Channel::Background),
+        "Cursor Color" => Some(Channel::Cursor),
+        "Cursor Text Color" => Some(Channel::CursorText),
+        "Selection Color" => Some(Channel::SelectionBg),
+        "Selected Text Color" => Some(Channel::SelectionFg),
+        _ => None,
+    }
+}
+
+fn iterm2_color(entries: &[(String, PlistValue)]) -> anyhow::Result<RgbaColor> {
+    let component = |name: &str| -> Option<f32> {
+        entries.iter().find_map(|(key, value)| match value {
+            PlistValue::Number(n) if key == name => Some(*n as f32),
+            _ => None,
+        })
+    };
+    let red = component("Red Component").ok_or_else(|| anyhow!("missing Red Component"))?;
+    let green = component("Green Component").ok_or_else(|| anyhow!("missing Green Component"))?;
+    let blue = component("Blue Component").ok_or_else(|| anyhow!("missing Blue Component"))?;
+    let alpha = component("Alpha Component").unwrap_or(1.0);
+    Ok(SrgbaTuple(red, green, blue, alpha).into())
+}
+
+fn import_iterm2(data: &str) -> anyhow::Result<ImportedColorScheme> {
+    let entries = match PlistReader::parse(data)? {
+        PlistValue::Dict(entries) => entries,
+        _ => bail!("expected the property list to hold a dict"),
+    };
+
+    let mut builder = PaletteBuilder::default();
+    for (key, value) in &entries {
+        match (iterm2_channel(key), value) {
+            (Some(channel), PlistValue::Dict(color)) => {
+                let color = iterm2_color(color).with_context(|| format!("parsing {}", key))?;
+                builder.set(channel, color);
+            }
+            _ => builder.unmapped(key),
+        }
+    }
+    Ok(builder.build(None))
+}
+
+fn windows_terminal_channel(key: &str) -> Option<Channel> {
+    const ANSI: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "purple", "cyan", "white",
+    ];
+    if let Some(idx) = ANSI.iter().position(|&name| name == key) {
+        return Some(Channel::Indexed(idx as u8));
+    }
+    if let Some(bright) = key.strip_prefix("bright") {
+        let bright = bright.to_ascii_lowercase();
+        if let Some(idx) = ANSI.iter().position(|&name| name == bright) {
+            return Some(Channel::Indexed(idx as u8 + 8));
+        }
+    }
+    match key {
+        "foreground" => Some(Channel::Foreground),
+        "background" => Some(Channel::Background),
+        "cursorColor" => Some(Channel::Cursor),
+        "selectionBackground" => Some(Channel::SelectionBg),
+        _ => None,
+    }
+}
+
+fn import_windows_terminal(data: &str) -> anyhow::Result<Vec<ImportedColorScheme>> {
+    use serde_json::Value;
+
+    let value: Value = serde_json::from_str(data).context("parsing JSON")?;
+    let schemes = match value {
+        Value::Object(mut obj) => match obj.remove("schemes") {
+            Some(Value::Array(schemes)) => schemes,
+            Some(_) => bail!("expected schemes to be a list"),
+            None => vec![Value::Object(obj)],
+        },
+        Value::Array(schemes) => schemes,
+        _ => bail!("expected an object or a list of color schemes"),
+    };
+
+    let mut result = vec![];
+    for scheme in schemes {
+        let scheme = match scheme {
+            Value::Object(scheme) => scheme,
+            _ => bail!("expected a color scheme object"),
+        };
+        let mut builder = PaletteBuilder::default();
+        let mut name = None;
+        for (key, value) in &scheme {
+            if key == "name" {
+                name = value.as_str().map(|s| s.to_string());
+                continue;
+            }
+            match (windows_terminal_channel(key), value.as_str()) {
+                (Some(channel), Some(color)) => {
+                    let color = parse_color(color).with_context(|| format!("parsing {}", key))?;
+                    builder.set(channel, color);
+                }
+                _ => builder.unmapped(key),
+            }
+        }
+        result.push(builder.build(name));
+    }
+    Ok(result)
+}
+
+/// Returns the value portion of a `key: value` line from a YAML
+/// document, removing any quotes and trailing comment
+fn yaml_value(value: &str) -> &str {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(value) = value.strip_prefix(quote) {
+            if let Some(end) = value.find(quote) {
+                return &value[..end];
+            }
+        }
+    }
+    match value.find(" #") {
+        Some(comment) => value[..comment].trim_end(),
+        None => value,
+    }
+}
+
+/// The mapping of base16 colors onto the terminal palette follows
+/// that used by base16-shell
+fn base16_channels(key: &str) -> &'static [Channel] {
+    use Channel::*;
+    match key {
+        "base00" => &[Background, Indexed(0), CursorText],
+        "base01" => &[Indexed(18)],
+        "base02" => &[Indexed(19), SelectionBg],
+        "base03" => &[Indexed(8)],
+        "base04" => &[Indexed(20)],
+        "base05" => &[Foreground, Indexed(7), Cursor, SelectionFg],
+        "base06" => &[Indexed(21)],
+        "base07" => &[Indexed(15)],
+        "base08" => &[Indexed(1), Indexed(9)],
+        "base09" => &[Indexed(16)],
+        "base0A" => &[Indexed(3), Indexed(11)],
+        "base0B" => &[Indexed(2), Indexed(10)],
+        "base0C" => &[Indexed(6), Indexed(14)],
+        "base0D" => &[Indexed(4), Indexed(12)],
+        "base0E" => &[Indexed(5), Indexed(13)],
+        "base0F" => &[Indexed(17)],
+        _ => &[],
+    }
+}
+
+fn import_base16(data: &str) -> anyhow::Result<ImportedColorScheme> {
+    const METADATA: &[&str] = &[
+        "scheme",
+        "name",
+        "author",
+        "slug",
+        "system",
+        "variant",
+        "description",
+        "palette",
+    ];
+
+    let mut builder = PaletteBuilder::default();
+    let mut name = None;
+    for line in data.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line == "---" {
+            continue;
+        }
+        let (key, value) = match line.split_once(':') {
+            Some((key, value)) => (key.trim(), yaml_value(value)),
+            None => bail!("expected `key: value` but found {:?}", line),
+        };
+
+        if METADATA.contains(&key) {
+            if (key == "scheme" || key == "name") && !value.is_empty() {
+                name = Some(value.to_string());
+            }
+            continue;
+        }
+
+        let channels = base16_channels(key);
+        if channels.is_empty() {
+            builder.unmapped(key);
+            continue;
+        }
+        let color = if value.starts_with('#') {
+            parse_color(value)
+        } else {
+            parse_color(&format!("#{}", value))
+        }
+        .with_context(|| format!("parsing {}", key))?;
+        for &channel in channels {
+            builder.set(channel, color);
+        }
+    }
+    Ok(builder.build(name))
+}
+
+fn kitty_channel(key: &str) -> Option<Channel> {
+    if let Some(idx) = key.strip_prefix("color") {
+        return idx.parse::<u8>().ok().map(Channel::Indexed);
+    }
+    match key {
+        "foreground" => Some(Channel::Foreground),
+        "background" => Some(Channel::Background),
+        "cursor" => Some(Channel::Cursor),
+        "cursor_text_color" => Some(Channel::CursorText),
+        "selection_foreground" => Some(Channel::SelectionFg),
+        "selection_background" => Some(Channel::SelectionBg),
+        "inactive_border_color" => Some(Channel::Split),
+        "visual_bell_color" => Some(Channel::VisualBell),
+        _ => None,
+    }
+}
+
+fn import_kitty(data: &str) -> anyhow::Result<ImportedColorScheme> {
+    let mut builder = PaletteBuilder::default();
+    let mut name = None;
+    let mut cursor_text_is_background = false;
+
+    for line in data.lines() {
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("## name:") {
+            name = Some(value.trim().to_string());
+            continue;
+        }
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = match line.split_once(char::is_whitespace) {
+            Some((key, value)) => (key, value.trim()),
+            None => (line, ""),
+        };
+
+        match (kitty_channel(key), value) {
+            // These leave the color to be determined by the cell
+            // being drawn, which is what wezterm does when the
+            // corresponding color is not set
+            (Some(Channel::SelectionFg), "none") | (Some(Channel::SelectionBg), "none") => {}
+            (Some(Channel::CursorText), "background") => cursor_text_is_background = true,
+            (Some(channel), value) => {
+                let color = parse_color(value).with_context(|| format!("parsing {}", key))?;
+                builder.set(channel, color);
+            }
+            (None, _) => builder.unmapped(key),
+        }
+    }
+
+    if cursor_text_is_background {
+        builder.palette.cursor_fg = builder.palette.background;
+    }
+    Ok(builder.build(name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rgb(color: &Option<RgbaColor>) -> String {
+        color.expect("color to be set").to_rgb_string()
+    }
+
+    fn ansi(palette: &Palette) -> Vec<String> {
+        palette
+            .ansi
+            .expect("ansi to be set")
+            .iter()
+            .map(|c| c.to_rgb_string())
+            .collect()
+    }
+
+    #[test]
+    fn iterm2() {
+        let data = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+	<key>Ansi 1 Color</key>
+	<dict>
+		<key>Color Space</key>
+		<string>sRGB</string>
+		<key>Blue Component</key>
+		<real>0.0</real>
+		<key>Green Component</key>
+		<real>0.0</real>
+		<key>Red Component</key>
+		<real>1</real>
+	</dict>
+	<key>Background Color</key>
+	<dict>
+		<key>Blue Component</key>
+		<real>1</real>
+		<key>Green Component</key>
+		<real>0.0</real>
+		<key>Red Component</key>
+		<real>0.0</real>
+	</dict>
+	<key>Bold Color</key>
+	<dict>
+		<key>Blue Component</key>
+		<real>1</real>
+		<key>Green Component</key>
+		<real>1</real>
+		<key>Red Component</key>
+		<real>1</real>
+	</dict>
+</dict>
+</plist>
+"#;
+        let scheme = import_iterm2(data).unwrap();
+        assert_eq!(rgb(&scheme.palette.background), "#0000ff");
+        assert_eq!(ansi(&scheme.palette)[1], "#ff0000");
+        // Unspecified colors come from the default palette
+        assert_eq!(ansi(&scheme.palette)[0], "#000000");
+        assert!(scheme.palette.brights.is_none());
+        assert_eq!(scheme.unmapped, vec!["Bold Color".to_string()]);
+    }
+
+    #[test]
+    fn windows_terminal() {
+        let data = r##"{
+            "schemes": [
+                {
+                    "name": "Campbell",
+                    "background": "#0C0C0C",
+                    "foreground": "#CCCCCC",
+                    "cursorColor": "#FFFFFF",
+                    "selectionBackground": "#FFFFFF",
+                    "purple": "#881798",
+                    "brightPurple": "#B4009E"
+                }
+            ]
+        }"##;
+        let schemes = import_windows_terminal(data).unwrap();
+        assert_eq!(schemes.len(), 1);
+        let scheme = &schemes[0];
+        assert_eq!(scheme.name.as_deref(), Some("Campbell"));
+        assert_eq!(rgb(&scheme.palette.cursor_border), "#ffffff");
+        assert_eq!(ansi(&scheme.palette)[5], "#881798");
+        assert_eq!(
+            scheme.palette.brights.unwrap()[5].to_rgb_string(),
+            "#b4009e"
+        );
+        assert!(scheme.unmapped.is_empty());
+    }
+
+    #[test]
+    fn base16() {
+        let data = r#"
+scheme: "Ocean"
+author: "Chris Kempson (http://chriskempson.com)"
+base00: "2b303b"
+base01: "343d46"
+base02: "4f5b66"
+base03: "65737e"
+base04: "a7adba"
+base05: "c0c5ce"
+base06: "dfe1e8"
+base07: "eff1f5"
+base08: "bf616a"
+base09: "d08770"
+base0A: "ebcb8b"
+base0B: "a3be8c"
+base0C: "96b5b4"
+base0D: "8fa1b3"
+base0E: "b48ead"
+base0F: "ab7967"
+base10: "000000"
+"#;
+        let scheme = import_base16(data).unwrap();
+        assert_eq!(scheme.name.as_deref(), Some("Ocean"));
+        assert_eq!(rgb(&scheme.palette.background), "#2b303b");
+        assert_eq!(rgb(&scheme.palette.foreground), "#c0c5ce");
+        assert_eq!(ansi(&scheme.palette)[1], "#bf616a");
+        assert_eq!(
+            scheme.palette.brights.unwrap()[0].to_rgb_string(),
+            "#65737e"
+        );
+        assert_eq!(scheme.palette.indexed[&16].to_rgb_string(), "#d08770");
+        assert_eq!(scheme.unmapped, vec!["base10".to_string()]);
+    }
+
+    #[test]
+    fn kitty() {
+        let data = "
+## name: Kitty Test
+# a comment
+foreground #dddddd
+background   #000000
+selection_foreground none
+cursor_text_color background
+color2 #00ff00
+color123 #123456
+url_color #0087bd
+";
+        let scheme = import_kitty(data).unwrap();
+        assert_eq!(scheme.name.as_deref(), Some("Kitty Test"));
+        assert_eq!(rgb(&scheme.palette.foreground), "#dddddd");
+        assert_eq!(rgb(&scheme.palette.cursor_fg), "#000000");
+        assert!(scheme.palette.selection_fg.is_none());
+        assert_eq!(ansi(&scheme.palette)[2], "#00ff00");
+        assert_eq!(scheme.palette.indexed[&123].to_rgb_string(), "#123456");
+        assert_eq!(scheme.unmapped, vec!["url_color".to_string()]);
+    }
+
+    #[test]
+    fn toml_round_trip() {
+        let scheme = import_kitty("foreground #dddddd\ncolor1 #ff0000\ncolor200 #123456").unwrap();
+        let toml = color_scheme_to_toml("Test", &scheme.palette);
+        let parsed = import_color_schemes(&toml, ColorSchemeFormat::Wezterm).unwrap();
+        let palette = &parsed[0].palette;
+        assert_eq!(rgb(&palette.foreground), "#dddddd");
+        assert_eq!(ansi(palette)[1], "#ff0000");
+        assert_eq!(palette.indexed[&200].to_rgb_string(), "#123456");
+    }
+}
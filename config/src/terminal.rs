@@ -45,6 +45,14 @@ impl wezterm_term::TerminalConfiguration for TermConfig {
         self.configuration().scrollback_lines
     }
 
+    fn scrollback_hot_lines(&self) -> usize {
+        self.configuration().scrollback_hot_lines
+    }
+
+    fn scrollback_spill_after_lines(&self) -> Option<usize> {
+        self.configuration().scrollback_spill_after_lines
+    }
+
     fn enable_csi_u_key_encoding(&self) -> bool {
         self.configuration().enable_csi_u_key_encoding
     }
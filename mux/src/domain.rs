@@ -12,11 +12,13 @@ use crate::window::WindowId;
 use crate::Mux;
 use anyhow::{bail, Error};
 use async_trait::async_trait;
+use config::keyassignment::{PaneDirection, RotationDirection};
 use config::{configuration, WslDomain};
 use downcast_rs::{impl_downcast, Downcast};
 use portable_pty::{native_pty_system, CommandBuilder, PtySize, PtySystem};
 use std::ffi::OsString;
 use std::rc::Rc;
+use termwiz::escape::Action;
 
 static DOMAIN_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
 pub type DomainId = usize;
@@ -96,6 +98,70 @@ pub trait Domain: Downcast {
         command_dir: Option<String>,
     ) -> anyhow::Result<Rc<dyn Pane>>;
 
+    /// Spawn a new pane whose terminal has first applied `actions`,
+    /// ahead of any output from the spawned program.  This is used to
+    /// replay the saved scrollback of a pane when restoring a session.
+    /// Domains that cannot apply the actions before the program's
+    /// output is processed discard them rather than interleave them.
+    async fn spawn_pane_with_initial_actions(
+        &self,
+        size: PtySize,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        actions: Vec<Action>,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        if !actions.is_empty() {
+            log::warn!(
+                "domain {} cannot replay output into new panes",
+                self.domain_name()
+            );
+        }
+        self.spawn_pane(size, command, command_dir).await
+    }
+
+    // The methods below allow a domain to take over rearranging the
+    // panes that it owns; this is needed when the authoritative
+    // layout lives elsewhere, such as in a remote mux server.
+    // They return false (or None) to indicate that the mux should
+    // rearrange its local tab structure itself.
+
+    /// Swap the active pane of the tab with its neighbor in the
+    /// specified direction
+    async fn swap_active_pane_direction(
+        &self,
+        _tab_id: TabId,
+        _direction: PaneDirection,
+    ) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// Rotate the panes within the tab
+    async fn rotate_panes(
+        &self,
+        _tab_id: TabId,
+        _direction: RotationDirection,
+    ) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    /// Remove the pane from its tab and place it into a new tab
+    /// in the specified window, or in a new window if window_id
+    /// is None.
+    async fn move_pane_to_new_tab(
+        &self,
+        _pane_id: PaneId,
+        _window_id: Option<WindowId>,
+        _workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<Option<(Rc<Tab>, WindowId)>> {
+        Ok(None)
+    }
+
+    /// Remove the pane from its tab and insert it into the specified
+    /// tab by splitting its active pane
+    async fn move_pane_to_tab(&self, _pane_id: PaneId, _tab_id: TabId) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
     /// Returns false if the `spawn` method will never succeed.
     /// There are some internal placeholder domains that are
     /// pre-created with local UI that we do not want to allow
@@ -265,6 +331,17 @@ impl Domain for LocalDomain {
         size: PtySize,
         command: Option<CommandBuilder>,
         command_dir: Option<String>,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        self.spawn_pane_with_initial_actions(size, command, command_dir, vec![])
+            .await
+    }
+
+    async fn spawn_pane_with_initial_actions(
+        &self,
+        size: PtySize,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        actions: Vec<Action>,
     ) -> anyhow::Result<Rc<dyn Pane>> {
         let mut cmd = self.build_command(command, command_dir)?;
         let pair = self.pty_system.openpty(size)?;
@@ -298,6 +375,11 @@ impl Domain for LocalDomain {
         if self.is_conpty() {
             terminal.enable_conpty_quirks();
         }
+        // The output of the child isn't read until the pane is
+        // added to the mux, so these are guaranteed to come first
+        if !actions.is_empty() {
+            terminal.perform_actions(actions);
+        }
 
         let pane: Rc<dyn Pane> = Rc::new(LocalPane::new(
             pane_id,
@@ -3,7 +3,7 @@ use crate::pane::{Pane, PaneId};
 use crate::tab::{SplitDirection, Tab, TabId};
 use crate::window::{Window, WindowId};
 use anyhow::{anyhow, Context, Error};
-use config::keyassignment::SpawnTabDomain;
+use config::keyassignment::{PaneDirection, RotationDirection, SpawnTabDomain};
 use config::{configuration, ExitBehavior};
 use domain::{Domain, DomainId, DomainState};
 use filedescriptor::{socketpair, AsRawSocketDescriptor, FileDescriptor};
@@ -21,8 +21,7 @@ use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::thread;
 use std::time::Instant;
-use termwiz::escape::csi::{DecPrivateMode, DecPrivateModeCode, Device, Mode};
-use termwiz::escape::{Action, CSI};
+use termwiz::escape::Action;
 use thiserror::*;
 use wezterm_term::{Clipboard, ClipboardSelection, DownloadHandler};
 #[cfg(windows)]
@@ -35,9 +34,12 @@ pub mod domain;
 pub mod localpane;
 pub mod pane;
 pub mod renderable;
+pub mod session;
 pub mod ssh;
 pub mod tab;
 pub mod termwiztermtab;
+#[cfg(test)]
+mod test_util;
 pub mod tmux;
 pub mod tmux_commands;
 mod tmux_pty;
@@ -56,6 +58,7 @@ pub enum MuxNotification {
     WindowRemoved(WindowId),
     WindowInvalidated(WindowId),
     WindowWorkspaceChanged(WindowId),
+    TabTitleChanged(TabId),
     ActiveWorkspaceChanged(Arc<ClientId>),
     Alert {
         pane_id: PaneId,
@@ -95,10 +98,16 @@ const BUFSIZE: usize = 1024 * 1024;
 /// the pty in the mux.
 /// It blocks until the mux has finished consuming the data, which provides
 /// some back-pressure so that eg: ctrl-c can remain responsive.
-fn send_actions_to_mux(pane_id: PaneId, dead: &Arc<AtomicBool>, actions: Vec<Action>) {
+fn send_actions_to_mux(
+    pane_id: PaneId,
+    dead: &Arc<AtomicBool>,
+    sync_timer: &Arc<AtomicBool>,
+    actions: Vec<Action>,
+) {
     let start = Instant::now();
     promise::spawn::block_on(promise::spawn::spawn_into_main_thread({
         let dead = Arc::clone(&dead);
+        let sync_timer = Arc::clone(sync_timer);
         async move {
             let mux = Mux::get().unwrap();
             if let Some(pane) = mux.get_pane(pane_id) {
@@ -108,7 +117,21 @@ fn send_actions_to_mux(pane_id: PaneId, dead: &Arc<AtomicBool>, actions: Vec<Act
                     "send_actions_to_mux.perform_actions.latency",
                     start.elapsed()
                 );
-                mux.notify(MuxNotification::PaneOutput(pane_id));
+                match pane.synchronized_output_deadline() {
+                    Some(deadline) => {
+                        // The application is part way through a synchronized
+                        // update; hold off on telling anyone about the output
+                        // until it completes, but make sure that the output is
+                        // published once the update times out.
+                        if !sync_timer.swap(true, Ordering::Relaxed) {
+                            promise::spawn::spawn(publish_synchronized_output_on_timeout(
+                                pane_id, deadline, sync_timer,
+                            ))
+                            .detach();
+                        }
+                    }
+                    None => mux.notify(MuxNotification::PaneOutput(pane_id)),
+                }
             } else {
                 // Something else removed the pane from
                 // the mux, so signal that we should stop
@@ -121,11 +144,40 @@ fn send_actions_to_mux(pane_id: PaneId, dead: &Arc<AtomicBool>, actions: Vec<Act
     histogram!("send_actions_to_mux.rate", 1.);
 }
 
+/// Waits until the synchronized output update in the pane times out, and
+/// then publishes its output.  If another update has begun by then, waits
+/// for that one instead, so that each pane has at most one pending timer;
+/// `pending` is cleared once this completes.
+async fn publish_synchronized_output_on_timeout(
+    pane_id: PaneId,
+    mut deadline: Instant,
+    pending: Arc<AtomicBool>,
+) {
+    loop {
+        smol::Timer::at(deadline).await;
+        let mux = match Mux::get() {
+            Some(mux) => mux,
+            None => return,
+        };
+        match mux
+            .get_pane(pane_id)
+            .and_then(|pane| pane.synchronized_output_deadline())
+        {
+            Some(next) => deadline = next,
+            None => {
+                pending.store(false, Ordering::Relaxed);
+                mux.notify(MuxNotification::PaneOutput(pane_id));
+                return;
+            }
+        }
+    }
+}
+
 fn parse_buffered_data(pane_id: PaneId, dead: &Arc<AtomicBool>, mut rx: FileDescriptor) {
     let mut buf = vec![0; configuration().mux_output_parser_buffer_size];
     let mut parser = termwiz::escape::parser::Parser::new();
-    let mut actions = vec![];
-    let mut hold = false;
+    // Set while a timer is pending to publish a synchronized update
+    let sync_timer = Arc::new(AtomicBool::new(false));
 
     loop {
         match rx.read(&mut buf) {
@@ -138,39 +190,10 @@ fn parse_buffered_data(pane_id: PaneId, dead: &Arc<AtomicBool>, mut rx: FileDesc
                 break;
             }
             Ok(size) => {
-                parser.parse(&buf[0..size], |action| {
-                    let mut flush = false;
-                    match &action {
-                        Action::CSI(CSI::Mode(Mode::SetDecPrivateMode(DecPrivateMode::Code(
-                            DecPrivateModeCode::SynchronizedOutput,
-                        )))) => {
-                            hold = true;
-
-                            // Flush prior actions
-                            if !actions.is_empty() {
-                                send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
-                            }
-                        }
-                        Action::CSI(CSI::Mode(Mode::ResetDecPrivateMode(
-                            DecPrivateMode::Code(DecPrivateModeCode::SynchronizedOutput),
-                        ))) => {
-                            hold = false;
-                            flush = true;
-                        }
-                        Action::CSI(CSI::Device(dev)) if matches!(**dev, Device::SoftReset) => {
-                            hold = false;
-                            flush = true;
-                        }
-                        _ => {}
-                    };
-                    actions.push(action);
-
-                    if flush && !actions.is_empty() {
-                        send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
-                    }
-                });
-                if !actions.is_empty() && !hold {
-                    send_actions_to_mux(pane_id, dead, std::mem::take(&mut actions));
+                histogram!("parse_buffered_data.bytes.rate", size as f64);
+                let actions = parser.parse_as_vec(&buf[0..size]);
+                if !actions.is_empty() {
+                    send_actions_to_mux(pane_id, dead, &sync_timer, actions);
                 }
 
                 buf.resize(configuration().mux_output_parser_buffer_size, 0);
@@ -986,6 +1009,175 @@ impl Mux {
         Ok((pane, size))
     }
 
+    /// Returns the domain that owns the panes in the specified tab
+    fn resolve_tab_domain(&self, tab: &Tab) -> anyhow::Result<Arc<dyn Domain>> {
+        let pane = tab
+            .get_active_pane()
+            .ok_or_else(|| anyhow!("tab {} has no active pane", tab.tab_id()))?;
+        self.get_domain(pane.domain_id()).ok_or_else(|| {
+            anyhow!(
+                "domain {} of pane {} is invalid",
+                pane.domain_id(),
+                pane.pane_id()
+            )
+        })
+    }
+
+    fn notify_tab_changed(&self, tab_id: TabId) {
+        if let Some(window_id) = self.window_containing_tab(tab_id) {
+            self.notify(MuxNotification::WindowInvalidated(window_id));
+        }
+    }
+
+    pub async fn swap_active_pane_direction(
+        &self,
+        tab_id: TabId,
+        direction: PaneDirection,
+    ) -> anyhow::Result<()> {
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab {} is invalid", tab_id))?;
+        let domain = self.resolve_tab_domain(&tab)?;
+        if !domain.swap_active_pane_direction(tab_id, direction).await? {
+            tab.swap_active_pane_direction(direction);
+        }
+        self.notify_tab_changed(tab_id);
+        Ok(())
+    }
+
+    pub async fn rotate_panes(
+        &self,
+        tab_id: TabId,
+        direction: RotationDirection,
+    ) -> anyhow::Result<()> {
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab {} is invalid", tab_id))?;
+        let domain = self.resolve_tab_domain(&tab)?;
+        if !domain.rotate_panes(tab_id, direction).await? {
+            match direction {
+                RotationDirection::Clockwise => tab.rotate_clockwise(),
+                RotationDirection::CounterClockwise => tab.rotate_counter_clockwise(),
+            }
+        }
+        self.notify_tab_changed(tab_id);
+        Ok(())
+    }
+
+    /// Removes the pane from its tab and places it into a new tab.
+    /// The new tab is added to window_id if specified, otherwise
+    /// a new window is created in workspace_for_new_window.
+    pub async fn move_pane_to_new_tab(
+        &self,
+        pane_id: PaneId,
+        window_id: Option<WindowId>,
+        workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<(Rc<Tab>, WindowId)> {
+        let (domain_id, src_window_id, src_tab_id) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} is invalid", pane_id))?;
+        let domain = self
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("domain {} of pane {} is invalid", domain_id, pane_id))?;
+
+        if let Some((tab, window_id)) = domain
+            .move_pane_to_new_tab(pane_id, window_id, workspace_for_new_window.clone())
+            .await?
+        {
+            return Ok((tab, window_id));
+        }
+
+        let src_tab = self
+            .get_tab(src_tab_id)
+            .ok_or_else(|| anyhow!("tab {} is invalid", src_tab_id))?;
+        let size = src_tab.get_size();
+        let pane = src_tab
+            .remove_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane {} is not in tab {}", pane_id, src_tab_id))?;
+
+        // Keep the builder alive until the window is populated
+        let window_builder;
+        let window_id = match window_id {
+            Some(window_id) => window_id,
+            None => {
+                window_builder = self.new_empty_window(workspace_for_new_window);
+                *window_builder
+            }
+        };
+
+        let tab = Rc::new(Tab::new(&size));
+        pane.resize(size)?;
+        tab.assign_pane(&pane);
+        self.add_tab_no_panes(&tab);
+        self.add_tab_to_window(&tab, window_id)?;
+
+        self.notify(MuxNotification::WindowInvalidated(src_window_id));
+        self.notify(MuxNotification::WindowInvalidated(window_id));
+        // The source tab may now be empty
+        self.prune_dead_windows();
+
+        Ok((tab, window_id))
+    }
+
+    /// Removes the pane from its tab and inserts it into the specified
+    /// tab, splitting the active pane of that tab along its longest
+    /// axis to make room for it.
+    pub async fn move_pane_to_tab(&self, pane_id: PaneId, tab_id: TabId) -> anyhow::Result<()> {
+        let (domain_id, src_window_id, src_tab_id) = self
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} is invalid", pane_id))?;
+        if src_tab_id == tab_id {
+            anyhow::bail!("pane {} is already in tab {}", pane_id, tab_id);
+        }
+        let domain = self
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("domain {} of pane {} is invalid", domain_id, pane_id))?;
+        let tab = self
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab {} is invalid", tab_id))?;
+        if self.resolve_tab_domain(&tab)?.domain_id() != domain_id {
+            anyhow::bail!(
+                "cannot move pane {} into tab {} because they belong to different domains",
+                pane_id,
+                tab_id
+            );
+        }
+
+        if !domain.move_pane_to_tab(pane_id, tab_id).await? {
+            let src_tab = self
+                .get_tab(src_tab_id)
+                .ok_or_else(|| anyhow!("tab {} is invalid", src_tab_id))?;
+            let pane = self
+                .get_pane(pane_id)
+                .ok_or_else(|| anyhow!("pane {} is invalid", pane_id))?;
+
+            // Ensure that we're not zoomed, otherwise we can't split
+            tab.set_zoomed(false);
+            let target = tab
+                .iter_panes()
+                .into_iter()
+                .find(|pos| pos.is_active)
+                .ok_or_else(|| anyhow!("tab {} has no active pane", tab_id))?;
+            // Cells are roughly twice as tall as they are wide
+            let direction = if target.width >= target.height * 2 {
+                SplitDirection::Horizontal
+            } else {
+                SplitDirection::Vertical
+            };
+
+            // Insert first, so that we leave things as they were
+            // if there is no room for the split
+            tab.split_and_insert(target.index, direction, Rc::clone(&pane))?;
+            src_tab.remove_pane(pane_id);
+        }
+
+        self.notify(MuxNotification::WindowInvalidated(src_window_id));
+        self.notify_tab_changed(tab_id);
+        // The source tab may now be empty
+        self.prune_dead_windows();
+        Ok(())
+    }
+
     pub async fn spawn_tab_or_window(
         &self,
         window_id: Option<WindowId>,
@@ -23,8 +23,8 @@ use termwiz::surface::{Line, SequenceNo, SEQ_ZERO};
 use url::Url;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Alert, AlertHandler, CellAttributes, Clipboard, DownloadHandler, KeyCode, KeyModifiers,
-    MouseEvent, SemanticZone, StableRowIndex, Terminal, TerminalConfiguration,
+    Alert, AlertHandler, CellAttributes, Clipboard, CommandRecord, DownloadHandler, KeyCode,
+    KeyModifiers, MouseEvent, SemanticZone, StableRowIndex, Terminal, TerminalConfiguration,
 };
 
 #[derive(Debug)]
@@ -252,6 +252,10 @@ impl Pane for LocalPane {
         self.terminal.borrow_mut().perform_actions(actions)
     }
 
+    fn synchronized_output_deadline(&self) -> Option<Instant> {
+        self.terminal.borrow().synchronized_output_deadline()
+    }
+
     fn mouse_event(&self, event: MouseEvent) -> Result<(), Error> {
         Mux::get().unwrap().record_input_for_current_identity();
         self.terminal.borrow_mut().mouse_event(event)
@@ -387,6 +391,11 @@ impl Pane for LocalPane {
         None
     }
 
+    fn get_process_info(&self) -> Option<LocalProcessInfo> {
+        self.divine_process_list(false)
+            .map(|info| info.root.clone())
+    }
+
     fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
         if let Some(info) = self.divine_process_list(true) {
             log::trace!(
@@ -461,6 +470,10 @@ impl Pane for LocalPane {
         term.get_semantic_zones()
     }
 
+    async fn get_commands(&self) -> anyhow::Result<Vec<CommandRecord>> {
+        Ok(self.terminal.borrow().get_commands())
+    }
+
     async fn search(&self, mut pattern: Pattern) -> anyhow::Result<Vec<SearchResult>> {
         let term = self.terminal.borrow();
         let screen = term.screen();
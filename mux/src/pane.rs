@@ -5,20 +5,22 @@ use async_trait::async_trait;
 use config::keyassignment::ScrollbackEraseMode;
 use downcast_rs::{impl_downcast, Downcast};
 use portable_pty::PtySize;
+use procinfo::LocalProcessInfo;
 use rangeset::RangeSet;
 use serde::{Deserialize, Serialize};
 use std::cell::RefMut;
 use std::collections::HashMap;
 use std::ops::Range;
 use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use termwiz::hyperlink::Rule;
 use termwiz::input::KeyboardEncoding;
 use termwiz::surface::{Line, SequenceNo, SEQ_ZERO};
 use url::Url;
 use wezterm_term::color::ColorPalette;
 use wezterm_term::{
-    Clipboard, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, SemanticZone, StableRowIndex,
-    TerminalConfiguration,
+    Clipboard, CommandRecord, DownloadHandler, KeyCode, KeyModifiers, MouseEvent, SemanticZone,
+    StableRowIndex, TerminalConfiguration,
 };
 
 static PANE_ID: ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
@@ -352,6 +354,13 @@ pub trait Pane: Downcast {
         false
     }
 
+    /// If the pane is part way through a synchronized output update,
+    /// returns the time at which the update will be published even
+    /// if the application hasn't completed it by then.
+    fn synchronized_output_deadline(&self) -> Option<Instant> {
+        None
+    }
+
     /// Certain panes are OK to be closed with impunity (no prompts)
     fn can_close_without_prompting(&self, _reason: CloseReason) -> bool {
         false
@@ -369,6 +378,12 @@ pub trait Pane: Downcast {
         Ok(vec![])
     }
 
+    /// Retrieve the log of commands that were reported by the shell
+    /// using OSC 133 semantic prompt escapes, oldest first
+    async fn get_commands(&self) -> anyhow::Result<Vec<CommandRecord>> {
+        Ok(vec![])
+    }
+
     /// Returns true if the terminal has grabbed the mouse and wants to
     /// give the embedded application a chance to process events.
     /// In practice this controls whether the gui will perform local
@@ -388,6 +403,12 @@ pub trait Pane: Downcast {
         None
     }
 
+    /// Returns information about the process that was spawned
+    /// into this pane, if it is a local process.
+    fn get_process_info(&self) -> Option<LocalProcessInfo> {
+        None
+    }
+
     fn trickle_paste(&self, text: String) -> anyhow::Result<()> {
         if text.len() <= PASTE_CHUNK_SIZE {
             // Send it all now
@@ -38,6 +38,16 @@ impl_lua_conversion!(RenderableDimensions);
 
 /// Implements Pane::get_cursor_position for Terminal
 pub fn terminal_get_cursor_position(term: &mut Terminal) -> StableCursorPosition {
+    if let Some(snapshot) = term.synchronized_output_snapshot() {
+        let pos = snapshot.cursor;
+        return StableCursorPosition {
+            x: pos.x,
+            y: snapshot.top + pos.y as StableRowIndex,
+            shape: pos.shape,
+            visibility: pos.visibility,
+        };
+    }
+
     let pos = term.cursor_pos();
 
     StableCursorPosition {
@@ -54,9 +64,13 @@ pub fn terminal_get_dirty_lines(
     lines: Range<StableRowIndex>,
     seqno: SequenceNo,
 ) -> RangeSet<StableRowIndex> {
+    let mut set = RangeSet::new();
+    if term.is_synchronized_output_active() {
+        // Changes are held back until the update is complete
+        return set;
+    }
     let screen = term.screen();
     let lines = screen.get_changed_stable_rows(lines, seqno);
-    let mut set = RangeSet::new();
     for line in lines {
         set.add(line);
     }
@@ -68,12 +82,47 @@ pub fn terminal_get_lines(
     term: &mut Terminal,
     lines: Range<StableRowIndex>,
 ) -> (StableRowIndex, Vec<Line>) {
-    let reverse = term.get_reverse_video();
-    let screen = term.screen_mut();
-    let phys_range = screen.stable_range(&lines);
+    // While a synchronized update is in progress, the rows of the
+    // visible screen are served from the snapshot taken when it began.
+    // Rows above that are scrollback that the update cannot change.
+    let (reverse, held) = match term.synchronized_output_snapshot() {
+        Some(snapshot) => {
+            let top = snapshot.top;
+            let start = lines.start.max(top);
+            let end = lines.end.min(top + snapshot.lines.len() as StableRowIndex);
+            let held: Vec<Line> = if start < end {
+                snapshot.lines[(start - top) as usize..(end - top) as usize].to_vec()
+            } else {
+                vec![]
+            };
+            (snapshot.reverse_video, Some((top, start, held)))
+        }
+        None => (term.get_reverse_video(), None),
+    };
 
-    let first = screen.phys_to_stable_row_index(phys_range.start);
-    let mut lines = screen.lines_in_phys_range(phys_range);
+    let screen = term.screen_mut();
+    let (first, mut lines) = match held {
+        None => {
+            let phys_range = screen.stable_range(&lines);
+            (
+                screen.phys_to_stable_row_index(phys_range.start),
+                screen.lines_in_phys_range(phys_range),
+            )
+        }
+        Some((top, start, held)) => {
+            let (first, mut live) = if lines.start < top {
+                let phys_range = screen.stable_range(&(lines.start..lines.end.min(top)));
+                (
+                    screen.phys_to_stable_row_index(phys_range.start),
+                    screen.lines_in_phys_range(phys_range),
+                )
+            } else {
+                (start, vec![])
+            };
+            live.extend(held);
+            (first, live)
+        }
+    };
     for line in &mut lines {
         line.set_reverse(reverse, SEQ_ZERO);
     }
@@ -84,6 +133,16 @@ pub fn terminal_get_lines(
 /// Implements Pane::get_dimensions for Terminal
 pub fn terminal_get_dimensions(term: &mut Terminal) -> RenderableDimensions {
     let screen = term.screen();
+    if let Some(snapshot) = term.synchronized_output_snapshot() {
+        let scrollback_top = screen.phys_to_stable_row_index(0).min(snapshot.top);
+        return RenderableDimensions {
+            cols: snapshot.physical_cols,
+            viewport_rows: snapshot.lines.len(),
+            scrollback_rows: (snapshot.top - scrollback_top) as usize + snapshot.lines.len(),
+            physical_top: snapshot.top,
+            scrollback_top,
+        };
+    }
     RenderableDimensions {
         cols: screen.physical_cols,
         viewport_rows: screen.physical_rows,
@@ -92,3 +151,81 @@ pub fn terminal_get_dimensions(term: &mut Terminal) -> RenderableDimensions {
         scrollback_top: screen.phys_to_stable_row_index(0),
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::sync::Arc;
+    use wezterm_term::color::ColorPalette;
+    use wezterm_term::{TerminalConfiguration, TerminalSize};
+
+    #[derive(Debug)]
+    struct TestConfig;
+
+    impl TerminalConfiguration for TestConfig {
+        fn scrollback_size(&self) -> usize {
+            10
+        }
+
+        fn color_palette(&self) -> ColorPalette {
+            ColorPalette::default()
+        }
+    }
+
+    fn text(lines: &[Line]) -> Vec<String> {
+        lines
+            .iter()
+            .map(|line| line.as_str().trim_end().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn synchronized_output_serves_snapshot() {
+        let mut term = Terminal::new(
+            TerminalSize {
+                physical_rows: 3,
+                physical_cols: 4,
+                pixel_width: 32,
+                pixel_height: 48,
+            },
+            Arc::new(TestConfig),
+            "WezTerm",
+            "O_o",
+            Box::new(std::io::sink()),
+        );
+        term.advance_bytes("1\r\n2\r\n3\r\n4\r\n5");
+        let seqno = term.current_seqno();
+
+        // Scroll the screen part way through an update
+        term.advance_bytes("\x1b[?2026h\r\n6\x1b[H");
+
+        let dims = terminal_get_dimensions(&mut term);
+        assert_eq!(dims.physical_top, 2);
+        assert_eq!(dims.scrollback_top, 0);
+        assert_eq!(dims.scrollback_rows, 5);
+        assert_eq!(dims.viewport_rows, 3);
+
+        let cursor = terminal_get_cursor_position(&mut term);
+        assert_eq!((cursor.x, cursor.y), (1, 4));
+
+        let (first, lines) = terminal_get_lines(&mut term, 0..5);
+        assert_eq!(first, 0);
+        assert_eq!(text(&lines), vec!["1", "2", "3", "4", "5"]);
+        let (first, lines) = terminal_get_lines(&mut term, 3..6);
+        assert_eq!(first, 3);
+        assert_eq!(text(&lines), vec!["4", "5"]);
+
+        assert!(terminal_get_dirty_lines(&mut term, 0..6, seqno).is_empty());
+
+        // Ending the update publishes the changes
+        term.advance_bytes("\x1b[?2026l");
+        let dims = terminal_get_dimensions(&mut term);
+        assert_eq!(dims.physical_top, 3);
+        let cursor = terminal_get_cursor_position(&mut term);
+        assert_eq!((cursor.x, cursor.y), (0, 3));
+        let (first, lines) = terminal_get_lines(&mut term, 3..6);
+        assert_eq!(first, 3);
+        assert_eq!(text(&lines), vec!["4", "5", "6"]);
+        assert!(!terminal_get_dirty_lines(&mut term, 0..6, seqno).is_empty());
+    }
+}
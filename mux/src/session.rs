@@ -0,0 +1,672 @@
+//! Saving and restoring the layout of the mux.
+//!
+//! A session captures the windows, tabs and split layout of each
+//! workspace, along with enough information about the process running
+//! in each pane to respawn it in its last known working directory.
+//! This allows `wezterm-mux-server` to rebuild its state after it
+//! has been restarted.
+use crate::pane::{Pane, PaneId};
+use crate::tab::{PaneNode, Tab};
+use crate::window::WindowId;
+use crate::Mux;
+use anyhow::{anyhow, bail, Context};
+use portable_pty::{CommandBuilder, PtySize};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use termwiz::escape::{Action, ControlCode};
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct SessionState {
+    pub active_workspace: String,
+    pub windows: Vec<WindowState>,
+    /// Per-pane state, keyed by the pane id recorded in the
+    /// `PaneNode` trees of the tabs.
+    pub panes: HashMap<PaneId, PaneState>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct WindowState {
+    pub workspace: String,
+    pub active_tab_idx: usize,
+    pub tabs: Vec<TabState>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct TabState {
+    pub title: String,
+    pub size: PtySize,
+    pub root: PaneNode,
+}
+
+#[derive(Deserialize, Serialize, Debug, Default)]
+pub struct PaneState {
+    pub domain_name: String,
+    /// The argument vector of the process that was spawned into the
+    /// pane.  An empty argv means that the default program should be
+    /// used when respawning it.
+    pub argv: Vec<String>,
+    pub cwd: Option<String>,
+    pub scrollback: Option<String>,
+}
+
+impl SessionState {
+    /// Captures the current state of the mux
+    pub fn capture(include_scrollback: bool) -> Self {
+        let mux = Mux::get().expect("to be called on main thread");
+        let mut state = SessionState {
+            active_workspace: mux.active_workspace(),
+            ..Default::default()
+        };
+
+        for window_id in mux.iter_windows() {
+            let window = match mux.get_window(window_id) {
+                Some(w) => w,
+                None => continue,
+            };
+            let mut window_state = WindowState {
+                workspace: window.get_workspace().to_string(),
+                active_tab_idx: window.get_active_idx(),
+                tabs: vec![],
+            };
+            let tabs: Vec<Rc<Tab>> = window.iter().cloned().collect();
+            // codec_pane_tree needs to borrow the window again
+            drop(window);
+
+            for tab in tabs {
+                let root = tab.codec_pane_tree();
+                if root == PaneNode::Empty {
+                    continue;
+                }
+                for pos in tab.iter_panes_ignoring_zoom() {
+                    state.panes.insert(
+                        pos.pane.pane_id(),
+                        PaneState::capture(&pos.pane, include_scrollback),
+                    );
+                }
+                window_state.tabs.push(TabState {
+                    title: tab.get_title(),
+                    size: tab.get_size(),
+                    root,
+                });
+            }
+
+            if !window_state.tabs.is_empty() {
+                window_state.active_tab_idx =
+                    window_state.active_tab_idx.min(window_state.tabs.len() - 1);
+                state.windows.push(window_state);
+            }
+        }
+
+        state
+    }
+
+    pub fn load_from_file(path: &Path) -> anyhow::Result<Self> {
+        let data = std::fs::read(path)
+            .with_context(|| format!("reading session file {}", path.display()))?;
+        serde_json::from_slice(&data)
+            .with_context(|| format!("parsing session file {}", path.display()))
+    }
+
+    /// Writes the session to the specified path.
+    /// The data is written to a temporary file alongside the target
+    /// which is then renamed into place, so that a crash part way
+    /// through doesn't leave a truncated session file behind.
+    pub fn save_to_file(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(parent) = path.parent() {
+            config::create_user_owned_dirs(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+        let data = serde_json::to_vec_pretty(self)?;
+        let temp = path.with_extension("tmp");
+        std::fs::write(&temp, data)
+            .with_context(|| format!("writing session file {}", temp.display()))?;
+        std::fs::rename(&temp, path)
+            .with_context(|| format!("renaming {} to {}", temp.display(), path.display()))?;
+        Ok(())
+    }
+
+    /// Rebuilds the windows, tabs and panes described by this session,
+    /// respawning the command for each pane.
+    /// Returns the number of windows that were created.
+    pub async fn restore(mut self) -> anyhow::Result<usize> {
+        let mux = Mux::get().expect("to be called on main thread");
+        let mut num_windows = 0;
+
+        for window_state in self.windows {
+            // Keep the builder alive until the window is populated, so that
+            // the WindowCreated notification isn't sent for an empty window
+            let window_builder = mux.new_empty_window(Some(window_state.workspace.clone()));
+            let window_id = *window_builder;
+            num_windows += 1;
+
+            for tab_state in window_state.tabs {
+                if let Err(err) = restore_tab(tab_state, &mut self.panes, window_id).await {
+                    // Don't leave a partially restored window behind
+                    mux.kill_window(window_id);
+                    return Err(err);
+                }
+            }
+
+            if let Some(mut window) = mux.get_window_mut(window_id) {
+                if window_state.active_tab_idx < window.len() {
+                    window.set_active_without_saving(window_state.active_tab_idx);
+                }
+            }
+        }
+
+        if !self.active_workspace.is_empty() {
+            mux.set_active_workspace(&self.active_workspace);
+        }
+
+        Ok(num_windows)
+    }
+}
+
+impl PaneState {
+    fn capture(pane: &Rc<dyn Pane>, include_scrollback: bool) -> Self {
+        let mux = Mux::get().expect("to be called on main thread");
+        let domain_name = mux
+            .get_domain(pane.domain_id())
+            .map(|domain| domain.domain_name().to_string())
+            .unwrap_or_default();
+
+        let info = pane.get_process_info();
+
+        let argv = match &info {
+            // A leading dash in argv[0] is the convention for a login
+            // shell, which is how the default program is spawned.
+            // We can't exec that name directly, so we record that
+            // the default program should be used instead.
+            Some(info) if !info.argv.is_empty() && !info.argv[0].starts_with('-') => {
+                info.argv.clone()
+            }
+            _ => vec![],
+        };
+
+        let cwd = pane
+            .get_current_working_dir()
+            .and_then(|url| url.to_file_path().ok())
+            .or_else(|| {
+                info.as_ref()
+                    .map(|info| info.cwd.clone())
+                    .filter(|cwd| !cwd.as_os_str().is_empty())
+            })
+            .map(|cwd| cwd.to_string_lossy().to_string());
+
+        let scrollback = if include_scrollback {
+            Some(capture_scrollback(pane))
+        } else {
+            None
+        };
+
+        Self {
+            domain_name,
+            argv,
+            cwd,
+            scrollback,
+        }
+    }
+
+    async fn spawn(self, size: PtySize) -> anyhow::Result<Rc<dyn Pane>> {
+        let mux = Mux::get().expect("to be called on main thread");
+        let domain = mux
+            .get_domain_by_name(&self.domain_name)
+            .filter(|domain| domain.spawnable())
+            .unwrap_or_else(|| mux.default_domain());
+
+        let command = if self.argv.is_empty() {
+            None
+        } else {
+            Some(CommandBuilder::from_argv(
+                self.argv.iter().map(Into::into).collect(),
+            ))
+        };
+
+        // The scrollback is replayed by the domain ahead of the output
+        // of the new program, so that the two don't interleave
+        let mut actions = vec![];
+        if let Some(text) = &self.scrollback {
+            for line in text.lines() {
+                actions.extend(line.chars().map(Action::Print));
+                actions.push(Action::Control(ControlCode::CarriageReturn));
+                actions.push(Action::Control(ControlCode::LineFeed));
+            }
+        }
+
+        match domain
+            .spawn_pane_with_initial_actions(size, command, self.cwd.clone(), actions.clone())
+            .await
+        {
+            Ok(pane) => Ok(pane),
+            Err(err) => {
+                log::error!(
+                    "failed to respawn {:?} in {:?}: {:#}; using the default program",
+                    self.argv,
+                    self.cwd,
+                    err
+                );
+                domain
+                    .spawn_pane_with_initial_actions(size, None, None, actions)
+                    .await
+            }
+        }
+    }
+}
+
+/// Spawns the panes of a tab and adds the tab to the specified window.
+/// If that fails, the panes that were spawned for it are killed.
+async fn restore_tab(
+    tab_state: TabState,
+    pane_states: &mut HashMap<PaneId, PaneState>,
+    window_id: WindowId,
+) -> anyhow::Result<()> {
+    let mux = Mux::get().expect("to be called on main thread");
+    let mut spawned = vec![];
+
+    let tab = match build_tab(tab_state, pane_states, &mut spawned).await {
+        Ok(tab) => tab,
+        Err(err) => {
+            for pane in spawned {
+                if mux.get_pane(pane.pane_id()).is_some() {
+                    mux.remove_pane(pane.pane_id());
+                } else {
+                    pane.kill();
+                }
+            }
+            return Err(err);
+        }
+    };
+
+    mux.add_tab_no_panes(&tab);
+    if let Err(err) = mux.add_tab_to_window(&tab, window_id) {
+        mux.remove_tab(tab.tab_id());
+        return Err(err);
+    }
+    Ok(())
+}
+
+/// Spawns a pane for each leaf of the layout and assembles them
+/// into a tab.  Each spawned pane is recorded in `spawned` so that
+/// the caller can clean up if this fails part way through.
+async fn build_tab(
+    tab_state: TabState,
+    pane_states: &mut HashMap<PaneId, PaneState>,
+    spawned: &mut Vec<Rc<dyn Pane>>,
+) -> anyhow::Result<Rc<Tab>> {
+    let mux = Mux::get().expect("to be called on main thread");
+
+    let mut pane_ids = vec![];
+    collect_pane_ids(&tab_state.root, &mut pane_ids);
+
+    let mut panes = HashMap::new();
+    for (pane_id, size) in pane_ids {
+        if panes.contains_key(&pane_id) {
+            bail!("the session lists pane {} more than once", pane_id);
+        }
+        let pane_state = pane_states.remove(&pane_id).unwrap_or_default();
+        let pane = pane_state.spawn(size).await?;
+        spawned.push(Rc::clone(&pane));
+        panes.insert(pane_id, pane);
+    }
+
+    let tab = Rc::new(Tab::new(&tab_state.size));
+    tab.try_sync_with_pane_tree(
+        tab_state.size,
+        tab_state.root,
+        |entry| -> anyhow::Result<Rc<dyn Pane>> {
+            let pane = panes
+                .remove(&entry.pane_id)
+                .ok_or_else(|| anyhow!("no pane was spawned for {}", entry.pane_id))?;
+            mux.add_pane(&pane)?;
+            Ok(pane)
+        },
+    )?;
+    if !tab_state.title.is_empty() {
+        tab.set_title(&tab_state.title);
+    }
+    Ok(tab)
+}
+
+fn collect_pane_ids(node: &PaneNode, ids: &mut Vec<(PaneId, PtySize)>) {
+    match node {
+        PaneNode::Empty => {}
+        PaneNode::Split { left, right, .. } => {
+            collect_pane_ids(left, ids);
+            collect_pane_ids(right, ids);
+        }
+        PaneNode::Leaf(entry) => ids.push((entry.pane_id, entry.size)),
+    }
+}
+
+fn capture_scrollback(pane: &Rc<dyn Pane>) -> String {
+    let dims = pane.get_dimensions();
+    let end = dims.physical_top + dims.viewport_rows as isize;
+    let (_first, lines) = pane.get_lines(dims.scrollback_top..end);
+
+    let mut text = String::new();
+    for line in &lines {
+        text.push_str(line.as_str().trim_end());
+        text.push('\n');
+    }
+    // Don't preserve the blank lines at the bottom of the screen
+    let len = text.trim_end().len();
+    text.truncate(len);
+    text
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState};
+    use crate::pane::alloc_pane_id;
+    use crate::tab::{PaneEntry, SplitDirection, SplitDirectionAndSize};
+    use crate::test_util::FakePane;
+    use async_trait::async_trait;
+    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    /// What a FakeDomain was asked to spawn
+    struct Spawned {
+        pane_id: PaneId,
+        killed: Arc<AtomicBool>,
+        argv: Vec<String>,
+        cwd: Option<String>,
+        actions: Vec<Action>,
+    }
+
+    struct FakeDomain {
+        id: DomainId,
+        spawned: Mutex<Vec<Spawned>>,
+        max_panes: AtomicUsize,
+    }
+
+    #[async_trait(?Send)]
+    impl Domain for FakeDomain {
+        async fn spawn_pane(
+            &self,
+            size: PtySize,
+            command: Option<CommandBuilder>,
+            command_dir: Option<String>,
+        ) -> anyhow::Result<Rc<dyn Pane>> {
+            self.spawn_pane_with_initial_actions(size, command, command_dir, vec![])
+                .await
+        }
+
+        async fn spawn_pane_with_initial_actions(
+            &self,
+            size: PtySize,
+            command: Option<CommandBuilder>,
+            command_dir: Option<String>,
+            actions: Vec<Action>,
+        ) -> anyhow::Result<Rc<dyn Pane>> {
+            let argv: Vec<String> = command
+                .map(|cmd| {
+                    cmd.get_argv()
+                        .iter()
+                        .map(|arg| arg.to_string_lossy().to_string())
+                        .collect()
+                })
+                .unwrap_or_default();
+            let mut spawned = self.spawned.lock().unwrap();
+            if spawned.len() >= self.max_panes.load(Ordering::Relaxed) {
+                bail!("refusing to spawn {:?}", argv);
+            }
+            let killed = Arc::new(AtomicBool::new(false));
+            let pane = Rc::new(FakePane {
+                id: alloc_pane_id(),
+                size: RefCell::new(size),
+                killed: Arc::clone(&killed),
+            });
+            spawned.push(Spawned {
+                pane_id: pane.id,
+                killed,
+                argv,
+                cwd: command_dir,
+                actions,
+            });
+            Ok(pane)
+        }
+
+        fn domain_id(&self) -> DomainId {
+            self.id
+        }
+
+        fn domain_name(&self) -> &str {
+            "fake"
+        }
+
+        async fn attach(&self, _window_id: Option<WindowId>) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn detach(&self) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn state(&self) -> DomainState {
+            DomainState::Attached
+        }
+    }
+
+    fn size(cols: u16, rows: u16) -> PtySize {
+        PtySize {
+            rows,
+            cols,
+            pixel_width: cols * 10,
+            pixel_height: rows * 20,
+        }
+    }
+
+    fn leaf(pane_id: PaneId, size: PtySize, is_active_pane: bool) -> PaneNode {
+        PaneNode::Leaf(PaneEntry {
+            window_id: 0,
+            tab_id: 0,
+            pane_id,
+            title: String::new(),
+            size,
+            working_dir: None,
+            is_active_pane,
+            is_zoomed_pane: false,
+            workspace: "default".to_string(),
+            cursor_pos: Default::default(),
+            physical_top: 0,
+        })
+    }
+
+    /// A tab with pane 10 on the left, and panes 11 and 12
+    /// stacked on the right
+    fn split_session() -> SessionState {
+        let root = PaneNode::Split {
+            left: Box::new(leaf(10, size(40, 24), false)),
+            right: Box::new(PaneNode::Split {
+                left: Box::new(leaf(11, size(39, 12), true)),
+                right: Box::new(leaf(12, size(39, 11), false)),
+                node: SplitDirectionAndSize {
+                    direction: SplitDirection::Vertical,
+                    first: size(39, 12),
+                    second: size(39, 11),
+                },
+            }),
+            node: SplitDirectionAndSize {
+                direction: SplitDirection::Horizontal,
+                first: size(40, 24),
+                second: size(39, 24),
+            },
+        };
+
+        let mut panes = HashMap::new();
+        panes.insert(
+            10,
+            PaneState {
+                domain_name: "fake".to_string(),
+                argv: vec!["vim".to_string(), "notes.txt".to_string()],
+                cwd: Some("/tmp".to_string()),
+                scrollback: None,
+            },
+        );
+        panes.insert(
+            11,
+            PaneState {
+                domain_name: "fake".to_string(),
+                argv: vec![],
+                cwd: None,
+                scrollback: Some("$ ls\nfoo".to_string()),
+            },
+        );
+
+        SessionState {
+            active_workspace: "work".to_string(),
+            windows: vec![WindowState {
+                workspace: "work".to_string(),
+                active_tab_idx: 0,
+                tabs: vec![TabState {
+                    title: "editing".to_string(),
+                    size: size(80, 24),
+                    root,
+                }],
+            }],
+            panes,
+        }
+    }
+
+    fn set_up_mux() -> Arc<FakeDomain> {
+        config::use_test_configuration();
+        // The mux schedules some housekeeping onto the main thread,
+        // which these tests have no need to run
+        drop(promise::spawn::SimpleExecutor::new());
+        let domain = Arc::new(FakeDomain {
+            id: alloc_domain_id(),
+            spawned: Mutex::new(vec![]),
+            max_panes: AtomicUsize::new(usize::MAX),
+        });
+        let default_domain: Arc<dyn Domain> = domain.clone();
+        let mux = Rc::new(Mux::new(Some(default_domain)));
+        Mux::set_mux(&mux);
+        domain
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let session = split_session();
+        let json = serde_json::to_string(&session).unwrap();
+        let parsed: SessionState = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed.active_workspace, "work");
+        assert_eq!(parsed.windows.len(), 1);
+        assert_eq!(parsed.windows[0].tabs[0].title, "editing");
+        assert_eq!(parsed.windows[0].tabs[0].size, size(80, 24));
+        assert_eq!(
+            parsed.windows[0].tabs[0].root,
+            session.windows[0].tabs[0].root
+        );
+        assert_eq!(parsed.panes[&10].argv, vec!["vim", "notes.txt"]);
+        assert_eq!(parsed.panes[&10].cwd.as_deref(), Some("/tmp"));
+        assert_eq!(parsed.panes[&11].scrollback.as_deref(), Some("$ ls\nfoo"));
+        assert_eq!(
+            serde_json::to_value(&parsed).unwrap(),
+            serde_json::to_value(&session).unwrap()
+        );
+    }
+
+    #[test]
+    fn restore_split_layout() {
+        let domain = set_up_mux();
+        let mux = Mux::get().unwrap();
+
+        let num_windows = smol::block_on(split_session().restore()).unwrap();
+        assert_eq!(num_windows, 1);
+
+        let window_ids = mux.iter_windows();
+        assert_eq!(window_ids.len(), 1);
+        let window = mux.get_window(window_ids[0]).unwrap();
+        assert_eq!(window.get_workspace(), "work");
+        assert_eq!(window.len(), 1);
+        let tab = Rc::clone(window.iter().next().unwrap());
+        drop(window);
+        assert_eq!(tab.get_title(), "editing");
+
+        let spawned = domain.spawned.lock().unwrap();
+        assert_eq!(spawned.len(), 3);
+        assert_eq!(spawned[0].argv, vec!["vim", "notes.txt"]);
+        assert_eq!(spawned[0].cwd.as_deref(), Some("/tmp"));
+        // Pane 11 has no saved argv, so it gets the default program,
+        // and its scrollback is replayed by the domain
+        assert!(spawned[1].argv.is_empty());
+        let replayed: String = spawned[1]
+            .actions
+            .iter()
+            .map(|action| match action {
+                Action::Print(c) => *c,
+                Action::Control(ControlCode::LineFeed) => '\n',
+                _ => ' ',
+            })
+            .collect();
+        assert_eq!(replayed, "$ ls \nfoo \n");
+        // Pane 12 wasn't recorded in the session at all
+        assert!(spawned[2].argv.is_empty());
+        assert!(spawned[2].actions.is_empty());
+
+        let panes = tab.iter_panes();
+        assert_eq!(panes.len(), 3);
+        let layout: Vec<_> = panes
+            .iter()
+            .map(|p| {
+                (
+                    p.pane.pane_id(),
+                    p.left,
+                    p.top,
+                    p.width,
+                    p.height,
+                    p.is_active,
+                )
+            })
+            .collect();
+        assert_eq!(
+            layout,
+            vec![
+                (spawned[0].pane_id, 0, 0, 40, 24, false),
+                (spawned[1].pane_id, 41, 0, 39, 12, true),
+                (spawned[2].pane_id, 41, 13, 39, 11, false),
+            ]
+        );
+        for s in spawned.iter() {
+            assert!(mux.get_pane(s.pane_id).is_some());
+        }
+
+        Mux::shutdown();
+    }
+
+    #[test]
+    fn restore_failure_cleans_up() {
+        // Pane 12 can't be spawned after the others have been
+        let domain = set_up_mux();
+        domain.max_panes.store(2, Ordering::Relaxed);
+        let mux = Mux::get().unwrap();
+        assert!(smol::block_on(split_session().restore()).is_err());
+        assert!(mux.iter_windows().is_empty());
+        assert!(mux.iter_panes().is_empty());
+        let spawned = domain.spawned.lock().unwrap();
+        assert_eq!(spawned.len(), 2);
+        assert!(spawned.iter().all(|s| s.killed.load(Ordering::Relaxed)));
+        drop(spawned);
+        Mux::shutdown();
+
+        // A layout that lists the same pane twice is rejected
+        let domain = set_up_mux();
+        let mux = Mux::get().unwrap();
+        let mut session = split_session();
+        if let PaneNode::Split { right, .. } = &mut session.windows[0].tabs[0].root {
+            **right = leaf(10, size(39, 24), true);
+        }
+        assert!(smol::block_on(session.restore()).is_err());
+        assert!(mux.iter_windows().is_empty());
+        let spawned = domain.spawned.lock().unwrap();
+        assert_eq!(spawned.len(), 1);
+        assert!(spawned[0].killed.load(Ordering::Relaxed));
+        drop(spawned);
+        Mux::shutdown();
+    }
+}
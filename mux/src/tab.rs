@@ -1,6 +1,7 @@
 use crate::domain::DomainId;
 use crate::pane::*;
-use crate::{Mux, WindowId};
+use crate::renderable::StableCursorPosition;
+use crate::{Mux, MuxNotification, WindowId};
 use bintree::PathBranch;
 use config::configuration;
 use config::keyassignment::PaneDirection;
@@ -11,6 +12,7 @@ use std::cell::{RefCell, RefMut};
 use std::convert::TryInto;
 use std::rc::Rc;
 use url::Url;
+use wezterm_term::StableRowIndex;
 
 pub type Tree = bintree::Tree<Rc<dyn Pane>, SplitDirectionAndSize>;
 pub type Cursor = bintree::Cursor<Rc<dyn Pane>, SplitDirectionAndSize>;
@@ -25,6 +27,7 @@ pub struct Tab {
     size: RefCell<PtySize>,
     active: RefCell<usize>,
     zoomed: RefCell<Option<Rc<dyn Pane>>>,
+    title: RefCell<String>,
 }
 
 #[derive(Clone)]
@@ -188,38 +191,40 @@ fn pane_tree(
                 },
                 working_dir: working_dir.map(Into::into),
                 workspace: workspace.to_string(),
+                cursor_pos: pane.get_cursor_position(),
+                physical_top: dims.physical_top,
             })
         }
     }
 }
 
-fn build_from_pane_tree<F>(
+fn build_from_pane_tree<F, E>(
     tree: bintree::Tree<PaneEntry, SplitDirectionAndSize>,
     active: &mut Option<Rc<dyn Pane>>,
     zoomed: &mut Option<Rc<dyn Pane>>,
     make_pane: &mut F,
-) -> Tree
+) -> Result<Tree, E>
 where
-    F: FnMut(PaneEntry) -> Rc<dyn Pane>,
+    F: FnMut(PaneEntry) -> Result<Rc<dyn Pane>, E>,
 {
     match tree {
-        bintree::Tree::Empty => Tree::Empty,
-        bintree::Tree::Node { left, right, data } => Tree::Node {
-            left: Box::new(build_from_pane_tree(*left, active, zoomed, make_pane)),
-            right: Box::new(build_from_pane_tree(*right, active, zoomed, make_pane)),
+        bintree::Tree::Empty => Ok(Tree::Empty),
+        bintree::Tree::Node { left, right, data } => Ok(Tree::Node {
+            left: Box::new(build_from_pane_tree(*left, active, zoomed, make_pane)?),
+            right: Box::new(build_from_pane_tree(*right, active, zoomed, make_pane)?),
             data,
-        },
+        }),
         bintree::Tree::Leaf(entry) => {
             let is_zoomed_pane = entry.is_zoomed_pane;
             let is_active_pane = entry.is_active_pane;
-            let pane = make_pane(entry);
+            let pane = make_pane(entry)?;
             if is_zoomed_pane {
                 zoomed.replace(Rc::clone(&pane));
             }
             if is_active_pane {
                 active.replace(Rc::clone(&pane));
             }
-            Tree::Leaf(pane)
+            Ok(Tree::Leaf(pane))
         }
     }
 }
@@ -415,6 +420,7 @@ impl Tab {
             size: RefCell::new(*size),
             active: RefCell::new(0),
             zoomed: RefCell::new(None),
+            title: RefCell::new(String::new()),
         }
     }
 
@@ -432,13 +438,34 @@ impl Tab {
     pub fn sync_with_pane_tree<F>(&self, size: PtySize, root: PaneNode, mut make_pane: F)
     where
         F: FnMut(PaneEntry) -> Rc<dyn Pane>,
+    {
+        let result: Result<(), std::convert::Infallible> =
+            self.try_sync_with_pane_tree(size, root, |entry| Ok(make_pane(entry)));
+        match result {
+            Ok(()) => {}
+            Err(never) => match never {},
+        }
+    }
+
+    /// Like `sync_with_pane_tree`, except that `make_pane` may fail.
+    /// If it does, the error is returned and the tab is left unchanged.
+    /// The caller is responsible for disposing of any panes that
+    /// `make_pane` created before the failure.
+    pub fn try_sync_with_pane_tree<F, E>(
+        &self,
+        size: PtySize,
+        root: PaneNode,
+        mut make_pane: F,
+    ) -> Result<(), E>
+    where
+        F: FnMut(PaneEntry) -> Result<Rc<dyn Pane>, E>,
     {
         let mut active = None;
         let mut zoomed = None;
 
         log::debug!("sync_with_pane_tree with size {:?}", size);
 
-        let t = build_from_pane_tree(root.into_tree(), &mut active, &mut zoomed, &mut make_pane);
+        let t = build_from_pane_tree(root.into_tree(), &mut active, &mut zoomed, &mut make_pane)?;
         let mut cursor = t.cursor();
 
         *self.active.borrow_mut() = 0;
@@ -477,6 +504,7 @@ impl Tab {
             self.iter_panes()
         );
         assert!(self.pane.borrow().is_some());
+        Ok(())
     }
 
     pub fn codec_pane_tree(&self) -> PaneNode {
@@ -1070,34 +1098,42 @@ impl Tab {
             }
             self.toggle_zoom();
         }
+
+        if !self.iter_panes().iter().any(|pane| pane.is_active) {
+            // No active pane somehow...
+            self.set_active_idx(0);
+            return;
+        }
+
+        if let Some(target) = self.get_pane_direction(direction) {
+            self.set_active_idx(target);
+        }
+    }
+
+    /// Returns the index of the pane that is adjacent to the active
+    /// pane in the specified direction.
+    /// In cases where there are multiple adjacent panes in the
+    /// intended direction, we take the pane that has the largest
+    /// edge intersection.
+    pub fn get_pane_direction(&self, direction: PaneDirection) -> Option<usize> {
         let panes = self.iter_panes();
 
-        let active = match panes.iter().find(|pane| pane.is_active) {
-            Some(p) => p,
-            None => {
-                // No active pane somehow...
-                self.set_active_idx(0);
-                return;
-            }
-        };
+        let active = panes.iter().find(|pane| pane.is_active)?;
 
         if matches!(direction, PaneDirection::Next | PaneDirection::Prev) {
             let max_pane_id = panes.iter().map(|p| p.index).max().unwrap_or(active.index);
 
-            if direction == PaneDirection::Next {
-                self.set_active_idx(if active.index == max_pane_id {
+            return Some(if direction == PaneDirection::Next {
+                if active.index == max_pane_id {
                     0
                 } else {
                     active.index + 1
-                });
+                }
+            } else if active.index == 0 {
+                max_pane_id
             } else {
-                self.set_active_idx(if active.index == 0 {
-                    max_pane_id
-                } else {
-                    active.index - 1
-                });
-            }
-            return;
+                active.index - 1
+            });
         }
 
         let mut best = None;
@@ -1159,24 +1195,149 @@ impl Tab {
             }
         }
 
-        if let Some((_, target)) = best.take() {
-            self.set_active_idx(target.index);
+        best.map(|(_, target)| target.index)
+    }
+
+    /// Swap the active pane with the adjacent pane in the specified
+    /// direction.  The active pane remains active in its new position.
+    pub fn swap_active_pane_direction(&self, direction: PaneDirection) {
+        if self.zoomed.borrow().is_some() {
+            return;
+        }
+        if let Some(target) = self.get_pane_direction(direction) {
+            self.swap_active_with_index(target, false);
+        }
+    }
+
+    /// Swap the active pane with the pane at the specified index.
+    /// If `keep_focus` is true, the pane that moves into the active
+    /// position becomes the active pane, otherwise focus follows the
+    /// previously active pane to its new position.
+    pub fn swap_active_with_index(&self, pane_index: usize, keep_focus: bool) -> Option<()> {
+        if self.zoomed.borrow().is_some() {
+            return None;
+        }
+
+        let active_idx = self.get_active_idx();
+        let mut panes: Vec<Rc<dyn Pane>> =
+            self.iter_panes().into_iter().map(|pos| pos.pane).collect();
+        if pane_index == active_idx || pane_index >= panes.len() || active_idx >= panes.len() {
+            return None;
+        }
+
+        let prior = self.get_active_pane();
+        panes.swap(active_idx, pane_index);
+        self.assign_panes_in_order(panes);
+        if !keep_focus {
+            *self.active.borrow_mut() = pane_index;
+        }
+        self.advise_focus_change(prior);
+        Some(())
+    }
+
+    /// Moves each pane to the position of the pane that precedes it
+    /// in the topological order; the first pane moves to the last
+    /// position.  The active pane remains active.
+    pub fn rotate_counter_clockwise(&self) {
+        self.rotate_panes(
+            |panes| panes.rotate_left(1),
+            |idx, len| (idx + len - 1) % len,
+        );
+    }
+
+    /// Moves each pane to the position of the pane that follows it
+    /// in the topological order; the last pane moves to the first
+    /// position.  The active pane remains active.
+    pub fn rotate_clockwise(&self) {
+        self.rotate_panes(|panes| panes.rotate_right(1), |idx, len| (idx + 1) % len);
+    }
+
+    fn rotate_panes<R, A>(&self, rotate: R, adjust_active: A)
+    where
+        R: FnOnce(&mut [Rc<dyn Pane>]),
+        A: FnOnce(usize, usize) -> usize,
+    {
+        if self.zoomed.borrow().is_some() {
+            return;
+        }
+
+        let mut panes: Vec<Rc<dyn Pane>> =
+            self.iter_panes().into_iter().map(|pos| pos.pane).collect();
+        if panes.len() < 2 {
+            return;
+        }
+
+        rotate(&mut panes);
+        let len = panes.len();
+        self.assign_panes_in_order(panes);
+        let active_idx = adjust_active(self.get_active_idx(), len);
+        *self.active.borrow_mut() = active_idx;
+    }
+
+    /// Replaces the panes in the tree, in topological order, with
+    /// the provided panes, resizing each of them to fit their new
+    /// positions.  The split structure of the tree is unchanged.
+    fn assign_panes_in_order(&self, panes: Vec<Rc<dyn Pane>>) {
+        let positions = self.iter_panes_ignoring_zoom();
+        let mut panes = panes.into_iter().zip(positions);
+
+        let mut root = self.pane.borrow_mut();
+        let mut cursor = root.take().unwrap().cursor();
+
+        loop {
+            if cursor.is_leaf() {
+                if let Some((pane, pos)) = panes.next() {
+                    pane.resize(PtySize {
+                        rows: pos.height as u16,
+                        cols: pos.width as u16,
+                        pixel_width: pos.pixel_width as u16,
+                        pixel_height: pos.pixel_height as u16,
+                    })
+                    .ok();
+                    if let Some(leaf) = cursor.leaf_mut() {
+                        *leaf = pane;
+                    }
+                }
+            }
+            match cursor.preorder_next() {
+                Ok(c) => cursor = c,
+                Err(c) => {
+                    root.replace(c.tree());
+                    break;
+                }
+            }
         }
     }
 
     pub fn prune_dead_panes(&self) -> bool {
-        self.remove_pane_if(|_, pane| pane.is_dead())
+        self.remove_pane_if(|_, pane| pane.is_dead(), true)
     }
 
     pub fn kill_pane(&self, pane_id: PaneId) -> bool {
-        self.remove_pane_if(|_, pane| pane.pane_id() == pane_id)
+        self.remove_pane_if(|_, pane| pane.pane_id() == pane_id, true)
     }
 
     pub fn kill_panes_in_domain(&self, domain: DomainId) -> bool {
-        self.remove_pane_if(|_, pane| pane.domain_id() == domain)
+        self.remove_pane_if(|_, pane| pane.domain_id() == domain, true)
+    }
+
+    /// Removes the pane from this tab without killing it, so that it
+    /// can be placed into a different tab.
+    /// Returns the removed pane.
+    pub fn remove_pane(&self, pane_id: PaneId) -> Option<Rc<dyn Pane>> {
+        let pane = self
+            .iter_panes_ignoring_zoom()
+            .into_iter()
+            .find(|pos| pos.pane.pane_id() == pane_id)?
+            .pane;
+        if self.remove_pane_if(|_, pane| pane.pane_id() == pane_id, false) {
+            Some(pane)
+        } else {
+            None
+        }
     }
 
-    fn remove_pane_if<F>(&self, f: F) -> bool
+    fn remove_pane_if<F>(&self, f: F, kill: bool) -> bool
     where
         F: Fn(usize, &Rc<dyn Pane>) -> bool,
     {
@@ -1267,7 +1428,11 @@ impl Tab {
             *self.active.borrow_mut() = active_idx;
         }
 
-        if !dead_panes.is_empty() {
+        if dead_panes.is_empty() {
+            return false;
+        }
+
+        if kill {
             promise::spawn::spawn_into_main_thread(async move {
                 let mux = Mux::get().unwrap();
                 for pane_id in dead_panes.into_iter() {
@@ -1275,10 +1440,8 @@ impl Tab {
                 }
             })
             .detach();
-            true
-        } else {
-            false
         }
+        true
     }
 
     pub fn can_close_without_prompting(&self, reason: CloseReason) -> bool {
@@ -1320,6 +1483,22 @@ impl Tab {
         *self.active.borrow()
     }
 
+    /// Returns the title that was explicitly assigned to this tab,
+    /// or an empty string if none has been set.
+    pub fn get_title(&self) -> String {
+        self.title.borrow().clone()
+    }
+
+    pub fn set_title(&self, title: &str) {
+        *self.title.borrow_mut() = title.to_string();
+        if let Some(mux) = Mux::get() {
+            mux.notify(MuxNotification::TabTitleChanged(self.id));
+            if let Some(window_id) = mux.window_containing_tab(self.id) {
+                mux.notify(MuxNotification::WindowInvalidated(window_id));
+            }
+        }
+    }
+
     pub fn set_active_pane(&self, pane: &Rc<dyn Pane>) {
         if let Some(item) = self
             .iter_panes()
@@ -1332,6 +1511,24 @@ impl Tab {
         }
     }
 
+    /// Makes the pane with the specified id the active pane without
+    /// advising the panes of a change in focus.  This is used by the
+    /// mux server to follow the active pane of a client's view of
+    /// the tab.  Returns false if the pane is not in this tab.
+    pub fn set_active_pane_id_without_focus(&self, pane_id: PaneId) -> bool {
+        match self
+            .iter_panes_ignoring_zoom()
+            .iter()
+            .find(|p| p.pane.pane_id() == pane_id)
+        {
+            Some(item) => {
+                *self.active.borrow_mut() = item.index;
+                true
+            }
+            None => false,
+        }
+    }
+
     fn advise_focus_change(&self, prior: Option<Rc<dyn Pane>>) {
         let current = self.get_active_pane();
         match (prior, current) {
@@ -1564,6 +1761,13 @@ pub struct PaneEntry {
     pub is_active_pane: bool,
     pub is_zoomed_pane: bool,
     pub workspace: String,
+    /// The cursor position at the time that the entry was produced
+    #[serde(default)]
+    pub cursor_pos: StableCursorPosition,
+    /// The top of the physical screen at the time that the entry
+    /// was produced, which makes `cursor_pos` relative to the viewport
+    #[serde(default)]
+    pub physical_top: StableRowIndex,
 }
 
 #[derive(Deserialize, Clone, Serialize, PartialEq, Debug)]
@@ -1601,102 +1805,7 @@ impl Into<String> for SerdeUrl {
 #[cfg(test)]
 mod test {
     use super::*;
-    use crate::renderable::*;
-    use rangeset::RangeSet;
-    use std::ops::Range;
-    use termwiz::surface::SequenceNo;
-    use url::Url;
-    use wezterm_term::color::ColorPalette;
-    use wezterm_term::{KeyCode, KeyModifiers, Line, MouseEvent, StableRowIndex};
-
-    struct FakePane {
-        id: PaneId,
-        size: RefCell<PtySize>,
-    }
-
-    impl FakePane {
-        fn new(id: PaneId, size: PtySize) -> Rc<dyn Pane> {
-            Rc::new(Self {
-                id,
-                size: RefCell::new(size),
-            })
-        }
-    }
-
-    impl Pane for FakePane {
-        fn pane_id(&self) -> PaneId {
-            self.id
-        }
-
-        fn get_cursor_position(&self) -> StableCursorPosition {
-            unimplemented!();
-        }
-
-        fn get_current_seqno(&self) -> SequenceNo {
-            unimplemented!();
-        }
-
-        fn get_changed_since(
-            &self,
-            _lines: Range<StableRowIndex>,
-            _: SequenceNo,
-        ) -> RangeSet<StableRowIndex> {
-            unimplemented!();
-        }
-
-        fn get_lines(&self, _lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
-            unimplemented!();
-        }
-
-        fn get_dimensions(&self) -> RenderableDimensions {
-            unimplemented!();
-        }
-
-        fn get_title(&self) -> String {
-            unimplemented!()
-        }
-        fn send_paste(&self, _text: &str) -> anyhow::Result<()> {
-            unimplemented!()
-        }
-        fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>> {
-            Ok(None)
-        }
-        fn writer(&self) -> RefMut<dyn std::io::Write> {
-            unimplemented!()
-        }
-        fn resize(&self, size: PtySize) -> anyhow::Result<()> {
-            *self.size.borrow_mut() = size;
-            Ok(())
-        }
-
-        fn key_down(&self, _key: KeyCode, _mods: KeyModifiers) -> anyhow::Result<()> {
-            unimplemented!()
-        }
-        fn key_up(&self, _: KeyCode, _: KeyModifiers) -> anyhow::Result<()> {
-            unimplemented!()
-        }
-        fn mouse_event(&self, _event: MouseEvent) -> anyhow::Result<()> {
-            unimplemented!()
-        }
-        fn is_dead(&self) -> bool {
-            false
-        }
-        fn palette(&self) -> ColorPalette {
-            unimplemented!()
-        }
-        fn domain_id(&self) -> DomainId {
-            1
-        }
-        fn is_mouse_grabbed(&self) -> bool {
-            false
-        }
-        fn is_alt_screen_active(&self) -> bool {
-            false
-        }
-        fn get_current_working_dir(&self) -> Option<Url> {
-            None
-        }
-    }
+    use crate::test_util::FakePane;
 
     #[test]
     fn tab_splitting() {
@@ -1708,7 +1817,7 @@ mod test {
         };
 
         let tab = Tab::new(&size);
-        tab.assign_pane(&FakePane::new(1, size));
+        tab.assign_pane(&FakePane::new_pane(1, size));
 
         let panes = tab.iter_panes();
         assert_eq!(1, panes.len());
@@ -1769,7 +1878,7 @@ mod test {
             .split_and_insert(
                 0,
                 SplitDirection::Horizontal,
-                FakePane::new(2, horz_size.second),
+                FakePane::new_pane(2, horz_size.second),
             )
             .unwrap();
         assert_eq!(new_index, 1);
@@ -1802,7 +1911,7 @@ mod test {
             .split_and_insert(
                 0,
                 SplitDirection::Vertical,
-                FakePane::new(3, vert_size.second),
+                FakePane::new_pane(3, vert_size.second),
             )
             .unwrap();
         assert_eq!(new_index, 1);
@@ -1857,4 +1966,159 @@ mod test {
         assert_eq!(390, panes[2].pixel_width);
         assert_eq!(600, panes[2].pixel_height);
     }
+
+    #[test]
+    fn tab_rearranging() {
+        let size = PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+        };
+
+        let tab = Tab::new(&size);
+        tab.assign_pane(&FakePane::new_pane(1, size));
+        let horz_size = tab
+            .compute_split_size(0, SplitDirection::Horizontal)
+            .unwrap();
+        tab.split_and_insert(
+            0,
+            SplitDirection::Horizontal,
+            FakePane::new_pane(2, horz_size.second),
+        )
+        .unwrap();
+        let vert_size = tab.compute_split_size(0, SplitDirection::Vertical).unwrap();
+        tab.split_and_insert(
+            0,
+            SplitDirection::Vertical,
+            FakePane::new_pane(3, vert_size.second),
+        )
+        .unwrap();
+
+        fn pane_ids(tab: &Tab) -> Vec<PaneId> {
+            tab.iter_panes()
+                .iter()
+                .map(|pos| pos.pane.pane_id())
+                .collect()
+        }
+
+        assert_eq!(pane_ids(&tab), vec![1, 3, 2]);
+        assert_eq!(tab.get_active_idx(), 1);
+
+        // There is nothing above the top left pane
+        tab.set_active_idx(0);
+        assert_eq!(tab.get_pane_direction(PaneDirection::Up), None);
+        tab.swap_active_pane_direction(PaneDirection::Up);
+        assert_eq!(pane_ids(&tab), vec![1, 3, 2]);
+
+        tab.set_active_idx(1);
+        assert_eq!(tab.get_pane_direction(PaneDirection::Right), Some(2));
+        tab.swap_active_pane_direction(PaneDirection::Right);
+        assert_eq!(pane_ids(&tab), vec![1, 2, 3]);
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 3);
+
+        // The panes took on the sizes of their new positions
+        let panes = tab.iter_panes();
+        assert_eq!(
+            *panes[1]
+                .pane
+                .downcast_ref::<FakePane>()
+                .unwrap()
+                .size
+                .borrow(),
+            vert_size.second
+        );
+        assert_eq!(
+            *panes[2]
+                .pane
+                .downcast_ref::<FakePane>()
+                .unwrap()
+                .size
+                .borrow(),
+            horz_size.second
+        );
+
+        tab.rotate_clockwise();
+        assert_eq!(pane_ids(&tab), vec![3, 1, 2]);
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 3);
+
+        tab.rotate_counter_clockwise();
+        assert_eq!(pane_ids(&tab), vec![1, 2, 3]);
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 3);
+
+        tab.swap_active_with_index(0, true);
+        assert_eq!(pane_ids(&tab), vec![3, 2, 1]);
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 1);
+
+        let removed = tab.remove_pane(2).unwrap();
+        assert_eq!(removed.pane_id(), 2);
+        assert_eq!(pane_ids(&tab), vec![3, 1]);
+        assert!(tab.remove_pane(2).is_none());
+
+        let panes = tab.iter_panes();
+        assert_eq!(40, panes[0].width);
+        assert_eq!(24, panes[0].height);
+
+        // Following a client's active pane
+        assert!(tab.set_active_pane_id_without_focus(3));
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 3);
+        assert!(!tab.set_active_pane_id_without_focus(2));
+        assert_eq!(tab.get_active_pane().unwrap().pane_id(), 3);
+    }
+
+    #[test]
+    fn tab_title() {
+        let size = PtySize {
+            rows: 24,
+            cols: 80,
+            pixel_width: 800,
+            pixel_height: 600,
+        };
+
+        let tab = Rc::new(Tab::new(&size));
+        tab.assign_pane(&FakePane::new_pane(1, size));
+        assert_eq!(tab.get_title(), "");
+
+        // Without a mux there is nobody to notify
+        tab.set_title("detached");
+        assert_eq!(tab.get_title(), "detached");
+
+        config::use_test_configuration();
+        drop(promise::spawn::SimpleExecutor::new());
+        let mux = Rc::new(Mux::new(None));
+        Mux::set_mux(&mux);
+        let window_id = *mux.new_empty_window(None);
+        mux.add_tab_and_active_pane(&tab).unwrap();
+        mux.add_tab_to_window(&tab, window_id).unwrap();
+
+        let notifications = Rc::new(RefCell::new(vec![]));
+        {
+            let notifications = Rc::clone(&notifications);
+            mux.subscribe(move |n| {
+                notifications.borrow_mut().push(n);
+                true
+            });
+        }
+
+        tab.set_title("editor");
+        assert_eq!(tab.get_title(), "editor");
+        {
+            let notifications = notifications.borrow();
+            assert_eq!(notifications.len(), 2);
+            assert!(matches!(
+                notifications[0],
+                MuxNotification::TabTitleChanged(id) if id == tab.tab_id()
+            ));
+            assert!(matches!(
+                notifications[1],
+                MuxNotification::WindowInvalidated(id) if id == window_id
+            ));
+        }
+
+        // An empty title reverts to showing the title of the active pane
+        tab.set_title("");
+        assert_eq!(tab.get_title(), "");
+        assert_eq!(notifications.borrow().len(), 4);
+        Mux::shutdown();
+    }
 }
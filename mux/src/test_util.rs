@@ -0,0 +1,111 @@
+//! Fixtures shared by the unit tests of this crate
+use crate::domain::DomainId;
+use crate::pane::{Pane, PaneId};
+use crate::renderable::*;
+use portable_pty::PtySize;
+use rangeset::RangeSet;
+use std::cell::{RefCell, RefMut};
+use std::ops::Range;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use termwiz::surface::SequenceNo;
+use url::Url;
+use wezterm_term::color::ColorPalette;
+use wezterm_term::{KeyCode, KeyModifiers, Line, MouseEvent, StableRowIndex};
+
+/// A pane without a terminal, which records the size that it
+/// was given and whether it was killed
+pub struct FakePane {
+    pub id: PaneId,
+    pub size: RefCell<PtySize>,
+    pub killed: Arc<AtomicBool>,
+}
+
+impl FakePane {
+    pub fn new_pane(id: PaneId, size: PtySize) -> Rc<dyn Pane> {
+        Rc::new(Self {
+            id,
+            size: RefCell::new(size),
+            killed: Arc::new(AtomicBool::new(false)),
+        })
+    }
+}
+
+impl Pane for FakePane {
+    fn pane_id(&self) -> PaneId {
+        self.id
+    }
+
+    fn get_cursor_position(&self) -> StableCursorPosition {
+        unimplemented!();
+    }
+
+    fn get_current_seqno(&self) -> SequenceNo {
+        unimplemented!();
+    }
+
+    fn get_changed_since(
+        &self,
+        _lines: Range<StableRowIndex>,
+        _: SequenceNo,
+    ) -> RangeSet<StableRowIndex> {
+        unimplemented!();
+    }
+
+    fn get_lines(&self, _lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
+        unimplemented!();
+    }
+
+    fn get_dimensions(&self) -> RenderableDimensions {
+        unimplemented!();
+    }
+
+    fn get_title(&self) -> String {
+        unimplemented!()
+    }
+    fn send_paste(&self, _text: &str) -> anyhow::Result<()> {
+        unimplemented!()
+    }
+    fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>> {
+        Ok(None)
+    }
+    fn writer(&self) -> RefMut<'_, dyn std::io::Write> {
+        unimplemented!()
+    }
+    fn resize(&self, size: PtySize) -> anyhow::Result<()> {
+        *self.size.borrow_mut() = size;
+        Ok(())
+    }
+
+    fn key_down(&self, _key: KeyCode, _mods: KeyModifiers) -> anyhow::Result<()> {
+        unimplemented!()
+    }
+    fn key_up(&self, _: KeyCode, _: KeyModifiers) -> anyhow::Result<()> {
+        unimplemented!()
+    }
+    fn mouse_event(&self, _event: MouseEvent) -> anyhow::Result<()> {
+        unimplemented!()
+    }
+    fn is_dead(&self) -> bool {
+        false
+    }
+    fn kill(&self) {
+        self.killed.store(true, Ordering::Relaxed);
+    }
+    fn palette(&self) -> ColorPalette {
+        unimplemented!()
+    }
+    fn domain_id(&self) -> DomainId {
+        1
+    }
+    fn is_mouse_grabbed(&self) -> bool {
+        false
+    }
+    fn is_alt_screen_active(&self) -> bool {
+        false
+    }
+    fn get_current_working_dir(&self) -> Option<Url> {
+        None
+    }
+}
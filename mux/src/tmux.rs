@@ -1,8 +1,11 @@
 use crate::domain::{alloc_domain_id, Domain, DomainId, DomainState};
 use crate::pane::{Pane, PaneId};
-use crate::tab::TabId;
-use crate::tmux_commands::{ListAllPanes, TmuxCommand};
-use crate::{Mux, MuxWindowBuilder};
+use crate::tab::{SplitDirection, Tab, TabId};
+use crate::tmux_commands::{
+    DetachClient, ListAllPanes, ListWindows, NewWindow, SplitWindow, TmuxCommand,
+};
+use crate::{Mux, MuxWindowBuilder, WindowId};
+use anyhow::anyhow;
 use async_trait::async_trait;
 use filedescriptor::FileDescriptor;
 use portable_pty::{CommandBuilder, PtySize};
@@ -60,6 +63,8 @@ pub(crate) struct TmuxDomainState {
     pub gui_tabs: RefCell<Vec<TmuxTab>>,
     pub remote_panes: RefCell<HashMap<TmuxPaneId, RefTmuxRemotePane>>,
     pub tmux_session: RefCell<Option<TmuxSessionId>>,
+    /// Set once tmux has told us that it is leaving control mode
+    detached: RefCell<bool>,
 }
 
 pub struct TmuxDomain {
@@ -104,12 +109,59 @@ impl TmuxDomainState {
                 }
                 Event::WindowAdd { window: _ } => {
                     self.create_gui_window();
+                    // Pick up the layout of the new window
+                    self.cmd_queue
+                        .lock()
+                        .unwrap()
+                        .push_back(Box::new(ListWindows));
+                }
+                Event::WindowClose { window } => {
+                    let panes: Vec<TmuxPaneId> = self
+                        .gui_tabs
+                        .borrow()
+                        .iter()
+                        .filter(|x| x.tmux_window_id == *window)
+                        .flat_map(|x| x.panes.iter().copied())
+                        .collect();
+                    for pane in panes {
+                        self.release_pane(pane);
+                    }
+                }
+                Event::LayoutChange { window, layout, .. } => {
+                    if let Err(err) = self.sync_window_layout(*window, layout) {
+                        log::error!(
+                            "Failed to apply layout of tmux window @{}: {:#}",
+                            window,
+                            err
+                        );
+                    }
+                }
+                Event::WindowPaneChanged { window: _, pane } => {
+                    self.activate_remote_pane(*pane);
+                }
+                Event::SessionWindowChanged { session, window }
+                    if *self.tmux_session.borrow() == Some(*session) =>
+                {
+                    self.activate_remote_window(*window);
                 }
                 Event::SessionChanged { session, name: _ } => {
-                    *self.tmux_session.borrow_mut() = Some(*session);
+                    let prior = self.tmux_session.borrow_mut().replace(*session);
                     log::info!("tmux session changed:{}", session);
+                    if prior.is_some() && prior != Some(*session) {
+                        // The client switched to a different session;
+                        // replace the windows of the prior session
+                        let panes: Vec<TmuxPaneId> =
+                            self.remote_panes.borrow().keys().copied().collect();
+                        for pane in panes {
+                            self.release_pane(pane);
+                        }
+                        let mut cmd_queue = self.cmd_queue.lock().unwrap();
+                        cmd_queue.push_back(Box::new(ListAllPanes));
+                        cmd_queue.push_back(Box::new(ListWindows));
+                    }
                 }
                 Event::Exit { reason: _ } => {
+                    *self.detached.borrow_mut() = true;
                     let mut pane_map = self.remote_panes.borrow_mut();
                     for (_, v) in pane_map.iter_mut() {
                         let remote_pane = v.lock().unwrap();
@@ -130,6 +182,78 @@ impl TmuxDomainState {
         }
     }
 
+    /// Queue up a command and arrange for it to be sent to tmux
+    pub fn queue_command(&self, cmd: Box<dyn TmuxCommand>) {
+        self.cmd_queue.lock().unwrap().push_back(cmd);
+        TmuxDomainState::schedule_send_next_command(self.domain_id);
+    }
+
+    /// Returns the remote pane id that corresponds to a local pane
+    pub fn remote_pane_id(&self, local_pane_id: PaneId) -> Option<TmuxPaneId> {
+        self.remote_panes
+            .borrow()
+            .values()
+            .map(|p| p.lock().unwrap())
+            .find(|p| p.local_pane_id == local_pane_id)
+            .map(|p| p.pane_id)
+    }
+
+    /// Forget about a remote pane that no longer exists in tmux.
+    /// Its local counterpart sees its child exit and is subsequently
+    /// pruned from its tab by the mux.
+    pub fn release_pane(&self, pane_id: TmuxPaneId) {
+        if let Some(remote_pane) = self.remote_panes.borrow_mut().remove(&pane_id) {
+            let remote_pane = remote_pane.lock().unwrap();
+            let (lock, condvar) = &*remote_pane.active_lock;
+            let mut released = lock.lock().unwrap();
+            *released = true;
+            condvar.notify_all();
+        }
+
+        let mut gui_tabs = self.gui_tabs.borrow_mut();
+        for tab in gui_tabs.iter_mut() {
+            tab.panes.remove(&pane_id);
+        }
+        gui_tabs.retain(|tab| !tab.panes.is_empty());
+    }
+
+    /// Make the local counterpart of a remote pane the active pane
+    /// in its tab
+    fn activate_remote_pane(&self, pane_id: TmuxPaneId) {
+        let local_pane_id = match self.remote_panes.borrow().get(&pane_id) {
+            Some(pane) => pane.lock().unwrap().local_pane_id,
+            None => return,
+        };
+        let mux = Mux::get().expect("to be called on main thread");
+        if let Some((_domain_id, _window_id, tab_id)) = mux.resolve_pane_id(local_pane_id) {
+            if let (Some(tab), Some(pane)) = (mux.get_tab(tab_id), mux.get_pane(local_pane_id)) {
+                tab.set_active_pane(&pane);
+            }
+        }
+    }
+
+    /// Make the local tab that mirrors a remote window the active tab
+    /// in its window
+    fn activate_remote_window(&self, window: TmuxWindowId) {
+        let tab_id = match self
+            .gui_tabs
+            .borrow()
+            .iter()
+            .find(|x| x.tmux_window_id == window)
+        {
+            Some(x) => x.tab_id,
+            None => return,
+        };
+        let mux = Mux::get().expect("to be called on main thread");
+        if let Some(window_id) = mux.window_containing_tab(tab_id) {
+            if let Some(mut window) = mux.get_window_mut(window_id) {
+                if let Some(idx) = window.idx_by_id(tab_id) {
+                    window.save_and_then_set_active(idx);
+                }
+            }
+        }
+    }
+
     /// send next command at the front of cmd_queue.
     /// must be called inside main thread
     fn send_next_command(&self) {
@@ -182,6 +306,7 @@ impl TmuxDomain {
         // let parser = RefCell::new(Parser::new());
         let mut cmd_queue = VecDeque::<Box<dyn TmuxCommand>>::new();
         cmd_queue.push_back(Box::new(ListAllPanes));
+        cmd_queue.push_back(Box::new(ListWindows));
         let inner = Arc::new(TmuxDomainState {
             domain_id,
             pane_id,
@@ -192,6 +317,7 @@ impl TmuxDomain {
             gui_tabs: RefCell::new(Vec::default()),
             remote_panes: RefCell::new(HashMap::default()),
             tmux_session: RefCell::new(None),
+            detached: RefCell::new(false),
         });
 
         Self { inner }
@@ -200,17 +326,97 @@ impl TmuxDomain {
     fn send_next_command(&self) {
         self.inner.send_next_command();
     }
+
+    /// Ask tmux to create a new window and wait for its sole pane to
+    /// be attached locally, returning the local pane id
+    async fn new_window(
+        &self,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        window_id: Option<WindowId>,
+    ) -> anyhow::Result<PaneId> {
+        let (reply, result) = smol::channel::bounded(1);
+        self.inner.queue_command(Box::new(NewWindow {
+            window_id,
+            command: command_line(command)?,
+            command_dir,
+            reply,
+        }));
+        result.recv().await?
+    }
+}
+
+/// Tmux runs commands via the remote shell, so we need to turn the
+/// command into a shell command line
+fn command_line(command: Option<CommandBuilder>) -> anyhow::Result<Option<String>> {
+    match command {
+        Some(cmd) if !cmd.is_default_prog() => Ok(Some(cmd.as_unix_command_line()?)),
+        _ => Ok(None),
+    }
 }
 
 #[async_trait(?Send)]
 impl Domain for TmuxDomain {
+    async fn spawn(
+        &self,
+        _size: PtySize,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        window: WindowId,
+    ) -> anyhow::Result<Rc<Tab>> {
+        let pane_id = self.new_window(command, command_dir, Some(window)).await?;
+        let mux = Mux::get().unwrap();
+        let (_domain_id, _window_id, tab_id) = mux
+            .resolve_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("tmux pane {} has no tab", pane_id))?;
+        mux.get_tab(tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", tab_id))
+    }
+
+    async fn split_pane(
+        &self,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
+        tab: TabId,
+        pane_id: PaneId,
+        direction: SplitDirection,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        let target = self
+            .inner
+            .remote_pane_id(pane_id)
+            .ok_or_else(|| anyhow!("pane {} is not a tmux pane", pane_id))?;
+
+        let (reply, result) = smol::channel::bounded(1);
+        self.inner.queue_command(Box::new(SplitWindow {
+            target,
+            tab_id: tab,
+            pane_id,
+            direction,
+            command: command_line(command)?,
+            command_dir,
+            reply,
+        }));
+        let pane_id = result.recv().await??;
+
+        Mux::get()
+            .unwrap()
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("tmux pane {} went away", pane_id))
+    }
+
+    /// Tmux panes can only exist within a tmux window, so this creates
+    /// a new tmux window and places it into the tmux gui window
     async fn spawn_pane(
         &self,
         _size: PtySize,
-        _command: Option<CommandBuilder>,
-        _command_dir: Option<String>,
+        command: Option<CommandBuilder>,
+        command_dir: Option<String>,
     ) -> anyhow::Result<Rc<dyn Pane>> {
-        anyhow::bail!("Spawn_pane not yet implemented for TmuxDomain");
+        let pane_id = self.new_window(command, command_dir, None).await?;
+        Mux::get()
+            .unwrap()
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("tmux pane {} went away", pane_id))
     }
 
     fn domain_id(&self) -> DomainId {
@@ -222,14 +428,23 @@ impl Domain for TmuxDomain {
     }
 
     async fn attach(&self, _window_id: Option<crate::WindowId>) -> anyhow::Result<()> {
+        if *self.inner.detached.borrow() {
+            anyhow::bail!("tmux has detached; run `tmux -CC attach` to re-attach");
+        }
         Ok(())
     }
 
+    /// Detach from the tmux session, leaving it running on the remote end
     fn detach(&self) -> anyhow::Result<()> {
-        anyhow::bail!("detach not implemented for TmuxDomain");
+        self.inner.queue_command(Box::new(DetachClient));
+        Ok(())
     }
 
     fn state(&self) -> DomainState {
-        DomainState::Attached
+        if *self.inner.detached.borrow() {
+            DomainState::Detached
+        } else {
+            DomainState::Attached
+        }
     }
 }
@@ -1,19 +1,27 @@
 use crate::domain::DomainId;
 use crate::localpane::LocalPane;
-use crate::pane::alloc_pane_id;
-use crate::tab::{Tab, TabId};
+use crate::pane::{alloc_pane_id, PaneId};
+use crate::tab::{PaneEntry, PaneNode, SplitDirection, SplitDirectionAndSize, Tab, TabId};
 use crate::tmux::{TmuxDomain, TmuxDomainState, TmuxRemotePane, TmuxTab};
 use crate::tmux_pty::{TmuxChild, TmuxPty};
+use crate::window::WindowId;
 use crate::{Mux, Pane};
 use anyhow::{anyhow, Context};
 use portable_pty::{MasterPty, PtySize};
-use std::collections::HashSet;
+use smol::channel::Sender;
+use std::collections::{HashMap, HashSet};
 use std::fmt::{Debug, Write};
 use std::io::Write as _;
 use std::rc::Rc;
 use std::sync::{Arc, Condvar, Mutex};
 use termwiz::tmux_cc::*;
 
+/// The format used with `list-panes -F` and `new-window -P -F` et al.
+/// to describe a pane; parsed by `PaneItem::parse`.
+const PANE_FORMAT: &str = "#{session_id} #{window_id} #{pane_id} \
+    #{pane_index} #{cursor_x} #{cursor_y} #{pane_width} #{pane_height} \
+    #{pane_left} #{pane_top}";
+
 pub(crate) trait TmuxCommand: Send + Debug {
     fn get_command(&self) -> String;
     fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()>;
@@ -33,27 +41,100 @@ pub(crate) struct PaneItem {
     pane_top: u64,
 }
 
+impl PaneItem {
+    /// Parse a line produced by tmux using `PANE_FORMAT`
+    fn parse(line: &str) -> anyhow::Result<Self> {
+        let mut fields = line.split(' ');
+        let session_id = fields.next().ok_or_else(|| anyhow!("missing session_id"))?;
+        let window_id = fields.next().ok_or_else(|| anyhow!("missing window_id"))?;
+        let pane_id = fields.next().ok_or_else(|| anyhow!("missing pane_id"))?;
+        let _pane_index = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_index"))?
+            .parse()?;
+        let cursor_x = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing cursor_x"))?
+            .parse()?;
+        let cursor_y = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing cursor_y"))?
+            .parse()?;
+        let pane_width = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_width"))?
+            .parse()?;
+        let pane_height = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_height"))?
+            .parse()?;
+        let pane_left = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_left"))?
+            .parse()?;
+        let pane_top = fields
+            .next()
+            .ok_or_else(|| anyhow!("missing pane_top"))?
+            .parse()?;
+
+        // These ids all have various sigils such as `$`, `%`, `@`,
+        // so skip those prior to parsing them
+        let session_id = session_id[1..].parse()?;
+        let window_id = window_id[1..].parse()?;
+        let pane_id = pane_id[1..].parse()?;
+
+        Ok(Self {
+            session_id,
+            window_id,
+            pane_id,
+            _pane_index,
+            cursor_x,
+            cursor_y,
+            pane_width,
+            pane_height,
+            pane_left,
+            pane_top,
+        })
+    }
+
+    /// Parse the output of a command that was run with `-P -F PANE_FORMAT`
+    /// and that is expected to describe exactly one pane
+    fn parse_single(result: &Guarded) -> anyhow::Result<Self> {
+        if result.error {
+            anyhow::bail!("{}", result.output.trim());
+        }
+        let line = result
+            .output
+            .split('\n')
+            .find(|line| !line.is_empty())
+            .ok_or_else(|| anyhow!("tmux did not report the new pane"))?;
+        Self::parse(line)
+    }
+}
+
 impl TmuxDomainState {
-    /// check if a PaneItem received from ListAllPanes has been attached
-    fn check_pane_attached(&self, target: &PaneItem) -> bool {
-        let pane_list = self.gui_tabs.borrow();
-        let local_tab = match pane_list
+    /// check if the window of a PaneItem received from ListAllPanes
+    /// already has a local tab.  The remaining panes of such a window
+    /// are placed into that tab when its layout is applied.
+    fn check_window_attached(&self, target: &PaneItem) -> bool {
+        self.gui_tabs
+            .borrow()
             .iter()
-            .find(|&x| x.tmux_window_id == target.window_id)
-        {
-            Some(x) => x,
-            None => {
-                return false;
-            }
-        };
-        match local_tab.panes.get(&target.pane_id) {
-            Some(_) => {
-                return true;
-            }
-            None => {
-                return false;
-            }
-        }
+            .any(|x| x.tmux_window_id == target.window_id)
+    }
+
+    /// Returns the local counterpart of a remote pane, if we have one
+    fn attached_local_pane(&self, pane_id: TmuxPaneId) -> Option<Rc<dyn Pane>> {
+        let local_pane_id = self
+            .remote_panes
+            .borrow()
+            .get(&pane_id)?
+            .lock()
+            .unwrap()
+            .local_pane_id;
+        Mux::get()
+            .expect("should be called at main thread")
+            .get_pane(local_pane_id)
     }
 
     /// after we create a tab for a remote pane, save its ID into the
@@ -85,6 +166,151 @@ impl TmuxDomainState {
         }
     }
 
+    /// Create the local counterpart of a remote tmux pane: a LocalPane
+    /// whose pty feeds from and writes to the tmux control connection.
+    /// The returned pane has not yet been added to a tab or to the mux.
+    fn create_local_pane(&self, pane: &PaneItem) -> anyhow::Result<Rc<dyn Pane>> {
+        let local_pane_id = alloc_pane_id();
+        let (output_read, output_write) = filedescriptor::socketpair()?;
+        let active_lock = Arc::new((Mutex::new(false), Condvar::new()));
+
+        let ref_pane = Arc::new(Mutex::new(TmuxRemotePane {
+            local_pane_id,
+            output_write,
+            active_lock: active_lock.clone(),
+            session_id: pane.session_id,
+            window_id: pane.window_id,
+            pane_id: pane.pane_id,
+            cursor_x: pane.cursor_x,
+            cursor_y: pane.cursor_y,
+            pane_width: pane.pane_width,
+            pane_height: pane.pane_height,
+            pane_left: pane.pane_left,
+            pane_top: pane.pane_top,
+        }));
+
+        {
+            let mut pane_map = self.remote_panes.borrow_mut();
+            pane_map.insert(pane.pane_id, ref_pane.clone());
+        }
+
+        let pane_pty = TmuxPty {
+            domain_id: self.domain_id,
+            reader: output_read,
+            cmd_queue: self.cmd_queue.clone(),
+            master_pane: ref_pane,
+        };
+        let writer = pane_pty.try_clone_writer()?;
+        let size = PtySize {
+            rows: pane.pane_height as u16,
+            cols: pane.pane_width as u16,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+
+        let child = TmuxChild {
+            active_lock,
+            domain_id: self.domain_id,
+            pane_id: pane.pane_id,
+            cmd_queue: self.cmd_queue.clone(),
+        };
+
+        let terminal = wezterm_term::Terminal::new(
+            crate::pty_size_to_terminal_size(size),
+            std::sync::Arc::new(config::TermConfig::new()),
+            "WezTerm",
+            config::wezterm_version(),
+            Box::new(writer),
+        );
+
+        Ok(Rc::new(LocalPane::new(
+            local_pane_id,
+            terminal,
+            Box::new(child),
+            Box::new(pane_pty),
+            self.domain_id,
+            "tmux pane".to_string(),
+        )))
+    }
+
+    /// Attach a remote pane as the sole pane of a new local tab.
+    /// The tab is placed into `window_id`, or into the tmux gui window
+    /// when `window_id` is `None`.
+    fn attach_pane_in_new_tab(
+        &self,
+        pane: &PaneItem,
+        window_id: Option<WindowId>,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        if let Some(local_pane) = self.attached_local_pane(pane.pane_id) {
+            // A layout change already told us about this pane
+            return Ok(local_pane);
+        }
+        let mux = Mux::get().expect("should be called at main thread");
+        let local_pane = self.create_local_pane(pane)?;
+        let size = PtySize {
+            rows: pane.pane_height as u16,
+            cols: pane.pane_width as u16,
+            pixel_width: 0,
+            pixel_height: 0,
+        };
+
+        let tab = Rc::new(Tab::new(&size));
+        tab.assign_pane(&local_pane);
+        mux.add_tab_and_active_pane(&tab)?;
+
+        match window_id {
+            Some(window_id) => mux.add_tab_to_window(&tab, window_id)?,
+            None => {
+                self.create_gui_window();
+                let mut gui_window = self.gui_window.borrow_mut();
+                let gui_window_id = match gui_window.as_mut() {
+                    Some(x) => x,
+                    None => {
+                        anyhow::bail!("No tmux gui created");
+                    }
+                };
+
+                mux.add_tab_to_window(&tab, **gui_window_id)?;
+                gui_window_id.notify();
+            }
+        }
+
+        self.add_attached_pane(pane, &tab.tab_id())?;
+        Ok(local_pane)
+    }
+
+    /// Attach a remote pane that tmux created by splitting the remote
+    /// counterpart of `split_pane_id`, which lives in local tab `tab_id`.
+    fn attach_split_pane(
+        &self,
+        pane: &PaneItem,
+        tab_id: TabId,
+        split_pane_id: PaneId,
+        direction: SplitDirection,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        if let Some(local_pane) = self.attached_local_pane(pane.pane_id) {
+            // A layout change already placed this pane into the tab
+            return Ok(local_pane);
+        }
+        let mux = Mux::get().expect("should be called at main thread");
+        let tab = mux
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("Invalid tab id {}", tab_id))?;
+        let pane_index = tab
+            .iter_panes()
+            .iter()
+            .find(|p| p.pane.pane_id() == split_pane_id)
+            .map(|p| p.index)
+            .ok_or_else(|| anyhow!("invalid pane id {}", split_pane_id))?;
+
+        let local_pane = self.create_local_pane(pane)?;
+        tab.split_and_insert(pane_index, direction, Rc::clone(&local_pane))?;
+        mux.add_pane(&local_pane)?;
+
+        self.add_attached_pane(pane, &tab_id)?;
+        Ok(local_pane)
+    }
+
     fn sync_pane_state(&self, panes: &[PaneItem]) -> anyhow::Result<()> {
         // TODO:
         // 1) iter over current session panes
@@ -93,85 +319,11 @@ impl TmuxDomainState {
         // 4) update pane state if exist
         let current_session = self.tmux_session.borrow().unwrap_or(0);
         for pane in panes.iter() {
-            if pane.session_id != current_session || self.check_pane_attached(&pane) {
+            if pane.session_id != current_session || self.check_window_attached(pane) {
                 continue;
             }
 
-            let local_pane_id = alloc_pane_id();
-            let (output_read, output_write) = filedescriptor::socketpair()?;
-            let active_lock = Arc::new((Mutex::new(false), Condvar::new()));
-
-            let ref_pane = Arc::new(Mutex::new(TmuxRemotePane {
-                local_pane_id,
-                output_write,
-                active_lock: active_lock.clone(),
-                session_id: pane.session_id,
-                window_id: pane.window_id,
-                pane_id: pane.pane_id,
-                cursor_x: pane.cursor_x,
-                cursor_y: pane.cursor_y,
-                pane_width: pane.pane_width,
-                pane_height: pane.pane_height,
-                pane_left: pane.pane_left,
-                pane_top: pane.pane_top,
-            }));
-
-            {
-                let mut pane_map = self.remote_panes.borrow_mut();
-                pane_map.insert(pane.pane_id, ref_pane.clone());
-            }
-
-            let pane_pty = TmuxPty {
-                domain_id: self.domain_id,
-                reader: output_read,
-                cmd_queue: self.cmd_queue.clone(),
-                master_pane: ref_pane,
-            };
-            let writer = pane_pty.try_clone_writer()?;
-            let mux = Mux::get().expect("should be called at main thread");
-            let size = PtySize {
-                rows: pane.pane_height as u16,
-                cols: pane.pane_width as u16,
-                pixel_width: 0,
-                pixel_height: 0,
-            };
-
-            let child = TmuxChild {
-                active_lock: active_lock.clone(),
-            };
-
-            let terminal = wezterm_term::Terminal::new(
-                crate::pty_size_to_terminal_size(size),
-                std::sync::Arc::new(config::TermConfig::new()),
-                "WezTerm",
-                config::wezterm_version(),
-                Box::new(writer),
-            );
-
-            let local_pane: Rc<dyn Pane> = Rc::new(LocalPane::new(
-                local_pane_id,
-                terminal,
-                Box::new(child),
-                Box::new(pane_pty),
-                self.domain_id,
-                "tmux pane".to_string(),
-            ));
-
-            let tab = Rc::new(Tab::new(&size));
-            tab.assign_pane(&local_pane);
-
-            self.create_gui_window();
-            let mut gui_window = self.gui_window.borrow_mut();
-            let gui_window_id = match gui_window.as_mut() {
-                Some(x) => x,
-                None => {
-                    anyhow::bail!("No tmux gui created");
-                }
-            };
-
-            mux.add_tab_and_active_pane(&tab)?;
-            mux.add_tab_to_window(&tab, **gui_window_id)?;
-            gui_window_id.notify();
+            self.attach_pane_in_new_tab(pane, None)?;
 
             self.cmd_queue
                 .lock()
@@ -179,21 +331,258 @@ impl TmuxDomainState {
                 .push_back(Box::new(CapturePane(pane.pane_id)));
             TmuxDomainState::schedule_send_next_command(self.domain_id);
 
-            self.add_attached_pane(&pane, &tab.tab_id())?;
             log::info!("new pane attached");
         }
         Ok(())
     }
 }
 
+impl TmuxDomainState {
+    /// Apply the layout of a tmux window to the local tab that mirrors
+    /// it.  The tab is created if this is a window that we haven't seen
+    /// before, as are any of its panes that are new to us.  Panes that
+    /// are no longer part of the window are released.
+    pub(crate) fn sync_window_layout(
+        &self,
+        window: TmuxWindowId,
+        layout: &WindowLayout,
+    ) -> anyhow::Result<()> {
+        let mux = Mux::get().expect("should be called at main thread");
+        let size = layout_size(&layout.root);
+
+        let existing_tab = self
+            .gui_tabs
+            .borrow()
+            .iter()
+            .find(|x| x.tmux_window_id == window)
+            .and_then(|x| mux.get_tab(x.tab_id));
+        let is_new_tab = existing_tab.is_none();
+        let tab = existing_tab.unwrap_or_else(|| Rc::new(Tab::new(&size)));
+        let tab_id = tab.tab_id();
+
+        let window_id = match mux.window_containing_tab(tab_id) {
+            Some(window_id) => window_id,
+            None => {
+                self.create_gui_window();
+                match self.gui_window.borrow().as_ref() {
+                    Some(gui_window) => **gui_window,
+                    None => anyhow::bail!("No tmux gui created"),
+                }
+            }
+        };
+        let workspace = mux
+            .get_window(window_id)
+            .map(|w| w.get_workspace().to_string())
+            .unwrap_or_default();
+        let active_pane_id = tab.get_active_pane().map(|p| p.pane_id());
+
+        let mut layout_panes = HashSet::new();
+        let mut local_panes = HashMap::new();
+        let root = layout_to_pane_node(&layout.root, &mut |pane_id, size| {
+            let local_pane = self.layout_pane(window, pane_id, size)?;
+            let entry = PaneEntry {
+                window_id,
+                tab_id,
+                pane_id: local_pane.pane_id(),
+                title: local_pane.get_title(),
+                size,
+                working_dir: None,
+                is_active_pane: active_pane_id == Some(local_pane.pane_id()),
+                is_zoomed_pane: false,
+                workspace: workspace.clone(),
+                cursor_pos: Default::default(),
+                physical_top: 0,
+            };
+            layout_panes.insert(pane_id);
+            local_panes.insert(local_pane.pane_id(), local_pane);
+            Ok(entry)
+        })?;
+
+        let stale_panes: Vec<TmuxPaneId> = self
+            .gui_tabs
+            .borrow()
+            .iter()
+            .filter(|x| x.tmux_window_id == window)
+            .flat_map(|x| x.panes.difference(&layout_panes).copied())
+            .collect();
+        for pane_id in stale_panes {
+            self.release_pane(pane_id);
+        }
+
+        tab.sync_with_pane_tree(size, root, |entry| {
+            local_panes
+                .remove(&entry.pane_id)
+                .expect("every layout entry to have a local pane")
+        });
+
+        {
+            let mut gui_tabs = self.gui_tabs.borrow_mut();
+            match gui_tabs.iter_mut().find(|x| x.tmux_window_id == window) {
+                Some(x) => x.panes = layout_panes,
+                None => gui_tabs.push(TmuxTab {
+                    tab_id,
+                    tmux_window_id: window,
+                    panes: layout_panes,
+                }),
+            }
+        }
+
+        if is_new_tab {
+            mux.add_tab_and_active_pane(&tab)?;
+            mux.add_tab_to_window(&tab, window_id)?;
+            if let Some(gui_window) = self.gui_window.borrow_mut().as_mut() {
+                gui_window.notify();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the local counterpart of a pane that is part of the layout
+    /// of `window`, creating it if it is new to us
+    fn layout_pane(
+        &self,
+        window: TmuxWindowId,
+        pane_id: TmuxPaneId,
+        size: PtySize,
+    ) -> anyhow::Result<Rc<dyn Pane>> {
+        if let Some(remote_pane) = self.remote_panes.borrow().get(&pane_id) {
+            // Record the size that tmux is using, so that resizing the
+            // local pane to match isn't reflected back to tmux
+            let mut remote_pane = remote_pane.lock().unwrap();
+            remote_pane.window_id = window;
+            remote_pane.pane_width = size.cols as u64;
+            remote_pane.pane_height = size.rows as u64;
+        }
+
+        if let Some(local_pane) = self.attached_local_pane(pane_id) {
+            return Ok(local_pane);
+        }
+
+        let item = PaneItem {
+            session_id: self.tmux_session.borrow().unwrap_or(0),
+            window_id: window,
+            pane_id,
+            _pane_index: 0,
+            cursor_x: 0,
+            cursor_y: 0,
+            pane_width: size.cols as u64,
+            pane_height: size.rows as u64,
+            pane_left: 0,
+            pane_top: 0,
+        };
+        let local_pane = self.create_local_pane(&item)?;
+        Mux::get()
+            .expect("should be called at main thread")
+            .add_pane(&local_pane)?;
+
+        self.queue_command(Box::new(CapturePane(pane_id)));
+        log::info!("new pane %{} attached via layout", pane_id);
+
+        Ok(local_pane)
+    }
+}
+
+fn layout_size(node: &WindowLayoutNode) -> PtySize {
+    PtySize {
+        rows: node.height() as u16,
+        cols: node.width() as u16,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+/// Convert a tmux window layout into the tree structure used by `Tab`.
+/// tmux splits may have any number of children whereas ours are binary,
+/// so the children after the first one are represented as a nested split
+/// in the same direction.
+/// `make_entry` is called to produce the entry for each pane.
+fn layout_to_pane_node<F>(node: &WindowLayoutNode, make_entry: &mut F) -> anyhow::Result<PaneNode>
+where
+    F: FnMut(TmuxPaneId, PtySize) -> anyhow::Result<PaneEntry>,
+{
+    match node {
+        WindowLayoutNode::Pane { pane, .. } => {
+            Ok(PaneNode::Leaf(make_entry(*pane, layout_size(node))?))
+        }
+        WindowLayoutNode::Horizontal { children, .. } => {
+            split_to_pane_node(children, SplitDirection::Horizontal, make_entry)
+        }
+        WindowLayoutNode::Vertical { children, .. } => {
+            split_to_pane_node(children, SplitDirection::Vertical, make_entry)
+        }
+    }
+}
+
+fn split_to_pane_node<F>(
+    children: &[WindowLayoutNode],
+    direction: SplitDirection,
+    make_entry: &mut F,
+) -> anyhow::Result<PaneNode>
+where
+    F: FnMut(TmuxPaneId, PtySize) -> anyhow::Result<PaneEntry>,
+{
+    match children {
+        [] => anyhow::bail!("tmux layout contains an empty split"),
+        [only] => layout_to_pane_node(only, make_entry),
+        [first, rest @ ..] => {
+            let (second, last) = (&rest[0], &rest[rest.len() - 1]);
+            let second = match direction {
+                SplitDirection::Horizontal => PtySize {
+                    rows: second.height() as u16,
+                    cols: (last.x() + last.width() - second.x()) as u16,
+                    pixel_width: 0,
+                    pixel_height: 0,
+                },
+                SplitDirection::Vertical => PtySize {
+                    rows: (last.y() + last.height() - second.y()) as u16,
+                    cols: second.width() as u16,
+                    pixel_width: 0,
+                    pixel_height: 0,
+                },
+            };
+            Ok(PaneNode::Split {
+                left: Box::new(layout_to_pane_node(first, make_entry)?),
+                right: Box::new(split_to_pane_node(rest, direction, make_entry)?),
+                node: SplitDirectionAndSize {
+                    direction,
+                    first: layout_size(first),
+                    second,
+                },
+            })
+        }
+    }
+}
+
+/// Pass the outcome of a pane creating command on to the task that
+/// is waiting for it, and return it for logging by the caller
+fn reply_with_pane(
+    reply: &Sender<anyhow::Result<PaneId>>,
+    outcome: anyhow::Result<Rc<dyn Pane>>,
+) -> anyhow::Result<()> {
+    match outcome {
+        Ok(pane) => {
+            reply.try_send(Ok(pane.pane_id())).ok();
+            Ok(())
+        }
+        Err(err) => {
+            reply.try_send(Err(anyhow!("{:#}", err))).ok();
+            Err(err)
+        }
+    }
+}
+
+/// Quote `s` so that it is seen as a single argument by the tmux
+/// command parser
+fn quote(s: &str) -> String {
+    shell_words::quote(s).into_owned()
+}
+
 #[derive(Debug)]
 pub(crate) struct ListAllPanes;
 impl TmuxCommand for ListAllPanes {
     fn get_command(&self) -> String {
-        "list-panes -aF '#{session_id} #{window_id} #{pane_id} \
-            #{pane_index} #{cursor_x} #{cursor_y} #{pane_width} #{pane_height} \
-            #{pane_left} #{pane_top}'\n"
-            .to_owned()
+        format!("list-panes -aF '{}'\n", PANE_FORMAT)
     }
 
     fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
@@ -203,57 +592,7 @@ impl TmuxCommand for ListAllPanes {
             if line.is_empty() {
                 continue;
             }
-            let mut fields = line.split(' ');
-            let session_id = fields.next().ok_or_else(|| anyhow!("missing session_id"))?;
-            let window_id = fields.next().ok_or_else(|| anyhow!("missing window_id"))?;
-            let pane_id = fields.next().ok_or_else(|| anyhow!("missing pane_id"))?;
-            let _pane_index = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_index"))?
-                .parse()?;
-            let cursor_x = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing cursor_x"))?
-                .parse()?;
-            let cursor_y = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing cursor_y"))?
-                .parse()?;
-            let pane_width = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_width"))?
-                .parse()?;
-            let pane_height = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_height"))?
-                .parse()?;
-            let pane_left = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_left"))?
-                .parse()?;
-            let pane_top = fields
-                .next()
-                .ok_or_else(|| anyhow!("missing pane_top"))?
-                .parse()?;
-
-            // These ids all have various sigils such as `$`, `%`, `@`,
-            // so skip those prior to parsing them
-            let session_id = session_id[1..].parse()?;
-            let window_id = window_id[1..].parse()?;
-            let pane_id = pane_id[1..].parse()?;
-
-            items.push(PaneItem {
-                session_id,
-                window_id,
-                pane_id,
-                _pane_index,
-                cursor_x,
-                cursor_y,
-                pane_width,
-                pane_height,
-                pane_left,
-                pane_top,
-            });
+            items.push(PaneItem::parse(line)?);
         }
 
         log::info!("panes in domain_id {}: {:?}", domain_id, items);
@@ -267,6 +606,49 @@ impl TmuxCommand for ListAllPanes {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct ListWindows;
+impl TmuxCommand for ListWindows {
+    fn get_command(&self) -> String {
+        "list-windows -F '#{window_id} #{window_layout}'\n".to_owned()
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        let mut layouts = vec![];
+        for line in result.output.split('\n') {
+            if line.is_empty() {
+                continue;
+            }
+            let mut fields = line.split(' ');
+            let window_id = fields.next().ok_or_else(|| anyhow!("missing window_id"))?;
+            let layout = fields
+                .next()
+                .ok_or_else(|| anyhow!("missing window_layout"))?;
+            let window_id: TmuxWindowId = window_id[1..].parse()?;
+            let layout: WindowLayout = layout.parse()?;
+            layouts.push((window_id, layout));
+        }
+
+        let mux = Mux::get().expect("to be called on main thread");
+        let domain = mux
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("Tmux domain lost"))?;
+        let tmux_domain = domain
+            .downcast_ref::<TmuxDomain>()
+            .ok_or_else(|| anyhow!("Tmux domain lost"))?;
+        for (window_id, layout) in layouts {
+            if let Err(err) = tmux_domain.inner.sync_window_layout(window_id, &layout) {
+                log::error!(
+                    "Failed to apply layout of tmux window @{}: {:#}",
+                    window_id,
+                    err
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Resize {
     pub size: portable_pty::PtySize,
@@ -341,3 +723,219 @@ impl TmuxCommand for SendKeys {
         Ok(())
     }
 }
+
+#[derive(Debug)]
+pub(crate) struct NewWindow {
+    /// The local window that should hold the new tab; when `None`,
+    /// the tab is placed into the tmux gui window.
+    pub window_id: Option<WindowId>,
+    pub command: Option<String>,
+    pub command_dir: Option<String>,
+    pub reply: Sender<anyhow::Result<PaneId>>,
+}
+
+impl NewWindow {
+    fn attach(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<Rc<dyn Pane>> {
+        let item = PaneItem::parse_single(result).context("new-window")?;
+        let mux = Mux::get().expect("to be called on main thread");
+        let domain = mux
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("Tmux domain lost"))?;
+        let tmux_domain = domain
+            .downcast_ref::<TmuxDomain>()
+            .ok_or_else(|| anyhow!("Tmux domain lost"))?;
+        tmux_domain
+            .inner
+            .attach_pane_in_new_tab(&item, self.window_id)
+    }
+}
+
+impl TmuxCommand for NewWindow {
+    fn get_command(&self) -> String {
+        let mut cmd = format!("new-window -P -F '{}'", PANE_FORMAT);
+        if let Some(dir) = &self.command_dir {
+            write!(&mut cmd, " -c {}", quote(dir)).expect("unable to write cwd");
+        }
+        if let Some(command) = &self.command {
+            write!(&mut cmd, " {}", quote(command)).expect("unable to write command");
+        }
+        cmd.push('\n');
+        cmd
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        reply_with_pane(&self.reply, self.attach(domain_id, result))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct SplitWindow {
+    /// The remote pane to split
+    pub target: TmuxPaneId,
+    /// The local tab holding the counterpart of `target`
+    pub tab_id: TabId,
+    /// The local counterpart of `target`
+    pub pane_id: PaneId,
+    pub direction: SplitDirection,
+    pub command: Option<String>,
+    pub command_dir: Option<String>,
+    pub reply: Sender<anyhow::Result<PaneId>>,
+}
+
+impl SplitWindow {
+    fn attach(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<Rc<dyn Pane>> {
+        let item = PaneItem::parse_single(result).context("split-window")?;
+        let mux = Mux::get().expect("to be called on main thread");
+        let domain = mux
+            .get_domain(domain_id)
+            .ok_or_else(|| anyhow!("Tmux domain lost"))?;
+        let tmux_domain = domain
+            .downcast_ref::<TmuxDomain>()
+            .ok_or_else(|| anyhow!("Tmux domain lost"))?;
+        tmux_domain
+            .inner
+            .attach_split_pane(&item, self.tab_id, self.pane_id, self.direction)
+    }
+}
+
+impl TmuxCommand for SplitWindow {
+    fn get_command(&self) -> String {
+        // Note that wezterm and tmux agree on the meaning of horizontal:
+        // the new pane is placed alongside the existing pane
+        let direction = match self.direction {
+            SplitDirection::Horizontal => "-h",
+            SplitDirection::Vertical => "-v",
+        };
+        let mut cmd = format!(
+            "split-window {} -t %{} -P -F '{}'",
+            direction, self.target, PANE_FORMAT
+        );
+        if let Some(dir) = &self.command_dir {
+            write!(&mut cmd, " -c {}", quote(dir)).expect("unable to write cwd");
+        }
+        if let Some(command) = &self.command {
+            write!(&mut cmd, " {}", quote(command)).expect("unable to write command");
+        }
+        cmd.push('\n');
+        cmd
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        reply_with_pane(&self.reply, self.attach(domain_id, result))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct KillPane(pub TmuxPaneId);
+impl TmuxCommand for KillPane {
+    fn get_command(&self) -> String {
+        format!("kill-pane -t %{}\n", self.0)
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        if result.error {
+            anyhow::bail!("kill-pane %{} failed: {}", self.0, result.output.trim());
+        }
+        let mux = Mux::get().expect("to be called on main thread");
+        let domain = match mux.get_domain(domain_id) {
+            Some(d) => d,
+            None => anyhow::bail!("Tmux domain lost"),
+        };
+        let tmux_domain = match domain.downcast_ref::<TmuxDomain>() {
+            Some(t) => t,
+            None => anyhow::bail!("Tmux domain lost"),
+        };
+        tmux_domain.inner.release_pane(self.0);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct DetachClient;
+impl TmuxCommand for DetachClient {
+    fn get_command(&self) -> String {
+        "detach-client\n".to_owned()
+    }
+
+    fn process_result(&self, domain_id: DomainId, result: &Guarded) -> anyhow::Result<()> {
+        // The panes are released when tmux subsequently sends %exit
+        if result.error {
+            log::error!(
+                "Error detaching: domain_id={} result={:?}",
+                domain_id,
+                result
+            );
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn entry(pane_id: TmuxPaneId, size: PtySize) -> anyhow::Result<PaneEntry> {
+        Ok(PaneEntry {
+            window_id: 0,
+            tab_id: 0,
+            pane_id: pane_id as PaneId,
+            title: String::new(),
+            size,
+            working_dir: None,
+            is_active_pane: false,
+            is_zoomed_pane: false,
+            workspace: String::new(),
+            cursor_pos: Default::default(),
+            physical_top: 0,
+        })
+    }
+
+    fn size(cols: u16, rows: u16) -> PtySize {
+        PtySize {
+            rows,
+            cols,
+            pixel_width: 0,
+            pixel_height: 0,
+        }
+    }
+
+    #[test]
+    fn layout_to_binary_tree() {
+        let layout: WindowLayout =
+            "5e8f,161x41,0,0{53x41,0,0,1,53x41,54,0,2,53x41,108,0[53x20,108,0,3,53x20,108,21,4]}"
+                .parse()
+                .unwrap();
+        let node = layout_to_pane_node(&layout.root, &mut entry).unwrap();
+
+        let leaf = |pane_id, cols, rows| PaneNode::Leaf(entry(pane_id, size(cols, rows)).unwrap());
+
+        assert_eq!(
+            node,
+            PaneNode::Split {
+                left: Box::new(leaf(1, 53, 41)),
+                right: Box::new(PaneNode::Split {
+                    left: Box::new(leaf(2, 53, 41)),
+                    right: Box::new(PaneNode::Split {
+                        left: Box::new(leaf(3, 53, 20)),
+                        right: Box::new(leaf(4, 53, 20)),
+                        node: SplitDirectionAndSize {
+                            direction: SplitDirection::Vertical,
+                            first: size(53, 20),
+                            second: size(53, 20),
+                        },
+                    }),
+                    node: SplitDirectionAndSize {
+                        direction: SplitDirection::Horizontal,
+                        first: size(53, 41),
+                        second: size(53, 41),
+                    },
+                }),
+                node: SplitDirectionAndSize {
+                    direction: SplitDirection::Horizontal,
+                    first: size(53, 41),
+                    second: size(107, 41),
+                },
+            }
+        );
+    }
+}
@@ -1,10 +1,11 @@
 use crate::tmux::{RefTmuxRemotePane, TmuxCmdQueue, TmuxDomainState};
-use crate::tmux_commands::{Resize, SendKeys};
-use crate::DomainId;
+use crate::tmux_commands::{KillPane, Resize, SendKeys};
+use crate::{DomainId, Mux};
 use filedescriptor::FileDescriptor;
 use portable_pty::{Child, ChildKiller, ExitStatus, MasterPty};
 use std::io::{Read, Write};
 use std::sync::{Arc, Condvar, Mutex};
+use termwiz::tmux_cc::TmuxPaneId;
 
 /// A local tmux pane(tab) based on a tmux pty
 #[derive(Debug)]
@@ -66,6 +67,9 @@ impl Write for TmuxPty {
 #[derive(Clone, Debug)]
 pub(crate) struct TmuxChild {
     pub active_lock: Arc<(Mutex<bool>, Condvar)>,
+    pub domain_id: DomainId,
+    pub pane_id: TmuxPaneId,
+    pub cmd_queue: Arc<Mutex<TmuxCmdQueue>>,
 }
 
 impl Child for TmuxChild {
@@ -92,15 +96,21 @@ impl Child for TmuxChild {
     }
 }
 
+/// Kills the remote tmux pane; the local pane is released once
+/// tmux has confirmed that the pane is gone
 #[derive(Clone, Debug)]
-struct TmuxChildKiller {}
+struct TmuxChildKiller {
+    domain_id: DomainId,
+    pane_id: TmuxPaneId,
+    cmd_queue: Arc<Mutex<TmuxCmdQueue>>,
+}
 
 impl ChildKiller for TmuxChildKiller {
     fn kill(&mut self) -> std::io::Result<()> {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "TmuxChildKiller: kill not implemented!",
-        ))
+        let mut cmd_queue = self.cmd_queue.lock().unwrap();
+        cmd_queue.push_back(Box::new(KillPane(self.pane_id)));
+        TmuxDomainState::schedule_send_next_command(self.domain_id);
+        Ok(())
     }
 
     fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
@@ -110,19 +120,43 @@ impl ChildKiller for TmuxChildKiller {
 
 impl ChildKiller for TmuxChild {
     fn kill(&mut self) -> std::io::Result<()> {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "TmuxPty: kill not implemented!",
-        ))
+        let mut cmd_queue = self.cmd_queue.lock().unwrap();
+        cmd_queue.push_back(Box::new(KillPane(self.pane_id)));
+        TmuxDomainState::schedule_send_next_command(self.domain_id);
+        Ok(())
     }
 
     fn clone_killer(&self) -> Box<dyn ChildKiller + Send + Sync> {
-        Box::new(TmuxChildKiller {})
+        Box::new(TmuxChildKiller {
+            domain_id: self.domain_id,
+            pane_id: self.pane_id,
+            cmd_queue: self.cmd_queue.clone(),
+        })
     }
 }
 
 impl MasterPty for TmuxPty {
     fn resize(&self, size: portable_pty::PtySize) -> Result<(), anyhow::Error> {
+        let local_pane_id = {
+            let pane = self.master_pane.lock().unwrap();
+            if pane.pane_width == size.cols as u64 && pane.pane_height == size.rows as u64 {
+                // We're being resized to match the layout that tmux
+                // told us about; there is nothing to tell tmux
+                return Ok(());
+            }
+            pane.local_pane_id
+        };
+
+        // tmux sizes its windows to fit the client, which corresponds
+        // to the tab rather than to this individual pane
+        let size = Mux::get()
+            .and_then(|mux| {
+                let (_domain_id, _window_id, tab_id) = mux.resolve_pane_id(local_pane_id)?;
+                mux.get_tab(tab_id)
+            })
+            .map(|tab| tab.get_size())
+            .unwrap_or(size);
+
         let mut cmd_queue = self.cmd_queue.lock().unwrap();
         cmd_queue.push_back(Box::new(Resize { size }));
         TmuxDomainState::schedule_send_next_command(self.domain_id);
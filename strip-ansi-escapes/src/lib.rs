@@ -0,0 +1,4 @@
+//! Converts the output of a program into markup.
+//! This is used by the `strip-ansi-escapes` utility, and by
+//! `wezterm export` to render asciicast recordings.
+pub mod markup;
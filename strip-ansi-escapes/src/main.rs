@@ -1,8 +1,30 @@
-use std::io::{Read, Result};
+use std::io::{BufWriter, Read};
+use std::str::FromStr;
 use structopt::StructOpt;
 use termwiz::escape::parser::Parser;
 use termwiz::escape::{Action, ControlCode};
 
+use strip_ansi_escapes::markup::{parse_color, Format, MarkupWriter, Palette};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputFormat {
+    Strip,
+    Html,
+    Json,
+}
+
+impl FromStr for OutputFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "strip" => Ok(Self::Strip),
+            "html" => Ok(Self::Html),
+            "json" => Ok(Self::Json),
+            _ => anyhow::bail!("invalid format {}; expected strip, html or json", s),
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(
     global_setting = structopt::clap::AppSettings::ColoredHelp,
@@ -11,13 +33,46 @@ use termwiz::escape::{Action, ControlCode};
 /// stdin and prints the result on stdout.
 /// It preserves only printable characters and CR, LF and HT.
 ///
+/// Alternatively, it can convert the colors, attributes and
+/// hyperlinks that are set by the escape sequences into HTML,
+/// or into a JSON list of runs of text.
+///
 /// This utility is part of WezTerm.
 ///
 /// https://github.com/wez/wezterm
-struct Opt {}
+struct Opt {
+    /// How to produce the output.
+    /// `strip` removes the escape sequences, while `html` and `json`
+    /// preserve the styling of the text.
+    #[structopt(
+        long,
+        default_value = "strip",
+        possible_values = &["strip", "html", "json"]
+    )]
+    format: OutputFormat,
 
-fn main() -> Result<()> {
-    let _ = Opt::from_args();
+    /// The colors to use in place of the first entries of the
+    /// default xterm palette, separated by `:` or `,`.
+    /// For example: `#000000:#cc0000:#4e9a06:#c4a000`
+    #[structopt(long)]
+    palette: Option<String>,
+
+    /// The default text color
+    #[structopt(long)]
+    foreground: Option<String>,
+
+    /// The default background color
+    #[structopt(long)]
+    background: Option<String>,
+
+    /// Treat a carriage return as moving back to the start of the
+    /// line, so that only the final state of a line that was
+    /// repeatedly overwritten, such as a progress bar, is output
+    #[structopt(long)]
+    collapse_cr: bool,
+}
+
+fn strip() -> anyhow::Result<()> {
     let mut buf = [0u8; 4096];
 
     let mut parser = Parser::new();
@@ -40,3 +95,48 @@ fn main() -> Result<()> {
         });
     }
 }
+
+fn markup(opt: &Opt, format: Format) -> anyhow::Result<()> {
+    let mut palette = Palette::default();
+    if let Some(spec) = &opt.palette {
+        palette.apply_spec(spec)?;
+    }
+    if let Some(fg) = &opt.foreground {
+        palette.foreground = parse_color(fg)?;
+    }
+    if let Some(bg) = &opt.background {
+        palette.background = parse_color(bg)?;
+    }
+
+    let stdout = std::io::stdout();
+    let mut writer = MarkupWriter::new(
+        BufWriter::new(stdout.lock()),
+        format,
+        palette,
+        opt.collapse_cr,
+    )?;
+
+    let mut buf = [0u8; 4096];
+    let mut parser = Parser::new();
+    loop {
+        let len = std::io::stdin().read(&mut buf)?;
+        if len == 0 {
+            break;
+        }
+        for action in parser.parse_as_vec(&buf[0..len]) {
+            writer.perform(action)?;
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    match opt.format {
+        OutputFormat::Strip => strip(),
+        OutputFormat::Html => markup(&opt, Format::Html),
+        OutputFormat::Json => markup(&opt, Format::Json),
+    }
+}
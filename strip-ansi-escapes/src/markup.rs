@@ -0,0 +1,503 @@
+//! Converts the output of a program into HTML or a JSON list of runs,
+//! preserving the colors, attributes and hyperlinks that were set
+//! by escape sequences.
+use serde::Serialize;
+use std::io::{Result, Write};
+use std::sync::Arc;
+use termwiz::cell::{CellAttributes, Intensity, Underline};
+use termwiz::color::{ColorAttribute, RgbColor, SrgbaTuple};
+use termwiz::escape::csi::{Edit, EraseInLine, Sgr};
+use termwiz::escape::{Action, ControlCode, OperatingSystemCommand, CSI};
+
+/// The colors used by xterm for the first 16 palette entries
+const DEFAULT_ANSI_COLORS: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+    "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Html,
+    Json,
+}
+
+/// The colors used to resolve the colors in the output
+#[derive(Debug, Clone)]
+pub struct Palette {
+    pub colors: Vec<SrgbaTuple>,
+    pub foreground: SrgbaTuple,
+    pub background: SrgbaTuple,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let mut colors: Vec<SrgbaTuple> = DEFAULT_ANSI_COLORS
+            .iter()
+            .map(|s| s.parse().expect("valid default color"))
+            .collect();
+
+        // The 6x6x6 color cube
+        let ramp = [0u8, 0x5f, 0x87, 0xaf, 0xd7, 0xff];
+        for idx in 0..216 {
+            let red = ramp[idx / 36];
+            let green = ramp[(idx / 6) % 6];
+            let blue = ramp[idx % 6];
+            colors.push(RgbColor::new_8bpc(red, green, blue).into());
+        }
+
+        // The grayscale ramp
+        for idx in 0..24u8 {
+            let level = 8 + idx * 10;
+            colors.push(RgbColor::new_8bpc(level, level, level).into());
+        }
+
+        Self {
+            foreground: colors[7],
+            background: colors[0],
+            colors,
+        }
+    }
+}
+
+impl Palette {
+    /// Replaces the leading entries of the palette with the colors
+    /// from `spec`, which is a list of colors separated by `:` or `,`
+    /// in the same form as the palette of an asciicast theme.
+    pub fn apply_spec(&mut self, spec: &str) -> anyhow::Result<()> {
+        for (idx, color) in spec.split(&[':', ','][..]).enumerate() {
+            if idx >= self.colors.len() {
+                anyhow::bail!("too many colors in palette {}", spec);
+            }
+            self.colors[idx] = parse_color(color)?;
+        }
+        Ok(())
+    }
+
+    fn resolve(&self, color: ColorAttribute, default: SrgbaTuple) -> SrgbaTuple {
+        match color {
+            ColorAttribute::Default => default,
+            ColorAttribute::PaletteIndex(idx) => self.colors[idx as usize],
+            ColorAttribute::TrueColorWithPaletteFallback(color, _)
+            | ColorAttribute::TrueColorWithDefaultFallback(color) => color.into(),
+        }
+    }
+}
+
+pub fn parse_color(color: &str) -> anyhow::Result<SrgbaTuple> {
+    RgbColor::from_named_or_rgb_string(color.trim())
+        .map(Into::into)
+        .ok_or_else(|| anyhow::anyhow!("invalid color {}", color))
+}
+
+/// A run of text that has the same attributes.
+/// Only the attributes that differ from the defaults are serialized.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct Run {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bg: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub dim: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub underline: Option<&'static str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub underline_color: Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub strikethrough: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub overline: bool,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub invisible: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+impl Run {
+    pub fn new(text: String, attrs: &CellAttributes, palette: &Palette) -> Self {
+        let mut fg = palette.resolve(attrs.foreground(), palette.foreground);
+        let mut bg = palette.resolve(attrs.background(), palette.background);
+        let reverse = attrs.reverse();
+        if reverse {
+            std::mem::swap(&mut fg, &mut bg);
+        }
+
+        Self {
+            text,
+            fg: if attrs.foreground() != ColorAttribute::Default || reverse {
+                Some(fg.to_rgb_string())
+            } else {
+                None
+            },
+            bg: if attrs.background() != ColorAttribute::Default || reverse {
+                Some(bg.to_rgb_string())
+            } else {
+                None
+            },
+            bold: attrs.intensity() == Intensity::Bold,
+            dim: attrs.intensity() == Intensity::Half,
+            italic: attrs.italic(),
+            underline: match attrs.underline() {
+                Underline::None => None,
+                Underline::Single => Some("solid"),
+                Underline::Double => Some("double"),
+                Underline::Curly => Some("wavy"),
+                Underline::Dotted => Some("dotted"),
+                Underline::Dashed => Some("dashed"),
+            },
+            underline_color: match attrs.underline_color() {
+                ColorAttribute::Default => None,
+                color => Some(palette.resolve(color, fg).to_rgb_string()),
+            },
+            strikethrough: attrs.strikethrough(),
+            overline: attrs.overline(),
+            invisible: attrs.invisible(),
+            link: attrs.hyperlink().map(|link| link.uri().to_string()),
+        }
+    }
+
+    /// Returns the lines that decorate the text, in the form
+    /// used by the css `text-decoration-line` property
+    pub fn text_decoration_line(&self) -> Option<String> {
+        let mut lines = vec![];
+        if self.underline.is_some() {
+            lines.push("underline");
+        }
+        if self.strikethrough {
+            lines.push("line-through");
+        }
+        if self.overline {
+            lines.push("overline");
+        }
+        if lines.is_empty() {
+            None
+        } else {
+            Some(lines.join(" "))
+        }
+    }
+
+    /// Returns the inline CSS for the run
+    pub fn css(&self) -> String {
+        let mut css = vec![];
+        if let Some(fg) = &self.fg {
+            css.push(format!("color:{}", fg));
+        }
+        if let Some(bg) = &self.bg {
+            css.push(format!("background-color:{}", bg));
+        }
+        if self.bold {
+            css.push("font-weight:bold".to_string());
+        }
+        if self.dim {
+            css.push("opacity:0.5".to_string());
+        }
+        if self.italic {
+            css.push("font-style:italic".to_string());
+        }
+        if let Some(lines) = self.text_decoration_line() {
+            css.push(format!("text-decoration-line:{}", lines));
+        }
+        if let Some(style) = self.underline {
+            css.push(format!("text-decoration-style:{}", style));
+        }
+        if let Some(color) = &self.underline_color {
+            css.push(format!("text-decoration-color:{}", color));
+        }
+        if self.invisible {
+            css.push("visibility:hidden".to_string());
+        }
+        css.join(";")
+    }
+
+    /// Writes the run as html, wrapped in a span that applies its
+    /// style and a link to its hyperlink, if any
+    pub fn write_html<W: Write>(&self, out: &mut W) -> Result<()> {
+        let text = escape_html(&self.text);
+        let css = self.css();
+        let text = if css.is_empty() {
+            text
+        } else {
+            format!("<span style=\"{}\">{}</span>", css, text)
+        };
+        match &self.link {
+            Some(link) => write!(out, "<a href=\"{}\">{}</a>", escape_html(link), text),
+            None => write!(out, "{}", text),
+        }
+    }
+}
+
+pub fn escape_html(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&#39;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Applies escape sequences to the current line of text, and writes
+/// out each line as it is completed.
+pub struct MarkupWriter<W: Write> {
+    out: W,
+    format: Format,
+    palette: Palette,
+    pen: CellAttributes,
+    /// When true, a carriage return moves back to the start of the
+    /// line so that subsequent text overwrites it, rather than being
+    /// written to the output
+    collapse_cr: bool,
+    line: Vec<(char, CellAttributes)>,
+    cursor: usize,
+    wrote_run: bool,
+}
+
+impl<W: Write> MarkupWriter<W> {
+    pub fn new(mut out: W, format: Format, palette: Palette, collapse_cr: bool) -> Result<Self> {
+        match format {
+            Format::Html => writeln!(
+                out,
+                "<pre style=\"color:{};background-color:{}\">",
+                palette.foreground.to_rgb_string(),
+                palette.background.to_rgb_string()
+            )?,
+            Format::Json => write!(out, "[")?,
+        }
+        Ok(Self {
+            out,
+            format,
+            palette,
+            pen: CellAttributes::default(),
+            collapse_cr,
+            line: vec![],
+            cursor: 0,
+            wrote_run: false,
+        })
+    }
+
+    fn print(&mut self, c: char) {
+        let cell = (c, self.pen.clone());
+        if self.cursor < self.line.len() {
+            self.line[self.cursor] = cell;
+        } else {
+            self.line.push(cell);
+        }
+        self.cursor += 1;
+    }
+
+    pub fn perform(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Print(c) => self.print(c),
+            Action::Control(ControlCode::HorizontalTab) => self.print('\t'),
+            Action::Control(ControlCode::CarriageReturn) => {
+                if self.collapse_cr {
+                    self.cursor = 0;
+                } else {
+                    self.print('\r');
+                }
+            }
+            Action::Control(ControlCode::LineFeed) => {
+                self.flush_line()?;
+            }
+            Action::CSI(CSI::Sgr(sgr)) => self.apply_sgr(sgr),
+            Action::CSI(CSI::Edit(Edit::EraseInLine(EraseInLine::EraseToEndOfLine)))
+                if self.collapse_cr =>
+            {
+                self.line.truncate(self.cursor);
+            }
+            Action::OperatingSystemCommand(osc) => {
+                if let OperatingSystemCommand::SetHyperlink(link) = *osc {
+                    self.pen.set_hyperlink(link.map(Arc::new));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn apply_sgr(&mut self, sgr: Sgr) {
+        match sgr {
+            Sgr::Reset => {
+                let link = self.pen.hyperlink().map(Arc::clone);
+                self.pen = CellAttributes::default();
+                self.pen.set_hyperlink(link);
+            }
+            Sgr::Intensity(intensity) => {
+                self.pen.set_intensity(intensity);
+            }
+            Sgr::Underline(underline) => {
+                self.pen.set_underline(underline);
+            }
+            Sgr::Overline(overline) => {
+                self.pen.set_overline(overline);
+            }
+            Sgr::Blink(blink) => {
+                self.pen.set_blink(blink);
+            }
+            Sgr::Italic(italic) => {
+                self.pen.set_italic(italic);
+            }
+            Sgr::Inverse(inverse) => {
+                self.pen.set_reverse(inverse);
+            }
+            Sgr::Invisible(invis) => {
+                self.pen.set_invisible(invis);
+            }
+            Sgr::StrikeThrough(strike) => {
+                self.pen.set_strikethrough(strike);
+            }
+            Sgr::Foreground(col) => {
+                self.pen.set_foreground(col);
+            }
+            Sgr::Background(col) => {
+                self.pen.set_background(col);
+            }
+            Sgr::UnderlineColor(col) => {
+                self.pen.set_underline_color(col);
+            }
+            Sgr::Font(_) => {}
+        }
+    }
+
+    fn write_run(&mut self, run: Run) -> Result<()> {
+        match self.format {
+            Format::Html => run.write_html(&mut self.out),
+            Format::Json => {
+                if self.wrote_run {
+                    write!(self.out, ",")?;
+                }
+                self.wrote_run = true;
+                serde_json::to_writer(&mut self.out, &run)?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes out the current line, grouping its characters into
+    /// runs with the same attributes
+    fn flush_line(&mut self) -> Result<()> {
+        let line = std::mem::take(&mut self.line);
+        self.cursor = 0;
+
+        let mut text = String::new();
+        let mut attrs: Option<CellAttributes> = None;
+        for (c, cell_attrs) in line {
+            if let Some(prior) = &attrs {
+                if *prior != cell_attrs {
+                    let run = Run::new(std::mem::take(&mut text), prior, &self.palette);
+                    self.write_run(run)?;
+                }
+            }
+            text.push(c);
+            attrs.replace(cell_attrs);
+        }
+        if let Some(attrs) = attrs {
+            let run = Run::new(text, &attrs, &self.palette);
+            self.write_run(run)?;
+        }
+
+        self.write_run(Run {
+            text: "\n".to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Writes out any partial line and closes the document
+    pub fn finish(mut self) -> Result<W> {
+        if !self.line.is_empty() {
+            self.flush_line()?;
+        }
+        match self.format {
+            Format::Html => writeln!(self.out, "</pre>")?,
+            Format::Json => writeln!(self.out, "]")?,
+        }
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::escape::parser::Parser;
+
+    fn convert(text: &str, format: Format, collapse_cr: bool) -> String {
+        let mut writer =
+            MarkupWriter::new(vec![], format, Palette::default(), collapse_cr).unwrap();
+        let mut parser = Parser::new();
+        for action in parser.parse_as_vec(text.as_bytes()) {
+            writer.perform(action).unwrap();
+        }
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn html() {
+        assert_eq!(
+            convert(
+                "plain \x1b[1;31mred<b>\x1b[0m \x1b[4:3;38:2::1:2:3mcurly\x1b[0m\n",
+                Format::Html,
+                false
+            ),
+            "<pre style=\"color:#e5e5e5;background-color:#000000\">\n\
+             plain <span style=\"color:#cd0000;font-weight:bold\">red&lt;b&gt;</span> \
+             <span style=\"color:#010203;text-decoration-line:underline;\
+             text-decoration-style:wavy\">curly</span>\n</pre>\n"
+        );
+    }
+
+    #[test]
+    fn html_hyperlink() {
+        assert_eq!(
+            convert(
+                "\x1b]8;;https://example.com/\x1b\\link\x1b]8;;\x1b\\\n",
+                Format::Html,
+                false
+            ),
+            "<pre style=\"color:#e5e5e5;background-color:#000000\">\n\
+             <a href=\"https://example.com/\">link</a>\n</pre>\n"
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            convert("a\x1b[38;5;196;3mb\x1b[0m", Format::Json, false),
+            "[{\"text\":\"a\"},{\"text\":\"b\",\"fg\":\"#ff0000\",\"italic\":true},\
+             {\"text\":\"\\n\"}]\n"
+        );
+    }
+
+    #[test]
+    fn collapse_cr() {
+        let progress = "10%\r50%\r100%\x1b[K\r\x1b[Kdone\n";
+        assert_eq!(
+            convert(progress, Format::Json, true),
+            "[{\"text\":\"done\"},{\"text\":\"\\n\"}]\n"
+        );
+        assert_eq!(
+            convert(progress, Format::Json, false),
+            "[{\"text\":\"10%\\r50%\\r100%\\rdone\"},{\"text\":\"\\n\"}]\n"
+        );
+    }
+
+    #[test]
+    fn palette() {
+        let mut palette = Palette::default();
+        palette.apply_spec("#111111:#222222").unwrap();
+        assert_eq!(palette.colors[1].to_rgb_string(), "#222222");
+        assert_eq!(palette.colors[2].to_rgb_string(), "#00cd00");
+        assert_eq!(palette.colors[231].to_rgb_string(), "#ffffff");
+        assert_eq!(palette.colors[232].to_rgb_string(), "#080808");
+        assert!(palette.apply_spec("#111111:bogus").is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::color::ColorPalette;
+use std::time::Duration;
 use termwiz::cell::UnicodeVersion;
 use termwiz::surface::{Line, SequenceNo};
 use wezterm_bidi::ParagraphDirectionHint;
@@ -146,6 +147,20 @@ pub trait TerminalConfiguration: std::fmt::Debug {
         3500
     }
 
+    /// Returns the number of the most recent lines that are kept in
+    /// their regular, uncompressed form.  Lines older than this are
+    /// compacted into a compressed representation.
+    fn scrollback_hot_lines(&self) -> usize {
+        10_000
+    }
+
+    /// If set, compacted scrollback lines that are more than this
+    /// many lines from the bottom of the screen are written out to
+    /// a temporary file rather than being held in memory.
+    fn scrollback_spill_after_lines(&self) -> Option<usize> {
+        None
+    }
+
     /// Return true if the embedding application wants to use CSI-u encoding
     /// for keys that would otherwise be ambiguous.
     /// <http://www.leonerd.org.uk/hacks/fixterms/>
@@ -193,6 +208,14 @@ pub trait TerminalConfiguration: std::fmt::Debug {
         false
     }
 
+    /// The longest that a synchronized output (DEC mode 2026) update
+    /// is allowed to hold back the display before it is published
+    /// anyway, so that a misbehaving application cannot freeze the
+    /// display indefinitely.
+    fn synchronized_output_timeout(&self) -> Duration {
+        Duration::from_secs(1)
+    }
+
     /// Returns (bidi_enabled, direction hint) that should be used
     /// unless an escape sequence has changed the default mode
     fn bidi_mode(&self) -> BidiMode {
@@ -35,6 +35,8 @@ pub use termwiz::surface::line::*;
 pub mod screen;
 pub use crate::screen::*;
 
+mod scrollback;
+
 pub mod selection;
 
 use termwiz::hyperlink::Hyperlink;
@@ -126,6 +128,34 @@ pub struct SemanticZone {
     pub semantic_type: SemanticType,
 }
 
+/// Describes a command that was run in the terminal, as reported
+/// by a shell that emits OSC 133 semantic prompt escapes.
+/// The rows are recorded as the command runs; they refer to the
+/// position of the command at that time and are not adjusted if
+/// the lines are subsequently rewrapped.
+#[cfg_attr(feature = "use_serde", derive(Deserialize, Serialize))]
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CommandRecord {
+    /// The text of the command line, taken from the input zone
+    pub command: String,
+    /// The exit status of the command, if it has completed and
+    /// the shell reported it
+    pub exit_status: Option<i32>,
+    /// The time at which the command started running
+    pub start_time: Option<std::time::SystemTime>,
+    /// How long the command ran for, if it has completed
+    pub duration: Option<std::time::Duration>,
+    /// The working directory, as reported via OSC 7, at the time
+    /// that the command started running
+    pub cwd: Option<String>,
+    /// The row on which the prompt for the command started
+    pub prompt_y: StableRowIndex,
+    /// The row on which the output of the command started
+    pub output_y: Option<StableRowIndex>,
+    /// The row of the cursor when the command completed
+    pub end_y: Option<StableRowIndex>,
+}
+
 pub mod color;
 
 #[cfg(test)]
@@ -1,8 +1,9 @@
 #![cfg_attr(feature = "cargo-clippy", allow(clippy::range_plus_one))]
 use super::*;
 use crate::config::BidiMode;
+use crate::scrollback::LineStore;
 use log::debug;
-use std::collections::VecDeque;
+use std::borrow::Cow;
 use std::sync::Arc;
 use termwiz::surface::SequenceNo;
 
@@ -13,14 +14,15 @@ use termwiz::surface::SequenceNo;
 #[derive(Debug, Clone)]
 pub struct Screen {
     /// Holds the line data that comprises the screen contents.
-    /// This is allocated with capacity for the entire scrollback.
+    /// Older lines of scrollback are held in a compacted form;
+    /// see `LineStore` for more details.
     /// The last N lines are the visible lines, with those prior being
     /// the lines that have scrolled off the top of the screen.
     /// Index 0 is the topmost line of the screen/scrollback (depending
     /// on the current window size) and will be the first line to be
     /// popped off the front of the screen when a new line is added that
     /// would otherwise have exceeded the line capacity
-    lines: VecDeque<Line>,
+    lines: LineStore,
 
     /// Whenever we scroll a line off the top of the scrollback, we
     /// increment this.  We use this offset to translate between
@@ -48,6 +50,24 @@ fn scrollback_size(config: &Arc<dyn TerminalConfiguration>, allow_scrollback: bo
     }
 }
 
+/// Returns the hot line and spill limits for the LineStore.
+/// The alternate screen has no scrollback, so there is no
+/// benefit in compacting its lines.
+fn line_store_limits(
+    config: &Arc<dyn TerminalConfiguration>,
+    allow_scrollback: bool,
+    physical_rows: usize,
+) -> (Option<usize>, Option<usize>) {
+    if allow_scrollback {
+        (
+            Some(config.scrollback_hot_lines().max(physical_rows)),
+            config.scrollback_spill_after_lines(),
+        )
+    } else {
+        (None, None)
+    }
+}
+
 impl Screen {
     /// Create a new Screen with the specified dimensions.
     /// The Cells in the viewable portion of the screen are set to the
@@ -63,8 +83,9 @@ impl Screen {
         let physical_rows = physical_rows.max(1);
         let physical_cols = physical_cols.max(1);
 
-        let mut lines =
-            VecDeque::with_capacity(physical_rows + scrollback_size(config, allow_scrollback));
+        let (hot_lines, spill_after_lines) =
+            line_store_limits(config, allow_scrollback, physical_rows);
+        let mut lines = LineStore::new(hot_lines, spill_after_lines);
         for _ in 0..physical_rows {
             let mut line = Line::with_width(physical_cols, seqno);
             bidi_mode.apply_to_line(&mut line, seqno);
@@ -93,12 +114,12 @@ impl Screen {
         cursor_y: PhysRowIndex,
         seqno: SequenceNo,
     ) -> (usize, PhysRowIndex) {
-        let mut rewrapped = VecDeque::new();
+        let mut rewrapped = self.lines.new_like();
         let mut logical_line: Option<Line> = None;
         let mut logical_cursor_x: Option<usize> = None;
         let mut adjusted_cursor = (cursor_y, cursor_y);
 
-        for (phys_idx, mut line) in self.lines.drain(..).enumerate() {
+        for (phys_idx, mut line) in self.lines.drain().enumerate() {
             line.invalidate_implicit_hyperlinks(seqno);
             line.update_last_change_seqno(seqno);
             let was_wrapped = line.last_cell_was_wrapped();
@@ -196,27 +217,22 @@ impl Screen {
             if self.allow_scrollback {
                 self.rewrap_lines(physical_cols, physical_rows, cursor.x, cursor_phys, seqno)
             } else {
-                for line in &mut self.lines {
-                    if physical_cols < self.physical_cols {
+                let prior_cols = self.physical_cols;
+                self.lines.for_each_mut(|_, line| {
+                    if physical_cols < prior_cols {
                         // Do a simple prune of the lines instead
                         line.resize(physical_cols, seqno);
                     } else {
                         // otherwise: invalidate them
                         line.update_last_change_seqno(seqno);
                     }
-                }
+                });
                 (cursor.x, cursor_phys)
             }
         } else {
             (cursor.x, cursor_phys)
         };
 
-        let capacity = physical_rows + self.scrollback_size();
-        let current_capacity = self.lines.capacity();
-        if capacity > current_capacity {
-            self.lines.reserve(capacity - current_capacity);
-        }
-
         // If we resized wider and the rewrap resulted in fewer
         // lines than the viewport size, or we resized taller,
         // pad us back out to the viewport size
@@ -279,6 +295,11 @@ impl Screen {
                 - (self.lines.len() as VisibleRowIndex - physical_rows as VisibleRowIndex);
         }
 
+        // Ensure that the visible lines are not compacted
+        let (hot_lines, spill_after_lines) =
+            line_store_limits(&self.config, self.allow_scrollback, physical_rows);
+        self.lines.set_limits(hot_lines, spill_after_lines);
+
         self.physical_rows = physical_rows;
         self.physical_cols = physical_cols;
         CursorPosition {
@@ -293,7 +314,9 @@ impl Screen {
     /// Get mutable reference to a line, relative to start of scrollback.
     #[inline]
     pub fn line_mut(&mut self, idx: PhysRowIndex) -> &mut Line {
-        &mut self.lines[idx]
+        self.lines
+            .get_mut(idx)
+            .unwrap_or_else(|| panic!("line_mut: index {} out of range", idx))
     }
 
     /// Returns the number of occupied rows of scrollback
@@ -305,29 +328,28 @@ impl Screen {
     #[inline]
     pub fn dirty_line(&mut self, idx: VisibleRowIndex, seqno: SequenceNo) {
         let line_idx = self.phys_row(idx);
-        if line_idx < self.lines.len() {
-            self.lines[line_idx].update_last_change_seqno(seqno);
+        if let Some(line) = self.lines.get_mut(line_idx) {
+            line.update_last_change_seqno(seqno);
         }
     }
 
+    /// Marks all lines, including the scrollback, as dirty
+    pub fn dirty_all_lines(&mut self, seqno: SequenceNo) {
+        self.lines.update_last_change_seqno(seqno);
+    }
+
     /// Returns a copy of the visible lines in the screen (no scrollback)
     #[cfg(test)]
     pub fn visible_lines(&self) -> Vec<Line> {
         let line_idx = self.lines.len() - self.physical_rows;
-        let mut lines = Vec::new();
-        for line in self.lines.iter().skip(line_idx) {
-            if lines.len() >= self.physical_rows {
-                break;
-            }
-            lines.push(line.clone());
-        }
-        lines
+        self.lines
+            .lines_in_range(line_idx..line_idx + self.physical_rows)
     }
 
     /// Returns a copy of the lines in the screen (including scrollback)
     #[cfg(test)]
     pub fn all_lines(&self) -> Vec<Line> {
-        self.lines.iter().map(|l| l.clone()).collect()
+        self.lines.lines_in_range(0..self.lines.len())
     }
 
     pub fn insert_cell(
@@ -525,7 +547,7 @@ impl Screen {
 
                 // Copy the source cells first
                 let cells = {
-                    self.lines[src_row]
+                    self.line_mut(src_row)
                         .cells()
                         .iter()
                         .skip(left_and_right_margins.start)
@@ -643,10 +665,25 @@ impl Screen {
         let to_move = lines_removed.min(num_rows);
         let (to_remove, to_add) = {
             for _ in 0..to_move {
-                let mut line = self.lines.remove(remove_idx).unwrap();
-                // Make the line like a new one of the appropriate width
-                line.resize_and_clear(self.physical_cols, seqno, blank_attr.clone());
-                line.update_last_change_seqno(seqno);
+                let line = match self.lines.remove(remove_idx) {
+                    Some(mut line) => {
+                        // Make the line like a new one of the appropriate width
+                        line.resize_and_clear(self.physical_cols, seqno, blank_attr.clone());
+                        line.update_last_change_seqno(seqno);
+                        line
+                    }
+                    None => {
+                        // The line had been compacted; it is cheaper
+                        // to make a new one than to expand it
+                        let mut line = Line::with_width_and_cell(
+                            self.physical_cols,
+                            Cell::blank_with_attrs(blank_attr.clone()),
+                            seqno,
+                        );
+                        bidi_mode.apply_to_line(&mut line, seqno);
+                        line
+                    }
+                };
                 if scroll_region.end as usize == self.physical_rows {
                     self.lines.push_back(line);
                 } else {
@@ -766,7 +803,7 @@ impl Screen {
 
                 // Copy the source cells first
                 let cells = {
-                    self.lines[src_row]
+                    self.line_mut(src_row)
                         .cells()
                         .iter()
                         .skip(left_and_right_margins.start)
@@ -817,12 +854,7 @@ impl Screen {
     }
 
     pub fn lines_in_phys_range(&self, phys_range: Range<PhysRowIndex>) -> Vec<Line> {
-        self.lines
-            .iter()
-            .skip(phys_range.start)
-            .take(phys_range.end - phys_range.start)
-            .map(|line| line.clone())
-            .collect()
+        self.lines.lines_in_range(phys_range)
     }
 
     pub fn get_changed_stable_rows(
@@ -831,36 +863,30 @@ impl Screen {
         seqno: SequenceNo,
     ) -> Vec<StableRowIndex> {
         let phys = self.stable_range(&stable_lines);
-        let mut set = vec![];
-        for (idx, line) in self
-            .lines
-            .iter()
-            .enumerate()
-            .skip(phys.start)
-            .take(phys.end - phys.start)
-        {
-            if line.changed_since(seqno) {
-                set.push(self.phys_to_stable_row_index(idx))
-            }
-        }
-        set
+        self.lines
+            .changed_since(phys, seqno)
+            .into_iter()
+            .map(|idx| self.phys_to_stable_row_index(idx))
+            .collect()
     }
 
-    pub fn for_each_phys_line<F>(&self, mut f: F)
+    pub fn for_each_phys_line<F>(&self, f: F)
     where
         F: FnMut(usize, &Line),
     {
-        for (idx, line) in self.lines.iter().enumerate() {
-            f(idx, line);
-        }
+        self.lines.for_each(f)
+    }
+
+    /// Returns an iterator over the physical lines, including the
+    /// scrollback.  Compacted lines are yielded as expanded copies.
+    pub fn phys_lines(&self) -> impl Iterator<Item = Cow<'_, Line>> {
+        self.lines.iter()
     }
 
-    pub fn for_each_phys_line_mut<F>(&mut self, mut f: F)
+    pub fn for_each_phys_line_mut<F>(&mut self, f: F)
     where
         F: FnMut(usize, &mut Line),
     {
-        for (idx, line) in self.lines.iter_mut().enumerate() {
-            f(idx, line);
-        }
+        self.lines.for_each_mut(f)
     }
 }
@@ -0,0 +1,1136 @@
+//! Storage for the lines of a `Screen`.
+//!
+//! Recent lines are held as regular `Line`s so that they can be
+//! cheaply mutated.  Once a line has moved far enough away from the
+//! bottom of the screen it is unlikely to change again, so the
+//! oldest lines are compacted in blocks: the cells are encoded into
+//! a compressed byte stream, with their attributes interned in a
+//! small per-block table.  Compacted blocks that are even older can
+//! optionally be spilled out of memory into a temporary file.
+//!
+//! Compacted lines are transparently expanded again if something
+//! needs to mutate them, or when they are read.
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{Seek, SeekFrom, Write};
+use std::ops::Range;
+use termwiz::cell::{Cell, CellAttributes};
+use termwiz::surface::{Line, SequenceNo, SEQ_ZERO};
+
+/// The number of lines in a compacted block
+const BLOCK_LINES: usize = 256;
+
+/// When interning attributes, how many of the most recently added
+/// entries to compare against before giving up and adding another.
+/// Runs of attributes tend to repeat closely, and this bounds the
+/// cost of encoding lines with a great many distinct attributes.
+const ATTR_SEARCH_DEPTH: usize = 32;
+
+/// The spill file is rewritten to reclaim the space used by blocks
+/// that have been discarded once at least this many bytes, and at
+/// least half of the file, are garbage.
+const MIN_SPILL_GARBAGE: u64 = 64 * 1024 * 1024;
+
+enum BlockData {
+    Memory(Vec<u8>),
+    Spilled { offset: u64, len: usize },
+}
+
+struct FrozenBlock {
+    seqnos: Vec<SequenceNo>,
+    /// Lines that have properties beyond their cells, such as
+    /// double-width or bidi settings, are recorded here as a
+    /// copy of the line without its cells
+    shells: Vec<(usize, Line)>,
+    attrs: Vec<CellAttributes>,
+    data: BlockData,
+}
+
+struct SpillFile {
+    file: File,
+    len: u64,
+    garbage: u64,
+}
+
+impl SpillFile {
+    fn new() -> anyhow::Result<Self> {
+        Ok(Self {
+            file: tempfile::tempfile()?,
+            len: 0,
+            garbage: 0,
+        })
+    }
+
+    fn write(&mut self, data: &[u8]) -> std::io::Result<u64> {
+        let offset = self.len;
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.write_all(data)?;
+        self.len += data.len() as u64;
+        Ok(offset)
+    }
+
+    #[cfg(unix)]
+    fn read(&self, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+        use std::os::unix::fs::FileExt;
+        let mut buf = vec![0u8; len];
+        self.file.read_exact_at(&mut buf, offset)?;
+        Ok(buf)
+    }
+
+    #[cfg(windows)]
+    fn read(&self, offset: u64, len: usize) -> std::io::Result<Vec<u8>> {
+        use std::os::windows::fs::FileExt;
+        let mut buf = vec![0u8; len];
+        let mut pos = 0;
+        while pos < len {
+            let n = self.file.seek_read(&mut buf[pos..], offset + pos as u64)?;
+            if n == 0 {
+                return Err(std::io::ErrorKind::UnexpectedEof.into());
+            }
+            pos += n;
+        }
+        Ok(buf)
+    }
+}
+
+fn write_varint(buf: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            buf.push(byte);
+            return;
+        }
+        buf.push(byte | 0x80);
+    }
+}
+
+fn read_varint(data: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *data.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn intern_attrs(attrs: &mut Vec<CellAttributes>, attr: &CellAttributes) -> usize {
+    let start = attrs.len().saturating_sub(ATTR_SEARCH_DEPTH);
+    if let Some(idx) = attrs[start..].iter().rposition(|a| a == attr) {
+        return start + idx;
+    }
+    attrs.push(attr.clone());
+    attrs.len() - 1
+}
+
+impl FrozenBlock {
+    fn encode(lines: impl Iterator<Item = Line>) -> Self {
+        let empty_shell = Line::from_cells(vec![], SEQ_ZERO);
+        let mut seqnos = vec![];
+        let mut shells = vec![];
+        let mut attrs = vec![];
+        let mut data = vec![];
+
+        for (idx, mut line) in lines.enumerate() {
+            seqnos.push(line.current_seqno());
+            let shell = line.split_off(line.cells().len(), SEQ_ZERO);
+            if shell != empty_shell {
+                shells.push((idx, shell));
+            }
+
+            let cells = line.cells();
+            write_varint(&mut data, cells.len());
+
+            // Attribute runs, followed by the cells
+            let mut runs: Vec<(usize, usize)> = vec![];
+            for cell in cells {
+                match runs.last_mut() {
+                    Some((count, attr_idx)) if attrs[*attr_idx] == *cell.attrs() => {
+                        *count += 1;
+                    }
+                    _ => runs.push((1, intern_attrs(&mut attrs, cell.attrs()))),
+                }
+            }
+            write_varint(&mut data, runs.len());
+            for (count, attr_idx) in runs {
+                write_varint(&mut data, count);
+                write_varint(&mut data, attr_idx);
+            }
+
+            for cell in cells {
+                let text = cell.str();
+                write_varint(&mut data, cell.width());
+                write_varint(&mut data, text.len());
+                data.extend_from_slice(text.as_bytes());
+            }
+        }
+
+        Self {
+            seqnos,
+            shells,
+            attrs,
+            data: BlockData::Memory(miniz_oxide::deflate::compress_to_vec(&data, 1)),
+        }
+    }
+
+    fn compressed_data<'a>(&'a self, spill: Option<&SpillFile>) -> anyhow::Result<Cow<'a, [u8]>> {
+        match &self.data {
+            BlockData::Memory(data) => Ok(Cow::Borrowed(data)),
+            BlockData::Spilled { offset, len } => {
+                let spill = spill.ok_or_else(|| anyhow::anyhow!("spill file is missing"))?;
+                Ok(Cow::Owned(spill.read(*offset, *len)?))
+            }
+        }
+    }
+
+    /// Returns a copy of the block with its data held in memory
+    fn clone_in_memory(&self, spill: Option<&SpillFile>) -> Self {
+        match self.compressed_data(spill) {
+            Ok(data) => Self {
+                seqnos: self.seqnos.clone(),
+                shells: self.shells.clone(),
+                attrs: self.attrs.clone(),
+                data: BlockData::Memory(data.into_owned()),
+            },
+            // decode produces blank lines in place of the lines
+            // that it cannot read
+            Err(_) => Self::encode(self.decode(spill).into_iter()),
+        }
+    }
+
+    fn decode(&self, spill: Option<&SpillFile>) -> Vec<Line> {
+        match self.try_decode(spill) {
+            Ok(lines) => lines,
+            Err(err) => {
+                // Rather than losing our place in the scrollback, produce
+                // blank lines in place of the lines that we couldn't read
+                log::error!("failed to decode compacted scrollback: {:#}", err);
+                self.seqnos
+                    .iter()
+                    .map(|seqno| Line::from_cells(vec![], *seqno))
+                    .collect()
+            }
+        }
+    }
+
+    fn try_decode(&self, spill: Option<&SpillFile>) -> anyhow::Result<Vec<Line>> {
+        let compressed = self.compressed_data(spill)?;
+        let data = miniz_oxide::inflate::decompress_to_vec(&compressed)
+            .map_err(|err| anyhow::anyhow!("inflate failed: {:?}", err))?;
+        let truncated = || anyhow::anyhow!("compacted scrollback data is truncated");
+
+        let mut pos = 0;
+        let mut shells = self.shells.iter().peekable();
+        let mut lines = Vec::with_capacity(self.seqnos.len());
+
+        for (idx, seqno) in self.seqnos.iter().enumerate() {
+            let num_cells = read_varint(&data, &mut pos).ok_or_else(truncated)?;
+            let num_runs = read_varint(&data, &mut pos).ok_or_else(truncated)?;
+            let mut runs = Vec::with_capacity(num_runs);
+            for _ in 0..num_runs {
+                let count = read_varint(&data, &mut pos).ok_or_else(truncated)?;
+                let attr_idx = read_varint(&data, &mut pos).ok_or_else(truncated)?;
+                let attrs = self.attrs.get(attr_idx).ok_or_else(truncated)?;
+                runs.push((count, attrs));
+            }
+
+            let mut cells = Vec::with_capacity(num_cells);
+            for (count, attrs) in runs {
+                for _ in 0..count {
+                    let width = read_varint(&data, &mut pos).ok_or_else(truncated)?;
+                    let len = read_varint(&data, &mut pos).ok_or_else(truncated)?;
+                    let text = data.get(pos..pos + len).ok_or_else(truncated)?;
+                    pos += len;
+                    let text = std::str::from_utf8(text)?;
+                    cells.push(Cell::new_grapheme_with_width(text, width, attrs.clone()));
+                }
+            }
+
+            let line = match shells.next_if(|(shell_idx, _)| *shell_idx == idx) {
+                Some((_, shell)) => {
+                    let mut line = shell.clone();
+                    line.append_line(Line::from_cells(cells, *seqno), *seqno);
+                    line
+                }
+                None => Line::from_cells(cells, *seqno),
+            };
+            lines.push(line);
+        }
+
+        Ok(lines)
+    }
+}
+
+/// A block of the oldest lines of a `LineStore`
+enum Block {
+    Frozen(FrozenBlock),
+    /// A block that was expanded so that its lines could be mutated.
+    /// It is compacted again the next time that the store compacts
+    /// a block of hot lines.
+    Thawed(Vec<Line>),
+}
+
+impl Block {
+    fn len(&self) -> usize {
+        match self {
+            Block::Frozen(block) => block.seqnos.len(),
+            Block::Thawed(lines) => lines.len(),
+        }
+    }
+}
+
+/// Holds the lines of a screen, compacting older lines to reduce
+/// the memory required by very large scrollback histories.
+/// Index 0 is the oldest line.
+pub struct LineStore {
+    /// Blocks of the oldest lines.  Blocks hold `BLOCK_LINES` lines
+    /// when they are created, but thawed blocks may have grown or
+    /// shrunk since then.
+    blocks: VecDeque<Block>,
+    /// The number of lines that have been discarded from the front
+    /// of the first block
+    front_skip: usize,
+    /// The number of lines in `blocks`, excluding `front_skip`
+    frozen_len: usize,
+    /// The number of blocks at the front of `blocks` that have been
+    /// written to the spill file, or that are thawed and will be
+    /// written to it again when they are compacted
+    num_spilled: usize,
+    /// The number of thawed blocks
+    num_thawed: usize,
+    /// The most recent lines
+    hot: VecDeque<Line>,
+    /// How many lines to keep in `hot` before compacting; None
+    /// to never compact
+    hot_lines: Option<usize>,
+    /// Blocks that are more than this many lines from the bottom
+    /// are written to the spill file
+    spill_after_lines: Option<usize>,
+    spill: Option<SpillFile>,
+}
+
+impl std::fmt::Debug for LineStore {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("LineStore")
+            .field("frozen_lines", &self.frozen_len)
+            .field("spilled_blocks", &self.num_spilled)
+            .field("thawed_blocks", &self.num_thawed)
+            .field("hot_lines", &self.hot.len())
+            .finish()
+    }
+}
+
+impl Clone for LineStore {
+    /// The clone doesn't share the spill file, so spilled blocks
+    /// are read back into memory.  They are spilled to a file of
+    /// its own when the clone is next compacted.
+    fn clone(&self) -> Self {
+        let spill = self.spill.as_ref();
+        Self {
+            blocks: self
+                .blocks
+                .iter()
+                .map(|block| match block {
+                    Block::Frozen(block) => Block::Frozen(block.clone_in_memory(spill)),
+                    Block::Thawed(lines) => Block::Thawed(lines.clone()),
+                })
+                .collect(),
+            front_skip: self.front_skip,
+            frozen_len: self.frozen_len,
+            num_spilled: 0,
+            num_thawed: self.num_thawed,
+            hot: self.hot.clone(),
+            hot_lines: self.hot_lines,
+            spill_after_lines: self.spill_after_lines,
+            spill: None,
+        }
+    }
+}
+
+impl LineStore {
+    pub fn new(hot_lines: Option<usize>, spill_after_lines: Option<usize>) -> Self {
+        Self {
+            blocks: VecDeque::new(),
+            front_skip: 0,
+            frozen_len: 0,
+            num_spilled: 0,
+            num_thawed: 0,
+            hot: VecDeque::new(),
+            hot_lines,
+            spill_after_lines,
+            spill: None,
+        }
+    }
+
+    /// Creates an empty store with the same limits as this one
+    pub fn new_like(&self) -> Self {
+        Self::new(self.hot_lines, self.spill_after_lines)
+    }
+
+    /// Changes the limits that govern compaction and spilling.
+    /// Compacted lines are expanded again if there is now room
+    /// to keep them hot.
+    pub fn set_limits(&mut self, hot_lines: Option<usize>, spill_after_lines: Option<usize>) {
+        self.hot_lines = hot_lines;
+        self.spill_after_lines = spill_after_lines;
+        let hot_lines = hot_lines.unwrap_or(usize::MAX);
+        while !self.blocks.is_empty() && self.hot.len() < hot_lines {
+            self.thaw_last_block();
+        }
+        self.compact();
+    }
+
+    fn frozen_len(&self) -> usize {
+        self.frozen_len
+    }
+
+    pub fn len(&self) -> usize {
+        self.frozen_len + self.hot.len()
+    }
+
+    /// Returns a reference to the line, unless it is out of range
+    /// or has been compacted
+    pub fn get(&self, idx: usize) -> Option<&Line> {
+        if idx < self.frozen_len {
+            let (block_idx, offset) = self.frozen_position(idx);
+            return match &self.blocks[block_idx] {
+                Block::Thawed(lines) => lines.get(offset),
+                Block::Frozen(_) => None,
+            };
+        }
+        self.hot.get(idx - self.frozen_len)
+    }
+
+    /// Returns a mutable reference to the line, expanding the
+    /// block that contains it if it has been compacted
+    pub fn get_mut(&mut self, idx: usize) -> Option<&mut Line> {
+        if idx < self.frozen_len {
+            let (block_idx, offset) = self.frozen_position(idx);
+            return self.thaw_block(block_idx).get_mut(offset);
+        }
+        let frozen_len = self.frozen_len;
+        self.hot.get_mut(idx - frozen_len)
+    }
+
+    pub fn back(&self) -> Option<&Line> {
+        self.hot.back()
+    }
+
+    pub fn push_back(&mut self, line: Line) {
+        self.hot.push_back(line);
+        self.compact();
+    }
+
+    pub fn pop_back(&mut self) -> Option<Line> {
+        if self.hot.is_empty() && !self.blocks.is_empty() {
+            self.thaw_last_block();
+        }
+        self.hot.pop_back()
+    }
+
+    /// Discards the oldest line
+    pub fn pop_front(&mut self) {
+        let first_len = match self.blocks.front() {
+            Some(block) => block.len(),
+            None => {
+                self.hot.pop_front();
+                return;
+            }
+        };
+        self.front_skip += 1;
+        self.frozen_len -= 1;
+        if self.front_skip == first_len {
+            self.front_skip = 0;
+            self.remove_block(0);
+        }
+    }
+
+    /// Removes the line at idx.  Returns None rather than the line
+    /// if it was the oldest line and had been compacted, as it is
+    /// cheaper to discard it than to expand it.
+    pub fn remove(&mut self, idx: usize) -> Option<Line> {
+        if idx < self.frozen_len {
+            if idx == 0 {
+                self.pop_front();
+                return None;
+            }
+            let (block_idx, offset) = self.frozen_position(idx);
+            let lines = self.thaw_block(block_idx);
+            let line = lines.remove(offset);
+            let now_empty = lines.is_empty();
+            self.frozen_len -= 1;
+            if now_empty {
+                self.remove_block(block_idx);
+            }
+            return Some(line);
+        }
+        let frozen_len = self.frozen_len;
+        self.hot.remove(idx - frozen_len)
+    }
+
+    pub fn insert(&mut self, idx: usize, line: Line) {
+        if idx < self.frozen_len {
+            let (block_idx, offset) = self.frozen_position(idx);
+            self.thaw_block(block_idx).insert(offset, line);
+            self.frozen_len += 1;
+        } else {
+            let frozen_len = self.frozen_len;
+            self.hot.insert(idx - frozen_len, line);
+        }
+        self.compact();
+    }
+
+    /// Returns copies of the lines in the specified range
+    pub fn lines_in_range(&self, range: Range<usize>) -> Vec<Line> {
+        let mut lines = Vec::with_capacity(range.end.saturating_sub(range.start));
+        self.for_each_in_range(range, |_, line| lines.push(line.clone()));
+        lines
+    }
+
+    /// Returns the indices of the lines in the specified range that
+    /// have changed since seqno
+    pub fn changed_since(&self, range: Range<usize>, seqno: SequenceNo) -> Vec<usize> {
+        let range = range.start..range.end.min(self.len());
+        let mut changed = vec![];
+
+        let mut idx = range.start;
+        if idx < self.frozen_len {
+            let (mut block_idx, mut offset) = self.frozen_position(idx);
+            while idx < range.end.min(self.frozen_len) {
+                let block = &self.blocks[block_idx];
+                for offset in offset..block.len() {
+                    if idx >= range.end {
+                        break;
+                    }
+                    let is_changed = match block {
+                        Block::Frozen(block) => {
+                            let line_seqno = block.seqnos[offset];
+                            line_seqno == SEQ_ZERO || line_seqno > seqno
+                        }
+                        Block::Thawed(lines) => lines[offset].changed_since(seqno),
+                    };
+                    if is_changed {
+                        changed.push(idx);
+                    }
+                    idx += 1;
+                }
+                block_idx += 1;
+                offset = 0;
+            }
+        }
+
+        for idx in idx..range.end {
+            if self.hot[idx - self.frozen_len].changed_since(seqno) {
+                changed.push(idx);
+            }
+        }
+        changed
+    }
+
+    /// Marks all lines as changed as of seqno
+    pub fn update_last_change_seqno(&mut self, seqno: SequenceNo) {
+        for block in &mut self.blocks {
+            match block {
+                Block::Frozen(block) => {
+                    for line_seqno in &mut block.seqnos {
+                        *line_seqno = (*line_seqno).max(seqno);
+                    }
+                }
+                Block::Thawed(lines) => {
+                    for line in lines {
+                        line.update_last_change_seqno(seqno);
+                    }
+                }
+            }
+        }
+        for line in &mut self.hot {
+            line.update_last_change_seqno(seqno);
+        }
+    }
+
+    fn for_each_in_range<F: FnMut(usize, &Line)>(&self, range: Range<usize>, mut f: F) {
+        let range = range.start..range.end.min(self.len());
+
+        let mut idx = range.start;
+        if idx < self.frozen_len {
+            let (mut block_idx, mut offset) = self.frozen_position(idx);
+            while idx < range.end.min(self.frozen_len) {
+                let decoded;
+                let lines = match &self.blocks[block_idx] {
+                    Block::Frozen(block) => {
+                        decoded = block.decode(self.spill.as_ref());
+                        &decoded
+                    }
+                    Block::Thawed(lines) => lines,
+                };
+                for line in lines.iter().skip(offset) {
+                    if idx >= range.end {
+                        break;
+                    }
+                    f(idx, line);
+                    idx += 1;
+                }
+                block_idx += 1;
+                offset = 0;
+            }
+        }
+
+        for idx in idx..range.end {
+            f(idx, &self.hot[idx - self.frozen_len]);
+        }
+    }
+
+    /// Returns an iterator over the lines, oldest first.  Compacted
+    /// lines are expanded a block at a time, without expanding the
+    /// entire store, and are yielded as owned lines.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            blocks: self.blocks.iter(),
+            skip: self.front_skip,
+            decoded: vec![].into_iter(),
+            thawed: [].iter(),
+            hot: self.hot.iter(),
+            spill: self.spill.as_ref(),
+        }
+    }
+
+    /// Calls f for each line.  Compacted lines are expanded in
+    /// turn, without expanding the entire store.
+    pub fn for_each<F: FnMut(usize, &Line)>(&self, mut f: F) {
+        for (idx, line) in self.iter().enumerate() {
+            f(idx, &line);
+        }
+    }
+
+    /// Calls f for each line, allowing it to be mutated.
+    /// Compacted lines are expanded a block at a time and
+    /// re-compacted afterwards if their cells were changed.
+    pub fn for_each_mut<F: FnMut(usize, &mut Line)>(&mut self, mut f: F) {
+        let mut idx = 0;
+        for block_idx in 0..self.blocks.len() {
+            let skip = if block_idx == 0 { self.front_skip } else { 0 };
+            let frozen = match &mut self.blocks[block_idx] {
+                Block::Frozen(block) => block,
+                Block::Thawed(lines) => {
+                    for line in lines.iter_mut().skip(skip) {
+                        f(idx, line);
+                        idx += 1;
+                    }
+                    continue;
+                }
+            };
+
+            let original = frozen.decode(self.spill.as_ref());
+            let mut lines = original.clone();
+            for line in lines.iter_mut().skip(skip) {
+                f(idx, line);
+                idx += 1;
+            }
+
+            let cells_changed = original
+                .iter()
+                .zip(lines.iter())
+                .any(|(a, b)| a.cells() != b.cells());
+            if cells_changed {
+                let prior = std::mem::replace(frozen, FrozenBlock::encode(lines.into_iter()));
+                if block_idx < self.num_spilled {
+                    // Keep the spilled blocks contiguous by spilling
+                    // the replacement too
+                    self.release_data(&prior);
+                    self.spill_block(block_idx);
+                }
+            } else {
+                for (line_seqno, line) in frozen.seqnos.iter_mut().zip(lines.iter()) {
+                    *line_seqno = line.current_seqno();
+                }
+            }
+        }
+
+        for line in self.hot.iter_mut() {
+            f(idx, line);
+            idx += 1;
+        }
+    }
+
+    /// Removes all of the lines, returning an iterator that expands
+    /// them one block at a time
+    pub fn drain(&mut self) -> Drain {
+        let drain = Drain {
+            blocks: std::mem::take(&mut self.blocks),
+            skip: self.front_skip,
+            current: vec![].into_iter(),
+            hot: std::mem::take(&mut self.hot),
+            spill: self.spill.take(),
+        };
+        self.front_skip = 0;
+        self.frozen_len = 0;
+        self.num_spilled = 0;
+        self.num_thawed = 0;
+        drain
+    }
+
+    /// Returns the block index and the offset within that block
+    /// for the frozen line at idx
+    fn frozen_position(&self, idx: usize) -> (usize, usize) {
+        let mut pos = idx + self.front_skip;
+        for (block_idx, block) in self.blocks.iter().enumerate() {
+            let len = block.len();
+            if pos < len {
+                return (block_idx, pos);
+            }
+            pos -= len;
+        }
+        panic!("frozen line {} is out of range", idx);
+    }
+
+    /// Expands the block at block_idx in place, returning its lines
+    fn thaw_block(&mut self, block_idx: usize) -> &mut Vec<Line> {
+        if let Block::Frozen(block) = &self.blocks[block_idx] {
+            let lines = block.decode(self.spill.as_ref());
+            if let Block::Frozen(block) =
+                std::mem::replace(&mut self.blocks[block_idx], Block::Thawed(lines))
+            {
+                self.release_data(&block);
+            }
+            self.num_thawed += 1;
+        }
+        match &mut self.blocks[block_idx] {
+            Block::Thawed(lines) => lines,
+            Block::Frozen(_) => unreachable!("block was thawed above"),
+        }
+    }
+
+    /// Moves the lines of the newest block to the front of the
+    /// hot lines
+    fn thaw_last_block(&mut self) {
+        let block_idx = match self.blocks.len().checked_sub(1) {
+            Some(idx) => idx,
+            None => return,
+        };
+        // Decode before removing the block, as removing it may
+        // release its space in the spill file
+        let mut lines = match &self.blocks[block_idx] {
+            Block::Frozen(block) => block.decode(self.spill.as_ref()),
+            Block::Thawed(_) => vec![],
+        };
+        if let Block::Thawed(thawed) = self.remove_block(block_idx) {
+            lines = thawed;
+        }
+        if block_idx == 0 {
+            lines.drain(0..self.front_skip);
+            self.front_skip = 0;
+        }
+        self.frozen_len -= lines.len();
+        for line in lines.into_iter().rev() {
+            self.hot.push_front(line);
+        }
+    }
+
+    /// Removes the block at block_idx.  The caller is responsible
+    /// for accounting for its lines.
+    fn remove_block(&mut self, block_idx: usize) -> Block {
+        let block = self.blocks.remove(block_idx).expect("block is in range");
+        if block_idx < self.num_spilled {
+            self.num_spilled -= 1;
+        }
+        match &block {
+            Block::Frozen(frozen) => self.release_data(frozen),
+            Block::Thawed(_) => self.num_thawed -= 1,
+        }
+        block
+    }
+
+    /// Compacts hot lines beyond the hot limit, and spills
+    /// blocks beyond the spill limit.  Thawed blocks are compacted
+    /// again along with each new block.
+    fn compact(&mut self) {
+        let hot_lines = match self.hot_lines {
+            Some(n) => n,
+            None => return,
+        };
+
+        let mut compacted = false;
+        while self.hot.len() >= hot_lines + BLOCK_LINES {
+            if self.blocks.is_empty() {
+                self.front_skip = 0;
+            }
+            let block = FrozenBlock::encode(self.hot.drain(0..BLOCK_LINES));
+            self.blocks.push_back(Block::Frozen(block));
+            self.frozen_len += BLOCK_LINES;
+            compacted = true;
+        }
+
+        if compacted && self.num_thawed > 0 {
+            self.refreeze();
+        }
+
+        if let Some(spill_after_lines) = self.spill_after_lines {
+            let len = self.len() + self.front_skip;
+            let mut end_of_block: usize = self
+                .blocks
+                .iter()
+                .take(self.num_spilled)
+                .map(Block::len)
+                .sum();
+            while self.num_spilled < self.blocks.len() {
+                end_of_block += self.blocks[self.num_spilled].len();
+                // The number of lines after the end of this block
+                if len - end_of_block < spill_after_lines {
+                    break;
+                }
+                if !self.spill_block(self.num_spilled) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Compacts the thawed blocks again
+    fn refreeze(&mut self) {
+        for block_idx in 0..self.blocks.len() {
+            if let Block::Thawed(lines) = &mut self.blocks[block_idx] {
+                let lines = std::mem::take(lines);
+                self.blocks[block_idx] = Block::Frozen(FrozenBlock::encode(lines.into_iter()));
+                if block_idx < self.num_spilled {
+                    self.spill_block(block_idx);
+                }
+            }
+        }
+        self.num_thawed = 0;
+    }
+
+    /// Writes the block at block_idx to the spill file.  Thawed
+    /// blocks are written when they are compacted again.
+    /// Returns false if that failed.
+    fn spill_block(&mut self, block_idx: usize) -> bool {
+        if self.spill.is_none() {
+            match SpillFile::new() {
+                Ok(spill) => {
+                    self.spill.replace(spill);
+                }
+                Err(err) => {
+                    log::error!("unable to create scrollback spill file: {:#}", err);
+                    self.spill_after_lines = None;
+                    return false;
+                }
+            }
+        }
+        let spill = self.spill.as_mut().expect("spill file was created above");
+        let block = match &mut self.blocks[block_idx] {
+            Block::Frozen(block) => block,
+            Block::Thawed(_) => {
+                self.num_spilled = self.num_spilled.max(block_idx + 1);
+                return true;
+            }
+        };
+        let data = match &block.data {
+            BlockData::Memory(data) => data,
+            BlockData::Spilled { .. } => return true,
+        };
+        match spill.write(data) {
+            Ok(offset) => {
+                block.data = BlockData::Spilled {
+                    offset,
+                    len: data.len(),
+                };
+                self.num_spilled = self.num_spilled.max(block_idx + 1);
+                true
+            }
+            Err(err) => {
+                log::error!("unable to write to scrollback spill file: {:#}", err);
+                self.spill_after_lines = None;
+                false
+            }
+        }
+    }
+
+    /// Accounts for the space in the spill file that was used by a
+    /// block that is no longer needed, reclaiming it when it is
+    /// worthwhile to do so
+    fn release_data(&mut self, block: &FrozenBlock) {
+        let len = match &block.data {
+            BlockData::Spilled { len, .. } => *len as u64,
+            BlockData::Memory(_) => return,
+        };
+        let spill = match self.spill.as_mut() {
+            Some(spill) => spill,
+            None => return,
+        };
+        spill.garbage += len;
+
+        if self.num_spilled == 0 {
+            spill.file.set_len(0).ok();
+            spill.len = 0;
+            spill.garbage = 0;
+        } else if spill.garbage >= MIN_SPILL_GARBAGE && spill.garbage * 2 >= spill.len {
+            if let Err(err) = self.rewrite_spill_file() {
+                log::error!("failed to rewrite scrollback spill file: {:#}", err);
+            }
+        }
+    }
+
+    /// Copies the live blocks into a new spill file
+    fn rewrite_spill_file(&mut self) -> anyhow::Result<()> {
+        let old = match self.spill.as_ref() {
+            Some(spill) => spill,
+            None => return Ok(()),
+        };
+        let mut new = SpillFile::new()?;
+        let mut offsets = vec![];
+        for block in self.blocks.iter().take(self.num_spilled) {
+            offsets.push(match block {
+                Block::Frozen(FrozenBlock {
+                    data: BlockData::Spilled { offset, len },
+                    ..
+                }) => Some(new.write(&old.read(*offset, *len)?)?),
+                _ => None,
+            });
+        }
+        for (block, new_offset) in self.blocks.iter_mut().zip(offsets) {
+            if let (
+                Block::Frozen(FrozenBlock {
+                    data: BlockData::Spilled { offset, .. },
+                    ..
+                }),
+                Some(new_offset),
+            ) = (block, new_offset)
+            {
+                *offset = new_offset;
+            }
+        }
+        self.spill.replace(new);
+        Ok(())
+    }
+}
+
+/// Iterates the lines of a LineStore; see `LineStore::iter`
+pub struct Iter<'a> {
+    blocks: std::collections::vec_deque::Iter<'a, Block>,
+    skip: usize,
+    decoded: std::vec::IntoIter<Line>,
+    thawed: std::slice::Iter<'a, Line>,
+    hot: std::collections::vec_deque::Iter<'a, Line>,
+    spill: Option<&'a SpillFile>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Cow<'a, Line>;
+
+    fn next(&mut self) -> Option<Cow<'a, Line>> {
+        loop {
+            if let Some(line) = self.decoded.next() {
+                return Some(Cow::Owned(line));
+            }
+            if let Some(line) = self.thawed.next() {
+                return Some(Cow::Borrowed(line));
+            }
+            match self.blocks.next() {
+                Some(Block::Frozen(block)) => {
+                    let mut lines = block.decode(self.spill);
+                    lines.drain(0..self.skip);
+                    self.decoded = lines.into_iter();
+                }
+                Some(Block::Thawed(lines)) => {
+                    self.thawed = lines[self.skip..].iter();
+                }
+                None => return self.hot.next().map(Cow::Borrowed),
+            }
+            self.skip = 0;
+        }
+    }
+}
+
+/// Iterates the lines removed from a LineStore by `LineStore::drain`
+pub struct Drain {
+    blocks: VecDeque<Block>,
+    skip: usize,
+    current: std::vec::IntoIter<Line>,
+    hot: VecDeque<Line>,
+    spill: Option<SpillFile>,
+}
+
+impl Iterator for Drain {
+    type Item = Line;
+
+    fn next(&mut self) -> Option<Line> {
+        loop {
+            if let Some(line) = self.current.next() {
+                return Some(line);
+            }
+            let mut lines = match self.blocks.pop_front() {
+                Some(Block::Frozen(block)) => block.decode(self.spill.as_ref()),
+                Some(Block::Thawed(lines)) => lines,
+                None => return self.hot.pop_front(),
+            };
+            lines.drain(0..self.skip);
+            self.skip = 0;
+            self.current = lines.into_iter();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use termwiz::cell::Intensity;
+    use termwiz::color::AnsiColor;
+
+    fn numbered_line(n: usize) -> Line {
+        let seqno = n as SequenceNo + 1;
+        let mut attrs = CellAttributes::default();
+        attrs.set_foreground(AnsiColor::Maroon);
+        let mut line = Line::from_text(&format!("line {}", n), &attrs, seqno, None);
+        attrs.set_intensity(Intensity::Bold);
+        line.append_line(Line::from_text(" bold", &attrs, seqno, None), seqno);
+        if n % 4 == 1 {
+            line.set_double_width(seqno);
+        }
+        line
+    }
+
+    fn store_with_lines(
+        num_lines: usize,
+        hot_lines: usize,
+        spill_after_lines: Option<usize>,
+    ) -> LineStore {
+        let mut store = LineStore::new(Some(hot_lines), spill_after_lines);
+        for n in 0..num_lines {
+            store.push_back(numbered_line(n));
+        }
+        store
+    }
+
+    #[test]
+    fn compaction_round_trip() {
+        let store = store_with_lines(1000, 10, None);
+        assert_eq!(store.len(), 1000);
+        assert!(store.frozen_len() > 0);
+        assert!(store.get(0).is_none());
+
+        let lines = store.lines_in_range(0..1000);
+        for (n, line) in lines.iter().enumerate() {
+            assert_eq!(*line, numbered_line(n));
+        }
+    }
+
+    #[test]
+    fn pop_front_and_thaw() {
+        let mut store = store_with_lines(1000, 10, None);
+        for _ in 0..300 {
+            store.pop_front();
+        }
+        assert_eq!(store.len(), 700);
+        assert_eq!(store.lines_in_range(0..1)[0], numbered_line(300));
+        assert_eq!(store.lines_in_range(699..700)[0], numbered_line(999));
+
+        // Mutating a compacted line expands only the block that holds it
+        let frozen_len = store.frozen_len();
+        let line = store.get_mut(1).unwrap();
+        assert_eq!(*line, numbered_line(301));
+        assert_eq!(store.frozen_len(), frozen_len);
+        assert_eq!(store.num_thawed, 1);
+        assert_eq!(store.get(1), Some(&numbered_line(301)));
+        assert!(store.get(300).is_none());
+        assert_eq!(store.lines_in_range(0..1)[0], numbered_line(300));
+
+        // and compacting another block compacts it again
+        for n in 1000..1000 + BLOCK_LINES {
+            store.push_back(numbered_line(n));
+        }
+        assert_eq!(store.num_thawed, 0);
+        assert!(store.get(1).is_none());
+        assert_eq!(store.lines_in_range(1..2)[0], numbered_line(301));
+        assert_eq!(store.lines_in_range(700..701)[0], numbered_line(1000));
+    }
+
+    #[test]
+    fn insert_and_remove_compacted() {
+        let mut store = store_with_lines(1000, 10, None);
+        store.insert(300, numbered_line(2000));
+        assert_eq!(store.len(), 1001);
+        assert_eq!(store.num_thawed, 1);
+        assert_eq!(store.remove(100), Some(numbered_line(100)));
+        assert_eq!(store.num_thawed, 2);
+        assert_eq!(store.len(), 1000);
+
+        let expected: Vec<Line> = (0..1000)
+            .filter(|&n| n != 100)
+            .flat_map(|n| {
+                if n == 300 {
+                    vec![numbered_line(2000), numbered_line(n)]
+                } else {
+                    vec![numbered_line(n)]
+                }
+            })
+            .collect();
+        assert_eq!(store.lines_in_range(0..1000), expected);
+        assert_eq!(
+            store.iter().map(Cow::into_owned).collect::<Vec<_>>(),
+            expected
+        );
+        assert_eq!(store.changed_since(0..1000, 1999), vec![299]);
+        assert_eq!(store.drain().collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn iter_and_clone() {
+        let mut store = store_with_lines(2000, 10, Some(300));
+        for _ in 0..10 {
+            store.pop_front();
+        }
+        store.get_mut(20).unwrap();
+        assert!(store.num_spilled > 0);
+
+        let iterated: Vec<Line> = store.iter().map(Cow::into_owned).collect();
+        assert_eq!(iterated, store.lines_in_range(0..1990));
+        for (n, line) in iterated.iter().enumerate() {
+            assert_eq!(*line, numbered_line(n + 10));
+        }
+
+        let mut clone = store.clone();
+        assert_eq!(clone.num_spilled, 0);
+        assert_eq!(clone.drain().collect::<Vec<_>>(), iterated);
+    }
+
+    #[test]
+    fn changed_since_uses_compacted_seqnos() {
+        let mut store = store_with_lines(600, 10, None);
+        assert_eq!(
+            store.changed_since(0..600, 590),
+            (590..600).collect::<Vec<_>>()
+        );
+        store.update_last_change_seqno(1000);
+        assert_eq!(store.changed_since(0..600, 999).len(), 600);
+    }
+
+    #[test]
+    fn spill_to_disk() {
+        let mut store = store_with_lines(2000, 10, Some(300));
+        assert!(store.num_spilled > 0);
+        let lines: Vec<Line> = store.drain().collect();
+        assert_eq!(lines.len(), 2000);
+        for (n, line) in lines.iter().enumerate() {
+            assert_eq!(*line, numbered_line(n));
+        }
+    }
+
+    #[test]
+    fn for_each_mut_recompacts_changes() {
+        let mut store = store_with_lines(600, 10, Some(100));
+        store.for_each_mut(|idx, line| {
+            if idx == 5 {
+                line.set_cell(0, Cell::new('L', CellAttributes::default()), 700);
+            }
+        });
+        let line = &store.lines_in_range(5..6)[0];
+        assert_eq!(line.cells()[0].str(), "L");
+        assert_eq!(line.current_seqno(), 700);
+        assert_eq!(store.lines_in_range(6..7)[0], numbered_line(6));
+    }
+}
@@ -1,6 +1,7 @@
 use super::*;
 use crate::terminalstate::performer::Performer;
 use std::sync::Arc;
+use termwiz::escape::osc::NotificationUrgency;
 use termwiz::escape::parser::Parser;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -44,6 +45,10 @@ pub enum Alert {
         /// Whether clicking on the notification should focus the
         /// window/tab/pane that generated it
         focus: bool,
+        urgency: NotificationUrgency,
+        /// When set, clicking on the notification should send
+        /// `OSC 99 ; i=report_id ; ST` to the pane that generated it
+        report_id: Option<String>,
     },
     /// When the title, or something that likely influences the title,
     /// has been changed
@@ -0,0 +1,153 @@
+//! Tracks the commands run by the shell, as reported by
+//! OSC 133 semantic prompt escapes
+use crate::terminalstate::TerminalState;
+use crate::{CommandRecord, StableRowIndex};
+use std::time::{Instant, SystemTime};
+use termwiz::cell::SemanticType;
+
+/// Limits the number of commands that are remembered
+const MAX_COMMANDS: usize = 1000;
+
+/// Returns the last row that is associated with the command
+fn last_row(record: &CommandRecord) -> StableRowIndex {
+    record.end_y.or(record.output_y).unwrap_or(record.prompt_y)
+}
+
+fn new_record(prompt_y: StableRowIndex) -> CommandRecord {
+    CommandRecord {
+        command: String::new(),
+        exit_status: None,
+        start_time: None,
+        duration: None,
+        cwd: None,
+        prompt_y,
+        output_y: None,
+        end_y: None,
+    }
+}
+
+impl TerminalState {
+    fn cursor_stable_row(&self) -> StableRowIndex {
+        self.screen().visible_row_to_stable_row(self.cursor.y)
+    }
+
+    /// Called when the shell starts a fresh prompt; OSC 133;A or N
+    pub(crate) fn command_prompt_started(&mut self) {
+        let prompt_y = self.cursor_stable_row();
+
+        match self.commands.back() {
+            // The prompt was redrawn without running a command,
+            // for example, after pressing CTRL-C at the prompt
+            Some(last) if last.output_y.is_none() => {
+                self.commands.pop_back();
+            }
+            // The command didn't report its status
+            Some(last) if last.end_y.is_none() => {
+                self.finish_command(prompt_y, None);
+            }
+            _ => {}
+        }
+
+        self.commands.push_back(new_record(prompt_y));
+        self.prune_commands();
+    }
+
+    /// Called when the command has been entered and is about
+    /// to start running; OSC 133;C
+    pub(crate) fn command_output_started(&mut self) {
+        let output_y = self.cursor_stable_row();
+
+        match self.commands.back() {
+            Some(last) if last.output_y.is_none() => {}
+            last => {
+                // The shell didn't tell us about the prompt
+                if matches!(last, Some(last) if last.end_y.is_none()) {
+                    self.finish_command(output_y, None);
+                }
+                self.commands.push_back(new_record(output_y));
+                self.prune_commands();
+            }
+        }
+
+        let prompt_y = self.commands.back().map(|last| last.prompt_y);
+        let command = prompt_y
+            .map(|prompt_y| self.command_line_text(prompt_y, output_y))
+            .unwrap_or_default();
+        let cwd = self.current_dir.as_ref().map(|url| url.to_string());
+        if let Some(last) = self.commands.back_mut() {
+            last.command = command;
+            last.cwd = cwd;
+            last.output_y.replace(output_y);
+            last.start_time.replace(SystemTime::now());
+        }
+        self.command_started.replace(Instant::now());
+    }
+
+    /// Called when the shell reports the exit status of the
+    /// command; OSC 133;D
+    pub(crate) fn command_finished(&mut self, status: i32) {
+        let running = matches!(
+            self.commands.back(),
+            Some(last) if last.output_y.is_some() && last.end_y.is_none()
+        );
+        if running {
+            let end_y = self.cursor_stable_row();
+            self.finish_command(end_y, Some(status));
+        }
+    }
+
+    fn finish_command(&mut self, end_y: StableRowIndex, exit_status: Option<i32>) {
+        let duration = self.command_started.take().map(|started| started.elapsed());
+        if let Some(last) = self.commands.back_mut() {
+            last.end_y.replace(end_y);
+            last.exit_status = exit_status;
+            last.duration = duration;
+        }
+    }
+
+    /// Discards the oldest commands once there are too many of
+    /// them, or once they have scrolled out of the scrollback
+    fn prune_commands(&mut self) {
+        let first_row = self.screen().phys_to_stable_row_index(0);
+        while let Some(first) = self.commands.front() {
+            if self.commands.len() <= MAX_COMMANDS && last_row(first) >= first_row {
+                break;
+            }
+            self.commands.pop_front();
+        }
+    }
+
+    /// Extracts the text of the Input zone(s) between the specified rows
+    fn command_line_text(&self, start_y: StableRowIndex, end_y: StableRowIndex) -> String {
+        let screen = self.screen();
+        let phys = screen.stable_range(&(start_y..end_y + 1));
+        let mut text = String::new();
+        for line in screen.lines_in_phys_range(phys) {
+            let mut had_input = false;
+            for (_, cell) in line.visible_cells() {
+                if cell.attrs().semantic_type() == SemanticType::Input {
+                    text.push_str(cell.str());
+                    had_input = true;
+                }
+            }
+            if had_input && !line.last_cell_was_wrapped() {
+                let trimmed = text.trim_end().len();
+                text.truncate(trimmed);
+                text.push('\n');
+            }
+        }
+        text.trim().to_string()
+    }
+
+    /// Returns the commands that have been reported by the shell
+    /// and that are still present in the scrollback, oldest first.
+    /// The most recent command may still be running.
+    pub fn get_commands(&self) -> Vec<CommandRecord> {
+        let first_row = self.screen().phys_to_stable_row_index(0);
+        self.commands
+            .iter()
+            .filter(|record| last_row(record) >= first_row)
+            .cloned()
+            .collect()
+    }
+}
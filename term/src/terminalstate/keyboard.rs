@@ -4,7 +4,11 @@ use termwiz::input::{KeyCodeEncodeModes, KeyboardEncoding};
 
 impl TerminalState {
     fn effective_keyboard_encoding(&self) -> KeyboardEncoding {
+        let kitty_flags = self.screen.kitty_keyboard_flags();
         match self.keyboard_encoding {
+            KeyboardEncoding::Xterm if !kitty_flags.is_empty() => {
+                KeyboardEncoding::Kitty(kitty_flags)
+            }
             KeyboardEncoding::Xterm if self.config.enable_csi_u_key_encoding() => {
                 KeyboardEncoding::CsiU
             }
@@ -6,29 +6,33 @@ use crate::color::{ColorPalette, RgbColor};
 use crate::config::{BidiMode, NewlineCanon};
 use log::debug;
 use num_traits::ToPrimitive;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
+use std::time::Instant;
 use terminfo::{Database, Value};
 use termwiz::cell::UnicodeVersion;
 use termwiz::escape::csi::{
-    Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode, Device, Edit, EraseInDisplay,
-    EraseInLine, Mode, Sgr, TabulationClear, TerminalMode, TerminalModeCode, Window, XtSmGraphics,
-    XtSmGraphicsAction, XtSmGraphicsItem, XtSmGraphicsStatus,
+    AttributeChangeExtent, Cursor, CursorStyle, DecPrivateMode, DecPrivateModeCode, Device, Edit,
+    EraseInDisplay, EraseInLine, Keyboard, KittyKeyboardMode, Mode, Sgr, TabulationClear,
+    TerminalMode, TerminalModeCode, Window, XtSmGraphics, XtSmGraphicsAction, XtSmGraphicsItem,
+    XtSmGraphicsStatus,
 };
 use termwiz::escape::{OneBased, OperatingSystemCommand, CSI};
 use termwiz::image::ImageData;
-use termwiz::input::KeyboardEncoding;
+use termwiz::input::{KeyboardEncoding, KittyKeyboardFlags};
 use termwiz::surface::{CursorShape, CursorVisibility, SequenceNo};
 use url::Url;
 use wezterm_bidi::ParagraphDirectionHint;
 
+mod commands;
 mod image;
 mod iterm;
 mod keyboard;
 mod kitty;
 mod mouse;
 pub(crate) mod performer;
+mod rectangle;
 mod sixel;
 use crate::terminalstate::image::*;
 use crate::terminalstate::kitty::*;
@@ -134,6 +138,43 @@ struct SavedCursor {
     // TODO: selective_erase when supported
 }
 
+/// The text accumulated from the chunks of a kitty desktop notification
+#[derive(Debug, Default)]
+struct PendingKittyNotification {
+    title: String,
+    body: String,
+}
+
+/// Tracks a synchronized output (DEC mode 2026) update that is in progress
+#[derive(Debug)]
+struct SynchronizedOutput {
+    /// When the application enabled the mode
+    started: Instant,
+    /// The seqno that was current when the mode was enabled;
+    /// this is what we publish until the update completes
+    seqno: SequenceNo,
+    snapshot: SynchronizedOutputSnapshot,
+}
+
+/// The visible screen as it was when a synchronized output update
+/// began.  Renderers should present this, rather than the partially
+/// updated screen, until the update completes.
+#[derive(Debug, Clone)]
+pub struct SynchronizedOutputSnapshot {
+    /// The stable row index of the first of `lines`
+    pub top: StableRowIndex,
+    /// The lines of the visible screen
+    pub lines: Vec<Line>,
+    pub physical_cols: usize,
+    /// The cursor position, relative to the top of `lines`
+    pub cursor: CursorPosition,
+    pub reverse_video: bool,
+}
+
+/// The maximum depth of the kitty keyboard protocol flag stack;
+/// pushing beyond this evicts the oldest entry
+const MAX_KITTY_KEYBOARD_STACK: usize = 16;
+
 struct ScreenOrAlt {
     /// The primary screen + scrollback
     screen: Screen,
@@ -143,6 +184,10 @@ struct ScreenOrAlt {
     alt_screen_is_active: bool,
     saved_cursor: Option<SavedCursor>,
     alt_saved_cursor: Option<SavedCursor>,
+    /// The kitty keyboard protocol flag stacks; each screen
+    /// maintains its own independent stack
+    kitty_keyboard: Vec<KittyKeyboardFlags>,
+    alt_kitty_keyboard: Vec<KittyKeyboardFlags>,
 }
 
 impl Deref for ScreenOrAlt {
@@ -191,6 +236,8 @@ impl ScreenOrAlt {
             alt_screen_is_active: false,
             saved_cursor: None,
             alt_saved_cursor: None,
+            kitty_keyboard: vec![],
+            alt_kitty_keyboard: vec![],
         }
     }
 
@@ -246,6 +293,28 @@ impl ScreenOrAlt {
             &mut self.saved_cursor
         }
     }
+
+    pub fn kitty_keyboard_stack(&mut self) -> &mut Vec<KittyKeyboardFlags> {
+        if self.alt_screen_is_active {
+            &mut self.alt_kitty_keyboard
+        } else {
+            &mut self.kitty_keyboard
+        }
+    }
+
+    pub fn kitty_keyboard_flags(&self) -> KittyKeyboardFlags {
+        let stack = if self.alt_screen_is_active {
+            &self.alt_kitty_keyboard
+        } else {
+            &self.kitty_keyboard
+        };
+        stack.last().copied().unwrap_or(KittyKeyboardFlags::NONE)
+    }
+
+    pub fn clear_kitty_keyboard_stacks(&mut self) {
+        self.kitty_keyboard.clear();
+        self.alt_kitty_keyboard.clear();
+    }
 }
 
 /// Manages the state for the terminal
@@ -289,6 +358,11 @@ pub struct TerminalState {
     left_and_right_margins: Range<usize>,
     left_and_right_margin_mode: bool,
 
+    /// https://vt100.net/docs/vt510-rm/DECSACE.html
+    /// Controls whether DECCARA and DECRARA affect a rectangle
+    /// or a stream of character positions.
+    attribute_change_extent: AttributeChangeExtent,
+
     /// When set, modifies the sequence of bytes sent for keys
     /// designated as cursor keys.  This includes various navigation
     /// keys.  The code in key_down() is responsible for interpreting this.
@@ -378,9 +452,22 @@ pub struct TerminalState {
 
     accumulating_title: Option<String>,
 
+    /// Kitty desktop notifications (OSC 99) that are being
+    /// received in chunks, keyed by their id
+    pending_kitty_notifications: HashMap<Option<String>, PendingKittyNotification>,
+
     lost_focus_seqno: SequenceNo,
     focused: bool,
 
+    /// Set while the application has enabled synchronized output
+    synchronized_output: Option<SynchronizedOutput>,
+
+    /// The commands reported via OSC 133; the most recent
+    /// entry may still be in progress
+    commands: VecDeque<CommandRecord>,
+    /// When the command that is in progress started running
+    command_started: Option<Instant>,
+
     /// True if lines should be marked as bidi-enabled, and thus
     /// have the renderer apply the bidi algorithm.
     /// true is equivalent to "implicit" bidi mode as described in
@@ -507,6 +594,7 @@ impl TerminalState {
             top_and_bottom_margins: 0..size.physical_rows as VisibleRowIndex,
             left_and_right_margins: 0..size.physical_cols,
             left_and_right_margin_mode: false,
+            attribute_change_extent: AttributeChangeExtent::Stream,
             wrap_next: false,
             clear_semantic_attribute_on_newline: false,
             // We default auto wrap to true even though the default for
@@ -560,8 +648,12 @@ impl TerminalState {
             suppress_initial_title_change: false,
             enable_conpty_quirks: false,
             accumulating_title: None,
+            pending_kitty_notifications: HashMap::new(),
             lost_focus_seqno: seqno,
             focused: true,
+            synchronized_output: None,
+            commands: VecDeque::new(),
+            command_started: None,
             bidi_enabled: None,
             bidi_hint: None,
         }
@@ -572,8 +664,57 @@ impl TerminalState {
         self.suppress_initial_title_change = true;
     }
 
+    /// Returns the seqno of the most recently published changes.
+    /// While synchronized output is active, this is held at the
+    /// value from the start of the update so that renderers don't
+    /// observe a partially updated screen.
     pub fn current_seqno(&self) -> SequenceNo {
-        self.seqno
+        match self.synchronized_output_state() {
+            Some(sync) => sync.seqno,
+            None => self.seqno,
+        }
+    }
+
+    fn synchronized_output_state(&self) -> Option<&SynchronizedOutput> {
+        let sync = self.synchronized_output.as_ref()?;
+        if sync.started.elapsed() >= self.config.synchronized_output_timeout() {
+            None
+        } else {
+            Some(sync)
+        }
+    }
+
+    /// Returns true if the application is part way through a
+    /// synchronized output (DEC mode 2026) update, and the
+    /// timeout for that update has not yet expired.
+    pub fn is_synchronized_output_active(&self) -> bool {
+        self.synchronized_output_state().is_some()
+    }
+
+    /// If synchronized output is active, returns the time at which
+    /// the update will be forcibly published if the application
+    /// has not completed it by then.
+    pub fn synchronized_output_deadline(&self) -> Option<Instant> {
+        self.synchronized_output_state()
+            .map(|sync| sync.started + self.config.synchronized_output_timeout())
+    }
+
+    /// If synchronized output is active, returns the visible screen
+    /// as it was when the update began
+    pub fn synchronized_output_snapshot(&self) -> Option<&SynchronizedOutputSnapshot> {
+        self.synchronized_output_state().map(|sync| &sync.snapshot)
+    }
+
+    fn take_synchronized_output_snapshot(&self) -> SynchronizedOutputSnapshot {
+        let screen = self.screen();
+        let phys = screen.phys_range(&(0..screen.physical_rows as VisibleRowIndex));
+        SynchronizedOutputSnapshot {
+            top: screen.phys_to_stable_row_index(phys.start),
+            lines: screen.lines_in_phys_range(phys),
+            physical_cols: screen.physical_cols,
+            cursor: self.cursor_pos(),
+            reverse_video: self.reverse_video_mode,
+        }
     }
 
     pub fn increment_seqno(&mut self) {
@@ -822,6 +963,10 @@ impl TerminalState {
             )
         };
 
+        // The snapshot no longer matches the screen, so publish
+        // any update that is in progress
+        self.synchronized_output.take();
+
         let (adjusted_cursor_main, adjusted_cursor_alt) = self.screen.resize(
             physical_rows,
             physical_cols,
@@ -865,10 +1010,7 @@ impl TerminalState {
     /// When dealing with selection, mark a range of lines as dirty
     pub fn make_all_lines_dirty(&mut self) {
         let seqno = self.seqno;
-        let screen = self.screen_mut();
-        screen.for_each_phys_line_mut(|_, line| {
-            line.update_last_change_seqno(seqno);
-        });
+        self.screen_mut().dirty_all_lines(seqno);
     }
 
     /// Returns the 0-based cursor position relative to the top left of
@@ -1187,6 +1329,7 @@ impl TerminalState {
                 self.top_and_bottom_margins = 0..self.screen().physical_rows as i64;
                 self.left_and_right_margins = 0..self.screen().physical_cols;
                 self.left_and_right_margin_mode = false;
+                self.synchronized_output.take();
                 self.screen.activate_alt_screen(self.seqno);
                 self.screen.saved_cursor().take();
                 self.screen.activate_primary_screen(self.seqno);
@@ -1321,6 +1464,7 @@ impl TerminalState {
 
         log::trace!("{:?} -> recognized={} status={}", mode, recognized, status);
         write!(self.writer, "\x1b[{}{};{}$y", prefix, number, status).ok();
+        self.writer.flush().ok();
     }
 
     fn perform_csi_mode(&mut self, mode: Mode) {
@@ -1452,19 +1596,26 @@ impl TerminalState {
             Mode::SetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::SynchronizedOutput,
             )) => {
-                // This is handled in wezterm's mux
+                // Setting the mode again while an update is in progress
+                // doesn't extend the timeout of that update
+                if !self.is_synchronized_output_active() {
+                    self.synchronized_output.replace(SynchronizedOutput {
+                        started: Instant::now(),
+                        seqno: self.seqno,
+                        snapshot: self.take_synchronized_output_snapshot(),
+                    });
+                }
             }
             Mode::ResetDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::SynchronizedOutput,
             )) => {
-                // This is handled in wezterm's mux
+                self.synchronized_output.take();
             }
             Mode::QueryDecPrivateMode(DecPrivateMode::Code(
                 DecPrivateModeCode::SynchronizedOutput,
             )) => {
-                // This is handled in wezterm's mux; if we get here, then it isn't enabled,
-                // so we always report false
-                self.decqrm_response(mode, true, false);
+                let active = self.is_synchronized_output_active();
+                self.decqrm_response(mode, true, active);
             }
 
             Mode::SetDecPrivateMode(DecPrivateMode::Code(DecPrivateModeCode::SmoothScroll))
@@ -1837,6 +1988,44 @@ impl TerminalState {
         checksum
     }
 
+    fn perform_csi_keyboard(&mut self, keyboard: Keyboard) {
+        match keyboard {
+            Keyboard::QueryKittySupport => {
+                let response = Keyboard::ReportKittyState(self.screen.kitty_keyboard_flags());
+                write!(self.writer, "{}", CSI::Keyboard(response)).ok();
+                self.writer.flush().ok();
+            }
+            Keyboard::PushKittyState(flags) => {
+                let stack = self.screen.kitty_keyboard_stack();
+                if stack.len() >= MAX_KITTY_KEYBOARD_STACK {
+                    stack.remove(0);
+                }
+                stack.push(flags);
+            }
+            Keyboard::PopKittyState(n) => {
+                let stack = self.screen.kitty_keyboard_stack();
+                let n = (n as usize).min(stack.len());
+                stack.truncate(stack.len() - n);
+            }
+            Keyboard::SetKittyState { flags, mode } => {
+                let stack = self.screen.kitty_keyboard_stack();
+                if stack.is_empty() {
+                    stack.push(KittyKeyboardFlags::NONE);
+                }
+                if let Some(current) = stack.last_mut() {
+                    match mode {
+                        KittyKeyboardMode::AssignAll => *current = flags,
+                        KittyKeyboardMode::SetSpecified => current.insert(flags),
+                        KittyKeyboardMode::ClearSpecified => current.remove(flags),
+                    }
+                }
+            }
+            Keyboard::ReportKittyState(_) => {
+                log::warn!("unhandled {:?}", keyboard);
+            }
+        }
+    }
+
     fn perform_csi_window(&mut self, window: Window) {
         match window {
             Window::ReportTextAreaSizeCells => {
@@ -2117,6 +2306,21 @@ impl TerminalState {
                 self.cursor.x = x;
                 self.cursor.y = y;
             }
+            Edit::CopyRectangularArea { src, top, left, .. } => {
+                self.copy_rectangular_area(src, top, left)
+            }
+            Edit::FillRectangularArea { ch, area } => self.fill_rectangular_area(ch, area),
+            Edit::EraseRectangularArea(area) => self.erase_rectangular_area(area, false),
+            Edit::SelectiveEraseRectangularArea(area) => self.erase_rectangular_area(area, true),
+            Edit::ChangeAttributesInRectangularArea { area, attributes } => {
+                self.change_attributes_in_rectangular_area(area, &attributes, false)
+            }
+            Edit::ReverseAttributesInRectangularArea { area, attributes } => {
+                self.change_attributes_in_rectangular_area(area, &attributes, true)
+            }
+            Edit::SelectAttributeChangeExtent(extent) => {
+                self.attribute_change_extent = extent;
+            }
         }
     }
 
@@ -2502,16 +2706,16 @@ impl TerminalState {
     /// By default, all screen data is of type Output.  The shell needs to
     /// employ OSC 133 escapes to markup its output.
     pub fn get_semantic_zones(&mut self) -> anyhow::Result<Vec<SemanticZone>> {
-        let screen = self.screen_mut();
+        let screen = self.screen();
 
         let mut current_zone: Option<SemanticZone> = None;
         let mut zones = vec![];
 
         let first_stable_row = screen.phys_to_stable_row_index(0);
-        screen.for_each_phys_line_mut(|idx, line| {
+        for (idx, line) in screen.phys_lines().enumerate() {
             let stable_row = first_stable_row + idx as StableRowIndex;
 
-            for zone_range in line.semantic_zone_ranges() {
+            for zone_range in line.semantic_zone_ranges_uncached().iter() {
                 let new_zone = match current_zone.as_ref() {
                     None => true,
                     Some(zone) => zone.semantic_type != zone_range.semantic_type,
@@ -2536,7 +2740,7 @@ impl TerminalState {
                     zone.end_y = stable_row;
                 }
             }
-        });
+        }
         if let Some(zone) = current_zone.take() {
             zones.push(zone);
         }
@@ -7,16 +7,21 @@ use log::{debug, error};
 use num_traits::FromPrimitive;
 use std::fmt::Write;
 use std::ops::{Deref, DerefMut};
-use termwiz::cell::{grapheme_column_width, Cell, CellAttributes, SemanticType};
-use termwiz::escape::csi::{CharacterPath, EraseInDisplay};
+use termwiz::cell::{
+    grapheme_column_width, Blink, Cell, CellAttributes, Intensity, SemanticType, Underline,
+};
+use termwiz::color::{ColorAttribute, ColorSpec};
+use termwiz::escape::csi::{AttributeChangeExtent, CharacterPath, EraseInDisplay, Sgr};
 use termwiz::escape::osc::{
     ChangeColorPair, ColorOrQuery, FinalTermSemanticPrompt, ITermProprietary,
-    ITermUnicodeVersionOp, Selection,
+    ITermUnicodeVersionOp, KittyNotification, KittyNotificationOccasion, KittyNotificationPayload,
+    NotificationUrgency, Selection,
 };
 use termwiz::escape::{
     Action, ControlCode, DeviceControlMode, Esc, EscCode, OperatingSystemCommand, CSI,
 };
 use termwiz::input::KeyboardEncoding;
+use termwiz::surface::CursorShape;
 use url::Url;
 use wezterm_bidi::ParagraphDirectionHint;
 
@@ -258,6 +263,48 @@ impl<'a> Performer<'a> {
                                 .ok();
                                 self.writer.flush().ok();
                             }
+                            &[b'm'] => {
+                                // SGR - graphic rendition of the pen
+                                let sgr = sgr_status_string(&self.pen);
+                                write!(self.writer, "{}1$r{}m{}", DCS, sgr, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b' ', b'q'] => {
+                                // DECSCUSR - cursor style
+                                let style = match self.cursor.shape {
+                                    CursorShape::Default => 0,
+                                    CursorShape::BlinkingBlock => 1,
+                                    CursorShape::SteadyBlock => 2,
+                                    CursorShape::BlinkingUnderline => 3,
+                                    CursorShape::SteadyUnderline => 4,
+                                    CursorShape::BlinkingBar => 5,
+                                    CursorShape::SteadyBar => 6,
+                                };
+                                write!(self.writer, "{}1$r{} q{}", DCS, style, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b'"', b'q'] => {
+                                // DECSCA - character protection attribute.
+                                // We don't support protected cells, so the
+                                // pen is never protected.
+                                write!(self.writer, "{}1$r0\"q{}", DCS, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b'*', b'x'] => {
+                                // DECSACE - attribute change extent
+                                let extent = match self.attribute_change_extent {
+                                    AttributeChangeExtent::Stream => 1,
+                                    AttributeChangeExtent::Rectangle => 2,
+                                };
+                                write!(self.writer, "{}1$r{}*x{}", DCS, extent, ST).ok();
+                                self.writer.flush().ok();
+                            }
+                            &[b't'] => {
+                                // DECSLPP - lines per page
+                                let rows = self.screen().physical_rows;
+                                write!(self.writer, "{}1$r{}t{}", DCS, rows, ST).ok();
+                                self.writer.flush().ok();
+                            }
                             _ => {
                                 log::warn!("unhandled DECRQSS {:?}", s);
                                 // Reply that the request is invalid
@@ -276,6 +323,73 @@ impl<'a> Performer<'a> {
         }
     }
 
+    fn kitty_notification(&mut self, notif: KittyNotification) {
+        // Limit how much we are willing to buffer on behalf of
+        // an application that never finishes sending its chunks
+        const MAX_PENDING: usize = 16;
+        const MAX_LEN: usize = 64 * 1024;
+
+        if notif.payload_type == KittyNotificationPayload::Query {
+            let response = OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: notif.id,
+                payload_type: KittyNotificationPayload::Query,
+                payload: "a=focus,report:o=always,unfocused,invisible:u=0,1,2:p=title,body,?"
+                    .to_string(),
+                ..Default::default()
+            });
+            write!(self.writer, "{}", response).ok();
+            self.writer.flush().ok();
+            return;
+        }
+
+        let mut pending = self
+            .pending_kitty_notifications
+            .remove(&notif.id)
+            .unwrap_or_default();
+        match notif.payload_type {
+            KittyNotificationPayload::Title => pending.title.push_str(&notif.payload),
+            KittyNotificationPayload::Body => pending.body.push_str(&notif.payload),
+            KittyNotificationPayload::Query => unreachable!("queries are answered above"),
+        }
+
+        if !notif.done {
+            if pending.title.len() + pending.body.len() > MAX_LEN
+                || self.pending_kitty_notifications.len() >= MAX_PENDING
+            {
+                log::warn!("discarding oversized kitty notification {:?}", notif.id);
+            } else {
+                self.pending_kitty_notifications.insert(notif.id, pending);
+            }
+            return;
+        }
+
+        let (title, body) = match (pending.title, pending.body) {
+            (title, body) if title.is_empty() && body.is_empty() => return,
+            (title, body) if title.is_empty() => (None, body),
+            (title, body) => (Some(title), body),
+        };
+
+        // We can't tell whether the window is visible, so treat
+        // invisible the same as unfocused
+        if notif.occasion != KittyNotificationOccasion::Always && self.focused {
+            return;
+        }
+
+        if let Some(handler) = self.alert_handler.as_mut() {
+            handler.alert(Alert::ToastNotification {
+                title,
+                body,
+                focus: notif.focus,
+                urgency: notif.urgency,
+                report_id: if notif.report {
+                    Some(notif.id.unwrap_or_else(|| "0".to_string()))
+                } else {
+                    None
+                },
+            });
+        }
+    }
+
     /// Draw a character to the screen
     fn print(&mut self, c: char) {
         // We buffer up the chars to increase the chances of correctly grouping graphemes into cells
@@ -415,6 +529,7 @@ impl<'a> Performer<'a> {
             CSI::Device(dev) => self.state.perform_device(*dev),
             CSI::Mouse(mouse) => error!("mouse report sent by app? {:?}", mouse),
             CSI::Window(window) => self.state.perform_csi_window(window),
+            CSI::Keyboard(keyboard) => self.state.perform_csi_keyboard(keyboard),
             CSI::SelectCharacterPath(CharacterPath::ImplementationDefault, _) => {
                 self.state.bidi_hint.take();
             }
@@ -536,7 +651,9 @@ impl<'a> Performer<'a> {
                 self.reverse_wraparound_mode = false;
                 self.reverse_video_mode = false;
                 self.dec_origin_mode = false;
+                self.attribute_change_extent = AttributeChangeExtent::Stream;
                 self.use_private_color_registers_for_each_graphic = false;
+                self.synchronized_output.take();
                 self.color_map = default_color_map();
                 self.application_cursor_keys = false;
                 self.sixel_display_mode = false;
@@ -546,6 +663,7 @@ impl<'a> Performer<'a> {
                 self.focus_tracking = false;
                 self.mouse_encoding = MouseEncoding::X10;
                 self.keyboard_encoding = KeyboardEncoding::Xterm;
+                self.screen.clear_kitty_keyboard_stacks();
                 self.sixel_scrolls_right = false;
                 self.any_event_mouse = false;
                 self.button_event_mouse = false;
@@ -668,6 +786,7 @@ impl<'a> Performer<'a> {
                 FinalTermSemanticPrompt::FreshLineAndStartPrompt { .. },
             ) => {
                 self.fresh_line();
+                self.command_prompt_started();
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
@@ -679,6 +798,7 @@ impl<'a> Performer<'a> {
                 FinalTermSemanticPrompt::MarkEndOfCommandWithFreshLine { .. },
             ) => {
                 self.fresh_line();
+                self.command_prompt_started();
                 self.pen.set_semantic_type(SemanticType::Prompt);
             }
             OperatingSystemCommand::FinalTermSemanticPrompt(
@@ -696,11 +816,14 @@ impl<'a> Performer<'a> {
                 FinalTermSemanticPrompt::MarkEndOfInputAndStartOfOutput { .. },
             ) => {
                 self.pen.set_semantic_type(SemanticType::Output);
+                self.command_output_started();
             }
 
             OperatingSystemCommand::FinalTermSemanticPrompt(
-                FinalTermSemanticPrompt::CommandStatus { .. },
-            ) => {}
+                FinalTermSemanticPrompt::CommandStatus { status, .. },
+            ) => {
+                self.command_finished(status);
+            }
 
             OperatingSystemCommand::SystemNotification(message) => {
                 if let Some(handler) = self.alert_handler.as_mut() {
@@ -708,32 +831,30 @@ impl<'a> Performer<'a> {
                         title: None,
                         body: message,
                         focus: true,
+                        // These have always been shown as persistent
+                        // notifications
+                        urgency: NotificationUrgency::Critical,
+                        report_id: None,
                     });
                 } else {
                     log::info!("Application sends SystemNotification: {}", message);
                 }
             }
-            OperatingSystemCommand::RxvtExtension(params) => {
-                if let Some("notify") = params.get(0).map(String::as_str) {
-                    let title = params.get(1);
-                    let body = params.get(2);
-                    let (title, body) = match (title.cloned(), body.cloned()) {
-                        (Some(title), None) => (None, title),
-                        (Some(title), Some(body)) => (Some(title), body),
-                        _ => {
-                            log::warn!("malformed rxvt notify escape: {:?}", params);
-                            return;
-                        }
-                    };
-                    if let Some(handler) = self.alert_handler.as_mut() {
-                        handler.alert(Alert::ToastNotification {
-                            title,
-                            body,
-                            focus: true,
-                        });
-                    }
+            OperatingSystemCommand::RxvtNotify { title, body } => {
+                if let Some(handler) = self.alert_handler.as_mut() {
+                    handler.alert(Alert::ToastNotification {
+                        title,
+                        body,
+                        focus: true,
+                        urgency: NotificationUrgency::Critical,
+                        report_id: None,
+                    });
                 }
             }
+            OperatingSystemCommand::KittyNotification(notif) => self.kitty_notification(notif),
+            OperatingSystemCommand::RxvtExtension(params) => {
+                log::debug!("unhandled OSC 777: {:?}", params);
+            }
             OperatingSystemCommand::CurrentWorkingDirectory(url) => {
                 self.current_dir = Url::parse(&url).ok();
                 if let Some(handler) = self.alert_handler.as_mut() {
@@ -899,3 +1020,62 @@ fn selection_to_selection(sel: Selection) -> ClipboardSelection {
         _ => ClipboardSelection::Clipboard,
     }
 }
+
+/// Renders the SGR parameters that would reproduce `attrs` when
+/// applied to a freshly reset pen, in the form used by the DECRPSS
+/// reply.  The result always begins with `0`, followed by the
+/// parameters for each of the non-default attributes.
+fn sgr_status_string(attrs: &CellAttributes) -> String {
+    fn color_spec(color: ColorAttribute) -> ColorSpec {
+        match color {
+            ColorAttribute::TrueColorWithPaletteFallback(color, _)
+            | ColorAttribute::TrueColorWithDefaultFallback(color) => ColorSpec::TrueColor(color),
+            ColorAttribute::PaletteIndex(idx) => ColorSpec::PaletteIndex(idx),
+            ColorAttribute::Default => ColorSpec::Default,
+        }
+    }
+
+    let mut sgr = vec![];
+    if attrs.intensity() != Intensity::Normal {
+        sgr.push(Sgr::Intensity(attrs.intensity()));
+    }
+    if attrs.italic() {
+        sgr.push(Sgr::Italic(true));
+    }
+    if attrs.underline() != Underline::None {
+        sgr.push(Sgr::Underline(attrs.underline()));
+    }
+    if attrs.blink() != Blink::None {
+        sgr.push(Sgr::Blink(attrs.blink()));
+    }
+    if attrs.reverse() {
+        sgr.push(Sgr::Inverse(true));
+    }
+    if attrs.invisible() {
+        sgr.push(Sgr::Invisible(true));
+    }
+    if attrs.strikethrough() {
+        sgr.push(Sgr::StrikeThrough(true));
+    }
+    if attrs.overline() {
+        sgr.push(Sgr::Overline(true));
+    }
+    if attrs.foreground() != ColorAttribute::Default {
+        sgr.push(Sgr::Foreground(color_spec(attrs.foreground())));
+    }
+    if attrs.background() != ColorAttribute::Default {
+        sgr.push(Sgr::Background(color_spec(attrs.background())));
+    }
+    if attrs.underline_color() != ColorAttribute::Default {
+        sgr.push(Sgr::UnderlineColor(color_spec(attrs.underline_color())));
+    }
+
+    let mut result = "0".to_string();
+    for item in sgr {
+        // Sgr renders the final `m` too; we only want the parameters
+        let item = item.to_string();
+        result.push(';');
+        result.push_str(item.trim_end_matches('m'));
+    }
+    result
+}
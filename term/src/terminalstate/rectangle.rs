@@ -0,0 +1,300 @@
+//! The VT420 rectangular area operations: DECCRA, DECFRA, DECERA,
+//! DECSERA, DECCARA and DECRARA.
+//! <https://vt100.net/docs/vt510-rm/chapter5.html#S5.8>
+use crate::terminalstate::TerminalState;
+use crate::VisibleRowIndex;
+use std::ops::Range;
+use termwiz::cell::{grapheme_column_width, Blink, Cell, CellAttributes, Intensity, Underline};
+use termwiz::escape::csi::{AttributeChangeExtent, RectangularArea, Sgr};
+use termwiz::escape::OneBased;
+
+impl TerminalState {
+    /// Returns the rows and columns that rectangular area coordinates
+    /// are relative to, and are clipped to.  When DECOM is enabled,
+    /// that is the region defined by the margins, otherwise it is the
+    /// whole screen.
+    fn rectangular_page(&self) -> (Range<VisibleRowIndex>, Range<usize>) {
+        if self.dec_origin_mode {
+            (
+                self.top_and_bottom_margins.clone(),
+                self.left_and_right_margins.clone(),
+            )
+        } else {
+            (
+                0..self.screen().physical_rows as VisibleRowIndex,
+                0..self.screen().physical_cols,
+            )
+        }
+    }
+
+    /// Converts `area` to the ranges of visible rows and columns that
+    /// it covers, clipped to the page.  The ranges may be empty, or
+    /// inverted if the application specified the edges backwards.
+    fn rectangular_area_bounds(
+        &self,
+        area: RectangularArea,
+    ) -> (Range<VisibleRowIndex>, Range<usize>) {
+        let (page_rows, page_cols) = self.rectangular_page();
+
+        let top = page_rows.start + area.top.as_zero_based() as VisibleRowIndex;
+        let bottom =
+            (page_rows.start + area.bottom.as_one_based() as VisibleRowIndex).min(page_rows.end);
+        let left = page_cols.start + area.left.as_zero_based() as usize;
+        let right = (page_cols.start + area.right.as_one_based() as usize).min(page_cols.end);
+
+        (top..bottom, left..right)
+    }
+
+    /// Resolves `area` to the ranges of visible rows and columns that
+    /// it covers, or None if it is empty.
+    fn resolve_rectangular_area(
+        &self,
+        area: RectangularArea,
+    ) -> Option<(Range<VisibleRowIndex>, Range<usize>)> {
+        let (rows, cols) = self.rectangular_area_bounds(area);
+        if rows.is_empty() || cols.is_empty() {
+            None
+        } else {
+            Some((rows, cols))
+        }
+    }
+
+    /// DECCRA
+    pub(crate) fn copy_rectangular_area(
+        &mut self,
+        src: RectangularArea,
+        top: OneBased,
+        left: OneBased,
+    ) {
+        let (rows, cols) = match self.resolve_rectangular_area(src) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let (page_rows, page_cols) = self.rectangular_page();
+        let dest_top = page_rows.start + top.as_zero_based() as VisibleRowIndex;
+        let dest_left = page_cols.start + left.as_zero_based() as usize;
+        let seqno = self.seqno;
+        let screen = self.screen_mut();
+
+        // Take a copy of the source first, as the destination may overlap it
+        let cells: Vec<Vec<Cell>> = rows
+            .map(|y| {
+                cols.clone()
+                    .map(|x| screen.get_cell(x, y).cloned().unwrap_or_else(Cell::blank))
+                    .collect()
+            })
+            .collect();
+
+        for (y, row) in (dest_top..page_rows.end).zip(cells) {
+            for (x, cell) in (dest_left..page_cols.end).zip(row) {
+                screen.set_cell(x, y, &cell, seqno);
+            }
+        }
+    }
+
+    /// DECFRA
+    pub(crate) fn fill_rectangular_area(&mut self, ch: char, area: RectangularArea) {
+        // The fill character must be a printable, single cell character
+        let mut buf = [0u8; 4];
+        let text = ch.encode_utf8(&mut buf);
+        if ch.is_control() || grapheme_column_width(text, None) != 1 {
+            log::debug!("ignoring DECFRA with fill character {:?}", ch);
+            return;
+        }
+        let (rows, cols) = match self.resolve_rectangular_area(area) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let cell = Cell::new(ch, self.pen.clone_sgr_only());
+        self.fill_rectangle(rows, cols, &cell);
+    }
+
+    /// DECERA, and DECSERA when `selective` is true.
+    /// We don't support DECSCA, so no characters are protected and
+    /// selective erase affects every character in the area; it
+    /// differs from DECERA in that it leaves the attributes alone.
+    pub(crate) fn erase_rectangular_area(&mut self, area: RectangularArea, selective: bool) {
+        let (rows, cols) = match self.resolve_rectangular_area(area) {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        if selective {
+            let seqno = self.seqno;
+            let screen = self.screen_mut();
+            for y in rows {
+                for x in cols.clone() {
+                    let attrs = match screen.get_cell(x, y) {
+                        Some(cell) => cell.attrs().clone(),
+                        None => continue,
+                    };
+                    screen.set_cell(x, y, &Cell::blank_with_attrs(attrs), seqno);
+                }
+            }
+        } else {
+            let cell = Cell::blank_with_attrs(self.pen.clone_sgr_only());
+            self.fill_rectangle(rows, cols, &cell);
+        }
+    }
+
+    fn fill_rectangle(&mut self, rows: Range<VisibleRowIndex>, cols: Range<usize>, cell: &Cell) {
+        let seqno = self.seqno;
+        let screen = self.screen_mut();
+        for y in rows {
+            let line_idx = screen.phys_row(y);
+            screen
+                .line_mut(line_idx)
+                .fill_range(cols.clone(), cell, seqno);
+        }
+    }
+
+    /// DECCARA, and DECRARA when `reverse` is true
+    pub(crate) fn change_attributes_in_rectangular_area(
+        &mut self,
+        area: RectangularArea,
+        attributes: &[Sgr],
+        reverse: bool,
+    ) {
+        let (rows, cols) = self.rectangular_area_bounds(area);
+        if rows.is_empty() {
+            return;
+        }
+
+        let spans: Vec<(VisibleRowIndex, Range<usize>)> = match self.attribute_change_extent {
+            AttributeChangeExtent::Rectangle => {
+                if cols.is_empty() {
+                    return;
+                }
+                rows.map(|y| (y, cols.clone())).collect()
+            }
+            AttributeChangeExtent::Stream => {
+                if rows.end - rows.start == 1 && cols.is_empty() {
+                    return;
+                }
+                // The area starts at the top left position and continues
+                // to the bottom right position, wrapping at the edges
+                // of the page
+                let (_, page_cols) = self.rectangular_page();
+                let last = rows.end - 1;
+                rows.clone()
+                    .map(|y| {
+                        let start = if y == rows.start {
+                            cols.start
+                        } else {
+                            page_cols.start
+                        };
+                        let end = if y == last { cols.end } else { page_cols.end };
+                        (y, start..end)
+                    })
+                    .collect()
+            }
+        };
+
+        let seqno = self.seqno;
+        let screen = self.screen_mut();
+        for (y, cols) in spans {
+            for x in cols {
+                let mut cell = screen.get_cell(x, y).cloned().unwrap_or_else(Cell::blank);
+                for sgr in attributes {
+                    if reverse {
+                        reverse_rectangular_sgr(cell.attrs_mut(), sgr);
+                    } else {
+                        apply_rectangular_sgr(cell.attrs_mut(), sgr);
+                    }
+                }
+                screen.set_cell(x, y, &cell, seqno);
+            }
+        }
+    }
+}
+
+/// Applies one of the attributes permitted by DECCARA
+fn apply_rectangular_sgr(attrs: &mut CellAttributes, sgr: &Sgr) {
+    match sgr {
+        Sgr::Reset => {
+            attrs
+                .set_intensity(Intensity::Normal)
+                .set_italic(false)
+                .set_underline(Underline::None)
+                .set_blink(Blink::None)
+                .set_reverse(false)
+                .set_invisible(false)
+                .set_strikethrough(false);
+        }
+        Sgr::Intensity(intensity) => {
+            attrs.set_intensity(*intensity);
+        }
+        Sgr::Italic(italic) => {
+            attrs.set_italic(*italic);
+        }
+        Sgr::Underline(underline) => {
+            attrs.set_underline(*underline);
+        }
+        Sgr::Blink(blink) => {
+            attrs.set_blink(*blink);
+        }
+        Sgr::Inverse(inverse) => {
+            attrs.set_reverse(*inverse);
+        }
+        Sgr::Invisible(invisible) => {
+            attrs.set_invisible(*invisible);
+        }
+        Sgr::StrikeThrough(strike) => {
+            attrs.set_strikethrough(*strike);
+        }
+        _ => {}
+    }
+}
+
+/// Toggles one of the attributes permitted by DECRARA.
+/// Parameters that would turn an attribute off have no effect.
+fn reverse_rectangular_sgr(attrs: &mut CellAttributes, sgr: &Sgr) {
+    match sgr {
+        Sgr::Reset => {
+            for sgr in &[
+                Sgr::Intensity(Intensity::Bold),
+                Sgr::Italic(true),
+                Sgr::Underline(Underline::Single),
+                Sgr::Blink(Blink::Slow),
+                Sgr::Inverse(true),
+                Sgr::Invisible(true),
+                Sgr::StrikeThrough(true),
+            ] {
+                reverse_rectangular_sgr(attrs, sgr);
+            }
+        }
+        Sgr::Intensity(intensity) if *intensity != Intensity::Normal => {
+            attrs.set_intensity(if attrs.intensity() == *intensity {
+                Intensity::Normal
+            } else {
+                *intensity
+            });
+        }
+        Sgr::Italic(true) => {
+            attrs.set_italic(!attrs.italic());
+        }
+        Sgr::Underline(underline) if *underline != Underline::None => {
+            attrs.set_underline(if attrs.underline() == Underline::None {
+                *underline
+            } else {
+                Underline::None
+            });
+        }
+        Sgr::Blink(blink) if *blink != Blink::None => {
+            attrs.set_blink(if attrs.blink() == Blink::None {
+                *blink
+            } else {
+                Blink::None
+            });
+        }
+        Sgr::Inverse(true) => {
+            attrs.set_reverse(!attrs.reverse());
+        }
+        Sgr::Invisible(true) => {
+            attrs.set_invisible(!attrs.invisible());
+        }
+        Sgr::StrikeThrough(true) => {
+            attrs.set_strikethrough(!attrs.strikethrough());
+        }
+        _ => {}
+    }
+}
@@ -1,4 +1,5 @@
 use super::*;
+use pretty_assertions::assert_eq;
 use termwiz::color::AnsiColor;
 
 /// In this issue, the `CSI 2 P` sequence incorrectly removed two
@@ -222,3 +223,381 @@ fn test_ed_erase_scrollback() {
     term.print("b");
     assert_all_contents(&term, file!(), line!(), &["111", "222", "ab "]);
 }
+
+/// Sends a DECRQSS request for `setting` and returns the
+/// body of the DECRPSS reply
+fn decrqss(term: &mut TestTerm, setting: &str) -> String {
+    term.print(format!("\x1bP$q{}\x1b\\", setting));
+    let reply = term.reply();
+    reply
+        .strip_prefix("\x1bP")
+        .and_then(|r| r.strip_suffix("\x1b\\"))
+        .unwrap_or_else(|| panic!("{:?} is not a DCS reply", reply))
+        .to_string()
+}
+
+#[test]
+fn test_decrqss_sgr() {
+    let mut term = TestTerm::new(3, 4, 0);
+    assert_eq!(decrqss(&mut term, "m"), "1$r0m");
+
+    term.print("\x1b[1;3;4;7;31;100m");
+    assert_eq!(decrqss(&mut term, "m"), "1$r0;1;3;4;7;31;100m");
+
+    term.print("\x1b[0;2;4:3;9;53;38:5:200;58:5:17m");
+    term.print("\x1b[48:2::1:2:3m");
+    assert_eq!(
+        decrqss(&mut term, "m"),
+        "1$r0;2;4:3;9;53;38:5:200;48:2::1:2:3;58:5:17m"
+    );
+
+    term.print("\x1b[m");
+    assert_eq!(decrqss(&mut term, "m"), "1$r0m");
+}
+
+#[test]
+fn test_decrqss_cursor_style() {
+    let mut term = TestTerm::new(3, 4, 0);
+    assert_eq!(decrqss(&mut term, " q"), "1$r0 q");
+    term.print("\x1b[4 q");
+    assert_eq!(decrqss(&mut term, " q"), "1$r4 q");
+    term.print("\x1b[5 q");
+    assert_eq!(decrqss(&mut term, " q"), "1$r5 q");
+}
+
+#[test]
+fn test_decrqss_margins_and_page() {
+    let mut term = TestTerm::new(5, 10, 0);
+    assert_eq!(decrqss(&mut term, "r"), "1$r1;5r");
+    term.set_scroll_region(1, 3);
+    assert_eq!(decrqss(&mut term, "r"), "1$r2;4r");
+
+    assert_eq!(decrqss(&mut term, "s"), "1$r1;10s");
+    assert_eq!(decrqss(&mut term, "t"), "1$r5t");
+    assert_eq!(decrqss(&mut term, "\"q"), "1$r0\"q");
+    assert_eq!(decrqss(&mut term, "*x"), "1$r1*x");
+    assert_eq!(decrqss(&mut term, "\"p"), "1$r65;1\"p");
+}
+
+#[test]
+fn test_decrqss_invalid() {
+    let mut term = TestTerm::new(3, 4, 0);
+    assert_eq!(decrqss(&mut term, "%x"), "0$r");
+}
+
+#[test]
+fn test_decfra() {
+    let mut term = TestTerm::new(4, 5, 0);
+    term.print("\x1b[88;2;2;3;4$x");
+    assert_visible_contents(&term, file!(), line!(), &["     ", " XXX", " XXX", "     "]);
+
+    // Omitted bottom and right edges extend to the edges of the screen
+    term.print("\x1b[46;3;4$x");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["     ", " XXX", " XX..", "   .."],
+    );
+
+    // Control characters are ignored
+    term.print("\x1b[10$x");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["     ", " XXX", " XX..", "   .."],
+    );
+}
+
+#[test]
+fn test_decera_decsera() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("abcd\r\n\x1b[1mefgh\x1b[m\r\nijkl");
+    term.print("\x1b[1;2;3;2$z");
+    assert_visible_contents(&term, file!(), line!(), &["a cd", "e gh", "i kl"]);
+
+    term.print("\x1b[2;3;2;4${");
+    assert_visible_contents(&term, file!(), line!(), &["a cd", "e   ", "i kl"]);
+
+    // Selective erase leaves the attributes alone
+    let bold = CellAttributes::default()
+        .set_intensity(Intensity::Bold)
+        .clone();
+    let cell = term.screen().get_cell(3, 1).unwrap().clone();
+    assert_eq!(cell.str(), " ");
+    assert_eq!(cell.attrs(), &bold);
+}
+
+#[test]
+fn test_deccra() {
+    let mut term = TestTerm::new(4, 4, 0);
+    term.print("abcd\r\nefgh\r\nijkl\r\nmnop");
+    // Copy the top left 2x2 block so that it overlaps itself
+    term.print("\x1b[1;1;2;2;1;2;2;1$v");
+    assert_visible_contents(&term, file!(), line!(), &["abcd", "eabh", "iefl", "mnop"]);
+
+    // The copy is clipped at the edge of the screen
+    term.print("\x1b[1;1;2;2;1;4;4;1$v");
+    assert_visible_contents(&term, file!(), line!(), &["abcd", "eabh", "iefl", "mnoa"]);
+}
+
+#[test]
+fn test_rectangular_origin_mode() {
+    let mut term = TestTerm::new(4, 6, 0);
+    term.set_mode("?69", true);
+    term.set_left_and_right_margins(1, 3);
+    term.set_scroll_region(1, 2);
+    term.set_mode("?6", true);
+
+    // Coordinates are relative to, and clipped by, the margins
+    term.print("\x1b[42;1;2;5;9$x");
+    assert_visible_contents(
+        &term,
+        file!(),
+        line!(),
+        &["      ", "  **", "  **", "      "],
+    );
+}
+
+#[test]
+fn test_deccara() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("abcd\r\nefgh\r\nijkl");
+
+    let bold = CellAttributes::default()
+        .set_intensity(Intensity::Bold)
+        .clone();
+    let is_bold =
+        |term: &TestTerm, x: usize, y: i64| term.screen().get_cell(x, y).unwrap().attrs() == &bold;
+
+    // The default stream extent wraps at the edges of the screen
+    term.print("\x1b[1;3;2;2;1$r");
+    assert_eq!(
+        (0..3)
+            .map(|y| (0..4).map(|x| is_bold(&term, x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        vec![
+            vec![false, false, true, true],
+            vec![true, true, false, false],
+            vec![false, false, false, false],
+        ]
+    );
+
+    term.print("\x1b[1;1;3;4;0$r");
+    term.print("\x1b[2*x");
+    term.print("\x1b[1;3;2;2;1$r");
+    assert_eq!(
+        (0..3)
+            .map(|y| (0..4).map(|x| is_bold(&term, x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        vec![
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+            vec![false, false, false, false],
+        ]
+    );
+
+    term.print("\x1b[1;2;2;3;1$r");
+    assert_eq!(
+        (0..3)
+            .map(|y| (0..4).map(|x| is_bold(&term, x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        vec![
+            vec![false, true, true, false],
+            vec![false, true, true, false],
+            vec![false, false, false, false],
+        ]
+    );
+
+    // DECRARA toggles the attribute
+    term.print("\x1b[1;1;1;4;1$t");
+    assert_eq!(
+        (0..3)
+            .map(|y| (0..4).map(|x| is_bold(&term, x, y)).collect::<Vec<_>>())
+            .collect::<Vec<_>>(),
+        vec![
+            vec![true, false, false, true],
+            vec![false, true, true, false],
+            vec![false, false, false, false],
+        ]
+    );
+
+    assert_visible_contents(&term, file!(), line!(), &["abcd", "efgh", "ijkl"]);
+}
+
+#[test]
+fn test_synchronized_output() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("\x1b[?2026$p");
+    assert_eq!(term.reply(), "\x1b[?2026;2$y");
+
+    term.print("a");
+    term.set_mode("?2026", true);
+    assert!(term.is_synchronized_output_active());
+    let published = term.current_seqno();
+    term.print("\x1b[?2026$p");
+    assert_eq!(term.reply(), "\x1b[?2026;1$y");
+
+    // Output during the update is applied to the model, but not published
+    term.print("bcd");
+    assert_eq!(term.current_seqno(), published);
+    assert_visible_contents(&term, file!(), line!(), &["abcd", "    ", "    "]);
+
+    term.set_mode("?2026", false);
+    assert!(!term.is_synchronized_output_active());
+    assert!(term.current_seqno() > published);
+    term.print("\x1b[?2026$p");
+    assert_eq!(term.reply(), "\x1b[?2026;2$y");
+
+    // DECSTR ends the update
+    term.set_mode("?2026", true);
+    term.print("\x1b[!p");
+    assert!(!term.is_synchronized_output_active());
+}
+
+#[test]
+fn test_synchronized_output_snapshot() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("ab\r\ncd");
+    term.set_mode("?2026", true);
+    term.print("\x1b[2J\x1b[Hxy");
+
+    // The snapshot is the screen from before the update
+    let snapshot = term.synchronized_output_snapshot().unwrap();
+    assert_eq!(snapshot.top, 0);
+    assert_eq!(
+        snapshot
+            .lines
+            .iter()
+            .map(|line| line.as_str())
+            .collect::<Vec<_>>(),
+        vec!["ab  ", "cd  ", "    "]
+    );
+    assert_eq!((snapshot.cursor.x, snapshot.cursor.y), (2, 1));
+    assert_visible_contents(&term, file!(), line!(), &["xy  ", "    ", "    "]);
+
+    term.set_mode("?2026", false);
+    assert!(term.synchronized_output_snapshot().is_none());
+
+    // A resize publishes the update, as the snapshot no longer
+    // matches the screen
+    term.set_mode("?2026", true);
+    assert!(term.is_synchronized_output_active());
+    term.resize(4, 4, 4 * 8, 4 * 16);
+    assert!(!term.is_synchronized_output_active());
+}
+
+#[test]
+fn test_synchronized_output_timeout() {
+    let timeout = Duration::from_millis(100);
+    let mut term = TestTerm::with_config(
+        3,
+        4,
+        TestTermConfig {
+            scrollback: 0,
+            hot_lines: 0,
+            synchronized_output_timeout: timeout,
+        },
+    );
+    term.set_mode("?2026", true);
+    let published = term.current_seqno();
+    let deadline = term.synchronized_output_deadline().unwrap();
+    term.print("abc");
+    assert_eq!(term.current_seqno(), published);
+
+    // Enabling the mode again doesn't extend the update
+    term.set_mode("?2026", true);
+    assert_eq!(term.synchronized_output_deadline(), Some(deadline));
+
+    std::thread::sleep(deadline.saturating_duration_since(std::time::Instant::now()));
+
+    // The held changes are published once the update times out
+    assert!(!term.is_synchronized_output_active());
+    assert!(term.synchronized_output_deadline().is_none());
+    assert!(term.synchronized_output_snapshot().is_none());
+    assert!(term.current_seqno() > published);
+    term.print("\x1b[?2026$p");
+    assert_eq!(term.reply(), "\x1b[?2026;2$y");
+
+    // and the next update starts afresh
+    term.set_mode("?2026", true);
+    assert!(term.is_synchronized_output_active());
+    assert!(term.synchronized_output_deadline().unwrap() > deadline);
+}
+
+/// Queries the kitty keyboard protocol flags and returns the reply
+fn kitty_keyboard_flags(term: &mut TestTerm) -> String {
+    term.print("\x1b[?u");
+    term.reply()
+}
+
+#[test]
+fn test_kitty_keyboard_stack() {
+    let mut term = TestTerm::new(3, 4, 0);
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?0u");
+
+    term.print("\x1b[>1u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?1u");
+    term.print("\x1b[>3u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?3u");
+
+    // Set, clear and assign the flags of the top of the stack
+    term.print("\x1b[=4;2u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?7u");
+    term.print("\x1b[=2;3u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?5u");
+    term.print("\x1b[=8u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?8u");
+
+    term.print("\x1b[<u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?1u");
+    // Popping more entries than the stack holds empties it
+    term.print("\x1b[<5u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?0u");
+
+    // When the stack is full, the oldest entry is discarded
+    term.print("\x1b[>1u");
+    for _ in 0..16 {
+        term.print("\x1b[>2u");
+    }
+    term.print("\x1b[<15u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?2u");
+    term.print("\x1b[<u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?0u");
+
+    // RIS clears the stack
+    term.print("\x1b[>1u");
+    term.print("\x1bc");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?0u");
+}
+
+#[test]
+fn test_kitty_keyboard_alt_screen() {
+    let mut term = TestTerm::new(3, 4, 0);
+    term.print("\x1b[>1u");
+
+    // The alternate screen has a stack of its own
+    term.set_mode("?1049", true);
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?0u");
+    term.key_down(KeyCode::Escape, KeyModifiers::NONE).unwrap();
+    assert_eq!(term.reply(), "\x1b");
+
+    term.print("\x1b[>4u");
+    term.print("\x1b[>8u");
+    term.print("\x1b[<u");
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?4u");
+
+    // Leaving it restores the flags of the primary screen
+    term.set_mode("?1049", false);
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?1u");
+    term.key_down(KeyCode::Escape, KeyModifiers::NONE).unwrap();
+    assert_eq!(term.reply(), "\x1b[27u");
+
+    // and the alternate screen keeps its stack while it is inactive
+    term.print("\x1b[<u");
+    term.set_mode("?1049", true);
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?4u");
+    term.set_mode("?1049", false);
+    assert_eq!(kitty_keyboard_flags(&mut term), "\x1b[?0u");
+}
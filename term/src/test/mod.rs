@@ -10,7 +10,9 @@ mod csi;
 use crate::color::ColorPalette;
 use pretty_assertions::assert_eq;
 use std::cell::RefCell;
+use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
+use std::time::Duration;
 use termwiz::escape::csi::{Edit, EraseInDisplay, EraseInLine};
 use termwiz::escape::{OneBased, OperatingSystemCommand, CSI};
 use termwiz::surface::{CursorShape, CursorVisibility, SequenceNo, SEQ_ZERO};
@@ -39,31 +41,78 @@ impl Clipboard for LocalClip {
     }
 }
 
+/// Sends each chunk of data that the terminal writes back to
+/// the host over a channel, so that tests can examine replies.
+struct LocalWriter {
+    sender: Sender<Vec<u8>>,
+}
+
+impl std::io::Write for LocalWriter {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.sender.send(buf.to_vec()).ok();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
 struct TestTerm {
     term: Terminal,
+    replies: Receiver<Vec<u8>>,
 }
 
 #[derive(Debug)]
 struct TestTermConfig {
     scrollback: usize,
+    hot_lines: usize,
+    synchronized_output_timeout: Duration,
 }
 impl TerminalConfiguration for TestTermConfig {
     fn scrollback_size(&self) -> usize {
         self.scrollback
     }
 
+    fn scrollback_hot_lines(&self) -> usize {
+        self.hot_lines
+    }
+
     fn color_palette(&self) -> ColorPalette {
         ColorPalette::default()
     }
+
+    fn synchronized_output_timeout(&self) -> Duration {
+        self.synchronized_output_timeout
+    }
 }
 
 impl TestTerm {
     fn new(height: usize, width: usize, scrollback: usize) -> Self {
+        Self::with_hot_lines(height, width, scrollback, 10_000)
+    }
+
+    /// Creates a terminal that compacts scrollback lines
+    /// beyond the most recent `hot_lines` lines
+    fn with_hot_lines(height: usize, width: usize, scrollback: usize, hot_lines: usize) -> Self {
+        Self::with_config(
+            height,
+            width,
+            TestTermConfig {
+                scrollback,
+                hot_lines,
+                synchronized_output_timeout: Duration::from_secs(1),
+            },
+        )
+    }
+
+    fn with_config(height: usize, width: usize, config: TestTermConfig) -> Self {
         let _ = env_logger::Builder::new()
             .is_test(true)
             .filter_level(log::LevelFilter::Trace)
             .try_init();
 
+        let (sender, replies) = channel();
         let mut term = Terminal::new(
             TerminalSize {
                 physical_rows: height,
@@ -71,15 +120,15 @@ impl TestTerm {
                 pixel_width: width * 8,
                 pixel_height: height * 16,
             },
-            Arc::new(TestTermConfig { scrollback }),
+            Arc::new(config),
             "WezTerm",
             "O_o",
-            Box::new(Vec::new()),
+            Box::new(LocalWriter { sender }),
         );
         let clip: Arc<dyn Clipboard> = Arc::new(LocalClip::new());
         term.set_clipboard(&clip);
 
-        let mut term = Self { term };
+        let mut term = Self { term, replies };
 
         term.set_auto_wrap(true);
 
@@ -90,6 +139,17 @@ impl TestTerm {
         self.term.advance_bytes(bytes);
     }
 
+    /// Returns the next reply that the terminal sent to the host.
+    /// Replies are written from a separate thread, so this waits
+    /// a little while for one to arrive.
+    fn reply(&self) -> String {
+        let data = self
+            .replies
+            .recv_timeout(Duration::from_secs(5))
+            .expect("terminal to send a reply");
+        String::from_utf8(data).expect("reply to be utf8")
+    }
+
     fn set_mode(&mut self, mode: &str, enable: bool) {
         self.print(CSI);
         self.print(mode);
@@ -507,6 +567,162 @@ fn test_semantic() {
     );
 }
 
+#[test]
+fn test_command_log() {
+    let mut term = TestTerm::new(5, 20, 10);
+    term.print("\x1b]7;file://host/tmp\x1b\\");
+    term.print("\x1b]133;A\x1b\\$ \x1b]133;B\x1b\\false\r\n\x1b]133;C\x1b\\");
+    term.print("\x1b]133;D;1\x1b\\\x1b]133;A\x1b\\$ \x1b]133;B\x1b\\echo hi\r\n");
+    term.print("\x1b]133;C\x1b\\hi\r\n\x1b]133;D;0\x1b\\");
+    term.print("\x1b]133;A\x1b\\$ \x1b]133;B\x1b\\");
+
+    let commands = term.get_commands();
+    assert_eq!(commands.len(), 3);
+
+    assert_eq!(commands[0].command, "false");
+    assert_eq!(commands[0].exit_status, Some(1));
+    assert_eq!(commands[0].cwd.as_deref(), Some("file://host/tmp"));
+    assert_eq!(
+        (
+            commands[0].prompt_y,
+            commands[0].output_y,
+            commands[0].end_y
+        ),
+        (0, Some(1), Some(1))
+    );
+    assert!(commands[0].duration.is_some());
+
+    assert_eq!(commands[1].command, "echo hi");
+    assert_eq!(commands[1].exit_status, Some(0));
+    assert_eq!(
+        (
+            commands[1].prompt_y,
+            commands[1].output_y,
+            commands[1].end_y
+        ),
+        (1, Some(2), Some(3))
+    );
+
+    // The current prompt has not yet run a command
+    assert_eq!(commands[2].prompt_y, 3);
+    assert_eq!(commands[2].output_y, None);
+    assert_eq!(commands[2].start_time, None);
+
+    // Redrawing the prompt replaces it rather than adding another
+    term.print("\r\n\x1b]133;A\x1b\\$ ");
+    let commands = term.get_commands();
+    assert_eq!(commands.len(), 3);
+    assert_eq!(commands[2].prompt_y, 4);
+}
+
+/// Collects the alerts raised by the terminal
+struct LocalAlerts {
+    sender: Sender<Alert>,
+}
+
+impl AlertHandler for LocalAlerts {
+    fn alert(&mut self, alert: Alert) {
+        self.sender.send(alert).ok();
+    }
+}
+
+#[test]
+fn test_kitty_notifications() {
+    use termwiz::escape::osc::{KittyNotification, NotificationUrgency};
+
+    let mut term = TestTerm::new(5, 20, 10);
+    let (sender, alerts) = channel();
+    term.set_notification_handler(Box::new(LocalAlerts { sender }));
+
+    let toast = |title: Option<&str>, body: &str, focus, urgency, report_id: Option<&str>| {
+        Alert::ToastNotification {
+            title: title.map(|s| s.to_string()),
+            body: body.to_string(),
+            focus,
+            urgency,
+            report_id: report_id.map(|s| s.to_string()),
+        }
+    };
+
+    // Chunks with the same id are accumulated until one is done,
+    // even when they are interleaved with those of another id
+    term.print("\x1b]99;i=1:d=0;Hello \x1b\\");
+    term.print("\x1b]99;i=2:d=0;Other\x1b\\");
+    term.print("\x1b]99;i=1:d=0;world\x1b\\");
+    term.print("\x1b]99;i=1:d=0:p=body;first \x1b\\");
+    assert!(alerts.try_recv().is_err());
+    term.print("\x1b]99;i=1:p=body:e=1;bGluZQ==\x1b\\");
+    assert_eq!(
+        alerts.try_recv().unwrap(),
+        toast(
+            Some("Hello world"),
+            "first line",
+            true,
+            NotificationUrgency::Normal,
+            None
+        )
+    );
+    term.print("\x1b]99;i=2:u=0:a=-focus;\x1b\\");
+    assert_eq!(
+        alerts.try_recv().unwrap(),
+        toast(Some("Other"), "", false, NotificationUrgency::Low, None)
+    );
+
+    // o=unfocused and o=invisible are only shown when unfocused
+    term.print("\x1b]99;o=unfocused;not shown\x1b\\");
+    term.print("\x1b]99;o=invisible;not shown\x1b\\");
+    assert!(alerts.try_recv().is_err());
+    term.focus_changed(false);
+    term.print("\x1b]99;o=unfocused;shown\x1b\\");
+    assert_eq!(
+        alerts.try_recv().unwrap(),
+        toast(Some("shown"), "", true, NotificationUrgency::Normal, None)
+    );
+    term.focus_changed(true);
+    // Discard OutputSinceFocusLost
+    while alerts.try_recv().is_ok() {}
+
+    // a=report carries the id, defaulting to 0, so that activating
+    // the notification can report back to the application
+    term.print("\x1b]99;i=abc:a=report:u=2;report me\x1b\\");
+    assert_eq!(
+        alerts.try_recv().unwrap(),
+        toast(
+            Some("report me"),
+            "",
+            true,
+            NotificationUrgency::Critical,
+            Some("abc")
+        )
+    );
+    term.print("\x1b]99;a=report;no id\x1b\\");
+    let report_id = match alerts.try_recv().unwrap() {
+        Alert::ToastNotification { report_id, .. } => report_id,
+        alert => panic!("unexpected {:?}", alert),
+    };
+    assert_eq!(report_id.as_deref(), Some("0"));
+    let activated = OperatingSystemCommand::KittyNotification(KittyNotification {
+        id: report_id,
+        ..Default::default()
+    });
+    assert_eq!(activated.to_string(), "\x1b]99;i=0;\x1b\\");
+
+    // Queries are answered with the supported features
+    term.print("\x1b]99;i=q:p=?;\x1b\\");
+    assert_eq!(
+        term.reply(),
+        "\x1b]99;i=q:p=?;a=focus,report:o=always,unfocused,invisible:u=0,1,2:p=title,body,?\x1b\\"
+    );
+    assert!(alerts.try_recv().is_err());
+
+    // OSC 9 and OSC 777 notifications are persistent
+    term.print("\x1b]9;ding\x1b\\");
+    assert_eq!(
+        alerts.try_recv().unwrap(),
+        toast(None, "ding", true, NotificationUrgency::Critical, None)
+    );
+}
+
 #[test]
 fn issue_1161() {
     let mut term = TestTerm::new(1, 5, 0);
@@ -1018,6 +1234,43 @@ fn test_scrollup() {
     assert_eq!(term.screen().visible_row_to_stable_row(0), 7);
 }
 
+#[test]
+fn test_compacted_scrollback() {
+    let mut term = TestTerm::with_hot_lines(4, 6, 1000, 4);
+    for n in 0..1200 {
+        term.print(format!("{}\r\n", n));
+    }
+
+    // The oldest lines were discarded, and the rest are compacted
+    // but remain addressable by their stable row index
+    let screen = term.screen();
+    assert_eq!(screen.scrollback_rows(), 1004);
+    assert_eq!(screen.visible_row_to_stable_row(0), 1197);
+    let phys = screen.stable_range(&(300..302));
+    let lines = screen.lines_in_phys_range(phys);
+    assert_eq!(lines[0].as_str().trim_end(), "300");
+    assert_eq!(lines[1].as_str().trim_end(), "301");
+
+    let mut found = vec![];
+    screen.for_each_phys_line(|idx, line| {
+        if line.as_str().trim_end() == "555" {
+            found.push(screen.phys_to_stable_row_index(idx));
+        }
+    });
+    assert_eq!(found, vec![555]);
+
+    // Resizing narrower rewraps the compacted lines too
+    term.resize(4, 2, 0, 0);
+    let screen = term.screen();
+    let lines = screen.lines_in_phys_range(0..screen.scrollback_rows());
+    let text: Vec<String> = lines
+        .iter()
+        .take(4)
+        .map(|line| line.as_str().trim_end().to_string())
+        .collect();
+    assert_eq!(text, vec!["19", "7", "19", "8"]);
+}
+
 #[test]
 fn test_ri() {
     let mut term = TestTerm::new(3, 1, 10);
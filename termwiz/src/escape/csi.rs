@@ -1,7 +1,7 @@
 use super::OneBased;
 use crate::cell::{Blink, Intensity, Underline};
 use crate::color::{AnsiColor, ColorSpec, RgbColor};
-use crate::input::{Modifiers, MouseButtons};
+use crate::input::{KittyKeyboardFlags, Modifiers, MouseButtons};
 use num_derive::*;
 use num_traits::{FromPrimitive, ToPrimitive};
 use std::fmt::{Display, Error as FmtError, Formatter};
@@ -27,6 +27,8 @@ pub enum CSI {
 
     Window(Window),
 
+    Keyboard(Keyboard),
+
     /// ECMA-48 SCP
     SelectCharacterPath(CharacterPath, i64),
 
@@ -82,6 +84,7 @@ impl Display for CSI {
             CSI::Mouse(mouse) => mouse.fmt(f)?,
             CSI::Device(dev) => dev.fmt(f)?,
             CSI::Window(window) => window.fmt(f)?,
+            CSI::Keyboard(k) => k.fmt(f)?,
             CSI::SelectCharacterPath(path, n) => {
                 let a = match path {
                     CharacterPath::ImplementationDefault => 0,
@@ -630,6 +633,53 @@ impl XtermKeyModifierResource {
     }
 }
 
+/// How the flags in a `Keyboard::SetKittyState` request are applied
+/// to the current kitty keyboard protocol state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyKeyboardMode {
+    AssignAll,
+    SetSpecified,
+    ClearSpecified,
+}
+
+/// Sequences from the kitty keyboard protocol.
+/// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/#progressive-enhancement>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Keyboard {
+    /// `CSI = flags ; mode u`: update the flags in the current entry
+    SetKittyState {
+        flags: KittyKeyboardFlags,
+        mode: KittyKeyboardMode,
+    },
+    /// `CSI > flags u`: push flags onto the stack
+    PushKittyState(KittyKeyboardFlags),
+    /// `CSI < number u`: pop `number` entries from the stack
+    PopKittyState(u32),
+    /// `CSI ? u`: ask the terminal for the current flags
+    QueryKittySupport,
+    /// `CSI ? flags u`: the response to `QueryKittySupport`
+    ReportKittyState(KittyKeyboardFlags),
+}
+
+impl Display for Keyboard {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        match self {
+            Self::SetKittyState { flags, mode } => {
+                let mode = match mode {
+                    KittyKeyboardMode::AssignAll => 1,
+                    KittyKeyboardMode::SetSpecified => 2,
+                    KittyKeyboardMode::ClearSpecified => 3,
+                };
+                write!(f, "={};{}u", flags.bits(), mode)
+            }
+            Self::PushKittyState(flags) => write!(f, ">{}u", flags.bits()),
+            Self::PopKittyState(n) => write!(f, "<{}u", n),
+            Self::QueryKittySupport => write!(f, "?u"),
+            Self::ReportKittyState(flags) => write!(f, "?{}u", flags.bits()),
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Mode {
     SetDecPrivateMode(DecPrivateMode),
@@ -1053,6 +1103,175 @@ pub enum Edit {
 
     /// REP - Repeat the preceding character n times
     Repeat(u32),
+
+    /// DECCRA - Copy Rectangular Area
+    /// Copies the text and attributes in `src` on page `src_page`
+    /// so that its top left corner is at `top`, `left` on `dest_page`.
+    /// <https://vt100.net/docs/vt510-rm/DECCRA.html>
+    CopyRectangularArea {
+        src: RectangularArea,
+        src_page: OneBased,
+        top: OneBased,
+        left: OneBased,
+        dest_page: OneBased,
+    },
+
+    /// DECFRA - Fill Rectangular Area
+    /// Fills `area` with `ch`, using the current graphic rendition.
+    /// <https://vt100.net/docs/vt510-rm/DECFRA.html>
+    FillRectangularArea { ch: char, area: RectangularArea },
+
+    /// DECERA - Erase Rectangular Area
+    /// <https://vt100.net/docs/vt510-rm/DECERA.html>
+    EraseRectangularArea(RectangularArea),
+
+    /// DECSERA - Selective Erase Rectangular Area
+    /// Erases the characters in `area` that are not protected,
+    /// without changing their attributes.
+    /// <https://vt100.net/docs/vt510-rm/DECSERA.html>
+    SelectiveEraseRectangularArea(RectangularArea),
+
+    /// DECCARA - Change Attributes in Rectangular Area.
+    /// Applies `attributes` to the cells in `area`; the extent
+    /// of the area is subject to DECSACE.
+    /// Only the subset of SGR that DEC (and xterm) allow here is
+    /// accepted by the parser.
+    /// <https://vt100.net/docs/vt510-rm/DECCARA.html>
+    ChangeAttributesInRectangularArea {
+        area: RectangularArea,
+        attributes: Vec<Sgr>,
+    },
+
+    /// DECRARA - Reverse Attributes in Rectangular Area.
+    /// Toggles each of the attributes that would be turned on by
+    /// the entries in `attributes`; `Sgr::Reset` toggles all of them.
+    /// The extent of the area is subject to DECSACE.
+    /// <https://vt100.net/docs/vt510-rm/DECRARA.html>
+    ReverseAttributesInRectangularArea {
+        area: RectangularArea,
+        attributes: Vec<Sgr>,
+    },
+
+    /// DECSACE - Select Attribute Change Extent
+    /// <https://vt100.net/docs/vt510-rm/DECSACE.html>
+    SelectAttributeChangeExtent(AttributeChangeExtent),
+}
+
+/// The area affected by the VT420 rectangular area operations.
+/// The `bottom` and `right` edges are inclusive.  When they are
+/// omitted from the sequence they default to `u32::MAX`, which
+/// the terminal clamps to the bottom and right of the page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RectangularArea {
+    pub top: OneBased,
+    pub left: OneBased,
+    pub bottom: OneBased,
+    pub right: OneBased,
+}
+
+impl Default for RectangularArea {
+    fn default() -> Self {
+        Self {
+            top: OneBased::new(1),
+            left: OneBased::new(1),
+            bottom: OneBased::new(u32::MAX),
+            right: OneBased::new(u32::MAX),
+        }
+    }
+}
+
+impl RectangularArea {
+    /// Parses the four parameters that describe an area, starting
+    /// at `idx`
+    fn parse(params: &Cracked, idx: usize) -> Result<Self, ()> {
+        fn edge(param: Option<&CsiParam>) -> Result<OneBased, ()> {
+            match param {
+                None | Some(CsiParam::Integer(0)) => Ok(OneBased::new(u32::MAX)),
+                Some(p) => OneBased::from_esc_param(p),
+            }
+        }
+        Ok(Self {
+            top: OneBased::from_optional_esc_param(params.get(idx))?,
+            left: OneBased::from_optional_esc_param(params.get(idx + 1))?,
+            bottom: edge(params.get(idx + 2))?,
+            right: edge(params.get(idx + 3))?,
+        })
+    }
+}
+
+impl Display for RectangularArea {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), FmtError> {
+        fn edge(value: OneBased) -> String {
+            if value.as_one_based() == u32::MAX {
+                String::new()
+            } else {
+                value.to_string()
+            }
+        }
+        write!(
+            f,
+            "{};{};{};{}",
+            self.top,
+            self.left,
+            edge(self.bottom),
+            edge(self.right)
+        )
+    }
+}
+
+/// Whether DECCARA and DECRARA apply to the rectangle described
+/// by their parameters, or to the stream of character positions
+/// from the top left to the bottom right, wrapping at the margins.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeChangeExtent {
+    Stream,
+    Rectangle,
+}
+
+impl Default for AttributeChangeExtent {
+    fn default() -> Self {
+        Self::Stream
+    }
+}
+
+/// The SGR codes that may be used with DECCARA and DECRARA
+fn rectangular_area_sgr_codes() -> [(i64, Sgr); 16] {
+    [
+        (0, Sgr::Reset),
+        (1, Sgr::Intensity(Intensity::Bold)),
+        (2, Sgr::Intensity(Intensity::Half)),
+        (3, Sgr::Italic(true)),
+        (4, Sgr::Underline(Underline::Single)),
+        (5, Sgr::Blink(Blink::Slow)),
+        (7, Sgr::Inverse(true)),
+        (8, Sgr::Invisible(true)),
+        (9, Sgr::StrikeThrough(true)),
+        (22, Sgr::Intensity(Intensity::Normal)),
+        (23, Sgr::Italic(false)),
+        (24, Sgr::Underline(Underline::None)),
+        (25, Sgr::Blink(Blink::None)),
+        (27, Sgr::Inverse(false)),
+        (28, Sgr::Invisible(false)),
+        (29, Sgr::StrikeThrough(false)),
+    ]
+}
+
+fn write_rectangular_area_sgr(
+    f: &mut Formatter,
+    area: &RectangularArea,
+    attributes: &[Sgr],
+    control: &str,
+) -> Result<(), FmtError> {
+    write!(f, "{}", area)?;
+    let codes = rectangular_area_sgr_codes();
+    for sgr in attributes {
+        let code = codes
+            .iter()
+            .find_map(|(code, s)| if s == sgr { Some(*code) } else { None })
+            .ok_or(FmtError)?;
+        write!(f, ";{}", code)?;
+    }
+    write!(f, "{}", control)
 }
 
 trait EncodeCSIParam {
@@ -1103,6 +1322,26 @@ impl Display for Edit {
             Edit::ScrollUp(n) => n.write_csi(f, "S")?,
             Edit::EraseInDisplay(n) => n.write_csi(f, "J")?,
             Edit::Repeat(n) => n.write_csi(f, "b")?,
+            Edit::CopyRectangularArea {
+                src,
+                src_page,
+                top,
+                left,
+                dest_page,
+            } => write!(f, "{};{};{};{};{}$v", src, src_page, top, left, dest_page)?,
+            Edit::FillRectangularArea { ch, area } => write!(f, "{};{}$x", *ch as u32, area)?,
+            Edit::EraseRectangularArea(area) => write!(f, "{}$z", area)?,
+            Edit::SelectiveEraseRectangularArea(area) => write!(f, "{}${{", area)?,
+            Edit::ChangeAttributesInRectangularArea { area, attributes } => {
+                write_rectangular_area_sgr(f, area, attributes, "$r")?
+            }
+            Edit::ReverseAttributesInRectangularArea { area, attributes } => {
+                write_rectangular_area_sgr(f, area, attributes, "$t")?
+            }
+            Edit::SelectAttributeChangeExtent(AttributeChangeExtent::Stream) => write!(f, "1*x")?,
+            Edit::SelectAttributeChangeExtent(AttributeChangeExtent::Rectangle) => {
+                write!(f, "2*x")?
+            }
         }
         Ok(())
     }
@@ -1606,6 +1845,25 @@ impl<'a> CSIParser<'a> {
             ('k', [.., CsiParam::P(b' ')]) => self.select_character_path(params),
             ('q', [.., CsiParam::P(b' ')]) => self.cursor_style(params),
             ('y', [.., CsiParam::P(b'*')]) => self.checksum_area(params),
+            ('x', [.., CsiParam::P(b'*')]) => self.decsace(params),
+            ('v', [.., CsiParam::P(b'$')]) => self.deccra(params),
+            ('x', [.., CsiParam::P(b'$')]) => self.decfra(params),
+            ('z', [.., CsiParam::P(b'$')]) => self
+                .rectangular_area(params)
+                .map(|area| CSI::Edit(Edit::EraseRectangularArea(area))),
+            ('{', [.., CsiParam::P(b'$')]) => self
+                .rectangular_area(params)
+                .map(|area| CSI::Edit(Edit::SelectiveEraseRectangularArea(area))),
+            ('r', [.., CsiParam::P(b'$')]) => {
+                self.rectangular_area_sgr(params).map(|(area, attributes)| {
+                    CSI::Edit(Edit::ChangeAttributesInRectangularArea { area, attributes })
+                })
+            }
+            ('t', [.., CsiParam::P(b'$')]) => {
+                self.rectangular_area_sgr(params).map(|(area, attributes)| {
+                    CSI::Edit(Edit::ReverseAttributesInRectangularArea { area, attributes })
+                })
+            }
 
             ('c', [CsiParam::P(b'='), ..]) => self
                 .req_tertiary_device_attributes(params)
@@ -1643,6 +1901,10 @@ impl<'a> CSIParser<'a> {
                 .dec(self.focus(params, 1, 0))
                 .map(|mode| CSI::Mode(Mode::SaveDecPrivateMode(mode))),
             ('m', [CsiParam::P(b'>'), ..]) => self.xterm_key_modifier(params),
+            ('u', [CsiParam::P(b'='), ..])
+            | ('u', [CsiParam::P(b'>'), ..])
+            | ('u', [CsiParam::P(b'<'), ..])
+            | ('u', [CsiParam::P(b'?'), ..]) => self.kitty_keyboard(params).map(CSI::Keyboard),
 
             ('p', [CsiParam::P(b'!')]) => Ok(CSI::Device(Box::new(Device::SoftReset))),
 
@@ -1789,6 +2051,64 @@ impl<'a> CSIParser<'a> {
         }))
     }
 
+    fn decsace(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        let extent = match params.opt_int(0).unwrap_or(0) {
+            0 | 1 => AttributeChangeExtent::Stream,
+            2 => AttributeChangeExtent::Rectangle,
+            _ => return Err(()),
+        };
+        Ok(CSI::Edit(Edit::SelectAttributeChangeExtent(extent)))
+    }
+
+    fn deccra(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        Ok(CSI::Edit(Edit::CopyRectangularArea {
+            src: RectangularArea::parse(&params, 0)?,
+            src_page: OneBased::from_optional_esc_param(params.get(4))?,
+            top: OneBased::from_optional_esc_param(params.get(5))?,
+            left: OneBased::from_optional_esc_param(params.get(6))?,
+            dest_page: OneBased::from_optional_esc_param(params.get(7))?,
+        }))
+    }
+
+    fn decfra(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        let ch = params.int(0)?;
+        let ch = ch.to_u32().and_then(char::from_u32).ok_or(())?;
+        Ok(CSI::Edit(Edit::FillRectangularArea {
+            ch,
+            area: RectangularArea::parse(&params, 1)?,
+        }))
+    }
+
+    fn rectangular_area(&mut self, params: &'a [CsiParam]) -> Result<RectangularArea, ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        if params.len() > 4 {
+            return Err(());
+        }
+        RectangularArea::parse(&params, 0)
+    }
+
+    fn rectangular_area_sgr(
+        &mut self,
+        params: &'a [CsiParam],
+    ) -> Result<(RectangularArea, Vec<Sgr>), ()> {
+        let params = Cracked::parse(&params[..params.len() - 1])?;
+        let area = RectangularArea::parse(&params, 0)?;
+        let codes = rectangular_area_sgr_codes();
+        let mut attributes = vec![];
+        for idx in 4..params.len().max(5) {
+            let code = params.opt_int(idx).unwrap_or(0);
+            let sgr = codes
+                .iter()
+                .find_map(|(c, sgr)| if *c == code { Some(sgr.clone()) } else { None })
+                .ok_or(())?;
+            attributes.push(sgr);
+        }
+        Ok((area, attributes))
+    }
+
     fn dsr(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
         match params {
             [CsiParam::Integer(5)] => {
@@ -1866,6 +2186,40 @@ impl<'a> CSIParser<'a> {
         }
     }
 
+    fn kitty_keyboard(&mut self, params: &'a [CsiParam]) -> Result<Keyboard, ()> {
+        fn flags(p: i64) -> Result<KittyKeyboardFlags, ()> {
+            if p < 0 || p > i64::from(u16::MAX) {
+                return Err(());
+            }
+            Ok(KittyKeyboardFlags::from_bits_truncate(p as u16))
+        }
+
+        match params {
+            [CsiParam::P(b'?')] => Ok(Keyboard::QueryKittySupport),
+            [CsiParam::P(b'?'), CsiParam::Integer(p)] => Ok(Keyboard::ReportKittyState(flags(*p)?)),
+            [CsiParam::P(b'>')] => Ok(Keyboard::PushKittyState(KittyKeyboardFlags::NONE)),
+            [CsiParam::P(b'>'), CsiParam::Integer(p)] => Ok(Keyboard::PushKittyState(flags(*p)?)),
+            [CsiParam::P(b'<')] => Ok(Keyboard::PopKittyState(1)),
+            [CsiParam::P(b'<'), p] => Ok(Keyboard::PopKittyState(to_1b_u32(p)?)),
+            [CsiParam::P(b'='), CsiParam::Integer(p)] => Ok(Keyboard::SetKittyState {
+                flags: flags(*p)?,
+                mode: KittyKeyboardMode::AssignAll,
+            }),
+            [CsiParam::P(b'='), CsiParam::Integer(p), CsiParam::P(b';'), CsiParam::Integer(m)] => {
+                Ok(Keyboard::SetKittyState {
+                    flags: flags(*p)?,
+                    mode: match m {
+                        1 => KittyKeyboardMode::AssignAll,
+                        2 => KittyKeyboardMode::SetSpecified,
+                        3 => KittyKeyboardMode::ClearSpecified,
+                        _ => return Err(()),
+                    },
+                })
+            }
+            _ => Err(()),
+        }
+    }
+
     fn decslrm(&mut self, params: &'a [CsiParam]) -> Result<CSI, ()> {
         match params {
             [] => {
@@ -2815,6 +3169,100 @@ mod test {
         assert_eq!(res, vec![CSI::Device(Box::new(Device::SoftReset))],);
     }
 
+    fn parse_bytes(text: &str) -> Vec<CSI> {
+        let mut parser = crate::escape::parser::Parser::new();
+        parser
+            .parse_as_vec(text.as_bytes())
+            .into_iter()
+            .map(|action| match action {
+                crate::escape::Action::CSI(csi) => csi,
+                action => panic!("expected CSI, got {:?}", action),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn rectangular_area() {
+        let area = RectangularArea {
+            top: OneBased::new(2),
+            left: OneBased::new(3),
+            bottom: OneBased::new(4),
+            right: OneBased::new(5),
+        };
+
+        let res = parse_bytes("\x1b[2;3;4;5;1;6;7;1$v");
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::CopyRectangularArea {
+                src: area,
+                src_page: OneBased::new(1),
+                top: OneBased::new(6),
+                left: OneBased::new(7),
+                dest_page: OneBased::new(1),
+            })]
+        );
+        assert_eq!(encode(&res), "\x1b[2;3;4;5;1;6;7;1$v");
+
+        let res = parse_bytes("\x1b[88;2;3;4;5$x");
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::FillRectangularArea { ch: 'X', area })]
+        );
+        assert_eq!(encode(&res), "\x1b[88;2;3;4;5$x");
+
+        let res = parse_bytes("\x1b[$z");
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::EraseRectangularArea(
+                RectangularArea::default()
+            ))]
+        );
+        assert_eq!(encode(&res), "\x1b[1;1;;$z");
+
+        let res = parse_bytes("\x1b[2;3;4;5${");
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::SelectiveEraseRectangularArea(area))]
+        );
+        assert_eq!(encode(&res), "\x1b[2;3;4;5${");
+
+        let res = parse_bytes("\x1b[2;3;4;5;1;24$r");
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::ChangeAttributesInRectangularArea {
+                area,
+                attributes: vec![
+                    Sgr::Intensity(Intensity::Bold),
+                    Sgr::Underline(Underline::None)
+                ],
+            })]
+        );
+        assert_eq!(encode(&res), "\x1b[2;3;4;5;1;24$r");
+
+        let res = parse_bytes("\x1b[2;3;4;5$t");
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::ReverseAttributesInRectangularArea {
+                area,
+                attributes: vec![Sgr::Reset],
+            })]
+        );
+        assert_eq!(encode(&res), "\x1b[2;3;4;5;0$t");
+
+        let res = parse_bytes("\x1b[2*x");
+        assert_eq!(
+            res,
+            vec![CSI::Edit(Edit::SelectAttributeChangeExtent(
+                AttributeChangeExtent::Rectangle
+            ))]
+        );
+        assert_eq!(encode(&res), "\x1b[2*x");
+
+        // Colors are not permitted in DECCARA
+        let res = parse_bytes("\x1b[2;3;4;5;31$r");
+        assert!(matches!(res.as_slice(), [CSI::Unspecified(_)]));
+    }
+
     #[test]
     fn device_attr() {
         let res: Vec<_> = CSI::parse(
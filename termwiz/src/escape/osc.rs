@@ -9,6 +9,9 @@ use std::collections::HashMap;
 use std::fmt::{Display, Error as FmtError, Formatter, Result as FmtResult};
 use std::str;
 
+#[cfg(feature = "use_serde")]
+use serde::{Deserialize, Serialize};
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum ColorOrQuery {
     Color(SrgbaTuple),
@@ -43,7 +46,14 @@ pub enum OperatingSystemCommand {
     ResetDynamicColor(DynamicColorNumber),
     CurrentWorkingDirectory(String),
     ResetColors(Vec<u8>),
+    /// `OSC 777 ; notify ; title ; body`; when only one string
+    /// is supplied it is treated as the body.
+    RxvtNotify {
+        title: Option<String>,
+        body: String,
+    },
     RxvtExtension(Vec<String>),
+    KittyNotification(KittyNotification),
 
     Unspecified(Vec<Vec<u8>>),
 }
@@ -302,6 +312,24 @@ impl OperatingSystemCommand {
             ITermProprietary => {
                 self::ITermProprietary::parse(osc).map(OperatingSystemCommand::ITermProprietary)
             }
+            RxvtProprietary if osc.get(1).map(|p| *p == b"notify").unwrap_or(false) => {
+                let mut params = osc[2..]
+                    .iter()
+                    .map(|p| String::from_utf8_lossy(p).to_string());
+                let first = params.next().unwrap_or_default();
+                let rest: Vec<String> = params.collect();
+                if rest.is_empty() {
+                    Ok(OperatingSystemCommand::RxvtNotify {
+                        title: None,
+                        body: first,
+                    })
+                } else {
+                    Ok(OperatingSystemCommand::RxvtNotify {
+                        title: Some(first),
+                        body: rest.join(";"),
+                    })
+                }
+            }
             RxvtProprietary => {
                 let mut vec = vec![];
                 for slice in osc.iter().skip(1) {
@@ -311,6 +339,9 @@ impl OperatingSystemCommand {
             }
             FinalTermSemanticPrompt => self::FinalTermSemanticPrompt::parse(osc)
                 .map(OperatingSystemCommand::FinalTermSemanticPrompt),
+            KittyNotification => {
+                self::KittyNotification::parse(osc).map(OperatingSystemCommand::KittyNotification)
+            }
             ChangeColorNumber => Self::parse_change_color_number(osc),
             ResetColors => Self::parse_reset_colors(osc),
 
@@ -409,6 +440,8 @@ osc_entries!(
     SetFont = "50",
     EmacsShell = "51",
     ManipulateSelectionData = "52",
+    /// See <https://sw.kovidgoyal.net/kitty/desktop-notifications/>
+    KittyNotification = "99",
     ResetColors = "104",
     ResetSpecialColor = "105",
     ResetTextForegroundColor = "110",
@@ -482,7 +515,13 @@ impl Display for OperatingSystemCommand {
             SetIconNameSun(title) => single_string!(SetIconNameSun, title),
             SetHyperlink(Some(link)) => link.fmt(f)?,
             SetHyperlink(None) => write!(f, "8;;")?,
+            RxvtNotify { title: None, body } => write!(f, "777;notify;{}", body)?,
+            RxvtNotify {
+                title: Some(title),
+                body,
+            } => write!(f, "777;notify;{};{}", title, body)?,
             RxvtExtension(params) => write!(f, "777;{}", params.join(";"))?,
+            KittyNotification(n) => n.fmt(f)?,
             Unspecified(v) => {
                 for (idx, item) in v.iter().enumerate() {
                     if idx > 0 {
@@ -1233,6 +1272,221 @@ impl Display for ITermProprietary {
     }
 }
 
+/// Which part of a kitty desktop notification a payload belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyNotificationPayload {
+    Title,
+    Body,
+    /// `p=?`; asks the terminal which features it supports.
+    /// The terminal responds with a notification of this type
+    /// whose payload lists them.
+    Query,
+}
+
+impl Default for KittyNotificationPayload {
+    fn default() -> Self {
+        Self::Title
+    }
+}
+
+/// When a kitty desktop notification should be displayed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KittyNotificationOccasion {
+    Always,
+    /// Only if the window is not focused
+    Unfocused,
+    /// Only if the window is not visible
+    Invisible,
+}
+
+impl Default for KittyNotificationOccasion {
+    fn default() -> Self {
+        Self::Always
+    }
+}
+
+#[cfg_attr(feature = "use_serde", derive(Serialize, Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NotificationUrgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+impl Default for NotificationUrgency {
+    fn default() -> Self {
+        Self::Normal
+    }
+}
+
+/// `OSC 99 ; metadata ; payload`, the kitty desktop notification
+/// protocol.  The metadata is a `:` separated list of `key=value`
+/// pairs; unrecognized keys are ignored.
+/// A notification may be sent in several chunks that share the same
+/// `id`; the terminal accumulates them until one with `done` set
+/// is received.
+/// See <https://sw.kovidgoyal.net/kitty/desktop-notifications/>
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyNotification {
+    /// `i=`
+    pub id: Option<String>,
+    /// `d=`; false if more chunks follow
+    pub done: bool,
+    /// `p=`
+    pub payload_type: KittyNotificationPayload,
+    /// `e=`; true if the payload was base64 encoded.
+    /// `payload` always holds the decoded text.
+    pub base64: bool,
+    /// `a=focus`; whether activating the notification should
+    /// focus the window that sent it
+    pub focus: bool,
+    /// `a=report`; whether activating the notification should
+    /// send `OSC 99 ; i=id ; ST` back to the application
+    pub report: bool,
+    /// `o=`
+    pub occasion: KittyNotificationOccasion,
+    /// `u=`
+    pub urgency: NotificationUrgency,
+    pub payload: String,
+}
+
+impl Default for KittyNotification {
+    fn default() -> Self {
+        Self {
+            id: None,
+            done: true,
+            payload_type: KittyNotificationPayload::default(),
+            base64: false,
+            focus: true,
+            report: false,
+            occasion: KittyNotificationOccasion::default(),
+            urgency: NotificationUrgency::default(),
+            payload: String::new(),
+        }
+    }
+}
+
+impl KittyNotification {
+    fn parse(osc: &[&[u8]]) -> Result<Self> {
+        let mut notif = Self::default();
+
+        if let Some(metadata) = osc.get(1) {
+            for item in str::from_utf8(metadata)?.split(':') {
+                if item.is_empty() {
+                    continue;
+                }
+                let (key, value) = match item.find('=') {
+                    Some(equal) => (&item[..equal], &item[equal + 1..]),
+                    None => bail!("malformed kitty notification metadata {}", item),
+                };
+                match key {
+                    "i" => notif.id = Some(value.to_string()),
+                    "d" => notif.done = value != "0",
+                    "e" => notif.base64 = value == "1",
+                    "p" => {
+                        notif.payload_type = match value {
+                            "title" => KittyNotificationPayload::Title,
+                            "body" => KittyNotificationPayload::Body,
+                            "?" => KittyNotificationPayload::Query,
+                            _ => bail!("unsupported kitty notification payload type {}", value),
+                        }
+                    }
+                    "a" => {
+                        for action in value.split(',') {
+                            let (enable, action) = match action.strip_prefix('-') {
+                                Some(action) => (false, action),
+                                None => (true, action),
+                            };
+                            match action {
+                                "focus" => notif.focus = enable,
+                                "report" => notif.report = enable,
+                                _ => {}
+                            }
+                        }
+                    }
+                    "o" => {
+                        notif.occasion = match value {
+                            "always" => KittyNotificationOccasion::Always,
+                            "unfocused" => KittyNotificationOccasion::Unfocused,
+                            "invisible" => KittyNotificationOccasion::Invisible,
+                            _ => bail!("invalid kitty notification occasion {}", value),
+                        }
+                    }
+                    "u" => {
+                        notif.urgency = match value {
+                            "0" => NotificationUrgency::Low,
+                            "1" => NotificationUrgency::Normal,
+                            "2" => NotificationUrgency::Critical,
+                            _ => bail!("invalid kitty notification urgency {}", value),
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        // The payload may itself contain `;`, which the osc parser
+        // will have treated as separators
+        let payload = osc
+            .get(2..)
+            .unwrap_or_default()
+            .iter()
+            .map(|p| String::from_utf8_lossy(p))
+            .collect::<Vec<_>>()
+            .join(";");
+        notif.payload = if notif.base64 {
+            String::from_utf8(base64::decode(payload)?)?
+        } else {
+            payload
+        };
+
+        Ok(notif)
+    }
+}
+
+impl Display for KittyNotification {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        let mut metadata = vec![];
+        if let Some(id) = &self.id {
+            metadata.push(format!("i={}", id));
+        }
+        if !self.done {
+            metadata.push("d=0".to_string());
+        }
+        match self.payload_type {
+            KittyNotificationPayload::Title => {}
+            KittyNotificationPayload::Body => metadata.push("p=body".to_string()),
+            KittyNotificationPayload::Query => metadata.push("p=?".to_string()),
+        }
+        if self.base64 {
+            metadata.push("e=1".to_string());
+        }
+        match (self.focus, self.report) {
+            (true, false) => {}
+            (true, true) => metadata.push("a=report".to_string()),
+            (false, false) => metadata.push("a=-focus".to_string()),
+            (false, true) => metadata.push("a=-focus,report".to_string()),
+        }
+        match self.occasion {
+            KittyNotificationOccasion::Always => {}
+            KittyNotificationOccasion::Unfocused => metadata.push("o=unfocused".to_string()),
+            KittyNotificationOccasion::Invisible => metadata.push("o=invisible".to_string()),
+        }
+        match self.urgency {
+            NotificationUrgency::Normal => {}
+            NotificationUrgency::Low => metadata.push("u=0".to_string()),
+            NotificationUrgency::Critical => metadata.push("u=2".to_string()),
+        }
+
+        write!(f, "99;{};", metadata.join(":"))?;
+        if self.base64 {
+            write!(f, "{}", base64::encode(&self.payload))
+        } else {
+            write!(f, "{}", self.payload)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -1543,12 +1797,89 @@ mod test {
                 &["777", "notify", "alert user", "the tea is ready"],
                 "\x1b]777;notify;alert user;the tea is ready\x1b\\"
             ),
-            OperatingSystemCommand::RxvtExtension(vec![
-                "notify".into(),
-                "alert user".into(),
-                "the tea is ready".into()
-            ]),
-        )
+            OperatingSystemCommand::RxvtNotify {
+                title: Some("alert user".into()),
+                body: "the tea is ready".into(),
+            },
+        );
+        assert_eq!(
+            parse(
+                &["777", "notify", "the tea is ready"],
+                "\x1b]777;notify;the tea is ready\x1b\\"
+            ),
+            OperatingSystemCommand::RxvtNotify {
+                title: None,
+                body: "the tea is ready".into(),
+            },
+        );
+        assert_eq!(
+            parse(&["777", "preexec", "woot"], "\x1b]777;preexec;woot\x1b\\"),
+            OperatingSystemCommand::RxvtExtension(vec!["preexec".into(), "woot".into()]),
+        );
+    }
+
+    #[test]
+    fn kitty_notification() {
+        assert_eq!(
+            parse(&["99", "", "Hello world"], "\x1b]99;;Hello world\x1b\\"),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                payload: "Hello world".into(),
+                ..Default::default()
+            }),
+        );
+
+        assert_eq!(
+            parse(
+                &["99", "i=1:d=0:a=-focus,report:u=2", "Title"],
+                "\x1b]99;i=1:d=0:a=-focus,report:u=2;Title\x1b\\"
+            ),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: Some("1".into()),
+                done: false,
+                focus: false,
+                report: true,
+                urgency: NotificationUrgency::Critical,
+                payload: "Title".into(),
+                ..Default::default()
+            }),
+        );
+
+        assert_eq!(
+            parse(
+                &["99", "i=1:p=body:e=1:o=unfocused", "Ym9keTsgdGV4dA=="],
+                "\x1b]99;i=1:p=body:e=1:o=unfocused;Ym9keTsgdGV4dA==\x1b\\"
+            ),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: Some("1".into()),
+                payload_type: KittyNotificationPayload::Body,
+                base64: true,
+                occasion: KittyNotificationOccasion::Unfocused,
+                payload: "body; text".into(),
+                ..Default::default()
+            }),
+        );
+
+        // Unencoded payloads may contain `;`
+        assert_eq!(
+            parse(
+                &["99", "p=body", "one", "two"],
+                "\x1b]99;p=body;one;two\x1b\\"
+            ),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                payload_type: KittyNotificationPayload::Body,
+                payload: "one;two".into(),
+                ..Default::default()
+            }),
+        );
+
+        assert_eq!(
+            parse(&["99", "i=q:p=?", ""], "\x1b]99;i=q:p=?;\x1b\\"),
+            OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: Some("q".into()),
+                payload_type: KittyNotificationPayload::Query,
+                ..Default::default()
+            }),
+        );
     }
 
     #[test]
@@ -339,10 +339,12 @@ mod test {
     use crate::cell::{Intensity, Underline};
     use crate::color::{ColorSpec, RgbColor};
     use crate::escape::csi::{
-        CharacterPath, DecPrivateMode, DecPrivateModeCode, Device, Mode, Sgr, Window, XtSmGraphics,
-        XtSmGraphicsItem, XtermKeyModifierResource,
+        CharacterPath, Cursor, DecPrivateMode, DecPrivateModeCode, Device, Keyboard,
+        KittyKeyboardMode, Mode, Sgr, Window, XtSmGraphics, XtSmGraphicsItem,
+        XtermKeyModifierResource,
     };
     use crate::escape::{EscCode, OneBased};
+    use crate::input::KittyKeyboardFlags;
     use pretty_assertions::assert_eq;
     use std::io::Write;
 
@@ -598,6 +600,43 @@ mod test {
         );
     }
 
+    #[test]
+    fn kitty_keyboard() {
+        assert_eq!(
+            round_trip_parse("\x1b[>5u"),
+            vec![Action::CSI(CSI::Keyboard(Keyboard::PushKittyState(
+                KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+                    | KittyKeyboardFlags::REPORT_ALTERNATE_KEYS
+            )))]
+        );
+        assert_eq!(
+            round_trip_parse("\x1b[<2u"),
+            vec![Action::CSI(CSI::Keyboard(Keyboard::PopKittyState(2)))]
+        );
+        assert_eq!(
+            round_trip_parse("\x1b[?u"),
+            vec![Action::CSI(CSI::Keyboard(Keyboard::QueryKittySupport))]
+        );
+        assert_eq!(
+            round_trip_parse("\x1b[?1u"),
+            vec![Action::CSI(CSI::Keyboard(Keyboard::ReportKittyState(
+                KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES
+            )))]
+        );
+        assert_eq!(
+            round_trip_parse("\x1b[=2;3u"),
+            vec![Action::CSI(CSI::Keyboard(Keyboard::SetKittyState {
+                flags: KittyKeyboardFlags::REPORT_EVENT_TYPES,
+                mode: KittyKeyboardMode::ClearSpecified,
+            }))]
+        );
+        // Plain SCORC is unaffected
+        assert_eq!(
+            round_trip_parse("\x1b[u"),
+            vec![Action::CSI(CSI::Cursor(Cursor::RestoreCursor))]
+        );
+    }
+
     #[test]
     fn window() {
         assert_eq!(
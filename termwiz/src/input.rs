@@ -22,6 +22,24 @@ pub enum KeyboardEncoding {
     CsiU,
     /// <https://github.com/microsoft/terminal/blob/main/doc/specs/%234999%20-%20Improved%20keyboard%20handling%20in%20Conpty.md>
     Win32,
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+    Kitty(KittyKeyboardFlags),
+}
+
+bitflags! {
+    /// The progressive enhancement flags that an application can request
+    /// via the kitty keyboard protocol.
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/#progressive-enhancement>
+    #[cfg_attr(feature="use_serde", derive(Serialize, Deserialize))]
+    #[derive(Default)]
+    pub struct KittyKeyboardFlags: u16 {
+        const NONE = 0;
+        const DISAMBIGUATE_ESCAPE_CODES = 1;
+        const REPORT_EVENT_TYPES = 2;
+        const REPORT_ALTERNATE_KEYS = 4;
+        const REPORT_ALL_KEYS_AS_ESCAPE_CODES = 8;
+        const REPORT_ASSOCIATED_TEXT = 16;
+    }
 }
 
 /// Specifies terminal modes/configuration that can influence how a KeyCode
@@ -260,6 +278,10 @@ impl KeyCode {
         modes: KeyCodeEncodeModes,
         is_down: bool,
     ) -> Result<String> {
+        if let KeyboardEncoding::Kitty(flags) = modes.encoding {
+            return self.encode_kitty(mods, modes, flags, is_down);
+        }
+
         if !is_down {
             return Ok(String::new());
         }
@@ -272,6 +294,10 @@ impl KeyCode {
     pub fn encode(&self, mods: Modifiers, modes: KeyCodeEncodeModes) -> Result<String> {
         use KeyCode::*;
 
+        if let KeyboardEncoding::Kitty(flags) = modes.encoding {
+            return self.encode_kitty(mods, modes, flags, true);
+        }
+
         let key = self.normalize_shift_to_upper_case(mods);
         // Normalize the modifier state for Char's that are uppercase; remove
         // the SHIFT modifier so that reduce ambiguity below
@@ -503,6 +529,221 @@ impl KeyCode {
 
         Ok(buf)
     }
+
+    /// Returns the kitty keyboard protocol number and the final character
+    /// of the escape sequence used to report this key, along with whether
+    /// the key is only reported when all keys are being reported as escape
+    /// codes (modifiers, locks and media keys).
+    /// Char keys are not handled here.
+    fn kitty_function_key(&self) -> Option<(u32, char, bool)> {
+        use KeyCode::*;
+        Some(match self {
+            Escape => (27, 'u', false),
+            Enter => (13, 'u', false),
+            Tab => (9, 'u', false),
+            Backspace => (127, 'u', false),
+            Insert => (2, '~', false),
+            Delete => (3, '~', false),
+            LeftArrow | ApplicationLeftArrow => (1, 'D', false),
+            RightArrow | ApplicationRightArrow => (1, 'C', false),
+            UpArrow | ApplicationUpArrow => (1, 'A', false),
+            DownArrow | ApplicationDownArrow => (1, 'B', false),
+            PageUp => (5, '~', false),
+            PageDown => (6, '~', false),
+            Home => (1, 'H', false),
+            End => (1, 'F', false),
+            Function(1) => (1, 'P', false),
+            Function(2) => (1, 'Q', false),
+            Function(3) => (13, '~', false),
+            Function(4) => (1, 'S', false),
+            Function(5) => (15, '~', false),
+            Function(6) => (17, '~', false),
+            Function(7) => (18, '~', false),
+            Function(8) => (19, '~', false),
+            Function(9) => (20, '~', false),
+            Function(10) => (21, '~', false),
+            Function(11) => (23, '~', false),
+            Function(12) => (24, '~', false),
+            Function(n) if *n >= 13 && *n <= 35 => (57376 + u32::from(*n) - 13, 'u', false),
+            Numpad0 => (57399, 'u', false),
+            Numpad1 => (57400, 'u', false),
+            Numpad2 => (57401, 'u', false),
+            Numpad3 => (57402, 'u', false),
+            Numpad4 => (57403, 'u', false),
+            Numpad5 => (57404, 'u', false),
+            Numpad6 => (57405, 'u', false),
+            Numpad7 => (57406, 'u', false),
+            Numpad8 => (57407, 'u', false),
+            Numpad9 => (57408, 'u', false),
+            Decimal => (57409, 'u', false),
+            Divide => (57410, 'u', false),
+            Multiply => (57411, 'u', false),
+            Subtract => (57412, 'u', false),
+            Add => (57413, 'u', false),
+            Separator => (57416, 'u', false),
+            CapsLock => (57358, 'u', true),
+            ScrollLock => (57359, 'u', true),
+            NumLock => (57360, 'u', true),
+            PrintScreen => (57361, 'u', true),
+            Pause => (57362, 'u', true),
+            Applications => (57363, 'u', true),
+            MediaPlayPause => (57430, 'u', true),
+            MediaStop => (57432, 'u', true),
+            MediaNextTrack => (57435, 'u', true),
+            MediaPrevTrack => (57436, 'u', true),
+            VolumeDown => (57438, 'u', true),
+            VolumeUp => (57439, 'u', true),
+            VolumeMute => (57440, 'u', true),
+            Shift | LeftShift => (57441, 'u', true),
+            Control | LeftControl => (57442, 'u', true),
+            Alt | LeftAlt | Menu | LeftMenu => (57443, 'u', true),
+            Super | LeftWindows => (57444, 'u', true),
+            Hyper => (57445, 'u', true),
+            Meta => (57446, 'u', true),
+            RightShift => (57447, 'u', true),
+            RightControl => (57448, 'u', true),
+            RightAlt | RightMenu => (57449, 'u', true),
+            RightWindows => (57450, 'u', true),
+            _ => return None,
+        })
+    }
+
+    /// Encodes this key using the kitty keyboard protocol, honoring
+    /// the progressive enhancement flags requested by the application.
+    /// Keys that the flags don't require to be disambiguated fall back
+    /// to the xterm compatible encoding.
+    /// <https://sw.kovidgoyal.net/kitty/keyboard-protocol/>
+    fn encode_kitty(
+        &self,
+        mods: Modifiers,
+        modes: KeyCodeEncodeModes,
+        flags: KittyKeyboardFlags,
+        is_down: bool,
+    ) -> Result<String> {
+        use KeyCode::*;
+
+        if !is_down && !flags.contains(KittyKeyboardFlags::REPORT_EVENT_TYPES) {
+            return Ok(String::new());
+        }
+
+        let legacy = KeyCodeEncodeModes {
+            encoding: KeyboardEncoding::Xterm,
+            ..modes
+        };
+        let report_all = flags.contains(KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES);
+        let mods = mods & (Modifiers::SHIFT | Modifiers::ALT | Modifiers::CTRL | Modifiers::SUPER);
+
+        let key = match self.normalize_shift_to_upper_case(mods) {
+            Char('\x7f') => Delete,
+            Char('\x08') => Backspace,
+            key => key,
+        };
+
+        let (number, suffix, only_when_reporting_all) = match key {
+            // The key number is always the unshifted form of the key;
+            // the shifted form is reported as an alternate key
+            Char(c) if c.is_uppercase() => {
+                let mut lower = c.to_lowercase();
+                match (lower.next(), lower.next()) {
+                    (Some(lower), None) => (lower as u32, 'u', false),
+                    _ => (c as u32, 'u', false),
+                }
+            }
+            Char(c) => (c as u32, 'u', false),
+            _ => match key.kitty_function_key() {
+                Some(info) => info,
+                None if is_down => return key.encode(mods, legacy),
+                None => return Ok(String::new()),
+            },
+        };
+
+        let use_escape = if report_all {
+            true
+        } else if only_when_reporting_all {
+            false
+        } else if !is_down {
+            // Release events for these keys are only reported when
+            // all keys are being reported as escape codes
+            !matches!(key, Enter | Tab | Backspace)
+        } else if flags.contains(KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES) {
+            match key {
+                Char(_) => mods.intersects(Modifiers::ALT | Modifiers::CTRL | Modifiers::SUPER),
+                Enter | Tab | Backspace => !mods.is_empty(),
+                Escape => true,
+                _ => suffix == 'u',
+            }
+        } else {
+            false
+        };
+
+        if !use_escape {
+            return if is_down {
+                key.encode(mods, legacy)
+            } else {
+                Ok(String::new())
+            };
+        }
+
+        let shifted = match key {
+            Char(c)
+                if flags.contains(KittyKeyboardFlags::REPORT_ALTERNATE_KEYS)
+                    && mods.contains(Modifiers::SHIFT)
+                    && c as u32 != number =>
+            {
+                Some(c)
+            }
+            _ => None,
+        };
+
+        let text = match key {
+            Char(c)
+                if is_down
+                    && report_all
+                    && flags.contains(KittyKeyboardFlags::REPORT_ASSOCIATED_TEXT)
+                    && !c.is_control()
+                    && !mods.intersects(Modifiers::ALT | Modifiers::CTRL | Modifiers::SUPER) =>
+            {
+                Some(c)
+            }
+            _ => None,
+        };
+
+        let encoded_mods = 1 + kitty_encode_modifiers(mods);
+        let event_type = if is_down { None } else { Some(3) };
+        let has_mods = encoded_mods != 1 || event_type.is_some();
+
+        let mut buf = String::new();
+        buf.push_str(CSI);
+        if suffix == 'u' || suffix == '~' || has_mods {
+            write!(buf, "{}", number)?;
+        }
+        if let Some(shifted) = shifted {
+            write!(buf, ":{}", shifted as u32)?;
+        }
+        if has_mods || text.is_some() {
+            buf.push(';');
+        }
+        if has_mods {
+            write!(buf, "{}", encoded_mods)?;
+            if let Some(event_type) = event_type {
+                write!(buf, ":{}", event_type)?;
+            }
+        }
+        if let Some(text) = text {
+            write!(buf, ";{}", text as u32)?;
+        }
+        buf.push(suffix);
+
+        Ok(buf)
+    }
+}
+
+fn kitty_encode_modifiers(mods: Modifiers) -> u8 {
+    let mut number = encode_modifiers(mods);
+    if mods.contains(Modifiers::SUPER) {
+        number |= 8;
+    }
+    number
 }
 
 fn encode_modifiers(mods: Modifiers) -> u8 {
@@ -1601,6 +1842,135 @@ mod test {
         );
     }
 
+    #[test]
+    fn encode_kitty() {
+        fn encode(
+            key: KeyCode,
+            mods: Modifiers,
+            flags: KittyKeyboardFlags,
+            is_down: bool,
+        ) -> String {
+            key.encode_up_down(
+                mods,
+                KeyCodeEncodeModes {
+                    encoding: KeyboardEncoding::Kitty(flags),
+                    newline_mode: false,
+                    application_cursor_keys: false,
+                },
+                is_down,
+            )
+            .unwrap()
+        }
+        let disambiguate = KittyKeyboardFlags::DISAMBIGUATE_ESCAPE_CODES;
+
+        // Tab and Ctrl-I are distinct
+        assert_eq!(
+            encode(KeyCode::Tab, Modifiers::NONE, disambiguate, true),
+            "\t"
+        );
+        assert_eq!(
+            encode(KeyCode::Char('i'), Modifiers::CTRL, disambiguate, true),
+            "\x1b[105;5u"
+        );
+        assert_eq!(
+            encode(KeyCode::Escape, Modifiers::NONE, disambiguate, true),
+            "\x1b[27u"
+        );
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::NONE, disambiguate, true),
+            "a"
+        );
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::SHIFT, disambiguate, true),
+            "A"
+        );
+        assert_eq!(
+            encode(
+                KeyCode::Char('a'),
+                Modifiers::ALT | Modifiers::SHIFT,
+                disambiguate,
+                true
+            ),
+            "\x1b[97;4u"
+        );
+        assert_eq!(
+            encode(KeyCode::Enter, Modifiers::SHIFT, disambiguate, true),
+            "\x1b[13;2u"
+        );
+        assert_eq!(
+            encode(KeyCode::Numpad1, Modifiers::NONE, disambiguate, true),
+            "\x1b[57400u"
+        );
+        assert_eq!(
+            encode(KeyCode::LeftArrow, Modifiers::CTRL, disambiguate, true),
+            "\x1b[1;5D"
+        );
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::NONE, disambiguate, false),
+            ""
+        );
+
+        // Release events
+        let events = disambiguate | KittyKeyboardFlags::REPORT_EVENT_TYPES;
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::NONE, events, true),
+            "a"
+        );
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::NONE, events, false),
+            "\x1b[97;1:3u"
+        );
+        assert_eq!(
+            encode(KeyCode::UpArrow, Modifiers::NONE, events, false),
+            "\x1b[1;1:3A"
+        );
+        assert_eq!(encode(KeyCode::Enter, Modifiers::NONE, events, false), "");
+        assert_eq!(
+            encode(KeyCode::LeftShift, Modifiers::SHIFT, events, true),
+            ""
+        );
+
+        // Alternate keys
+        let alternates = disambiguate | KittyKeyboardFlags::REPORT_ALTERNATE_KEYS;
+        assert_eq!(
+            encode(
+                KeyCode::Char('a'),
+                Modifiers::CTRL | Modifiers::SHIFT,
+                alternates,
+                true
+            ),
+            "\x1b[97:65;6u"
+        );
+
+        // All keys as escape codes, with associated text
+        let all = KittyKeyboardFlags::REPORT_ALL_KEYS_AS_ESCAPE_CODES;
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::NONE, all, true),
+            "\x1b[97u"
+        );
+        assert_eq!(
+            encode(KeyCode::Enter, Modifiers::NONE, all, true),
+            "\x1b[13u"
+        );
+        assert_eq!(
+            encode(KeyCode::LeftShift, Modifiers::SHIFT, all, true),
+            "\x1b[57441;2u"
+        );
+        assert_eq!(
+            encode(KeyCode::UpArrow, Modifiers::NONE, all, true),
+            "\x1b[A"
+        );
+        let text = all | KittyKeyboardFlags::REPORT_ASSOCIATED_TEXT;
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::SHIFT, text, true),
+            "\x1b[97;2;65u"
+        );
+        assert_eq!(
+            encode(KeyCode::Char('a'), Modifiers::NONE, text, true),
+            "\x1b[97;;97u"
+        );
+    }
+
     #[test]
     fn partial_bracketed_paste() {
         let mut p = InputParser::new();
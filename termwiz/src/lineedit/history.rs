@@ -1,5 +1,10 @@
+use crate::error::Context;
+use crate::Result;
 use std::borrow::Cow;
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 /// Represents a position within the history.
 /// Smaller numbers are assumed to be before larger numbers,
@@ -112,26 +117,374 @@ impl History for BasicHistory {
         direction: SearchDirection,
         pattern: &str,
     ) -> Option<SearchResult> {
-        let mut idx = idx;
-
-        loop {
-            let line = match self.entries.get(idx) {
-                Some(line) => line,
-                None => return None,
-            };
-
-            if let Some(cursor) = style.match_against(pattern, line) {
-                return Some(SearchResult {
-                    line: Cow::Borrowed(line.as_str()),
-                    idx,
-                    cursor,
-                });
+        search_entries(&self.entries, idx, style, direction, pattern)
+    }
+}
+
+fn search_entries<'a>(
+    entries: &'a VecDeque<String>,
+    idx: HistoryIndex,
+    style: SearchStyle,
+    direction: SearchDirection,
+    pattern: &str,
+) -> Option<SearchResult<'a>> {
+    let mut idx = idx;
+
+    loop {
+        let line = entries.get(idx)?;
+
+        if let Some(cursor) = style.match_against(pattern, line) {
+            return Some(SearchResult {
+                line: Cow::Borrowed(line.as_str()),
+                idx,
+                cursor,
+            });
+        }
+
+        idx = direction.next(idx)?;
+    }
+}
+
+/// A history implementation that persists its entries to a file,
+/// so that they survive across sessions.
+///
+/// Each entry is stored on its own line, with newlines and backslashes
+/// escaped.  Adding an entry appends it to the file and then re-reads
+/// the file, so that several processes can share the same history file
+/// and see each other's entries.  Updates are serialized via an advisory
+/// lock on a `.lock` file alongside the history file.
+///
+/// Repeated entries are collapsed so that only the most recent one is
+/// retained, and only the most recent `max_entries` entries are kept.
+/// The file is allowed to grow beyond that with duplicate and stale
+/// entries until it reaches twice that size, at which point it is
+/// compacted by writing the retained entries to a temporary file that
+/// is then atomically renamed over the history file.
+pub struct FileHistory {
+    path: PathBuf,
+    entries: VecDeque<String>,
+    max_entries: usize,
+    /// The number of lines in the file when we last read it
+    lines_in_file: usize,
+}
+
+impl FileHistory {
+    pub const DEFAULT_MAX_ENTRIES: usize = 1000;
+
+    /// Loads the history from the specified path, retaining up to
+    /// `DEFAULT_MAX_ENTRIES` entries.  The file need not exist yet.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_max_entries(path, Self::DEFAULT_MAX_ENTRIES)
+    }
+
+    /// Loads the history from the specified path, retaining up to
+    /// `max_entries` entries.  The file need not exist yet.
+    pub fn with_max_entries<P: AsRef<Path>>(path: P, max_entries: usize) -> Result<Self> {
+        let mut history = Self {
+            path: path.as_ref().to_path_buf(),
+            entries: VecDeque::new(),
+            max_entries: max_entries.max(1),
+            lines_in_file: 0,
+        };
+        history.reload()?;
+        Ok(history)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Re-reads the history file, picking up any entries that were
+    /// added by other processes.
+    pub fn reload(&mut self) -> Result<()> {
+        let _lock = FileLock::acquire(&self.lock_path())?;
+        self.read_file()
+    }
+
+    fn lock_path(&self) -> PathBuf {
+        let mut name = self.path.file_name().unwrap_or_default().to_os_string();
+        name.push(".lock");
+        self.path.with_file_name(name)
+    }
+
+    /// Reads the file; must be called with the lock held
+    fn read_file(&mut self) -> Result<()> {
+        let mut data = String::new();
+        match File::open(&self.path) {
+            Ok(mut file) => {
+                file.read_to_string(&mut data)
+                    .with_context(|| format!("reading history from {}", self.path.display()))?;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("opening history file {}", self.path.display()))
+            }
+        }
+
+        let lines: Vec<&str> = data.lines().collect();
+        self.lines_in_file = lines.len();
+
+        // Walk backwards so that we keep the most recent of any
+        // duplicated entries, and stop once we have enough
+        let mut seen = HashSet::new();
+        let mut entries = VecDeque::new();
+        for line in lines.into_iter().rev() {
+            if entries.len() >= self.max_entries {
+                break;
             }
+            let entry = unescape_entry(line);
+            if seen.insert(entry.clone()) {
+                entries.push_front(entry);
+            }
+        }
+        self.entries = entries;
+        Ok(())
+    }
+
+    fn append(&mut self, line: &str) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+        let _lock = FileLock::acquire(&self.lock_path())?;
+
+        let mut data = escape_entry(line);
+        data.push('\n');
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .with_context(|| format!("opening history file {}", self.path.display()))?;
+        // A single write of the complete entry, so that a reader
+        // never observes a partial line
+        file.write_all(data.as_bytes())
+            .with_context(|| format!("appending to history file {}", self.path.display()))?;
+        drop(file);
+
+        self.read_file()?;
+        if self.lines_in_file > self.max_entries * 2 {
+            self.compact()?;
+        }
+        Ok(())
+    }
+
+    /// Rewrites the file with just the retained entries;
+    /// must be called with the lock held
+    fn compact(&mut self) -> Result<()> {
+        let mut data = String::new();
+        for entry in &self.entries {
+            data.push_str(&escape_entry(entry));
+            data.push('\n');
+        }
+        let temp = self.path.with_extension("tmp");
+        std::fs::write(&temp, data)
+            .with_context(|| format!("writing history file {}", temp.display()))?;
+        std::fs::rename(&temp, &self.path)
+            .with_context(|| format!("renaming {} to {}", temp.display(), self.path.display()))?;
+        self.lines_in_file = self.entries.len();
+        Ok(())
+    }
 
-            idx = match direction.next(idx) {
-                None => return None,
-                Some(idx) => idx,
-            };
+    fn add_to_memory(&mut self, line: &str) {
+        if let Some(idx) = self.entries.iter().position(|entry| entry == line) {
+            self.entries.remove(idx);
+        }
+        self.entries.push_back(line.to_owned());
+        while self.entries.len() > self.max_entries {
+            self.entries.pop_front();
+        }
+    }
+}
+
+impl History for FileHistory {
+    fn get(&self, idx: HistoryIndex) -> Option<Cow<'_, str>> {
+        self.entries.get(idx).map(|s| Cow::Borrowed(s.as_str()))
+    }
+
+    fn last(&self) -> Option<HistoryIndex> {
+        if self.entries.is_empty() {
+            None
+        } else {
+            Some(self.entries.len() - 1)
         }
     }
+
+    fn add(&mut self, line: &str) {
+        if let Err(err) = self.append(line) {
+            log::error!("failed to save history: {:#}", err);
+            // Keep the entry for the remainder of this session
+            self.add_to_memory(line);
+        }
+    }
+
+    fn search(
+        &self,
+        idx: HistoryIndex,
+        style: SearchStyle,
+        direction: SearchDirection,
+        pattern: &str,
+    ) -> Option<SearchResult<'_>> {
+        search_entries(&self.entries, idx, style, direction, pattern)
+    }
+}
+
+fn escape_entry(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    for c in line.chars() {
+        match c {
+            '\\' => result.push_str("\\\\"),
+            '\n' => result.push_str("\\n"),
+            '\r' => result.push_str("\\r"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+fn unescape_entry(line: &str) -> String {
+    let mut result = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some(c) => result.push(c),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
+
+/// An exclusive advisory lock, held until dropped
+struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    fn acquire(path: &Path) -> Result<Self> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("creating directory {}", parent.display()))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("opening lock file {}", path.display()))?;
+        Self::lock(&file).with_context(|| format!("locking {}", path.display()))?;
+        Ok(Self { _file: file })
+    }
+
+    #[cfg(unix)]
+    fn lock(file: &File) -> std::io::Result<()> {
+        use std::os::unix::io::AsRawFd;
+        let res = unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX) };
+        if res != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    #[cfg(windows)]
+    fn lock(file: &File) -> std::io::Result<()> {
+        use std::os::windows::io::AsRawHandle;
+        use winapi::um::fileapi::LockFileEx;
+        use winapi::um::minwinbase::{LOCKFILE_EXCLUSIVE_LOCK, OVERLAPPED};
+        let mut overlapped: OVERLAPPED = unsafe { std::mem::zeroed() };
+        let res = unsafe {
+            LockFileEx(
+                file.as_raw_handle() as _,
+                LOCKFILE_EXCLUSIVE_LOCK,
+                0,
+                !0,
+                !0,
+                &mut overlapped,
+            )
+        };
+        if res == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+        // The lock is released when the handle is closed
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_history_path(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("termwiz-history-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir.join("history")
+    }
+
+    fn entries(history: &FileHistory) -> Vec<String> {
+        history.entries.iter().cloned().collect()
+    }
+
+    #[test]
+    fn escaping() {
+        for entry in &["plain", "back\\slash", "multi\nline", "trailing\\", "\r\n"] {
+            assert_eq!(unescape_entry(&escape_entry(entry)), *entry);
+            assert!(!escape_entry(entry).contains('\n'));
+        }
+    }
+
+    #[test]
+    fn persists_and_dedups() {
+        let path = temp_history_path("dedup");
+        let mut history = FileHistory::open(&path).unwrap();
+        assert_eq!(history.last(), None);
+        history.add("one");
+        history.add("two");
+        history.add("one");
+        history.add("three\nlines");
+        assert_eq!(entries(&history), vec!["two", "one", "three\nlines"]);
+
+        let reopened = FileHistory::open(&path).unwrap();
+        assert_eq!(entries(&reopened), vec!["two", "one", "three\nlines"]);
+
+        let result = reopened
+            .search(2, SearchStyle::Substring, SearchDirection::Backwards, "w")
+            .unwrap();
+        assert_eq!(result.idx, 0);
+        assert_eq!(result.line, "two");
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn shared_and_capped() {
+        let path = temp_history_path("shared");
+        let mut a = FileHistory::with_max_entries(&path, 3).unwrap();
+        let mut b = FileHistory::with_max_entries(&path, 3).unwrap();
+
+        a.add("a1");
+        b.add("b1");
+        // b sees a's entry because adding re-reads the file
+        assert_eq!(entries(&b), vec!["a1", "b1"]);
+        // a sees b's entry once it reloads
+        a.reload().unwrap();
+        assert_eq!(entries(&a), vec!["a1", "b1"]);
+
+        for i in 0..10 {
+            a.add(&format!("a{}", i + 2));
+        }
+        assert_eq!(entries(&a), vec!["a9", "a10", "a11"]);
+        // The file has been compacted
+        assert!(a.lines_in_file <= 6);
+
+        b.reload().unwrap();
+        assert_eq!(entries(&b), vec!["a9", "a10", "a11"]);
+
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
 }
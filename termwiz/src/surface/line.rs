@@ -5,6 +5,7 @@ use crate::surface::{Change, SequenceNo, SEQ_ZERO};
 use bitflags::bitflags;
 #[cfg(feature = "use_serde")]
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
 use std::ops::Range;
 use std::sync::Arc;
 use unicode_segmentation::UnicodeSegmentation;
@@ -373,7 +374,7 @@ impl Line {
         self.zones.clear();
     }
 
-    fn compute_zones(&mut self) {
+    fn compute_zones(&self) -> Vec<ZoneRange> {
         let blank_cell = Cell::blank();
         let mut last_cell: Option<&Cell> = None;
         let mut current_zone: Option<ZoneRange> = None;
@@ -421,16 +422,27 @@ impl Line {
         if let Some(zone) = current_zone.take() {
             zones.push(zone);
         }
-        self.zones = zones;
+        zones
     }
 
     pub fn semantic_zone_ranges(&mut self) -> &[ZoneRange] {
         if self.zones.is_empty() {
-            self.compute_zones();
+            self.zones = self.compute_zones();
         }
         &self.zones
     }
 
+    /// Like `semantic_zone_ranges`, but for a line that cannot be
+    /// mutated: the zones are computed without being cached if they
+    /// have not been cached already.
+    pub fn semantic_zone_ranges_uncached(&self) -> Cow<'_, [ZoneRange]> {
+        if self.zones.is_empty() {
+            Cow::Owned(self.compute_zones())
+        } else {
+            Cow::Borrowed(&self.zones)
+        }
+    }
+
     /// If we have any cells with an implicit hyperlink, remove the hyperlink
     /// from the cell attributes but leave the remainder of the attributes alone.
     pub fn invalidate_implicit_hyperlinks(&mut self, seqno: SequenceNo) {
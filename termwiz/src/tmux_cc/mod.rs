@@ -28,6 +28,99 @@ pub struct WindowLayout {
     pub layout_id: String,
     pub width: u64,
     pub height: u64,
+    pub root: WindowLayoutNode,
+}
+
+impl std::str::FromStr for WindowLayout {
+    type Err = anyhow::Error;
+
+    /// Parses a layout string, such as the `#{window_layout}` format
+    /// variable, for example "b25d,80x24,0,0,0"
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut pairs = parser::TmuxParser::parse(Rule::window_layout_entire, s)?;
+        let pair = pairs.next().ok_or_else(|| anyhow::anyhow!("no pairs!?"))?;
+        parse_window_layout(pair).ok_or_else(|| anyhow::anyhow!("invalid window layout {}", s))
+    }
+}
+
+/// A cell in the layout tree of a tmux window.
+/// Each cell has a size and a position relative to the window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WindowLayoutNode {
+    Pane {
+        width: u64,
+        height: u64,
+        x: u64,
+        y: u64,
+        pane: TmuxPaneId,
+    },
+    /// The children are laid out side by side, from left to right.
+    /// This is represented using `{}` in the layout string.
+    Horizontal {
+        width: u64,
+        height: u64,
+        x: u64,
+        y: u64,
+        children: Vec<WindowLayoutNode>,
+    },
+    /// The children are stacked from top to bottom.
+    /// This is represented using `[]` in the layout string.
+    Vertical {
+        width: u64,
+        height: u64,
+        x: u64,
+        y: u64,
+        children: Vec<WindowLayoutNode>,
+    },
+}
+
+impl WindowLayoutNode {
+    pub fn width(&self) -> u64 {
+        match self {
+            Self::Pane { width, .. }
+            | Self::Horizontal { width, .. }
+            | Self::Vertical { width, .. } => *width,
+        }
+    }
+
+    pub fn height(&self) -> u64 {
+        match self {
+            Self::Pane { height, .. }
+            | Self::Horizontal { height, .. }
+            | Self::Vertical { height, .. } => *height,
+        }
+    }
+
+    pub fn x(&self) -> u64 {
+        match self {
+            Self::Pane { x, .. } | Self::Horizontal { x, .. } | Self::Vertical { x, .. } => *x,
+        }
+    }
+
+    pub fn y(&self) -> u64 {
+        match self {
+            Self::Pane { y, .. } | Self::Horizontal { y, .. } | Self::Vertical { y, .. } => *y,
+        }
+    }
+
+    /// Returns the ids of the panes in this portion of the layout,
+    /// in left-to-right, top-to-bottom order
+    pub fn panes(&self) -> Vec<TmuxPaneId> {
+        let mut panes = vec![];
+        self.collect_panes(&mut panes);
+        panes
+    }
+
+    fn collect_panes(&self, panes: &mut Vec<TmuxPaneId>) {
+        match self {
+            Self::Pane { pane, .. } => panes.push(*pane),
+            Self::Horizontal { children, .. } | Self::Vertical { children, .. } => {
+                for child in children {
+                    child.collect_panes(panes);
+                }
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -164,20 +257,53 @@ fn parse_window_layout(pair: Pair<Rule>) -> Option<WindowLayout> {
     match pair.as_rule() {
         Rule::window_layout => {
             let mut pairs = pair.into_inner();
-            let layout_id_option = pairs.next()?.as_str().parse::<String>().ok();
-            let width_option = pairs.next()?.as_str().parse::<u64>().ok();
-            let height_option = pairs.next()?.as_str().parse::<u64>().ok();
-            if let (Some(layout_id), Some(width), Some(height)) =
-                (layout_id_option, width_option, height_option)
-            {
-                return Some(WindowLayout {
-                    layout_id,
-                    width,
-                    height,
-                });
-            }
-            return None;
+            let layout_id = pairs.next()?.as_str().to_owned();
+            let root = parse_layout_cell(pairs.next()?)?;
+            Some(WindowLayout {
+                layout_id,
+                width: root.width(),
+                height: root.height(),
+                root,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Parses a cell of a layout string, for example "80x24,0,0,0" or
+/// "161x41,0,0{80x41,0,0,1,80x41,81,0,2}"
+fn parse_layout_cell(pair: Pair<Rule>) -> Option<WindowLayoutNode> {
+    let rule = pair.as_rule();
+    let mut pairs = pair.into_inner();
+    let width = pairs.next()?.as_str().parse::<u64>().ok()?;
+    let height = pairs.next()?.as_str().parse::<u64>().ok()?;
+    let x = pairs.next()?.as_str().parse::<u64>().ok()?;
+    let y = pairs.next()?.as_str().parse::<u64>().ok()?;
+    match rule {
+        Rule::layout_pane => {
+            let pane = pairs.next()?.as_str().parse::<TmuxPaneId>().ok()?;
+            Some(WindowLayoutNode::Pane {
+                width,
+                height,
+                x,
+                y,
+                pane,
+            })
         }
+        Rule::layout_horizontal => Some(WindowLayoutNode::Horizontal {
+            width,
+            height,
+            x,
+            y,
+            children: pairs.map(parse_layout_cell).collect::<Option<Vec<_>>>()?,
+        }),
+        Rule::layout_vertical => Some(WindowLayoutNode::Vertical {
+            width,
+            height,
+            x,
+            y,
+            children: pairs.map(parse_layout_cell).collect::<Option<Vec<_>>>()?,
+        }),
         _ => None,
     }
 }
@@ -285,7 +411,10 @@ fn parse_line(line: &str) -> anyhow::Result<Event> {
         Rule::layout_change => {
             let mut pairs = pair.into_inner();
             let window = parse_window_id(pairs.next().unwrap())?;
-            let layout = pairs.next().and_then(parse_window_layout).unwrap();
+            let layout = pairs
+                .next()
+                .and_then(parse_window_layout)
+                .ok_or_else(|| anyhow::anyhow!("invalid layout in {}", line))?;
             let visible_layout = pairs.next().and_then(parse_window_layout);
             let raw_flags = pairs.next().map(|r| r.as_str().to_owned());
             Ok(Event::LayoutChange {
@@ -301,6 +430,11 @@ fn parse_line(line: &str) -> anyhow::Result<Event> {
         | Rule::window_id
         | Rule::session_id
         | Rule::window_layout
+        | Rule::layout_pane
+        | Rule::layout_horizontal
+        | Rule::layout_vertical
+        | Rule::layout_cell
+        | Rule::window_layout_entire
         | Rule::any_text
         | Rule::line
         | Rule::line_entire
@@ -654,6 +788,64 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_layout() {
+        let layout: WindowLayout =
+            "de50,161x41,0,0{80x41,0,0,1,80x41,81,0[80x20,81,0,2,80x20,81,21,3]}"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            layout,
+            WindowLayout {
+                layout_id: "de50".to_owned(),
+                width: 161,
+                height: 41,
+                root: WindowLayoutNode::Horizontal {
+                    width: 161,
+                    height: 41,
+                    x: 0,
+                    y: 0,
+                    children: vec![
+                        WindowLayoutNode::Pane {
+                            width: 80,
+                            height: 41,
+                            x: 0,
+                            y: 0,
+                            pane: 1
+                        },
+                        WindowLayoutNode::Vertical {
+                            width: 80,
+                            height: 41,
+                            x: 81,
+                            y: 0,
+                            children: vec![
+                                WindowLayoutNode::Pane {
+                                    width: 80,
+                                    height: 20,
+                                    x: 81,
+                                    y: 0,
+                                    pane: 2
+                                },
+                                WindowLayoutNode::Pane {
+                                    width: 80,
+                                    height: 20,
+                                    x: 81,
+                                    y: 21,
+                                    pane: 3
+                                },
+                            ]
+                        },
+                    ]
+                }
+            }
+        );
+        assert_eq!(layout.root.panes(), vec![1, 2, 3]);
+
+        assert!("de50,161x41,0,0{80x41,0,0,1"
+            .parse::<WindowLayout>()
+            .is_err());
+    }
+
     #[test]
     fn test_parse_sequence() {
         let input = b"%sessions-changed
@@ -670,6 +862,7 @@ here
 %client-detached /dev/pts/10
 %layout-change @1 b25d,80x24,0,0,0
 %layout-change @1 cafd,120x29,0,0,0 cafd,120x29,0,0,0 *
+%layout-change @2 8b9e,120x29,0,0[120x14,0,0,4,120x14,0,15,5] 8b9e,120x29,0,0[120x14,0,0,4,120x14,0,15,5] *
 %output %1 \\033[1m\\033[7m%\\033[27m\\033[1m\\033[0m    \\015 \\015
 %output %1 \\033kwez@cube-localdomain:~\\033\\134\\033]2;wez@cube-localdomain:~\\033\\134
 %output %1 \\033]7;file://cube-localdomain/home/wez\\033\\134
@@ -678,6 +871,34 @@ here
 %exit I said so
 ";
 
+        let two_pane_layout = WindowLayout {
+            layout_id: "8b9e".to_owned(),
+            width: 120,
+            height: 29,
+            root: WindowLayoutNode::Vertical {
+                width: 120,
+                height: 29,
+                x: 0,
+                y: 0,
+                children: vec![
+                    WindowLayoutNode::Pane {
+                        width: 120,
+                        height: 14,
+                        x: 0,
+                        y: 0,
+                        pane: 4,
+                    },
+                    WindowLayoutNode::Pane {
+                        width: 120,
+                        height: 14,
+                        x: 0,
+                        y: 15,
+                        pane: 5,
+                    },
+                ],
+            },
+        };
+
         let mut p = Parser::new();
         let events = p.advance_bytes(input).unwrap();
         assert_eq!(
@@ -710,7 +931,14 @@ here
                     layout: WindowLayout {
                         layout_id: "b25d".to_owned(),
                         width: 80,
-                        height: 24
+                        height: 24,
+                        root: WindowLayoutNode::Pane {
+                            width: 80,
+                            height: 24,
+                            x: 0,
+                            y: 0,
+                            pane: 0
+                        }
                     },
                     visible_layout: None,
                     raw_flags: None
@@ -720,15 +948,35 @@ here
                     layout: WindowLayout {
                         layout_id: "cafd".to_owned(),
                         width: 120,
-                        height: 29
+                        height: 29,
+                        root: WindowLayoutNode::Pane {
+                            width: 120,
+                            height: 29,
+                            x: 0,
+                            y: 0,
+                            pane: 0
+                        }
                     },
                     visible_layout: Some(WindowLayout {
                         layout_id: "cafd".to_owned(),
                         width: 120,
-                        height: 29
+                        height: 29,
+                        root: WindowLayoutNode::Pane {
+                            width: 120,
+                            height: 29,
+                            x: 0,
+                            y: 0,
+                            pane: 0
+                        }
                     }),
                     raw_flags: Some("*".to_owned())
                 },
+                Event::LayoutChange {
+                    window: 2,
+                    layout: two_pane_layout.clone(),
+                    visible_layout: Some(two_pane_layout),
+                    raw_flags: Some("*".to_owned())
+                },
                 Event::Output {
                     pane: 1,
                     text: "\x1b[1m\x1b[7m%\x1b[27m\x1b[1m\x1b[0m    \r \r".to_owned()
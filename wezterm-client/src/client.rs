@@ -220,6 +220,34 @@ fn process_unilateral(
 
             return Ok(());
         }
+        Pdu::TabTitleChanged(TabTitleChanged { tab_id, title }) => {
+            let tab_id = *tab_id;
+            let title = title.to_string();
+            promise::spawn::spawn_into_main_thread(async move {
+                let mux = Mux::get().ok_or_else(|| anyhow!("no more mux"))?;
+                let client_domain = mux
+                    .get_domain(local_domain_id)
+                    .ok_or_else(|| anyhow!("no such domain {}", local_domain_id))?;
+                let client_domain =
+                    client_domain
+                        .downcast_ref::<ClientDomain>()
+                        .ok_or_else(|| {
+                            anyhow!("domain {} is not a ClientDomain instance", local_domain_id)
+                        })?;
+
+                let local_tab_id = client_domain
+                    .remote_to_local_tab_id(tab_id)
+                    .ok_or_else(|| anyhow!("no local tab for remote tab id {}", tab_id))?;
+                if let Some(tab) = mux.get_tab(local_tab_id) {
+                    tab.set_title(&title);
+                }
+
+                anyhow::Result::<()>::Ok(())
+            })
+            .detach();
+
+            return Ok(());
+        }
         _ => {}
     }
 
@@ -1208,7 +1236,29 @@ impl Client {
     rpc!(kill_pane, KillPane, UnitResponse);
     rpc!(set_client_id, SetClientId, UnitResponse);
     rpc!(list_clients, GetClientList, GetClientListResponse);
+    rpc!(get_metrics, GetMetrics, GetMetricsResponse);
+    rpc!(get_pane_commands, GetPaneCommands, GetPaneCommandsResponse);
     rpc!(set_window_workspace, SetWindowWorkspace, UnitResponse);
     rpc!(set_focused_pane_id, SetFocusedPane, UnitResponse);
     rpc!(get_image_cell, GetImageCell, GetImageCellResponse);
+    rpc!(activate_pane, ActivatePane, UnitResponse);
+    rpc!(set_tab_title, SetTabTitle, UnitResponse);
+    rpc!(save_session, SaveSession, UnitResponse);
+    rpc!(
+        swap_active_pane_direction,
+        SwapActivePaneDirection,
+        UnitResponse
+    );
+    rpc!(rotate_panes, RotatePanes, UnitResponse);
+    rpc!(
+        move_pane_to_new_tab,
+        MovePaneToNewTab,
+        MovePaneToNewTabResponse
+    );
+    rpc!(move_pane_to_tab, MovePaneToTab, UnitResponse);
+    rpc!(
+        get_dimensions,
+        GetPaneRenderableDimensions,
+        GetPaneRenderableDimensionsResponse
+    );
 }
@@ -2,8 +2,11 @@ use crate::client::Client;
 use crate::pane::ClientPane;
 use anyhow::{anyhow, bail};
 use async_trait::async_trait;
-use codec::{ListPanesResponse, SpawnV2, SplitPane};
-use config::keyassignment::SpawnTabDomain;
+use codec::{
+    ListPanesResponse, MovePaneToNewTab, MovePaneToTab, RotatePanes, SpawnV2, SplitPane,
+    SwapActivePaneDirection,
+};
+use config::keyassignment::{PaneDirection, RotationDirection, SpawnTabDomain};
 use config::{SshDomain, TlsDomainClient, UnixDomain};
 use mux::connui::{ConnectionUI, ConnectionUIParams};
 use mux::domain::{alloc_domain_id, Domain, DomainId, DomainState};
@@ -14,7 +17,7 @@ use mux::{Mux, MuxNotification};
 use portable_pty::{CommandBuilder, PtySize};
 use promise::spawn::spawn_into_new_thread;
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
@@ -99,6 +102,32 @@ impl ClientInner {
         );
     }
 
+    pub fn local_to_remote_tab_id(&self, local_tab_id: TabId) -> Option<TabId> {
+        let map = self.remote_to_local_tab.lock().unwrap();
+        for (remote, local) in map.iter() {
+            if *local == local_tab_id {
+                return Some(*remote);
+            }
+        }
+        None
+    }
+
+    /// Forgets the mapping for any remote tabs that are not present
+    /// in `remote_tab_ids`, returning the corresponding local tab ids
+    fn remove_stale_tab_mappings(&self, remote_tab_ids: &HashSet<TabId>) -> Vec<TabId> {
+        let mut map = self.remote_to_local_tab.lock().unwrap();
+        let mut stale = vec![];
+        map.retain(|remote, local| {
+            if remote_tab_ids.contains(remote) {
+                true
+            } else {
+                stale.push(*local);
+                false
+            }
+        });
+        stale
+    }
+
     pub fn remote_to_local_tab_id(&self, remote_tab_id: TabId) -> Option<TabId> {
         let map = self.remote_to_local_tab.lock().unwrap();
         for (remote, local) in map.iter() {
@@ -272,6 +301,11 @@ impl ClientDomain {
         inner.remote_to_local_pane_id(remote_pane_id)
     }
 
+    pub fn remote_to_local_tab_id(&self, remote_tab_id: TabId) -> Option<TabId> {
+        let inner = self.inner()?;
+        inner.remote_to_local_tab_id(remote_tab_id)
+    }
+
     pub fn remote_to_local_window_id(&self, remote_window_id: WindowId) -> Option<WindowId> {
         let inner = self.inner()?;
         inner.remote_to_local_window(remote_window_id)
@@ -328,7 +362,16 @@ impl ClientDomain {
         let mux = Mux::get().expect("to be called on main thread");
         log::debug!("ListPanes result {:#?}", panes);
 
+        let remote_tab_ids: HashSet<TabId> = panes
+            .tabs
+            .iter()
+            .filter_map(|tabroot| tabroot.window_and_tab_ids())
+            .map(|(_window_id, tab_id)| tab_id)
+            .collect();
+
+        let mut tab_titles = panes.tab_titles.into_iter();
         for tabroot in panes.tabs {
+            let tab_title = tab_titles.next();
             let root_size = match tabroot.root_size() {
                 Some(size) => size,
                 None => continue,
@@ -412,12 +455,64 @@ impl ClientDomain {
                     inner.record_remote_to_local_window_mapping(remote_window_id, *local_window_id);
                     mux.add_tab_to_window(&tab, *local_window_id)?;
                 }
+
+                if let Some(title) = tab_title {
+                    if tab.get_title() != title {
+                        tab.set_title(&title);
+                    }
+                }
+            }
+        }
+
+        // Tabs that no longer exist on the server, for example because
+        // their only pane was moved into another tab, are removed here.
+        // Their panes are detached rather than killed, as they may have
+        // been adopted by one of the tabs that we just synced.
+        for tab_id in inner.remove_stale_tab_mappings(&remote_tab_ids) {
+            if let Some(tab) = mux.get_tab(tab_id) {
+                for pos in tab.iter_panes_ignoring_zoom() {
+                    tab.remove_pane(pos.pane.pane_id());
+                }
+                mux.remove_tab(tab_id);
             }
         }
 
         Ok(())
     }
 
+    fn remote_pane_id(pane_id: PaneId) -> anyhow::Result<PaneId> {
+        let mux = Mux::get().unwrap();
+        let pane = mux
+            .get_pane(pane_id)
+            .ok_or_else(|| anyhow!("pane_id {} is invalid", pane_id))?;
+        let pane = pane
+            .downcast_ref::<ClientPane>()
+            .ok_or_else(|| anyhow!("pane_id {} is not a ClientPane", pane_id))?;
+        Ok(pane.remote_pane_id)
+    }
+
+    /// Returns the remote tab id and the remote id of the active pane
+    /// of the tab.  The server doesn't track which pane is active in
+    /// our local view of the tab, so we pass it along with requests
+    /// that rearrange panes relative to the active pane.
+    fn remote_tab_and_active_pane(
+        inner: &ClientInner,
+        tab_id: TabId,
+    ) -> anyhow::Result<(TabId, Option<PaneId>)> {
+        let mux = Mux::get().unwrap();
+        let tab = mux
+            .get_tab(tab_id)
+            .ok_or_else(|| anyhow!("tab_id {} is invalid", tab_id))?;
+        let remote_tab_id = inner
+            .local_to_remote_tab_id(tab_id)
+            .ok_or_else(|| anyhow!("tab_id {} has no remote counterpart", tab_id))?;
+        let active_pane_id = match tab.get_active_pane() {
+            Some(pane) => Some(Self::remote_pane_id(pane.pane_id())?),
+            None => None,
+        };
+        Ok((remote_tab_id, active_pane_id))
+    }
+
     fn finish_attach(
         domain_id: DomainId,
         client: Client,
@@ -571,6 +666,104 @@ impl Domain for ClientDomain {
         Ok(pane)
     }
 
+    async fn swap_active_pane_direction(
+        &self,
+        tab_id: TabId,
+        direction: PaneDirection,
+    ) -> anyhow::Result<bool> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+        let (tab_id, active_pane_id) = Self::remote_tab_and_active_pane(&inner, tab_id)?;
+        inner
+            .client
+            .swap_active_pane_direction(SwapActivePaneDirection {
+                tab_id,
+                direction,
+                active_pane_id,
+            })
+            .await?;
+        self.resync().await?;
+        Ok(true)
+    }
+
+    async fn rotate_panes(
+        &self,
+        tab_id: TabId,
+        direction: RotationDirection,
+    ) -> anyhow::Result<bool> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+        let (tab_id, active_pane_id) = Self::remote_tab_and_active_pane(&inner, tab_id)?;
+        inner
+            .client
+            .rotate_panes(RotatePanes {
+                tab_id,
+                direction,
+                active_pane_id,
+            })
+            .await?;
+        self.resync().await?;
+        Ok(true)
+    }
+
+    async fn move_pane_to_new_tab(
+        &self,
+        pane_id: PaneId,
+        window_id: Option<WindowId>,
+        workspace_for_new_window: Option<String>,
+    ) -> anyhow::Result<Option<(Rc<Tab>, WindowId)>> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+
+        let result = inner
+            .client
+            .move_pane_to_new_tab(MovePaneToNewTab {
+                pane_id: Self::remote_pane_id(pane_id)?,
+                window_id: window_id.and_then(|w| inner.local_to_remote_window(w)),
+                workspace_for_new_window,
+            })
+            .await?;
+
+        if let Some(local_window_id) = window_id {
+            // The server may have created a new window if it had no
+            // counterpart to our local window; make sure that the
+            // resync places the new tab into our local window.
+            inner.record_remote_to_local_window_mapping(result.window_id, local_window_id);
+        }
+        self.resync().await?;
+
+        let mux = Mux::get().unwrap();
+        let tab = inner
+            .remote_to_local_tab_id(result.tab_id)
+            .and_then(|tab_id| mux.get_tab(tab_id))
+            .ok_or_else(|| anyhow!("remote tab {} was not synced", result.tab_id))?;
+        let window_id = inner
+            .remote_to_local_window(result.window_id)
+            .ok_or_else(|| anyhow!("remote window {} was not synced", result.window_id))?;
+        Ok(Some((tab, window_id)))
+    }
+
+    async fn move_pane_to_tab(&self, pane_id: PaneId, tab_id: TabId) -> anyhow::Result<bool> {
+        let inner = self
+            .inner()
+            .ok_or_else(|| anyhow!("domain is not attached"))?;
+        let pane_id = Self::remote_pane_id(pane_id)?;
+        let (tab_id, active_pane_id) = Self::remote_tab_and_active_pane(&inner, tab_id)?;
+        inner
+            .client
+            .move_pane_to_tab(MovePaneToTab {
+                pane_id,
+                tab_id,
+                active_pane_id,
+            })
+            .await?;
+        self.resync().await?;
+        Ok(true)
+    }
+
     async fn attach(&self, window_id: Option<WindowId>) -> anyhow::Result<()> {
         if self.state() == DomainState::Attached {
             // Already attached
@@ -22,7 +22,9 @@ use termwiz::input::KeyEvent;
 use termwiz::surface::SequenceNo;
 use url::Url;
 use wezterm_term::color::ColorPalette;
-use wezterm_term::{Alert, Clipboard, KeyCode, KeyModifiers, Line, MouseEvent, StableRowIndex};
+use wezterm_term::{
+    Alert, Clipboard, CommandRecord, KeyCode, KeyModifiers, Line, MouseEvent, StableRowIndex,
+};
 
 pub struct ClientPane {
     client: Arc<ClientInner>,
@@ -326,6 +328,17 @@ impl Pane for ClientPane {
         }
     }
 
+    async fn get_commands(&self) -> anyhow::Result<Vec<CommandRecord>> {
+        let GetPaneCommandsResponse { commands } = self
+            .client
+            .client
+            .get_pane_commands(GetPaneCommands {
+                pane_id: self.remote_pane_id,
+            })
+            .await?;
+        Ok(commands)
+    }
+
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
         let input_serial;
         {
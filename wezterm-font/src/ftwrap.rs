@@ -88,6 +88,64 @@ pub fn compute_load_flags_from_config(
     (load_flags as i32, render)
 }
 
+/// Describes one of the design axes of a variable font
+#[derive(Debug, Clone, PartialEq)]
+pub struct VariationAxis {
+    /// The four character OpenType tag, such as `wght`
+    pub tag: String,
+    /// The human readable name of the axis
+    pub name: String,
+    pub minimum: f64,
+    pub default: f64,
+    pub maximum: f64,
+}
+
+impl std::fmt::Display for VariationAxis {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            fmt,
+            "{}={}..{} (default {})",
+            self.tag, self.minimum, self.maximum, self.default
+        )?;
+        if !self.name.is_empty() {
+            write!(fmt, " {}", self.name)?;
+        }
+        Ok(())
+    }
+}
+
+fn fixed_to_f64(value: FT_Fixed) -> f64 {
+    value as f64 / 65536.0
+}
+
+fn tag_to_string(tag: FT_ULong) -> String {
+    let bytes = (tag as u32).to_be_bytes();
+    String::from_utf8_lossy(&bytes).trim_end().to_string()
+}
+
+/// Parses a variation axis setting of the form `wght=450`,
+/// returning the axis tag and its value
+pub fn parse_variation_axis(setting: &str) -> anyhow::Result<(String, f64)> {
+    let (tag, value) = setting.split_once('=').ok_or_else(|| {
+        anyhow!(
+            "variation axis `{}` must be of the form `tag=value`",
+            setting
+        )
+    })?;
+    let tag = tag.trim();
+    if tag.is_empty() || tag.len() > 4 || !tag.chars().all(|c| c.is_ascii_graphic()) {
+        anyhow::bail!(
+            "variation axis `{}` must have a tag of up to 4 characters",
+            setting
+        );
+    }
+    let value = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|err| anyhow!("variation axis `{}` has an invalid value: {}", setting, err))?;
+    Ok((tag.to_string(), value))
+}
+
 pub struct Face {
     pub face: FT_Face,
     source: FontDataHandle,
@@ -192,6 +250,121 @@ impl Face {
         }
     }
 
+    /// Returns the design axes of a variable font, or an empty
+    /// list if the font is not variable
+    pub fn variation_axes(&self) -> Vec<VariationAxis> {
+        unsafe {
+            if ((*self.face).face_flags & FT_FACE_FLAG_MULTIPLE_MASTERS as FT_Long) == 0 {
+                return vec![];
+            }
+
+            let mut mm = std::ptr::null_mut();
+            if !succeeded(FT_Get_MM_Var(self.face, &mut mm)) {
+                return vec![];
+            }
+
+            let axes = std::slice::from_raw_parts((*mm).axis, (*mm).num_axis as usize)
+                .iter()
+                .map(|axis| VariationAxis {
+                    tag: tag_to_string(axis.tag),
+                    name: if axis.name.is_null() {
+                        String::new()
+                    } else {
+                        CStr::from_ptr(axis.name).to_string_lossy().to_string()
+                    },
+                    minimum: fixed_to_f64(axis.minimum),
+                    default: fixed_to_f64(axis.def),
+                    maximum: fixed_to_f64(axis.maximum),
+                })
+                .collect();
+
+            FT_Done_MM_Var(self.lib, mm);
+            axes
+        }
+    }
+
+    /// Applies explicit values to the design axes of a variable font.
+    /// `settings` are of the form `wght=450`.  Axes that are not
+    /// mentioned keep the values of the named instance that was
+    /// loaded, or their defaults.  Invalid settings, and settings for
+    /// axes that the font doesn't have, are logged and ignored.
+    pub fn set_variation_axes(&mut self, settings: &[String]) -> anyhow::Result<()> {
+        if settings.is_empty() {
+            return Ok(());
+        }
+        let axes = self.variation_axes();
+        if axes.is_empty() {
+            log::warn!(
+                "variation_axes {:?} ignored for {} because it is not a variable font",
+                settings,
+                self.family_name()
+            );
+            return Ok(());
+        }
+
+        let mut coords = vec![0 as FT_Fixed; axes.len()];
+        unsafe {
+            ft_result(
+                FT_Get_Var_Design_Coordinates(
+                    self.face,
+                    coords.len() as FT_UInt,
+                    coords.as_mut_ptr(),
+                ),
+                (),
+            )
+            .context("FT_Get_Var_Design_Coordinates")?;
+        }
+
+        for setting in settings {
+            let (tag, value) = match parse_variation_axis(setting) {
+                Ok(parsed) => parsed,
+                Err(err) => {
+                    log::warn!("{:#}", err);
+                    continue;
+                }
+            };
+            match axes.iter().position(|axis| axis.tag == tag) {
+                Some(idx) => {
+                    let axis = &axes[idx];
+                    let clamped = value.max(axis.minimum).min(axis.maximum);
+                    if clamped != value {
+                        log::warn!(
+                            "variation axis {} of {} ranges from {} to {}; using {} instead of {}",
+                            tag,
+                            self.family_name(),
+                            axis.minimum,
+                            axis.maximum,
+                            clamped,
+                            value
+                        );
+                    }
+                    coords[idx] = (clamped * 65536.0).round() as FT_Fixed;
+                }
+                None => log::warn!(
+                    "{} has no variation axis named {}; it has {}",
+                    self.family_name(),
+                    tag,
+                    axes.iter()
+                        .map(|axis| axis.tag.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            }
+        }
+
+        unsafe {
+            ft_result(
+                FT_Set_Var_Design_Coordinates(
+                    self.face,
+                    coords.len() as FT_UInt,
+                    coords.as_mut_ptr(),
+                ),
+                (),
+            )
+            .context("FT_Set_Var_Design_Coordinates")
+        }
+    }
+
     pub fn get_glyph_name(&self, glyph_index: u32) -> Option<String> {
         let mut buf = [0u8; 128];
         let res = unsafe {
@@ -427,6 +600,9 @@ impl Face {
     pub fn pixel_sizes(&self) -> Vec<u16> {
         let sizes = unsafe {
             let rec = &(*self.face);
+            if rec.available_sizes.is_null() {
+                return vec![];
+            }
             std::slice::from_raw_parts(rec.available_sizes, rec.num_fixed_sizes as usize)
         };
         sizes
@@ -653,6 +829,154 @@ impl Face {
         }
     }
 
+    /// Returns the length of the sfnt table with the specified tag,
+    /// or None if the font doesn't have that table
+    fn sfnt_table_len(&self, tag: &[u8; 4]) -> Option<FT_ULong> {
+        let tag = u32::from_be_bytes(*tag) as FT_ULong;
+        let mut length: FT_ULong = 0;
+        let res = unsafe { FT_Load_Sfnt_Table(self.face, tag, 0, ptr::null_mut(), &mut length) };
+        if succeeded(res) {
+            Some(length)
+        } else {
+            None
+        }
+    }
+
+    pub fn has_sfnt_table(&self, tag: &[u8; 4]) -> bool {
+        self.sfnt_table_len(tag).is_some()
+    }
+
+    /// Returns the raw data of the sfnt table with the specified tag,
+    /// or None if the font doesn't have that table
+    pub fn load_sfnt_table(&self, tag: &[u8; 4]) -> Option<Vec<u8>> {
+        let mut length = self.sfnt_table_len(tag)?;
+        let tag = u32::from_be_bytes(*tag) as FT_ULong;
+        unsafe {
+            let mut data = vec![0u8; length as usize];
+            if !succeeded(FT_Load_Sfnt_Table(
+                self.face,
+                tag,
+                0,
+                data.as_mut_ptr(),
+                &mut length,
+            )) {
+                return None;
+            }
+            Some(data)
+        }
+    }
+
+    /// Returns the number of pixels per font unit in the x and y
+    /// directions for the currently selected size
+    pub fn pixels_per_font_unit(&self) -> (f64, f64) {
+        unsafe {
+            let size = (*self.face).size;
+            if size.is_null() {
+                return (0., 0.);
+            }
+            let metrics = &(*size).metrics;
+            (
+                metrics.x_scale as f64 / (65536.0 * 64.0),
+                metrics.y_scale as f64 / (65536.0 * 64.0),
+            )
+        }
+    }
+
+    /// Loads the unscaled outline of a glyph and applies the transform,
+    /// which maps font units to 26.6 pixel coordinates.
+    /// Returns None if the glyph has no outline.
+    fn load_transformed_outline(
+        &mut self,
+        glyph_index: FT_UInt,
+        matrix: &FT_Matrix,
+        delta: &FT_Vector,
+    ) -> anyhow::Result<Option<&mut FT_Outline>> {
+        unsafe {
+            ft_result(
+                FT_Load_Glyph(
+                    self.face,
+                    glyph_index,
+                    (FT_LOAD_NO_SCALE | FT_LOAD_NO_BITMAP) as i32,
+                ),
+                (),
+            )
+            .with_context(|| format!("FT_Load_Glyph glyph_index:{}", glyph_index))?;
+            let slot = &mut *(*self.face).glyph;
+            if slot.format != FT_Glyph_Format::FT_GLYPH_FORMAT_OUTLINE || slot.outline.n_points == 0
+            {
+                return Ok(None);
+            }
+            FT_Outline_Transform(&slot.outline, matrix);
+            FT_Outline_Translate(&slot.outline, delta.x, delta.y);
+            Ok(Some(&mut slot.outline))
+        }
+    }
+
+    /// Computes the control box of a glyph outline after applying
+    /// the transform, returning (x_min, y_min, x_max, y_max) in pixels
+    pub fn transformed_outline_bounds(
+        &mut self,
+        glyph_index: FT_UInt,
+        matrix: &FT_Matrix,
+        delta: &FT_Vector,
+    ) -> anyhow::Result<Option<(f64, f64, f64, f64)>> {
+        let outline = match self.load_transformed_outline(glyph_index, matrix, delta)? {
+            Some(outline) => outline,
+            None => return Ok(None),
+        };
+        let mut bbox = FT_BBox {
+            xMin: 0,
+            yMin: 0,
+            xMax: 0,
+            yMax: 0,
+        };
+        unsafe {
+            FT_Outline_Get_CBox(outline, &mut bbox);
+        }
+        Ok(Some((
+            bbox.xMin as f64 / 64.,
+            bbox.yMin as f64 / 64.,
+            bbox.xMax as f64 / 64.,
+            bbox.yMax as f64 / 64.,
+        )))
+    }
+
+    /// Renders the coverage of a glyph outline, after applying the
+    /// transform, into an 8-bit mask of the specified dimensions.
+    /// The transformed outline coordinates are relative to the bottom
+    /// left corner of the mask, and the first row of the mask is its
+    /// top row.
+    pub fn render_transformed_outline_mask(
+        &mut self,
+        glyph_index: FT_UInt,
+        matrix: &FT_Matrix,
+        delta: &FT_Vector,
+        width: usize,
+        height: usize,
+    ) -> anyhow::Result<Vec<u8>> {
+        let mut mask = vec![0u8; width * height];
+        let lib = self.lib;
+        let outline = match self.load_transformed_outline(glyph_index, matrix, delta)? {
+            Some(outline) => outline,
+            None => return Ok(mask),
+        };
+        let bitmap = FT_Bitmap {
+            rows: height as _,
+            width: width as _,
+            pitch: width as _,
+            buffer: mask.as_mut_ptr(),
+            num_grays: 256,
+            pixel_mode: FT_Pixel_Mode::FT_PIXEL_MODE_GRAY as _,
+            palette_mode: 0,
+            palette: ptr::null_mut(),
+        };
+        unsafe {
+            ft_result(FT_Outline_Get_Bitmap(lib, outline, &bitmap), ())
+                .context("FT_Outline_Get_Bitmap")?;
+        }
+        Ok(mask)
+    }
+
     /// Compute the cap-height metric in pixels.
     /// This is pixel-perfect based on the rendered glyph data for `I`,
     /// which is a technique that works for any font regardless
@@ -1134,3 +1458,34 @@ pub struct NameRecord {
     pub name_id: u16,
     pub name: String,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn variation_axis_settings() {
+        assert_eq!(
+            parse_variation_axis("wght=450").unwrap(),
+            ("wght".to_string(), 450.)
+        );
+        assert_eq!(
+            parse_variation_axis(" opsz = 12.5").unwrap(),
+            ("opsz".to_string(), 12.5)
+        );
+        assert_eq!(
+            parse_variation_axis("XPN=-1").unwrap(),
+            ("XPN".to_string(), -1.)
+        );
+        assert!(parse_variation_axis("wght").is_err());
+        assert!(parse_variation_axis("weight=400").is_err());
+        assert!(parse_variation_axis("=400").is_err());
+        assert!(parse_variation_axis("wght=bold").is_err());
+    }
+
+    #[test]
+    fn axis_tags() {
+        assert_eq!(tag_to_string(0x77676874), "wght");
+        assert_eq!(tag_to_string(0x58504e20), "XPN");
+    }
+}
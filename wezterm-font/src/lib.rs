@@ -19,6 +19,7 @@ use std::time::Duration;
 use termwiz::cell::Presentation;
 use thiserror::Error;
 use wezterm_bidi::Direction;
+use wezterm_color_types::SrgbaTuple;
 use wezterm_term::CellAttributes;
 use wezterm_toast_notification::ToastNotification;
 
@@ -247,14 +248,31 @@ impl LoadedFont {
         }
     }
 
+    /// Returns true if the font at the specified fallback index has
+    /// color glyphs whose appearance depends on the foreground color
+    pub fn has_colr(&self, font_idx: FallbackIdx) -> bool {
+        self.handles
+            .borrow()
+            .get(font_idx)
+            .map(|p| p.has_colr)
+            .unwrap_or(false)
+    }
+
+    /// Returns true if any of the fonts in the fallback list have
+    /// color glyphs whose appearance depends on the foreground color
+    pub fn has_any_colr(&self) -> bool {
+        self.handles.borrow().iter().any(|p| p.has_colr)
+    }
+
     pub fn rasterize_glyph(
         &self,
         glyph_pos: u32,
         fallback: FallbackIdx,
+        foreground: Option<SrgbaTuple>,
     ) -> anyhow::Result<RasterizedGlyph> {
         let mut rasterizers = self.rasterizers.borrow_mut();
         if let Some(raster) = rasterizers.get(&fallback) {
-            raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi)
+            raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi, foreground)
         } else {
             let raster_selection = self
                 .font_config
@@ -263,7 +281,7 @@ impl LoadedFont {
                     c.config.borrow().font_rasterizer
                 });
             let raster = new_rasterizer(raster_selection, &(self.handles.borrow())[fallback])?;
-            let result = raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi);
+            let result = raster.rasterize_glyph(glyph_pos, self.font_size, self.dpi, foreground);
             rasterizers.insert(fallback, raster);
             result
         }
@@ -406,6 +424,7 @@ impl FallbackResolveInfo {
                     ),
                     url: Some(url.to_string()),
                     timeout: Some(Duration::from_secs(15)),
+                    ..Default::default()
                 }
                 .show();
             } else {
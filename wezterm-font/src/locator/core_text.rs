@@ -194,6 +194,7 @@ fn build_fallback_list_impl() -> anyhow::Result<Vec<ParsedFont>> {
         is_fallback: true,
         is_synthetic: true,
         harfbuzz_features: None,
+        variation_axes: None,
         freetype_load_target: None,
         freetype_render_target: None,
         freetype_load_flags: None,
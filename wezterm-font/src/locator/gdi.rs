@@ -324,6 +324,7 @@ impl FontLocator for GdiFontLocator {
                         is_fallback: true,
                         is_synthetic: true,
                         harfbuzz_features: None,
+                        variation_axes: None,
                         freetype_load_target: None,
                         freetype_render_target: None,
                         freetype_load_flags: None,
@@ -1,3 +1,4 @@
+use crate::ftwrap::VariationAxis;
 use crate::locator::{FontDataHandle, FontDataSource, FontOrigin};
 use crate::shaper::GlyphInfo;
 use config::{FontAttributes, FontStyle, FreeTypeLoadFlags, FreeTypeLoadTarget};
@@ -26,8 +27,13 @@ pub struct ParsedFont {
     pub synthesize_dim: bool,
     pub assume_emoji_presentation: bool,
     pub pixel_sizes: Vec<u16>,
+    /// Whether the font has a COLR table of color glyphs
+    pub has_colr: bool,
+    /// The design axes, if this is a variable font
+    pub axes: Vec<VariationAxis>,
 
     pub harfbuzz_features: Option<Vec<String>>,
+    pub variation_axes: Option<Vec<String>>,
     pub freetype_load_target: Option<FreeTypeLoadTarget>,
     pub freetype_render_target: Option<FreeTypeLoadTarget>,
     pub freetype_load_flags: Option<FreeTypeLoadFlags>,
@@ -48,7 +54,10 @@ impl std::fmt::Debug for ParsedFont {
             .field("synthesize_dim", &self.synthesize_dim)
             .field("assume_emoji_presentation", &self.assume_emoji_presentation)
             .field("pixel_sizes", &self.pixel_sizes)
+            .field("has_colr", &self.has_colr)
+            .field("axes", &self.axes)
             .field("harfbuzz_features", &self.harfbuzz_features)
+            .field("variation_axes", &self.variation_axes)
             .field("freetype_load_target", &self.freetype_load_target)
             .field("freetype_render_target", &self.freetype_render_target)
             .field("freetype_load_flags", &self.freetype_load_flags)
@@ -72,7 +81,10 @@ impl Clone for ParsedFont {
             cap_height: self.cap_height.clone(),
             coverage: Mutex::new(self.coverage.lock().unwrap().clone()),
             pixel_sizes: self.pixel_sizes.clone(),
+            has_colr: self.has_colr,
+            axes: self.axes.clone(),
             harfbuzz_features: self.harfbuzz_features.clone(),
+            variation_axes: self.variation_axes.clone(),
             freetype_load_target: self.freetype_load_target,
             freetype_render_target: self.freetype_render_target,
             freetype_load_flags: self.freetype_load_flags,
@@ -270,6 +282,17 @@ impl ParsedFont {
         }
     }
 
+    /// Returns a description of the design axes of a variable font,
+    /// or an empty string for other fonts
+    pub fn axes_summary(&self) -> String {
+        if self.axes.is_empty() {
+            String::new()
+        } else {
+            let axes: Vec<String> = self.axes.iter().map(|axis| axis.to_string()).collect();
+            format!(" axes=[{}]", axes.join(", "))
+        }
+    }
+
     pub fn lua_name(&self) -> String {
         format!(
             "wezterm.font(\"{}\", {{weight={}, stretch=\"{}\", style=\"{}\"}})",
@@ -296,6 +319,9 @@ impl ParsedFont {
             if !p.pixel_sizes.is_empty() {
                 code.push_str(&format!("  -- Pixel sizes: {:?}\n", p.pixel_sizes));
             }
+            for axis in &p.axes {
+                code.push_str(&format!("  -- Axis: {}\n", axis));
+            }
             for aka in &p.names.aliases {
                 code.push_str(&format!("  -- AKA: \"{}\"\n", aka));
             }
@@ -307,6 +333,7 @@ impl ParsedFont {
                 && p.freetype_load_target.is_none()
                 && p.freetype_load_flags.is_none()
                 && p.harfbuzz_features.is_none()
+                && p.variation_axes.is_none()
                 && p.scale.is_none()
             {
                 code.push_str(&format!("  \"{}\",\n", p.names.family));
@@ -345,6 +372,18 @@ impl ParsedFont {
                     }
                     code.push('}');
                 }
+                if let Some(axes) = &p.variation_axes {
+                    code.push_str(", variation_axes={");
+                    for (idx, a) in axes.iter().enumerate() {
+                        if idx > 0 {
+                            code.push_str(", ");
+                        }
+                        code.push('"');
+                        code.push_str(a);
+                        code.push('"');
+                    }
+                    code.push('}');
+                }
                 code.push_str("},\n")
             }
             code.push_str("\n");
@@ -364,6 +403,8 @@ impl ParsedFont {
         let stretch = FontStretch::from_opentype_stretch(width);
         let cap_height = face.cap_height();
         let pixel_sizes = face.pixel_sizes();
+        let axes = face.variation_axes();
+        let has_colr = face.has_sfnt_table(b"COLR");
         let has_color = unsafe {
             (((*face.face).face_flags as u32) & (crate::ftwrap::FT_FACE_FLAG_COLOR as u32)) != 0
         };
@@ -408,7 +449,10 @@ impl ParsedFont {
             coverage: Mutex::new(RangeSet::new()),
             cap_height,
             pixel_sizes,
+            has_colr,
+            axes,
             harfbuzz_features: None,
+            variation_axes: None,
             freetype_render_target: None,
             freetype_load_target: None,
             freetype_load_flags: None,
@@ -650,6 +694,7 @@ impl ParsedFont {
     /// italic for this font.
     pub fn synthesize(mut self, attr: &FontAttributes) -> Self {
         self.harfbuzz_features = attr.harfbuzz_features.clone();
+        self.variation_axes = attr.variation_axes.clone();
         self.freetype_render_target = attr.freetype_render_target;
         self.freetype_load_target = attr.freetype_load_target;
         self.freetype_load_flags = attr.freetype_load_flags;
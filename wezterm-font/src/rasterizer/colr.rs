@@ -0,0 +1,1679 @@
+//! Rasterizes color glyphs that are described by the COLR and CPAL tables.
+//!
+//! COLRv0 glyphs are a stack of outlines, each filled with a color from
+//! the palette.  COLRv1 glyphs are a graph of paint operations that can
+//! additionally fill with gradients, transform and composite sub-graphs.
+//! FreeType can only produce the former (and only with the first palette),
+//! so we parse the tables ourselves and use FreeType to rasterize the
+//! individual outlines that act as clip masks.
+//!
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/colr>
+//! <https://docs.microsoft.com/en-us/typography/opentype/spec/cpal>
+use crate::ftwrap::{FT_Matrix, FT_Vector, Face};
+use crate::rasterizer::RasterizedGlyph;
+use crate::units::PixelLength;
+use anyhow::{anyhow, bail};
+use wezterm_color_types::{LinearRgba, SrgbaTuple};
+
+/// The palette index that selects the text foreground color
+const FOREGROUND_PALETTE_INDEX: u16 = 0xffff;
+/// CPAL palette type flags
+const USABLE_WITH_LIGHT_BACKGROUND: u32 = 0x1;
+const USABLE_WITH_DARK_BACKGROUND: u32 = 0x2;
+/// Limits the recursion through nested paints, so that a malformed
+/// paint graph cannot overflow the stack
+const MAX_PAINT_DEPTH: usize = 64;
+/// Limits the total number of paints decoded for a glyph.  Paints
+/// can be shared by several parents, so a small table can describe
+/// a graph that expands exponentially when it is decoded as a tree.
+const MAX_PAINTS: usize = 8192;
+/// We refuse to allocate a canvas larger than this in either dimension
+const MAX_CANVAS_SIZE: f64 = 4096.;
+
+/// A color with premultiplied alpha; the color channels are sRGB
+/// encoded, as that is the space in which gradients and blending
+/// are specified to operate
+type Color = [f32; 4];
+
+const TRANSPARENT: Color = [0., 0., 0., 0.];
+
+fn bytes(data: &[u8], offset: usize, len: usize) -> anyhow::Result<&[u8]> {
+    offset
+        .checked_add(len)
+        .and_then(|end| data.get(offset..end))
+        .ok_or_else(|| anyhow!("COLR/CPAL data is truncated at offset {}", offset))
+}
+
+fn read_u8(data: &[u8], offset: usize) -> anyhow::Result<u8> {
+    Ok(bytes(data, offset, 1)?[0])
+}
+
+fn read_u16(data: &[u8], offset: usize) -> anyhow::Result<u16> {
+    let b = bytes(data, offset, 2)?;
+    Ok(u16::from_be_bytes([b[0], b[1]]))
+}
+
+/// Reads an FWORD, a signed quantity in font design units
+fn read_fword(data: &[u8], offset: usize) -> anyhow::Result<f64> {
+    Ok(read_u16(data, offset)? as i16 as f64)
+}
+
+fn read_u24(data: &[u8], offset: usize) -> anyhow::Result<usize> {
+    let b = bytes(data, offset, 3)?;
+    Ok(u32::from_be_bytes([0, b[0], b[1], b[2]]) as usize)
+}
+
+fn read_u32(data: &[u8], offset: usize) -> anyhow::Result<u32> {
+    let b = bytes(data, offset, 4)?;
+    Ok(u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn read_f2dot14(data: &[u8], offset: usize) -> anyhow::Result<f64> {
+    Ok(read_u16(data, offset)? as i16 as f64 / 16384.)
+}
+
+fn read_fixed(data: &[u8], offset: usize) -> anyhow::Result<f64> {
+    Ok(read_u32(data, offset)? as i32 as f64 / 65536.)
+}
+
+/// An affine transformation, laid out in the same way as the COLR
+/// Affine2x3 table: `x' = xx * x + xy * y + dx`, `y' = yx * x + yy * y + dy`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform {
+    pub xx: f64,
+    pub yx: f64,
+    pub xy: f64,
+    pub yy: f64,
+    pub dx: f64,
+    pub dy: f64,
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        xx: 1.,
+        yx: 0.,
+        xy: 0.,
+        yy: 1.,
+        dx: 0.,
+        dy: 0.,
+    };
+
+    pub fn translate(dx: f64, dy: f64) -> Self {
+        Self {
+            dx,
+            dy,
+            ..Self::IDENTITY
+        }
+    }
+
+    pub fn scale(sx: f64, sy: f64) -> Self {
+        Self {
+            xx: sx,
+            yy: sy,
+            ..Self::IDENTITY
+        }
+    }
+
+    /// A counter-clockwise rotation
+    pub fn rotate(degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        Self {
+            xx: cos,
+            yx: sin,
+            xy: -sin,
+            yy: cos,
+            dx: 0.,
+            dy: 0.,
+        }
+    }
+
+    /// A skew, with angles measured counter-clockwise as in PaintSkew
+    pub fn skew(x_degrees: f64, y_degrees: f64) -> Self {
+        Self {
+            xy: -x_degrees.to_radians().tan(),
+            yx: y_degrees.to_radians().tan(),
+            ..Self::IDENTITY
+        }
+    }
+
+    /// Returns the transform that applies `inner` and then `self`
+    pub fn concat(&self, inner: &Self) -> Self {
+        Self {
+            xx: self.xx * inner.xx + self.xy * inner.yx,
+            yx: self.yx * inner.xx + self.yy * inner.yx,
+            xy: self.xx * inner.xy + self.xy * inner.yy,
+            yy: self.yx * inner.xy + self.yy * inner.yy,
+            dx: self.xx * inner.dx + self.xy * inner.dy + self.dx,
+            dy: self.yx * inner.dx + self.yy * inner.dy + self.dy,
+        }
+    }
+
+    /// Returns self applied about the point (cx, cy) rather than the origin
+    pub fn around_center(&self, cx: f64, cy: f64) -> Self {
+        Self::translate(cx, cy)
+            .concat(self)
+            .concat(&Self::translate(-cx, -cy))
+    }
+
+    pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
+        (
+            self.xx * x + self.xy * y + self.dx,
+            self.yx * x + self.yy * y + self.dy,
+        )
+    }
+
+    pub fn invert(&self) -> Option<Self> {
+        let det = self.xx * self.yy - self.xy * self.yx;
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+        let xx = self.yy / det;
+        let yx = -self.yx / det;
+        let xy = -self.xy / det;
+        let yy = self.xx / det;
+        Some(Self {
+            xx,
+            yx,
+            xy,
+            yy,
+            dx: -(xx * self.dx + xy * self.dy),
+            dy: -(yx * self.dx + yy * self.dy),
+        })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Extend {
+    Pad,
+    Repeat,
+    Reflect,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+struct ColorStop {
+    offset: f64,
+    palette_index: u16,
+    alpha: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct ColorLine {
+    extend: Extend,
+    stops: Vec<ColorStop>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum CompositeMode {
+    Clear,
+    Src,
+    Dest,
+    SrcOver,
+    DestOver,
+    SrcIn,
+    DestIn,
+    SrcOut,
+    DestOut,
+    SrcAtop,
+    DestAtop,
+    Xor,
+    Plus,
+    Screen,
+    Overlay,
+    Darken,
+    Lighten,
+    ColorDodge,
+    ColorBurn,
+    HardLight,
+    SoftLight,
+    Difference,
+    Exclusion,
+    Multiply,
+    HslHue,
+    HslSaturation,
+    HslColor,
+    HslLuminosity,
+}
+
+impl CompositeMode {
+    fn from_u8(mode: u8) -> Self {
+        match mode {
+            0 => Self::Clear,
+            1 => Self::Src,
+            2 => Self::Dest,
+            4 => Self::DestOver,
+            5 => Self::SrcIn,
+            6 => Self::DestIn,
+            7 => Self::SrcOut,
+            8 => Self::DestOut,
+            9 => Self::SrcAtop,
+            10 => Self::DestAtop,
+            11 => Self::Xor,
+            12 => Self::Plus,
+            13 => Self::Screen,
+            14 => Self::Overlay,
+            15 => Self::Darken,
+            16 => Self::Lighten,
+            17 => Self::ColorDodge,
+            18 => Self::ColorBurn,
+            19 => Self::HardLight,
+            20 => Self::SoftLight,
+            21 => Self::Difference,
+            22 => Self::Exclusion,
+            23 => Self::Multiply,
+            24 => Self::HslHue,
+            25 => Self::HslSaturation,
+            26 => Self::HslColor,
+            27 => Self::HslLuminosity,
+            // The spec says that unknown modes should behave as src_over
+            _ => Self::SrcOver,
+        }
+    }
+}
+
+/// A decoded node of a COLRv1 paint graph.
+/// COLRv0 glyphs are represented as Layers of Glyphs filled with Solids.
+#[derive(Clone, Debug, PartialEq)]
+enum Paint {
+    Layers(Vec<Paint>),
+    Solid {
+        palette_index: u16,
+        alpha: f32,
+    },
+    LinearGradient {
+        color_line: ColorLine,
+        p0: (f64, f64),
+        p1: (f64, f64),
+        p2: (f64, f64),
+    },
+    RadialGradient {
+        color_line: ColorLine,
+        c0: (f64, f64),
+        r0: f64,
+        c1: (f64, f64),
+        r1: f64,
+    },
+    SweepGradient {
+        color_line: ColorLine,
+        center: (f64, f64),
+        start_angle: f64,
+        end_angle: f64,
+    },
+    Glyph {
+        glyph: u32,
+        paint: Box<Paint>,
+    },
+    Transform {
+        transform: Transform,
+        paint: Box<Paint>,
+    },
+    Composite {
+        source: Box<Paint>,
+        mode: CompositeMode,
+        backdrop: Box<Paint>,
+    },
+}
+
+struct Cpal {
+    data: Vec<u8>,
+    num_entries: usize,
+    color_records: usize,
+    /// The index of the first color record of each palette
+    palettes: Vec<usize>,
+    palette_types: Vec<u32>,
+}
+
+impl Cpal {
+    fn parse(data: Vec<u8>) -> anyhow::Result<Self> {
+        let version = read_u16(&data, 0)?;
+        let num_entries = read_u16(&data, 2)? as usize;
+        let num_palettes = read_u16(&data, 4)? as usize;
+        let color_records = read_u32(&data, 8)? as usize;
+        let mut palettes = vec![];
+        for i in 0..num_palettes {
+            palettes.push(read_u16(&data, 12 + i * 2)? as usize);
+        }
+        let mut palette_types = vec![];
+        if version >= 1 {
+            let types_offset = read_u32(&data, 12 + num_palettes * 2)? as usize;
+            if types_offset != 0 {
+                for i in 0..num_palettes {
+                    palette_types.push(read_u32(&data, types_offset + i * 4)?);
+                }
+            }
+        }
+        Ok(Self {
+            data,
+            num_entries,
+            color_records,
+            palettes,
+            palette_types,
+        })
+    }
+
+    /// Picks the palette designed for the background that is implied
+    /// by the foreground color: light text implies a dark background.
+    /// Falls back to the default palette if the font doesn't indicate
+    /// a suitable palette.
+    fn select_palette(&self, foreground: Option<SrgbaTuple>) -> usize {
+        let wanted = match foreground {
+            Some(fg) => {
+                let fg = fg.to_linear();
+                let black = LinearRgba::with_components(0., 0., 0., 1.);
+                let white = LinearRgba::with_components(1., 1., 1., 1.);
+                if fg.contrast_ratio(black) > fg.contrast_ratio(white) {
+                    USABLE_WITH_DARK_BACKGROUND
+                } else {
+                    USABLE_WITH_LIGHT_BACKGROUND
+                }
+            }
+            None => return 0,
+        };
+        self.palette_types
+            .iter()
+            .position(|t| t & wanted != 0)
+            .unwrap_or(0)
+    }
+
+    fn palette(&self, idx: usize) -> anyhow::Result<Vec<Color>> {
+        let first = match self.palettes.get(idx) {
+            Some(first) => *first,
+            None => return Ok(vec![]),
+        };
+        let mut colors = Vec::with_capacity(self.num_entries);
+        for i in 0..self.num_entries {
+            let bgra = bytes(&self.data, self.color_records + (first + i) * 4, 4)?;
+            let alpha = bgra[3] as f32 / 255.;
+            colors.push([
+                bgra[2] as f32 / 255. * alpha,
+                bgra[1] as f32 / 255. * alpha,
+                bgra[0] as f32 / 255. * alpha,
+                alpha,
+            ]);
+        }
+        Ok(colors)
+    }
+}
+
+/// The parsed COLR and CPAL tables of a font
+pub struct ColorTables {
+    colr: Vec<u8>,
+    cpal: Option<Cpal>,
+    num_base_glyph_records: usize,
+    base_glyph_records: usize,
+    layer_records: usize,
+    num_layer_records: usize,
+    base_glyph_list: Option<usize>,
+    layer_list: Option<usize>,
+    clip_list: Option<usize>,
+}
+
+impl ColorTables {
+    /// Loads the color tables from the face.
+    /// Returns None if the face doesn't have a COLR table.
+    pub fn load(face: &Face) -> anyhow::Result<Option<Self>> {
+        let colr = match face.load_sfnt_table(b"COLR") {
+            Some(colr) => colr,
+            None => return Ok(None),
+        };
+        let cpal = face.load_sfnt_table(b"CPAL").map(Cpal::parse).transpose()?;
+        Self::parse(colr, cpal).map(Some)
+    }
+
+    fn parse(colr: Vec<u8>, cpal: Option<Cpal>) -> anyhow::Result<Self> {
+        let version = read_u16(&colr, 0)?;
+        if version > 1 {
+            bail!("unsupported COLR version {}", version);
+        }
+        let num_base_glyph_records = read_u16(&colr, 2)? as usize;
+        let base_glyph_records = read_u32(&colr, 4)? as usize;
+        let layer_records = read_u32(&colr, 8)? as usize;
+        let num_layer_records = read_u16(&colr, 12)? as usize;
+
+        let optional_offset = |offset| -> anyhow::Result<Option<usize>> {
+            if version == 0 {
+                return Ok(None);
+            }
+            let value = read_u32(&colr, offset)? as usize;
+            Ok(if value == 0 { None } else { Some(value) })
+        };
+        let base_glyph_list = optional_offset(14)?;
+        let layer_list = optional_offset(18)?;
+        let clip_list = optional_offset(22)?;
+
+        Ok(Self {
+            num_base_glyph_records,
+            base_glyph_records,
+            layer_records,
+            num_layer_records,
+            base_glyph_list,
+            layer_list,
+            clip_list,
+            colr,
+            cpal,
+        })
+    }
+
+    /// Binary searches a table of records sorted by their leading
+    /// 16-bit glyph id, returning the offset of the matching record
+    fn find_record(
+        &self,
+        start: usize,
+        count: usize,
+        record_size: usize,
+        glyph: u32,
+    ) -> anyhow::Result<Option<usize>> {
+        let (mut lo, mut hi) = (0, count);
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            let offset = start + mid * record_size;
+            let gid = read_u16(&self.colr, offset)? as u32;
+            if gid == glyph {
+                return Ok(Some(offset));
+            } else if gid < glyph {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns the offset of the COLRv1 root paint for the glyph
+    fn base_paint(&self, glyph: u32) -> anyhow::Result<Option<usize>> {
+        let list = match self.base_glyph_list {
+            Some(list) => list,
+            None => return Ok(None),
+        };
+        let count = read_u32(&self.colr, list)? as usize;
+        match self.find_record(list + 4, count, 6, glyph)? {
+            Some(record) => Ok(Some(list + read_u32(&self.colr, record + 2)? as usize)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the COLRv1 clip box for the glyph, in font units
+    fn clip_box(&self, glyph: u32) -> anyhow::Result<Option<(f64, f64, f64, f64)>> {
+        let list = match self.clip_list {
+            Some(list) => list,
+            None => return Ok(None),
+        };
+        let count = read_u32(&self.colr, list + 1)? as usize;
+        for i in 0..count {
+            let record = list + 5 + i * 7;
+            let start = read_u16(&self.colr, record)? as u32;
+            let end = read_u16(&self.colr, record + 2)? as u32;
+            if (start..=end).contains(&glyph) {
+                let clip = list + read_u24(&self.colr, record + 4)?;
+                return Ok(Some((
+                    read_fword(&self.colr, clip + 1)?,
+                    read_fword(&self.colr, clip + 3)?,
+                    read_fword(&self.colr, clip + 5)?,
+                    read_fword(&self.colr, clip + 7)?,
+                )));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Returns true if the font has color layers or a paint graph
+    /// for the glyph
+    pub fn has_color_glyph(&self, glyph: u32) -> bool {
+        matches!(self.base_paint(glyph), Ok(Some(_)))
+            || matches!(
+                self.find_record(
+                    self.base_glyph_records,
+                    self.num_base_glyph_records,
+                    6,
+                    glyph
+                ),
+                Ok(Some(_))
+            )
+    }
+
+    /// Decodes the paint graph for a glyph, preferring COLRv1 over
+    /// COLRv0 when the font has both
+    fn glyph_paint(&self, glyph: u32) -> anyhow::Result<Option<Paint>> {
+        if let Some(offset) = self.base_paint(glyph)? {
+            let mut state = PaintDecodeState {
+                glyphs: vec![glyph],
+                ..PaintDecodeState::default()
+            };
+            return Ok(Some(self.parse_paint(offset, &mut state)?));
+        }
+        let record = match self.find_record(
+            self.base_glyph_records,
+            self.num_base_glyph_records,
+            6,
+            glyph,
+        )? {
+            Some(record) => record,
+            None => return Ok(None),
+        };
+        let first = read_u16(&self.colr, record + 2)? as usize;
+        let count = read_u16(&self.colr, record + 4)? as usize;
+        if first + count > self.num_layer_records {
+            bail!("COLR base glyph {} references missing layers", glyph);
+        }
+        let mut layers = Vec::with_capacity(count);
+        for i in first..first + count {
+            let layer = self.layer_records + i * 4;
+            layers.push(Paint::Glyph {
+                glyph: read_u16(&self.colr, layer)? as u32,
+                paint: Box::new(Paint::Solid {
+                    palette_index: read_u16(&self.colr, layer + 2)?,
+                    alpha: 1.,
+                }),
+            });
+        }
+        Ok(Some(Paint::Layers(layers)))
+    }
+
+    fn parse_color_line(&self, offset: usize, variable: bool) -> anyhow::Result<ColorLine> {
+        let extend = match read_u8(&self.colr, offset)? {
+            1 => Extend::Repeat,
+            2 => Extend::Reflect,
+            _ => Extend::Pad,
+        };
+        let num_stops = read_u16(&self.colr, offset + 1)? as usize;
+        // VarColorStop has a trailing varIndexBase
+        let stop_size = if variable { 10 } else { 6 };
+        let mut stops = Vec::with_capacity(num_stops);
+        for i in 0..num_stops {
+            let stop = offset + 3 + i * stop_size;
+            stops.push(ColorStop {
+                offset: read_f2dot14(&self.colr, stop)?,
+                palette_index: read_u16(&self.colr, stop + 2)?,
+                alpha: read_f2dot14(&self.colr, stop + 4)? as f32,
+            });
+        }
+        stops.sort_by(|a, b| a.offset.partial_cmp(&b.offset).unwrap());
+        Ok(ColorLine { extend, stops })
+    }
+
+    /// Decodes the paint table at the specified offset, after checking
+    /// that doing so doesn't exceed the limits on the paint graph
+    fn parse_paint(&self, offset: usize, state: &mut PaintDecodeState) -> anyhow::Result<Paint> {
+        if state.path.len() >= MAX_PAINT_DEPTH {
+            bail!("COLR paint graph is nested too deeply");
+        }
+        if state.path.contains(&offset) {
+            bail!("COLR paint graph has a cycle at offset {}", offset);
+        }
+        state.num_paints += 1;
+        if state.num_paints > MAX_PAINTS {
+            bail!("COLR paint graph has too many paints");
+        }
+        state.path.push(offset);
+        let result = self.parse_paint_table(offset, state);
+        state.path.pop();
+        result
+    }
+
+    /// Decodes the child paint whose offset, relative to the parent
+    /// paint, is in the specified field of the parent
+    fn parse_child_paint(
+        &self,
+        offset: usize,
+        field: usize,
+        state: &mut PaintDecodeState,
+    ) -> anyhow::Result<Box<Paint>> {
+        let child = offset + read_u24(&self.colr, offset + field)?;
+        Ok(Box::new(self.parse_paint(child, state)?))
+    }
+
+    /// Decodes the paint table at the specified offset.
+    /// The variable paint formats are decoded using their default
+    /// values, as we don't yet apply variation deltas to paints.
+    fn parse_paint_table(
+        &self,
+        offset: usize,
+        state: &mut PaintDecodeState,
+    ) -> anyhow::Result<Paint> {
+        let data = &self.colr;
+        let point = |field: usize| -> anyhow::Result<(f64, f64)> {
+            Ok((
+                read_fword(data, offset + field)?,
+                read_fword(data, offset + field + 2)?,
+            ))
+        };
+        let f2dot14 = |field: usize| read_f2dot14(data, offset + field);
+
+        let format = read_u8(data, offset)?;
+        match format {
+            1 => {
+                let num_layers = read_u8(data, offset + 1)? as usize;
+                let first = read_u32(data, offset + 2)? as usize;
+                let list = self
+                    .layer_list
+                    .ok_or_else(|| anyhow!("PaintColrLayers used without a LayerList"))?;
+                let mut layers = Vec::with_capacity(num_layers);
+                for i in first..first + num_layers {
+                    let layer = list + read_u32(data, list + 4 + i * 4)? as usize;
+                    layers.push(self.parse_paint(layer, state)?);
+                }
+                Ok(Paint::Layers(layers))
+            }
+            2 | 3 => Ok(Paint::Solid {
+                palette_index: read_u16(data, offset + 1)?,
+                alpha: f2dot14(3)? as f32,
+            }),
+            4 | 5 => Ok(Paint::LinearGradient {
+                color_line: self
+                    .parse_color_line(offset + read_u24(data, offset + 1)?, format == 5)?,
+                p0: point(4)?,
+                p1: point(8)?,
+                p2: point(12)?,
+            }),
+            6 | 7 => Ok(Paint::RadialGradient {
+                color_line: self
+                    .parse_color_line(offset + read_u24(data, offset + 1)?, format == 7)?,
+                c0: point(4)?,
+                r0: read_u16(data, offset + 8)? as f64,
+                c1: point(10)?,
+                r1: read_u16(data, offset + 14)? as f64,
+            }),
+            8 | 9 => Ok(Paint::SweepGradient {
+                color_line: self
+                    .parse_color_line(offset + read_u24(data, offset + 1)?, format == 9)?,
+                center: point(4)?,
+                start_angle: f2dot14(8)? * 180.,
+                end_angle: f2dot14(10)? * 180.,
+            }),
+            10 => Ok(Paint::Glyph {
+                paint: self.parse_child_paint(offset, 1, state)?,
+                glyph: read_u16(data, offset + 4)? as u32,
+            }),
+            11 => {
+                let glyph = read_u16(data, offset + 1)? as u32;
+                if state.glyphs.contains(&glyph) {
+                    bail!("COLR paint graph has a cycle through glyph {}", glyph);
+                }
+                let paint = self.base_paint(glyph)?.ok_or_else(|| {
+                    anyhow!("PaintColrGlyph references glyph {} without a paint", glyph)
+                })?;
+                state.glyphs.push(glyph);
+                let result = self.parse_paint(paint, state);
+                state.glyphs.pop();
+                result
+            }
+            12..=31 => Ok(Paint::Transform {
+                transform: self.parse_transform(format, offset)?,
+                paint: self.parse_child_paint(offset, 1, state)?,
+            }),
+            32 => Ok(Paint::Composite {
+                source: self.parse_child_paint(offset, 1, state)?,
+                mode: CompositeMode::from_u8(read_u8(data, offset + 4)?),
+                backdrop: self.parse_child_paint(offset, 5, state)?,
+            }),
+            _ => bail!("unsupported COLR paint format {}", format),
+        }
+    }
+
+    /// Decodes the transform of one of the transform paint formats
+    fn parse_transform(&self, format: u8, offset: usize) -> anyhow::Result<Transform> {
+        let data = &self.colr;
+        let point = |field: usize| -> anyhow::Result<(f64, f64)> {
+            Ok((
+                read_fword(data, offset + field)?,
+                read_fword(data, offset + field + 2)?,
+            ))
+        };
+        let f2dot14 = |field: usize| read_f2dot14(data, offset + field);
+
+        Ok(match format {
+            12 | 13 => {
+                let affine = offset + read_u24(data, offset + 4)?;
+                Transform {
+                    xx: read_fixed(data, affine)?,
+                    yx: read_fixed(data, affine + 4)?,
+                    xy: read_fixed(data, affine + 8)?,
+                    yy: read_fixed(data, affine + 12)?,
+                    dx: read_fixed(data, affine + 16)?,
+                    dy: read_fixed(data, affine + 20)?,
+                }
+            }
+            14 | 15 => {
+                let (dx, dy) = point(4)?;
+                Transform::translate(dx, dy)
+            }
+            16 | 17 => Transform::scale(f2dot14(4)?, f2dot14(6)?),
+            18 | 19 => {
+                let (cx, cy) = point(8)?;
+                Transform::scale(f2dot14(4)?, f2dot14(6)?).around_center(cx, cy)
+            }
+            20 | 21 => {
+                let scale = f2dot14(4)?;
+                Transform::scale(scale, scale)
+            }
+            22 | 23 => {
+                let scale = f2dot14(4)?;
+                let (cx, cy) = point(6)?;
+                Transform::scale(scale, scale).around_center(cx, cy)
+            }
+            24 | 25 => Transform::rotate(f2dot14(4)? * 180.),
+            26 | 27 => {
+                let (cx, cy) = point(6)?;
+                Transform::rotate(f2dot14(4)? * 180.).around_center(cx, cy)
+            }
+            28 | 29 => Transform::skew(f2dot14(4)? * 180., f2dot14(6)? * 180.),
+            30 | 31 => {
+                let (cx, cy) = point(8)?;
+                Transform::skew(f2dot14(4)? * 180., f2dot14(6)? * 180.).around_center(cx, cy)
+            }
+            _ => bail!("COLR paint format {} is not a transform", format),
+        })
+    }
+
+    /// Rasterizes the color glyph using the currently selected size of
+    /// the face.  The foreground color selects the palette and fills
+    /// the layers that use the foreground palette index.
+    /// When synthesizing italics, the glyph is skewed to match
+    /// the transform that FreeType applies to regular outlines.
+    /// Returns None if the glyph isn't a color glyph.
+    pub fn rasterize(
+        &self,
+        face: &mut Face,
+        glyph: u32,
+        foreground: Option<SrgbaTuple>,
+        synthesize_italic: bool,
+    ) -> anyhow::Result<Option<RasterizedGlyph>> {
+        let paint = match self.glyph_paint(glyph)? {
+            Some(paint) => paint,
+            None => return Ok(None),
+        };
+
+        let (x_scale, y_scale) = face.pixels_per_font_unit();
+        let mut base = Transform::scale(x_scale, y_scale);
+        if synthesize_italic {
+            base = Transform {
+                xy: 0.2,
+                ..Transform::IDENTITY
+            }
+            .concat(&base);
+        }
+
+        let bounds = match self.clip_box(glyph)? {
+            Some((x_min, y_min, x_max, y_max)) => {
+                let mut bounds = Bounds::default();
+                for &(x, y) in &[
+                    (x_min, y_min),
+                    (x_min, y_max),
+                    (x_max, y_min),
+                    (x_max, y_max),
+                ] {
+                    let (x, y) = base.apply(x, y);
+                    bounds.add(x, y, x, y);
+                }
+                bounds
+            }
+            None => {
+                let mut bounds = Bounds::default();
+                compute_bounds(face, &paint, &base, &mut bounds)?;
+                bounds
+            }
+        };
+
+        // The font scale is a 16.16 fixed point value, so edges that
+        // should fall on a pixel boundary can land fractionally past it
+        const SLOP: f64 = 1e-3;
+        let (left, bottom, right, top) = match bounds.rect {
+            Some((x_min, y_min, x_max, y_max)) => (
+                (x_min + SLOP).floor(),
+                (y_min + SLOP).floor(),
+                (x_max - SLOP).ceil(),
+                (y_max - SLOP).ceil(),
+            ),
+            None => (0., 0., 0., 0.),
+        };
+        if right - left > MAX_CANVAS_SIZE || top - bottom > MAX_CANVAS_SIZE {
+            bail!("color glyph {} is too large to rasterize", glyph);
+        }
+
+        let palette = match &self.cpal {
+            Some(cpal) => cpal.palette(cpal.select_palette(foreground))?,
+            None => vec![],
+        };
+        let foreground = match foreground {
+            Some(SrgbaTuple(r, g, b, a)) => [r * a, g * a, b * a, a],
+            None => [1., 1., 1., 1.],
+        };
+
+        let mut canvas = Canvas {
+            face,
+            palette,
+            foreground,
+            left,
+            top,
+            width: (right - left) as usize,
+            height: (top - bottom) as usize,
+        };
+        let mut pixels = canvas.new_layer();
+        canvas.paint(&paint, &base, &mut pixels)?;
+
+        let data = pixels
+            .iter()
+            .flat_map(|color| color.iter().map(|c| (c.clamp(0., 1.) * 255.).round() as u8))
+            .collect();
+
+        Ok(Some(RasterizedGlyph {
+            data,
+            height: canvas.height,
+            width: canvas.width,
+            bearing_x: PixelLength::new(left),
+            bearing_y: PixelLength::new(top),
+            has_color: true,
+        }))
+    }
+}
+
+fn ft_matrix_and_delta(transform: &Transform, left: f64, bottom: f64) -> (FT_Matrix, FT_Vector) {
+    // The outline is in font units and we want 26.6 pixel coordinates
+    let fixed = |v: f64| (v * 64. * 65536.).round() as _;
+    (
+        FT_Matrix {
+            xx: fixed(transform.xx),
+            xy: fixed(transform.xy),
+            yx: fixed(transform.yx),
+            yy: fixed(transform.yy),
+        },
+        FT_Vector {
+            x: ((transform.dx - left) * 64.).round() as _,
+            y: ((transform.dy - bottom) * 64.).round() as _,
+        },
+    )
+}
+
+/// Tracks the decoding of a paint graph, so that a malformed graph
+/// cannot make us loop or expand without bound
+#[derive(Default)]
+struct PaintDecodeState {
+    /// The offsets of the paints from the root to the current paint
+    path: Vec<usize>,
+    /// The glyphs whose paints are being decoded on the current path
+    glyphs: Vec<u32>,
+    /// The number of paints decoded so far
+    num_paints: usize,
+}
+
+#[derive(Default)]
+struct Bounds {
+    rect: Option<(f64, f64, f64, f64)>,
+}
+
+impl Bounds {
+    fn add(&mut self, x_min: f64, y_min: f64, x_max: f64, y_max: f64) {
+        self.rect = Some(match self.rect {
+            Some((a, b, c, d)) => (a.min(x_min), b.min(y_min), c.max(x_max), d.max(y_max)),
+            None => (x_min, y_min, x_max, y_max),
+        });
+    }
+}
+
+/// Accumulates the pixel bounds of the outlines that clip the paint graph.
+/// Fills are unbounded and are only ever visible through an outline,
+/// so only the outlines contribute.
+fn compute_bounds(
+    face: &mut Face,
+    paint: &Paint,
+    transform: &Transform,
+    bounds: &mut Bounds,
+) -> anyhow::Result<()> {
+    match paint {
+        Paint::Layers(layers) => {
+            for layer in layers {
+                compute_bounds(face, layer, transform, bounds)?;
+            }
+        }
+        Paint::Glyph { glyph, .. } => {
+            let (matrix, delta) = ft_matrix_and_delta(transform, 0., 0.);
+            if let Some((x_min, y_min, x_max, y_max)) =
+                face.transformed_outline_bounds(*glyph, &matrix, &delta)?
+            {
+                bounds.add(x_min, y_min, x_max, y_max);
+            }
+        }
+        Paint::Transform {
+            transform: inner,
+            paint,
+        } => {
+            compute_bounds(face, paint, &transform.concat(inner), bounds)?;
+        }
+        Paint::Composite {
+            source, backdrop, ..
+        } => {
+            compute_bounds(face, source, transform, bounds)?;
+            compute_bounds(face, backdrop, transform, bounds)?;
+        }
+        Paint::Solid { .. }
+        | Paint::LinearGradient { .. }
+        | Paint::RadialGradient { .. }
+        | Paint::SweepGradient { .. } => {}
+    }
+    Ok(())
+}
+
+/// The pixel grid that a color glyph is rendered into.
+/// Pixel (0, 0) is the top left; `left` and `top` are the position
+/// of that corner in the pixel coordinate space of the glyph, in which
+/// y increases upwards.
+struct Canvas<'a> {
+    face: &'a mut Face,
+    palette: Vec<Color>,
+    foreground: Color,
+    left: f64,
+    top: f64,
+    width: usize,
+    height: usize,
+}
+
+impl<'a> Canvas<'a> {
+    fn new_layer(&self) -> Vec<Color> {
+        vec![TRANSPARENT; self.width * self.height]
+    }
+
+    fn color(&self, palette_index: u16, alpha: f32) -> Color {
+        let color = if palette_index == FOREGROUND_PALETTE_INDEX {
+            self.foreground
+        } else {
+            self.palette
+                .get(palette_index as usize)
+                .copied()
+                .unwrap_or(TRANSPARENT)
+        };
+        scale(color, alpha)
+    }
+
+    /// Fills the target by evaluating `shader` at the center of
+    /// each pixel, expressed in the coordinate space of the paint
+    fn shade<F: Fn(f64, f64) -> Option<Color>>(
+        &self,
+        transform: &Transform,
+        target: &mut [Color],
+        shader: F,
+    ) {
+        let inverse = match transform.invert() {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        for row in 0..self.height {
+            let y = self.top - row as f64 - 0.5;
+            for col in 0..self.width {
+                let x = self.left + col as f64 + 0.5;
+                let (px, py) = inverse.apply(x, y);
+                if let Some(color) = shader(px, py) {
+                    let dest = &mut target[row * self.width + col];
+                    *dest = src_over(color, *dest);
+                }
+            }
+        }
+    }
+
+    fn paint(
+        &mut self,
+        paint: &Paint,
+        transform: &Transform,
+        target: &mut [Color],
+    ) -> anyhow::Result<()> {
+        match paint {
+            Paint::Layers(layers) => {
+                for layer in layers {
+                    self.paint(layer, transform, target)?;
+                }
+            }
+            Paint::Solid {
+                palette_index,
+                alpha,
+            } => {
+                let color = self.color(*palette_index, *alpha);
+                for dest in target.iter_mut() {
+                    *dest = src_over(color, *dest);
+                }
+            }
+            Paint::LinearGradient {
+                color_line,
+                p0,
+                p1,
+                p2,
+            } => {
+                let stops = self.resolve_stops(color_line);
+                // The color is constant along lines parallel to p0p2;
+                // project p1 onto the perpendicular of p0p2 through p0
+                // to find the end of the gradient vector.
+                let (nx, ny) = (-(p2.1 - p0.1), p2.0 - p0.0);
+                let n_len = nx * nx + ny * ny;
+                if n_len == 0. {
+                    return Ok(());
+                }
+                let k = ((p1.0 - p0.0) * nx + (p1.1 - p0.1) * ny) / n_len;
+                let (vx, vy) = (nx * k, ny * k);
+                let v_len = vx * vx + vy * vy;
+                if v_len == 0. {
+                    return Ok(());
+                }
+                self.shade(transform, target, |x, y| {
+                    let t = ((x - p0.0) * vx + (y - p0.1) * vy) / v_len;
+                    Some(evaluate_color_line(color_line.extend, &stops, t))
+                });
+            }
+            Paint::RadialGradient {
+                color_line,
+                c0,
+                r0,
+                c1,
+                r1,
+            } => {
+                let stops = self.resolve_stops(color_line);
+                self.shade(transform, target, |x, y| {
+                    radial_t(*c0, *r0, *c1, *r1, x, y)
+                        .map(|t| evaluate_color_line(color_line.extend, &stops, t))
+                });
+            }
+            Paint::SweepGradient {
+                color_line,
+                center,
+                start_angle,
+                end_angle,
+            } => {
+                let stops = self.resolve_stops(color_line);
+                let span = end_angle - start_angle;
+                if span == 0. {
+                    return Ok(());
+                }
+                self.shade(transform, target, |x, y| {
+                    let angle = (y - center.1).atan2(x - center.0).to_degrees();
+                    let angle = if angle < 0. { angle + 360. } else { angle };
+                    let t = (angle - start_angle) / span;
+                    Some(evaluate_color_line(color_line.extend, &stops, t))
+                });
+            }
+            Paint::Glyph { glyph, paint } => {
+                let (matrix, delta) =
+                    ft_matrix_and_delta(transform, self.left, self.top - self.height as f64);
+                let mask = self.face.render_transformed_outline_mask(
+                    *glyph,
+                    &matrix,
+                    &delta,
+                    self.width,
+                    self.height,
+                )?;
+                let mut layer = self.new_layer();
+                self.paint(paint, transform, &mut layer)?;
+                for ((dest, src), coverage) in target.iter_mut().zip(layer).zip(mask) {
+                    *dest = src_over(scale(src, coverage as f32 / 255.), *dest);
+                }
+            }
+            Paint::Transform {
+                transform: inner,
+                paint,
+            } => {
+                self.paint(paint, &transform.concat(inner), target)?;
+            }
+            Paint::Composite {
+                source,
+                mode,
+                backdrop,
+            } => {
+                let mut src_layer = self.new_layer();
+                self.paint(source, transform, &mut src_layer)?;
+                let mut layer = self.new_layer();
+                self.paint(backdrop, transform, &mut layer)?;
+                for (backdrop, src) in layer.iter_mut().zip(src_layer) {
+                    *backdrop = composite(*mode, src, *backdrop);
+                }
+                for (dest, src) in target.iter_mut().zip(layer) {
+                    *dest = src_over(src, *dest);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn resolve_stops(&self, color_line: &ColorLine) -> Vec<(f64, Color)> {
+        color_line
+            .stops
+            .iter()
+            .map(|stop| (stop.offset, self.color(stop.palette_index, stop.alpha)))
+            .collect()
+    }
+}
+
+/// Computes the gradient position for a point within a two point
+/// conical gradient, which is the largest t for which the point lies
+/// on the circle interpolated between the two circles at t, and for
+/// which the interpolated radius is non-negative.
+fn radial_t(c0: (f64, f64), r0: f64, c1: (f64, f64), r1: f64, x: f64, y: f64) -> Option<f64> {
+    let (cdx, cdy) = (c1.0 - c0.0, c1.1 - c0.1);
+    let (pdx, pdy) = (x - c0.0, y - c0.1);
+    let dr = r1 - r0;
+    let a = cdx * cdx + cdy * cdy - dr * dr;
+    let b = pdx * cdx + pdy * cdy + r0 * dr;
+    let c = pdx * pdx + pdy * pdy - r0 * r0;
+    let radius_ok = |t: f64| r0 + t * dr >= 0.;
+
+    if a.abs() < 1e-9 {
+        if b == 0. {
+            return None;
+        }
+        let t = c / (2. * b);
+        return if radius_ok(t) { Some(t) } else { None };
+    }
+
+    let discriminant = b * b - a * c;
+    if discriminant < 0. {
+        return None;
+    }
+    let root = discriminant.sqrt();
+    let t1 = (b + root) / a;
+    let t2 = (b - root) / a;
+    let (hi, lo) = if t1 > t2 { (t1, t2) } else { (t2, t1) };
+    if radius_ok(hi) {
+        Some(hi)
+    } else if radius_ok(lo) {
+        Some(lo)
+    } else {
+        None
+    }
+}
+
+/// Computes the color at position t along the color line
+fn evaluate_color_line(extend: Extend, stops: &[(f64, Color)], t: f64) -> Color {
+    let (first, last) = match (stops.first(), stops.last()) {
+        (Some(first), Some(last)) => (first.0, last.0),
+        _ => return TRANSPARENT,
+    };
+    let len = last - first;
+    let t = if len > 0. {
+        match extend {
+            Extend::Pad => t,
+            Extend::Repeat => {
+                let u = (t - first) / len;
+                first + (u - u.floor()) * len
+            }
+            Extend::Reflect => {
+                let u = ((t - first) / len).rem_euclid(2.);
+                let u = if u > 1. { 2. - u } else { u };
+                first + u * len
+            }
+        }
+    } else {
+        t
+    };
+
+    if t <= first {
+        return stops[0].1;
+    }
+    if t >= last {
+        return stops[stops.len() - 1].1;
+    }
+    for pair in stops.windows(2) {
+        let (a, b) = (&pair[0], &pair[1]);
+        if t <= b.0 {
+            let span = b.0 - a.0;
+            if span <= 0. {
+                return b.1;
+            }
+            let f = ((t - a.0) / span) as f32;
+            let lerp = |i: usize| a.1[i] + (b.1[i] - a.1[i]) * f;
+            return [lerp(0), lerp(1), lerp(2), lerp(3)];
+        }
+    }
+    stops[stops.len() - 1].1
+}
+
+fn scale(color: Color, factor: f32) -> Color {
+    [
+        color[0] * factor,
+        color[1] * factor,
+        color[2] * factor,
+        color[3] * factor,
+    ]
+}
+
+fn src_over(src: Color, dest: Color) -> Color {
+    let inv = 1. - src[3];
+    [
+        src[0] + dest[0] * inv,
+        src[1] + dest[1] * inv,
+        src[2] + dest[2] * inv,
+        src[3] + dest[3] * inv,
+    ]
+}
+
+/// Porter-Duff compositing: the result is `src * fa + dest * fb`
+fn porter_duff(src: Color, dest: Color, fa: f32, fb: f32) -> Color {
+    [
+        src[0] * fa + dest[0] * fb,
+        src[1] * fa + dest[1] * fb,
+        src[2] * fa + dest[2] * fb,
+        src[3] * fa + dest[3] * fb,
+    ]
+}
+
+fn unpremultiply(color: Color) -> [f32; 3] {
+    if color[3] <= 0. {
+        [0., 0., 0.]
+    } else {
+        [
+            color[0] / color[3],
+            color[1] / color[3],
+            color[2] / color[3],
+        ]
+    }
+}
+
+/// Blends src onto dest using a blend function that is defined on
+/// non-premultiplied colors, as described in
+/// <https://www.w3.org/TR/compositing-1/#blending>
+fn blend<F: Fn([f32; 3], [f32; 3]) -> [f32; 3]>(src: Color, dest: Color, func: F) -> Color {
+    let (sa, da) = (src[3], dest[3]);
+    let blended = func(unpremultiply(src), unpremultiply(dest));
+    let channel = |i: usize| (1. - da) * src[i] + (1. - sa) * dest[i] + sa * da * blended[i];
+    [channel(0), channel(1), channel(2), sa + da - sa * da]
+}
+
+fn separable<F: Fn(f32, f32) -> f32>(func: F) -> impl Fn([f32; 3], [f32; 3]) -> [f32; 3] {
+    move |s, d| [func(s[0], d[0]), func(s[1], d[1]), func(s[2], d[2])]
+}
+
+fn screen(s: f32, d: f32) -> f32 {
+    s + d - s * d
+}
+
+fn hard_light(s: f32, d: f32) -> f32 {
+    if s <= 0.5 {
+        d * 2. * s
+    } else {
+        screen(2. * s - 1., d)
+    }
+}
+
+fn color_dodge(s: f32, d: f32) -> f32 {
+    if d <= 0. {
+        0.
+    } else if s >= 1. {
+        1.
+    } else {
+        (d / (1. - s)).min(1.)
+    }
+}
+
+fn color_burn(s: f32, d: f32) -> f32 {
+    if d >= 1. {
+        1.
+    } else if s <= 0. {
+        0.
+    } else {
+        1. - ((1. - d) / s).min(1.)
+    }
+}
+
+fn soft_light(s: f32, d: f32) -> f32 {
+    if s <= 0.5 {
+        d - (1. - 2. * s) * d * (1. - d)
+    } else {
+        let dd = if d <= 0.25 {
+            ((16. * d - 12.) * d + 4.) * d
+        } else {
+            d.sqrt()
+        };
+        d + (2. * s - 1.) * (dd - d)
+    }
+}
+
+fn lum(c: [f32; 3]) -> f32 {
+    0.3 * c[0] + 0.59 * c[1] + 0.11 * c[2]
+}
+
+fn clip_color(c: [f32; 3]) -> [f32; 3] {
+    let l = lum(c);
+    let n = c[0].min(c[1]).min(c[2]);
+    let x = c[0].max(c[1]).max(c[2]);
+    let mut c = c;
+    if n < 0. {
+        for v in c.iter_mut() {
+            *v = l + (*v - l) * l / (l - n);
+        }
+    }
+    if x > 1. {
+        for v in c.iter_mut() {
+            *v = l + (*v - l) * (1. - l) / (x - l);
+        }
+    }
+    c
+}
+
+fn set_lum(c: [f32; 3], l: f32) -> [f32; 3] {
+    let d = l - lum(c);
+    clip_color([c[0] + d, c[1] + d, c[2] + d])
+}
+
+fn sat(c: [f32; 3]) -> f32 {
+    c[0].max(c[1]).max(c[2]) - c[0].min(c[1]).min(c[2])
+}
+
+fn set_sat(c: [f32; 3], s: f32) -> [f32; 3] {
+    let max = c[0].max(c[1]).max(c[2]);
+    let min = c[0].min(c[1]).min(c[2]);
+    if max <= min {
+        return [0., 0., 0.];
+    }
+    let channel = |i: usize| (c[i] - min) * s / (max - min);
+    [channel(0), channel(1), channel(2)]
+}
+
+fn composite(mode: CompositeMode, src: Color, dest: Color) -> Color {
+    use CompositeMode::*;
+    let (sa, da) = (src[3], dest[3]);
+    match mode {
+        Clear => TRANSPARENT,
+        Src => src,
+        Dest => dest,
+        SrcOver => src_over(src, dest),
+        DestOver => src_over(dest, src),
+        SrcIn => porter_duff(src, dest, da, 0.),
+        DestIn => porter_duff(src, dest, 0., sa),
+        SrcOut => porter_duff(src, dest, 1. - da, 0.),
+        DestOut => porter_duff(src, dest, 0., 1. - sa),
+        SrcAtop => porter_duff(src, dest, da, 1. - sa),
+        DestAtop => porter_duff(src, dest, 1. - da, sa),
+        Xor => porter_duff(src, dest, 1. - da, 1. - sa),
+        Plus => {
+            let c = porter_duff(src, dest, 1., 1.);
+            [c[0].min(1.), c[1].min(1.), c[2].min(1.), c[3].min(1.)]
+        }
+        Screen => blend(src, dest, separable(screen)),
+        Overlay => blend(src, dest, separable(|s, d| hard_light(d, s))),
+        Darken => blend(src, dest, separable(f32::min)),
+        Lighten => blend(src, dest, separable(f32::max)),
+        ColorDodge => blend(src, dest, separable(color_dodge)),
+        ColorBurn => blend(src, dest, separable(color_burn)),
+        HardLight => blend(src, dest, separable(hard_light)),
+        SoftLight => blend(src, dest, separable(soft_light)),
+        Difference => blend(src, dest, separable(|s, d| (s - d).abs())),
+        Exclusion => blend(src, dest, separable(|s, d| s + d - 2. * s * d)),
+        Multiply => blend(src, dest, separable(|s, d| s * d)),
+        HslHue => blend(src, dest, |s, d| set_lum(set_sat(s, sat(d)), lum(d))),
+        HslSaturation => blend(src, dest, |s, d| set_lum(set_sat(d, sat(s)), lum(d))),
+        HslColor => blend(src, dest, |s, d| set_lum(s, lum(d))),
+        HslLuminosity => blend(src, dest, |s, d| set_lum(d, lum(s))),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ftwrap::{FT_Get_Char_Index, Library};
+    use crate::locator::{FontDataHandle, FontDataSource, FontOrigin};
+    use crate::parser::ParsedFont;
+    use crate::rasterizer::freetype::FreeTypeRasterizer;
+    use crate::rasterizer::FontRasterizer;
+    use std::path::PathBuf;
+
+    // These fonts are generated by test-data/colr/make_fonts.py
+    const COLRV0: &[u8] = include_bytes!("../../test-data/colr/colrv0-test.ttf");
+    const COLRV1: &[u8] = include_bytes!("../../test-data/colr/colrv1-test.ttf");
+
+    const DARK_FG: SrgbaTuple = SrgbaTuple(0.1, 0.1, 0.1, 1.);
+    const LIGHT_FG: SrgbaTuple = SrgbaTuple(0.9, 0.9, 0.8, 1.);
+
+    struct TestFont {
+        raster: FreeTypeRasterizer,
+        face: Face,
+        _lib: Library,
+    }
+
+    impl TestFont {
+        fn load(name: &'static str, data: &'static [u8]) -> Self {
+            let handle = FontDataHandle {
+                source: FontDataSource::BuiltIn { data, name },
+                index: 0,
+                variation: 0,
+                origin: FontOrigin::BuiltIn,
+                coverage: None,
+            };
+            let lib = Library::new().unwrap();
+            let face = lib.face_from_locator(&handle).unwrap();
+            let parsed = ParsedFont::from_face(&face, handle).unwrap();
+            assert!(parsed.has_colr);
+            Self {
+                raster: FreeTypeRasterizer::from_locator(&parsed).unwrap(),
+                face,
+                _lib: lib,
+            }
+        }
+
+        fn glyph(&self, c: char) -> u32 {
+            let glyph = unsafe { FT_Get_Char_Index(self.face.face, c as _) };
+            assert_ne!(glyph, 0, "no glyph for {}", c);
+            glyph
+        }
+
+        fn rasterize(&self, c: char, foreground: SrgbaTuple) -> RasterizedGlyph {
+            // 24pt at 96dpi is 32 pixels per em
+            self.raster
+                .rasterize_glyph(self.glyph(c), 24., 96, Some(foreground))
+                .unwrap()
+        }
+    }
+
+    /// Compares the glyph against the golden image of the same name,
+    /// or writes the golden image when WEZTERM_FONT_BLESS is set
+    fn check_golden(name: &str, glyph: &RasterizedGlyph) {
+        assert!(glyph.has_color, "{} should be a color glyph", name);
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test-data/colr")
+            .join(format!("{}.png", name));
+        if std::env::var_os("WEZTERM_FONT_BLESS").is_some() {
+            image::RgbaImage::from_raw(glyph.width as u32, glyph.height as u32, glyph.data.clone())
+                .unwrap()
+                .save(&path)
+                .unwrap();
+            return;
+        }
+
+        let golden = image::open(&path)
+            .unwrap_or_else(|err| panic!("loading {}: {}", path.display(), err))
+            .to_rgba8();
+        assert_eq!(
+            (golden.width() as usize, golden.height() as usize),
+            (glyph.width, glyph.height),
+            "{} has different dimensions from its golden image",
+            name
+        );
+        // Allow for small differences in rounding between platforms
+        let worst = golden
+            .as_raw()
+            .iter()
+            .zip(&glyph.data)
+            .map(|(a, b)| (*a as i16 - *b as i16).abs())
+            .max()
+            .unwrap_or(0);
+        assert!(
+            worst <= 2,
+            "{} differs from its golden image by up to {}",
+            name,
+            worst
+        );
+    }
+
+    #[test]
+    fn colrv0_layers() {
+        let font = TestFont::load("colrv0-test.ttf", COLRV0);
+        check_golden("colrv0-A", &font.rasterize('A', DARK_FG));
+        // A single palette is used regardless of the foreground,
+        // but the layers of B that use the foreground color change
+        check_golden("colrv0-B-dark-fg", &font.rasterize('B', DARK_FG));
+        check_golden("colrv0-B-light-fg", &font.rasterize('B', LIGHT_FG));
+    }
+
+    #[test]
+    fn colrv1_paints() {
+        let font = TestFont::load("colrv1-test.ttf", COLRV1);
+        for &c in &['L', 'R', 'S', 'T', 'C', 'M', 'G'] {
+            check_golden(
+                &format!("colrv1-{}-dark-fg", c),
+                &font.rasterize(c, DARK_FG),
+            );
+            check_golden(
+                &format!("colrv1-{}-light-fg", c),
+                &font.rasterize(c, LIGHT_FG),
+            );
+        }
+    }
+
+    #[test]
+    fn clip_box_bounds() {
+        let font = TestFont::load("colrv1-test.ttf", COLRV1);
+        // The clip box of L is (0, -200) - (1000, 800) in font units,
+        // which is (0, -6.4) - (32, 25.6) in pixels
+        let glyph = font.rasterize('L', DARK_FG);
+        assert_eq!((glyph.width, glyph.height), (32, 33));
+        assert_eq!(glyph.bearing_x.get(), 0.);
+        assert_eq!(glyph.bearing_y.get(), 26.);
+    }
+
+    #[test]
+    fn palette_selection() {
+        let font = TestFont::load("colrv1-test.ttf", COLRV1);
+        let tables = ColorTables::load(&font.face).unwrap().unwrap();
+        let cpal = tables.cpal.as_ref().unwrap();
+        assert_eq!(cpal.select_palette(None), 0);
+        assert_eq!(cpal.select_palette(Some(DARK_FG)), 0);
+        assert_eq!(cpal.select_palette(Some(LIGHT_FG)), 1);
+
+        assert!(tables.has_color_glyph(font.glyph('L')));
+        // Glyph 1 is one of the plain outlines used by the layers
+        assert!(!tables.has_color_glyph(1));
+    }
+
+    /// Builds a COLRv1 table from paint tables.  The base glyph
+    /// records and the layer list refer to the paints by index.
+    fn colrv1_table(base: &[(u16, usize)], layers: &[usize], paints: &[Vec<u8>]) -> ColorTables {
+        let base_list = 34;
+        let layer_list = base_list + 4 + base.len() * 6;
+        let mut offsets = vec![];
+        let mut offset = layer_list + 4 + layers.len() * 4;
+        for paint in paints {
+            offsets.push(offset);
+            offset += paint.len();
+        }
+
+        let mut colr = vec![];
+        colr.extend_from_slice(&1u16.to_be_bytes());
+        // No COLRv0 base glyph or layer records
+        colr.extend_from_slice(&[0; 12]);
+        colr.extend_from_slice(&(base_list as u32).to_be_bytes());
+        colr.extend_from_slice(&(layer_list as u32).to_be_bytes());
+        // No clip list or variations
+        colr.extend_from_slice(&[0; 12]);
+
+        colr.extend_from_slice(&(base.len() as u32).to_be_bytes());
+        for &(glyph, paint) in base {
+            colr.extend_from_slice(&glyph.to_be_bytes());
+            colr.extend_from_slice(&((offsets[paint] - base_list) as u32).to_be_bytes());
+        }
+        colr.extend_from_slice(&(layers.len() as u32).to_be_bytes());
+        for &paint in layers {
+            colr.extend_from_slice(&((offsets[paint] - layer_list) as u32).to_be_bytes());
+        }
+        for paint in paints {
+            colr.extend_from_slice(paint);
+        }
+        ColorTables::parse(colr, None).unwrap()
+    }
+
+    fn paint_colr_layers(num_layers: u8, first: u32) -> Vec<u8> {
+        let mut paint = vec![1, num_layers];
+        paint.extend_from_slice(&first.to_be_bytes());
+        paint
+    }
+
+    fn paint_colr_glyph(glyph: u16) -> Vec<u8> {
+        let mut paint = vec![11];
+        paint.extend_from_slice(&glyph.to_be_bytes());
+        paint
+    }
+
+    const PAINT_SOLID: [u8; 5] = [2, 0, 0, 0x40, 0];
+
+    #[test]
+    fn paint_graph_limits() {
+        // Glyph 2 is shared by both layers of glyph 1, which is fine
+        let tables = colrv1_table(
+            &[(1, 0), (2, 1)],
+            &[2, 2],
+            &[
+                paint_colr_layers(2, 0),
+                PAINT_SOLID.to_vec(),
+                paint_colr_glyph(2),
+            ],
+        );
+        match tables.glyph_paint(1).unwrap() {
+            Some(Paint::Layers(layers)) => assert_eq!(layers.len(), 2),
+            _ => panic!("expected the layers of glyph 1"),
+        }
+
+        // Glyphs 1 and 2 paint each other
+        let tables = colrv1_table(
+            &[(1, 0), (2, 1)],
+            &[],
+            &[paint_colr_glyph(2), paint_colr_glyph(1)],
+        );
+        let err = tables.glyph_paint(1).unwrap_err().to_string();
+        assert!(err.contains("cycle"), "{}", err);
+
+        // A PaintColrLayers that includes itself
+        let tables = colrv1_table(&[(1, 0)], &[0], &[paint_colr_layers(1, 0)]);
+        let err = tables.glyph_paint(1).unwrap_err().to_string();
+        assert!(err.contains("cycle"), "{}", err);
+
+        // Each level shares 255 layers, so the graph would expand
+        // to tens of millions of paints
+        let layers: Vec<usize> = std::iter::repeat(2)
+            .take(255)
+            .chain(std::iter::repeat(3).take(255))
+            .chain(std::iter::repeat(4).take(255))
+            .collect();
+        let tables = colrv1_table(
+            &[(1, 0), (2, 1), (3, 5), (4, 6)],
+            &layers,
+            &[
+                paint_colr_layers(255, 0),
+                paint_colr_layers(255, 255),
+                paint_colr_glyph(2),
+                paint_colr_glyph(3),
+                paint_colr_glyph(4),
+                paint_colr_layers(255, 510),
+                PAINT_SOLID.to_vec(),
+            ],
+        );
+        let err = tables.glyph_paint(1).unwrap_err().to_string();
+        assert!(err.contains("too many paints"), "{}", err);
+    }
+
+    #[test]
+    fn transforms() {
+        let t = Transform::rotate(90.).around_center(10., 10.);
+        let (x, y) = t.apply(20., 10.);
+        assert!((x - 10.).abs() < 1e-9 && (y - 20.).abs() < 1e-9);
+
+        let t = Transform::skew(10., 20.)
+            .concat(&Transform::scale(2., 3.))
+            .concat(&Transform::translate(5., -7.));
+        let (x, y) = t.apply(3., 4.);
+        let (x, y) = t.invert().unwrap().apply(x, y);
+        assert!((x - 3.).abs() < 1e-9 && (y - 4.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn color_line_extend() {
+        let stops = vec![(0.25, [0., 0., 0., 1.]), (0.75, [1., 1., 1., 1.])];
+        let gray = |extend, t| evaluate_color_line(extend, &stops, t)[0];
+        assert_eq!(gray(Extend::Pad, 0.), 0.);
+        assert_eq!(gray(Extend::Pad, 0.5), 0.5);
+        assert_eq!(gray(Extend::Pad, 1.), 1.);
+        assert_eq!(gray(Extend::Repeat, 0.875), 0.25);
+        assert_eq!(gray(Extend::Reflect, 0.875), 0.75);
+        assert_eq!(gray(Extend::Reflect, 0.125), 0.25);
+    }
+}
@@ -1,4 +1,5 @@
 use crate::parser::ParsedFont;
+use crate::rasterizer::colr::ColorTables;
 use crate::rasterizer::FontRasterizer;
 use crate::units::*;
 use crate::{ftwrap, RasterizedGlyph};
@@ -7,10 +8,11 @@ use anyhow::bail;
 use config::{FreeTypeLoadFlags, FreeTypeLoadTarget};
 use std::cell::RefCell;
 use std::{mem, slice};
-use wezterm_color_types::linear_u8_to_srgb8;
+use wezterm_color_types::{linear_u8_to_srgb8, SrgbaTuple};
 
 pub struct FreeTypeRasterizer {
     has_color: bool,
+    color_tables: Option<ColorTables>,
     face: RefCell<ftwrap::Face>,
     _lib: ftwrap::Library,
     synthesize_bold: bool,
@@ -27,11 +29,16 @@ impl FontRasterizer for FreeTypeRasterizer {
         glyph_pos: u32,
         size: f64,
         dpi: u32,
+        foreground: Option<SrgbaTuple>,
     ) -> anyhow::Result<RasterizedGlyph> {
         self.face
             .borrow_mut()
             .set_font_size(size * self.scale, dpi)?;
 
+        if let Some(glyph) = self.rasterize_color_glyph(glyph_pos, foreground)? {
+            return Ok(glyph);
+        }
+
         let (load_flags, render_mode) = ftwrap::compute_load_flags_from_config(
             self.freetype_load_flags,
             self.freetype_load_target,
@@ -73,6 +80,30 @@ impl FontRasterizer for FreeTypeRasterizer {
 }
 
 impl FreeTypeRasterizer {
+    /// Renders the glyph from the COLR table, if the font has one
+    /// that describes this glyph
+    fn rasterize_color_glyph(
+        &self,
+        glyph_pos: u32,
+        foreground: Option<SrgbaTuple>,
+    ) -> anyhow::Result<Option<RasterizedGlyph>> {
+        let tables = match &self.color_tables {
+            Some(tables) if tables.has_color_glyph(glyph_pos) => tables,
+            _ => return Ok(None),
+        };
+        let mut face = self.face.borrow_mut();
+        // The color glyph applies the italic skew itself, as it
+        // also needs to skew its gradients
+        if self.synthesize_italic {
+            face.set_transform(None);
+        }
+        let result = tables.rasterize(&mut face, glyph_pos, foreground, self.synthesize_italic);
+        if self.synthesize_italic {
+            face.set_transform(Some(italic_transform()));
+        }
+        result
+    }
+
     fn rasterize_mono(
         &self,
         pitch: usize,
@@ -299,23 +330,30 @@ impl FreeTypeRasterizer {
         log::trace!("Rasterizier wants {:?}", parsed);
         let lib = ftwrap::Library::new()?;
         let mut face = lib.face_from_locator(&parsed.handle)?;
+        if let Some(axes) = &parsed.variation_axes {
+            face.set_variation_axes(axes)?;
+        }
         let has_color = unsafe {
             (((*face.face).face_flags as u32) & (ftwrap::FT_FACE_FLAG_COLOR as u32)) != 0
         };
 
+        let color_tables = match ColorTables::load(&face) {
+            Ok(tables) => tables,
+            Err(err) => {
+                log::warn!("Ignoring the color glyphs in {:?}: {:#}", parsed, err);
+                None
+            }
+        };
+
         if parsed.synthesize_italic {
-            face.set_transform(Some(FT_Matrix {
-                xx: 1 * 65536,            // scale x
-                yy: 1 * 65536,            // scale y
-                xy: (0.2 * 65536.0) as _, // skew x
-                yx: 0 * 65536,            // skey y
-            }));
+            face.set_transform(Some(italic_transform()));
         }
 
         Ok(Self {
             _lib: lib,
             face: RefCell::new(face),
             has_color,
+            color_tables,
             synthesize_bold: parsed.synthesize_bold,
             synthesize_italic: parsed.synthesize_italic,
             freetype_load_flags: parsed.freetype_load_flags,
@@ -325,3 +363,12 @@ impl FreeTypeRasterizer {
         })
     }
 }
+
+fn italic_transform() -> FT_Matrix {
+    FT_Matrix {
+        xx: 65536,                // scale x
+        yy: 65536,                // scale y
+        xy: (0.2 * 65536.0) as _, // skew x
+        yx: 0,                    // skew y
+    }
+}
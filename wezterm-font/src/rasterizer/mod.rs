@@ -1,7 +1,9 @@
 use crate::parser::ParsedFont;
 use crate::units::*;
 use config::FontRasterizerSelection;
+use wezterm_color_types::SrgbaTuple;
 
+pub mod colr;
 pub mod freetype;
 
 /// A bitmap representation of a glyph.
@@ -76,13 +78,17 @@ impl RasterizedGlyph {
 }
 
 /// Rasterizes the specified glyph index in the associated font
-/// and returns the generated bitmap
+/// and returns the generated bitmap.
+/// The foreground color is used by color glyphs that pick their
+/// palette to suit the text color, or that are partially painted
+/// using the text color.
 pub trait FontRasterizer {
     fn rasterize_glyph(
         &self,
         glyph_pos: u32,
         size: f64,
         dpi: u32,
+        foreground: Option<SrgbaTuple>,
     ) -> anyhow::Result<RasterizedGlyph>;
 }
 
@@ -137,7 +137,13 @@ impl HarfbuzzShaper {
                 if opt_pair.is_none() {
                     let handle = &self.handles[font_idx];
                     log::trace!("shaper wants {} {:?}", font_idx, handle);
-                    let face = self.lib.face_from_locator(&handle.handle)?;
+                    let mut face = self.lib.face_from_locator(&handle.handle)?;
+                    if let Some(axes) = &handle.variation_axes {
+                        face.set_variation_axes(axes)?;
+                    }
+                    // The harfbuzz font picks up the variation coordinates
+                    // of the face as it is created, so that shaping uses
+                    // the same glyph metrics as the rasterizer
                     let mut font = harfbuzz::Font::new(face.face);
                     let (load_flags, _) = ftwrap::compute_load_flags_from_config(
                         handle.freetype_load_flags,
@@ -694,6 +700,7 @@ mod test {
                     freetype_load_target: None,
                     freetype_render_target: None,
                     harfbuzz_features: None,
+                    variation_axes: None,
                     scale: None,
                 },
                 14,
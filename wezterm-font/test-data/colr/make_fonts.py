@@ -0,0 +1,584 @@
+#!/usr/bin/env python3
+"""
+Generates the small COLRv0 and COLRv1 fonts that are used by the
+golden image tests in wezterm-font/src/rasterizer/colr.rs.
+
+Only the python standard library is required, so that the fonts
+can be regenerated without installing fonttools:
+
+    python3 make_fonts.py
+
+After changing this script, regenerate the golden images by running
+the tests with WEZTERM_FONT_BLESS=1 set in the environment and review
+the resulting png files before committing them.
+"""
+
+import math
+import os
+import struct
+
+UNITS_PER_EM = 1000
+ASCENT = 800
+DESCENT = -200
+
+# Outline glyph ids shared by both fonts; each is a list of contours,
+# each contour is a list of (x, y, on_curve) points in clockwise order.
+NOTDEF, BOX, CIRCLE, TRIANGLE, SMALL_SQUARE, DIAMOND = range(6)
+
+
+def circle(cx, cy, r):
+    points = []
+    ctrl = r / math.cos(math.pi / 8)
+    for i in range(8):
+        # walk clockwise, starting from 90 degrees
+        a = math.pi / 2 - i * math.pi / 4
+        points.append((round(cx + r * math.cos(a)), round(cy + r * math.sin(a)), True))
+        a -= math.pi / 8
+        points.append(
+            (round(cx + ctrl * math.cos(a)), round(cy + ctrl * math.sin(a)), False)
+        )
+    return points
+
+
+OUTLINES = {
+    NOTDEF: [],
+    BOX: [[(50, -150, True), (50, 750, True), (950, 750, True), (950, -150, True)]],
+    CIRCLE: [circle(500, 300, 400)],
+    TRIANGLE: [[(100, -150, True), (500, 750, True), (900, -150, True)]],
+    SMALL_SQUARE: [
+        [(350, 150, True), (350, 450, True), (650, 450, True), (650, 150, True)]
+    ],
+    DIAMOND: [[(500, 750, True), (950, 300, True), (500, -150, True), (50, 300, True)]],
+}
+
+
+def encode_glyph(contours):
+    if not contours:
+        return b""
+    xs = [p[0] for c in contours for p in c]
+    ys = [p[1] for c in contours for p in c]
+    data = struct.pack(">hhhhh", len(contours), min(xs), min(ys), max(xs), max(ys))
+    end = -1
+    for c in contours:
+        end += len(c)
+        data += struct.pack(">H", end)
+    data += struct.pack(">H", 0)
+    points = [p for c in contours for p in c]
+    data += bytes(1 if on else 0 for (_, _, on) in points)
+    x = y = 0
+    for px, _, _ in points:
+        data += struct.pack(">h", px - x)
+        x = px
+    for _, py, _ in points:
+        data += struct.pack(">h", py - y)
+        y = py
+    return data
+
+
+def checksum(data):
+    data += b"\0" * (-len(data) % 4)
+    return sum(struct.unpack(">%dI" % (len(data) // 4), data)) & 0xFFFFFFFF
+
+
+def build_font(family, glyphs, cmap, extra_tables):
+    """glyphs is a list of outline contours, indexed by glyph id.
+    cmap maps characters to glyph ids."""
+    num_glyphs = len(glyphs)
+
+    glyf = b""
+    loca = []
+    for contours in glyphs:
+        loca.append(len(glyf))
+        glyf += encode_glyph(contours)
+        glyf += b"\0" * (-len(glyf) % 4)
+    loca.append(len(glyf))
+
+    max_points = max(sum(len(c) for c in g) for g in glyphs)
+    max_contours = max(len(g) for g in glyphs)
+
+    tables = {}
+    tables[b"head"] = struct.pack(
+        ">IIIIHHqqhhhhHHhhh",
+        0x00010000,
+        0x00010000,
+        0,
+        0x5F0F3CF5,
+        0x000B,
+        UNITS_PER_EM,
+        0,
+        0,
+        0,
+        DESCENT,
+        UNITS_PER_EM,
+        ASCENT,
+        0,
+        8,
+        2,
+        1,
+        0,
+    )
+    tables[b"hhea"] = struct.pack(
+        ">IhhhHhhhhhhhhhhhH",
+        0x00010000,
+        ASCENT,
+        DESCENT,
+        0,
+        UNITS_PER_EM,
+        0,
+        0,
+        UNITS_PER_EM,
+        1,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        num_glyphs,
+    )
+    tables[b"maxp"] = struct.pack(
+        ">IHHHHHHHHHHHHHH",
+        0x00010000,
+        num_glyphs,
+        max_points,
+        max_contours,
+        0,
+        0,
+        2,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0,
+    )
+    # FreeType shifts unscaled outlines so that x_min matches the
+    # left side bearing, so the bearing must match the outline
+    tables[b"hmtx"] = b"".join(
+        struct.pack(">Hh", UNITS_PER_EM, min((p[0] for c in g for p in c), default=0))
+        for g in glyphs
+    )
+    tables[b"loca"] = b"".join(struct.pack(">I", off) for off in loca)
+    tables[b"glyf"] = glyf
+    tables[b"post"] = struct.pack(">IIhhIIIII", 0x00030000, 0, -100, 50, 0, 0, 0, 0, 0)
+
+    # cmap: a single format 4 subtable with one segment per character
+    segments = sorted((ord(c), gid) for c, gid in cmap.items())
+    seg_count = len(segments) + 1
+    search_range = 2 * (1 << int(math.log2(seg_count)))
+    entry_selector = int(math.log2(search_range // 2))
+    range_shift = 2 * seg_count - search_range
+    ends = [c for c, _ in segments] + [0xFFFF]
+    starts = [c for c, _ in segments] + [0xFFFF]
+    deltas = [(gid - c) & 0xFFFF for c, gid in segments] + [1]
+    subtable = struct.pack(
+        ">HHHH", seg_count * 2, search_range, entry_selector, range_shift
+    )
+    subtable += b"".join(struct.pack(">H", v) for v in ends)
+    subtable += struct.pack(">H", 0)
+    subtable += b"".join(struct.pack(">H", v) for v in starts)
+    subtable += b"".join(struct.pack(">H", v) for v in deltas)
+    subtable += b"".join(struct.pack(">H", 0) for _ in range(seg_count))
+    subtable = struct.pack(">HHH", 4, len(subtable) + 6, 0) + subtable
+    tables[b"cmap"] = struct.pack(">HHHHI", 0, 1, 3, 1, 12) + subtable
+
+    names = {1: family, 2: "Regular", 4: family + " Regular", 6: family.replace(" ", "")}
+    strings = b""
+    records = b""
+    for name_id, value in sorted(names.items()):
+        encoded = value.encode("utf-16-be")
+        records += struct.pack(">HHHHHH", 3, 1, 0x409, name_id, len(encoded), len(strings))
+        strings += encoded
+    tables[b"name"] = (
+        struct.pack(">HHH", 0, len(names), 6 + len(records)) + records + strings
+    )
+
+    tables.update(extra_tables)
+
+    num_tables = len(tables)
+    search_range = 16 * (1 << int(math.log2(num_tables)))
+    entry_selector = int(math.log2(search_range // 16))
+    range_shift = num_tables * 16 - search_range
+    header = struct.pack(
+        ">IHHHH", 0x00010000, num_tables, search_range, entry_selector, range_shift
+    )
+    offset = len(header) + 16 * num_tables
+    directory = b""
+    body = b""
+    for tag in sorted(tables):
+        data = tables[tag]
+        directory += struct.pack(">4sIII", tag, checksum(data), offset + len(body), len(data))
+        body += data + b"\0" * (-len(data) % 4)
+    return header + directory + body
+
+
+def cpal(palettes, palette_types=None):
+    """palettes is a list of lists of (r, g, b, a) tuples"""
+    num_entries = len(palettes[0])
+    version = 0 if palette_types is None else 1
+    header_len = 12 + 2 * len(palettes) + (12 if version else 0)
+    records = b"".join(
+        struct.pack(">BBBB", b, g, r, a) for palette in palettes for (r, g, b, a) in palette
+    )
+    data = struct.pack(
+        ">HHHHI", version, num_entries, len(palettes), num_entries * len(palettes), header_len
+    )
+    data += b"".join(struct.pack(">H", i * num_entries) for i in range(len(palettes)))
+    if version:
+        data += struct.pack(">III", header_len + len(records), 0, 0)
+    data += records
+    if version:
+        data += b"".join(struct.pack(">I", t) for t in palette_types)
+    return data
+
+
+def f2dot14(value):
+    return struct.pack(">h", round(value * 16384))
+
+
+def fixed(value):
+    return struct.pack(">i", round(value * 65536))
+
+
+def u24(value):
+    return struct.pack(">I", value)[1:]
+
+
+# COLRv1 paint tables.  Each function returns the bytes of a paint table
+# followed by its child tables, with offsets relative to the start of
+# the paint table itself.
+
+
+def color_line(extend, stops):
+    data = struct.pack(">BH", extend, len(stops))
+    for offset, palette_index, alpha in stops:
+        data += f2dot14(offset) + struct.pack(">H", palette_index) + f2dot14(alpha)
+    return data
+
+
+def paint_colr_layers(first_layer, num_layers):
+    return struct.pack(">BBI", 1, num_layers, first_layer)
+
+
+def paint_solid(palette_index, alpha=1.0):
+    return struct.pack(">BH", 2, palette_index) + f2dot14(alpha)
+
+
+def paint_linear(line, p0, p1, p2):
+    head_len = 16
+    return (
+        struct.pack(">B", 4)
+        + u24(head_len)
+        + struct.pack(">hhhhhh", *p0, *p1, *p2)
+        + line
+    )
+
+
+def paint_radial(line, c0, r0, c1, r1):
+    head_len = 16
+    return (
+        struct.pack(">B", 6)
+        + u24(head_len)
+        + struct.pack(">hhHhhH", c0[0], c0[1], r0, c1[0], c1[1], r1)
+        + line
+    )
+
+
+def paint_sweep(line, center, start, end):
+    head_len = 12
+    return (
+        struct.pack(">B", 8)
+        + u24(head_len)
+        + struct.pack(">hh", *center)
+        + f2dot14(start)
+        + f2dot14(end)
+        + line
+    )
+
+
+def paint_glyph(paint, glyph_id):
+    return struct.pack(">B", 10) + u24(6) + struct.pack(">H", glyph_id) + paint
+
+
+def paint_colr_glyph(glyph_id):
+    return struct.pack(">BH", 11, glyph_id)
+
+
+def paint_transform(paint, xx, yx, xy, yy, dx, dy):
+    affine = b"".join(fixed(v) for v in (xx, yx, xy, yy, dx, dy))
+    return struct.pack(">B", 12) + u24(7) + u24(7 + len(paint)) + paint + affine
+
+
+def paint_translate(paint, dx, dy):
+    return struct.pack(">B", 14) + u24(8) + struct.pack(">hh", dx, dy) + paint
+
+
+def paint_scale_uniform_around_center(paint, scale, center):
+    return (
+        struct.pack(">B", 22)
+        + u24(10)
+        + f2dot14(scale)
+        + struct.pack(">hh", *center)
+        + paint
+    )
+
+
+def paint_rotate_around_center(paint, degrees, center):
+    return (
+        struct.pack(">B", 26)
+        + u24(10)
+        + f2dot14(degrees / 180)
+        + struct.pack(">hh", *center)
+        + paint
+    )
+
+
+def paint_skew_around_center(paint, x_degrees, y_degrees, center):
+    return (
+        struct.pack(">B", 30)
+        + u24(12)
+        + f2dot14(x_degrees / 180)
+        + f2dot14(y_degrees / 180)
+        + struct.pack(">hh", *center)
+        + paint
+    )
+
+
+def paint_composite(source, mode, backdrop):
+    return (
+        struct.pack(">B", 32)
+        + u24(8)
+        + struct.pack(">B", mode)
+        + u24(8 + len(source))
+        + source
+        + backdrop
+    )
+
+
+COMPOSITE_SRC_IN = 5
+COMPOSITE_MULTIPLY = 23
+
+EXTEND_PAD = 0
+EXTEND_REPEAT = 1
+EXTEND_REFLECT = 2
+
+FOREGROUND = 0xFFFF
+
+
+def colr_v0(base_glyphs):
+    """base_glyphs maps glyph ids to lists of (glyph_id, palette_index) layers"""
+    base_records = b""
+    layer_records = b""
+    num_layers = 0
+    for gid in sorted(base_glyphs):
+        layers = base_glyphs[gid]
+        base_records += struct.pack(">HHH", gid, num_layers, len(layers))
+        for layer_gid, palette_index in layers:
+            layer_records += struct.pack(">HH", layer_gid, palette_index)
+        num_layers += len(layers)
+    header_len = 14
+    return (
+        struct.pack(
+            ">HHIIH",
+            0,
+            len(base_glyphs),
+            header_len,
+            header_len + len(base_records),
+            num_layers,
+        )
+        + base_records
+        + layer_records
+    )
+
+
+def colr_v1(base_paints, layers, clips):
+    """base_paints maps glyph ids to paint tables, layers is the list
+    of paint tables in the LayerList and clips maps glyph ids to
+    (x_min, y_min, x_max, y_max) clip boxes."""
+    header_len = 34
+
+    base_list = struct.pack(">I", len(base_paints))
+    paints = b""
+    records_len = 4 + 6 * len(base_paints)
+    for gid in sorted(base_paints):
+        base_list += struct.pack(">HI", gid, records_len + len(paints))
+        paints += base_paints[gid]
+    base_list += paints
+
+    layer_list = struct.pack(">I", len(layers))
+    paints = b""
+    for paint in layers:
+        layer_list += struct.pack(">I", 4 + 4 * len(layers) + len(paints))
+        paints += paint
+    layer_list += paints
+
+    clip_list = struct.pack(">BI", 1, len(clips))
+    boxes = b""
+    records_len = 5 + 7 * len(clips)
+    for gid in sorted(clips):
+        clip_list += struct.pack(">HH", gid, gid) + u24(records_len + len(boxes))
+        boxes += struct.pack(">Bhhhh", 1, *clips[gid])
+    clip_list += boxes
+
+    base_list_offset = header_len
+    layer_list_offset = base_list_offset + len(base_list)
+    clip_list_offset = layer_list_offset + len(layer_list)
+    return (
+        struct.pack(
+            ">HHIIHIIIII",
+            1,
+            0,
+            0,
+            0,
+            0,
+            base_list_offset,
+            layer_list_offset,
+            clip_list_offset,
+            0,
+            0,
+        )
+        + base_list
+        + layer_list
+        + clip_list
+    )
+
+
+def make_colrv0():
+    glyphs = [OUTLINES[gid] for gid in range(6)]
+    chars = {}
+    base_glyphs = {}
+
+    def add(char, layers):
+        gid = len(glyphs)
+        glyphs.append(OUTLINES[BOX])
+        chars[char] = gid
+        base_glyphs[gid] = layers
+
+    add("A", [(BOX, 0), (CIRCLE, 1), (TRIANGLE, 2)])
+    add("B", [(CIRCLE, 3), (SMALL_SQUARE, FOREGROUND)])
+
+    palettes = [
+        [(0x20, 0x40, 0xA0, 0xFF), (0xF0, 0xC0, 0x20, 0xFF), (0xC0, 0x20, 0x20, 0x80), (0x30, 0xA0, 0x50, 0xFF)]
+    ]
+    return build_font(
+        "COLRv0 Test",
+        glyphs,
+        chars,
+        {b"COLR": colr_v0(base_glyphs), b"CPAL": cpal(palettes)},
+    )
+
+
+def make_colrv1():
+    glyphs = [OUTLINES[gid] for gid in range(6)]
+    chars = {}
+    base_paints = {}
+
+    def add(char, paint):
+        gid = len(glyphs)
+        glyphs.append(OUTLINES[BOX])
+        chars[char] = gid
+        base_paints[gid] = paint
+        return gid
+
+    gradient = color_line(EXTEND_PAD, [(0.0, 0, 1.0), (0.5, 1, 1.0), (1.0, 2, 1.0)])
+
+    add(
+        "L",
+        paint_glyph(paint_linear(gradient, (100, 300), (900, 300), (100, 1300)), BOX),
+    )
+    add(
+        "R",
+        paint_glyph(
+            paint_radial(
+                color_line(EXTEND_REPEAT, [(0.0, 2, 1.0), (1.0, 3, 0.5)]),
+                (500, 300),
+                0,
+                (500, 300),
+                200,
+            ),
+            CIRCLE,
+        ),
+    )
+    add(
+        "S",
+        paint_glyph(
+            paint_sweep(
+                color_line(EXTEND_REFLECT, [(0.0, 0, 1.0), (1.0, 1, 1.0)]),
+                (500, 300),
+                0.0,
+                1.0,
+            ),
+            BOX,
+        ),
+    )
+
+    layers = [
+        paint_glyph(paint_solid(3), BOX),
+        paint_rotate_around_center(
+            paint_glyph(paint_solid(FOREGROUND), SMALL_SQUARE), 45, (500, 300)
+        ),
+        paint_transform(
+            paint_glyph(paint_solid(0, 0.75), DIAMOND), 0.5, 0, 0, 0.5, 450, 350
+        ),
+    ]
+    add("T", paint_colr_layers(0, 3))
+
+    composite = add(
+        "C",
+        paint_composite(
+            paint_glyph(paint_solid(1), CIRCLE),
+            COMPOSITE_SRC_IN,
+            paint_glyph(paint_solid(2), DIAMOND),
+        ),
+    )
+    add(
+        "M",
+        paint_composite(
+            paint_glyph(paint_linear(gradient, (100, 300), (900, 300), (100, 1300)), CIRCLE),
+            COMPOSITE_MULTIPLY,
+            paint_glyph(paint_solid(3), BOX),
+        ),
+    )
+
+    layers.append(
+        paint_translate(
+            paint_skew_around_center(paint_colr_glyph(composite), 15, 0, (500, 300)),
+            0,
+            0,
+        )
+    )
+    layers.append(
+        paint_scale_uniform_around_center(
+            paint_glyph(paint_solid(FOREGROUND, 0.5), SMALL_SQUARE), 0.5, (500, 300)
+        )
+    )
+    add("G", paint_colr_layers(3, 2))
+
+    clips = {chars["L"]: (0, -200, 1000, 800)}
+
+    palettes = [
+        # For use with a light background
+        [(0x20, 0x40, 0xA0, 0xFF), (0xA0, 0x20, 0x80, 0xFF), (0x10, 0x70, 0x30, 0xFF), (0x60, 0x60, 0x60, 0xFF)],
+        # For use with a dark background
+        [(0x80, 0xC0, 0xFF, 0xFF), (0xFF, 0x90, 0xE0, 0xFF), (0x90, 0xF0, 0xA0, 0xFF), (0xD0, 0xD0, 0xD0, 0xFF)],
+    ]
+    return build_font(
+        "COLRv1 Test",
+        glyphs,
+        chars,
+        {
+            b"COLR": colr_v1(base_paints, layers, clips),
+            b"CPAL": cpal(palettes, palette_types=[0x1, 0x2]),
+        },
+    )
+
+
+if __name__ == "__main__":
+    here = os.path.dirname(os.path.abspath(__file__))
+    with open(os.path.join(here, "colrv0-test.ttf"), "wb") as f:
+        f.write(make_colrv0())
+    with open(os.path.join(here, "colrv1-test.ttf"), "wb") as f:
+        f.write(make_colrv1())
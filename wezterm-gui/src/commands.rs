@@ -649,6 +649,27 @@ static DEFS: &[CommandDef] = &[
         keys: &[(Modifiers::CTRL.union(Modifiers::SHIFT), "z")],
         args: &[ArgType::ActivePane],
     },
+    CommandDef {
+        brief: "Rotate Panes Clockwise",
+        doc: "Moves each pane in the current tab into the position of the next pane",
+        exp: |exp| exp.push(RotatePanes(RotationDirection::Clockwise)),
+        keys: &[],
+        args: &[ArgType::ActivePane],
+    },
+    CommandDef {
+        brief: "Rotate Panes Counter-Clockwise",
+        doc: "Moves each pane in the current tab into the position of the previous pane",
+        exp: |exp| exp.push(RotatePanes(RotationDirection::CounterClockwise)),
+        keys: &[],
+        args: &[ArgType::ActivePane],
+    },
+    CommandDef {
+        brief: "Move Pane to New Tab",
+        doc: "Removes the current pane from its tab and places it into a new tab",
+        exp: |exp| exp.push(MovePaneToNewTab),
+        keys: &[],
+        args: &[ArgType::ActivePane],
+    },
     CommandDef {
         brief: "Activate the last active tab",
         doc: "If there was no prior active tab, has no effect.",
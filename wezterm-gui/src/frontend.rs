@@ -4,12 +4,15 @@ use ::window::*;
 use anyhow::Error;
 pub use config::FrontEndSelection;
 use mux::client::ClientId;
+use mux::pane::PaneId;
 use mux::window::WindowId as MuxWindowId;
 use mux::{Mux, MuxNotification};
 use std::cell::RefCell;
 use std::collections::{BTreeMap, HashSet};
 use std::rc::Rc;
 use std::sync::Arc;
+use termwiz::escape::osc::{KittyNotification, NotificationUrgency};
+use termwiz::escape::OperatingSystemCommand;
 use wezterm_term::{Alert, ClipboardSelection};
 use wezterm_toast_notification::*;
 
@@ -46,6 +49,7 @@ impl GuiFrontEnd {
             if let Some(fe) = fe.upgrade() {
                 match n {
                     MuxNotification::WindowWorkspaceChanged(_)
+                    | MuxNotification::TabTitleChanged(_)
                     | MuxNotification::ActiveWorkspaceChanged(_) => {}
                     MuxNotification::WindowCreated(_) | MuxNotification::WindowRemoved(_) => {
                         promise::spawn::spawn(async move {
@@ -61,20 +65,49 @@ impl GuiFrontEnd {
                     MuxNotification::PaneOutput(_) => {}
                     MuxNotification::PaneAdded(_) => {}
                     MuxNotification::Alert {
-                        pane_id: _,
+                        pane_id,
                         alert:
                             Alert::ToastNotification {
                                 title,
                                 body,
-                                focus: _,
+                                focus,
+                                urgency,
+                                report_id,
                             },
                     } => {
-                        let message = if title.is_none() { "" } else { &body };
-                        let title = title.as_ref().unwrap_or(&body);
-                        // FIXME: if notification.focus is true, we should do
-                        // something here to arrange to focus pane_id when the
-                        // notification is clicked
-                        persistent_toast_notification(title, message);
+                        let message = if title.is_none() {
+                            String::new()
+                        } else {
+                            body.clone()
+                        };
+                        let title = title.unwrap_or(body);
+                        let on_click: Option<ClickHandler> = if focus || report_id.is_some() {
+                            Some(Arc::new(move || {
+                                let report_id = report_id.clone();
+                                promise::spawn::spawn_into_main_thread(async move {
+                                    self::front_end().activate_notification_pane(
+                                        pane_id,
+                                        focus,
+                                        report_id.as_deref(),
+                                    );
+                                })
+                                .detach();
+                            }))
+                        } else {
+                            None
+                        };
+                        ToastNotification {
+                            title,
+                            message,
+                            urgency: match urgency {
+                                NotificationUrgency::Low => Urgency::Low,
+                                NotificationUrgency::Normal => Urgency::Normal,
+                                NotificationUrgency::Critical => Urgency::Critical,
+                            },
+                            on_click,
+                            ..Default::default()
+                        }
+                        .show();
                     }
                     MuxNotification::Alert {
                         pane_id: _,
@@ -268,6 +301,58 @@ impl GuiFrontEnd {
         }
     }
 
+    /// Called when the user clicks on a notification that was
+    /// generated by `pane_id`.  Activates the pane, switching to
+    /// its workspace and tab if needed, and/or tells the
+    /// application that the notification was activated.
+    fn activate_notification_pane(&self, pane_id: PaneId, focus: bool, report_id: Option<&str>) {
+        let mux = Mux::get().expect("mux started and running on main thread");
+        let pane = match mux.get_pane(pane_id) {
+            Some(pane) => pane,
+            None => return,
+        };
+
+        if let Some(id) = report_id {
+            // kitty's OSC 99 protocol: the application asked to be told
+            // when its notification is activated
+            let response = OperatingSystemCommand::KittyNotification(KittyNotification {
+                id: Some(id.to_string()),
+                ..Default::default()
+            });
+            if let Err(err) = write!(pane.writer(), "{}", response) {
+                log::error!("failed to report notification activation: {:#}", err);
+            }
+        }
+
+        if !focus {
+            return;
+        }
+        let (_domain_id, window_id, tab_id) = match mux.resolve_pane_id(pane_id) {
+            Some(ids) => ids,
+            None => return,
+        };
+        if let Some(tab) = mux.get_tab(tab_id) {
+            tab.set_active_pane(&pane);
+        }
+        let workspace = match mux.get_window_mut(window_id) {
+            Some(mut window) => {
+                if let Some(tab_idx) = window.idx_by_id(tab_id) {
+                    window.save_and_then_set_active(tab_idx);
+                }
+                window.get_workspace().to_string()
+            }
+            None => return,
+        };
+        if workspace != mux.active_workspace_for_client(&self.client_id) {
+            self.switch_workspace(&workspace);
+        }
+        for (window, &mux_window_id) in self.known_windows.borrow().iter() {
+            if mux_window_id == window_id {
+                window.show();
+            }
+        }
+    }
+
     pub fn is_switching_workspace(&self) -> bool {
         *self.switching_workspaces.borrow()
     }
@@ -5,7 +5,7 @@ use ::window::bitmaps::atlas::{Atlas, OutOfTextureSpace, Sprite};
 #[cfg(test)]
 use ::window::bitmaps::ImageTexture;
 use ::window::bitmaps::{BitmapImage, Image, Texture2d};
-use ::window::color::SrgbaPixel;
+use ::window::color::{SrgbaPixel, SrgbaTuple};
 use ::window::glium::backend::Context as GliumContext;
 use ::window::glium::texture::SrgbTexture2d;
 use ::window::glium::CapabilitiesSource;
@@ -56,6 +56,8 @@ pub struct GlyphKey {
     pub followed_by_space: bool,
     pub metric: CellMetricKey,
     pub id: LoadedFontId,
+    /// The text color, for color fonts that pick a palette based on it
+    pub foreground: Option<SrgbaTuple>,
 }
 
 /// We'd like to avoid allocating when resolving from the cache
@@ -72,6 +74,8 @@ pub struct BorrowedGlyphKey<'a> {
     pub followed_by_space: bool,
     pub metric: CellMetricKey,
     pub id: LoadedFontId,
+    /// The text color, for color fonts that pick a palette based on it
+    pub foreground: Option<SrgbaTuple>,
 }
 
 impl<'a> BorrowedGlyphKey<'a> {
@@ -84,6 +88,7 @@ impl<'a> BorrowedGlyphKey<'a> {
             followed_by_space: self.followed_by_space,
             metric: self.metric,
             id: self.id,
+            foreground: self.foreground,
         }
     }
 }
@@ -102,6 +107,7 @@ impl GlyphKeyTrait for GlyphKey {
             followed_by_space: self.followed_by_space,
             metric: self.metric,
             id: self.id,
+            foreground: self.foreground,
         }
     }
 }
@@ -343,7 +349,15 @@ impl<T: Texture2d> GlyphCache<T> {
         font: &Rc<LoadedFont>,
         metrics: &RenderMetrics,
         num_cells: u8,
+        foreground: Option<SrgbaTuple>,
     ) -> anyhow::Result<Rc<CachedGlyph<T>>> {
+        // Only color fonts vary with the text color, so avoid
+        // fragmenting the cache for everything else
+        let foreground = if font.has_colr(info.font_idx) {
+            foreground
+        } else {
+            None
+        };
         let key = BorrowedGlyphKey {
             font_idx: info.font_idx,
             glyph_pos: info.glyph_pos,
@@ -352,6 +366,7 @@ impl<T: Texture2d> GlyphCache<T> {
             followed_by_space,
             metric: metrics.into(),
             id: font.id(),
+            foreground,
         };
 
         if let Some(entry) = self.glyph_cache.get(&key as &dyn GlyphKeyTrait) {
@@ -360,7 +375,7 @@ impl<T: Texture2d> GlyphCache<T> {
         }
         metrics::histogram!("glyph_cache.glyph_cache.miss.rate", 1.);
 
-        let glyph = match self.load_glyph(info, font, followed_by_space, num_cells) {
+        let glyph = match self.load_glyph(info, font, followed_by_space, num_cells, foreground) {
             Ok(g) => g,
             Err(err) => {
                 if err
@@ -407,6 +422,7 @@ impl<T: Texture2d> GlyphCache<T> {
         font: &Rc<LoadedFont>,
         followed_by_space: bool,
         num_cells: u8,
+        foreground: Option<SrgbaTuple>,
     ) -> anyhow::Result<Rc<CachedGlyph<T>>> {
         let base_metrics;
         let idx_metrics;
@@ -415,7 +431,7 @@ impl<T: Texture2d> GlyphCache<T> {
 
         {
             base_metrics = font.metrics();
-            glyph = font.rasterize_glyph(info.glyph_pos, info.font_idx)?;
+            glyph = font.rasterize_glyph(info.glyph_pos, info.font_idx, foreground)?;
 
             idx_metrics = font.metrics_for_idx(info.font_idx)?;
             brightness_adjust = font.brightness_adjust(info.font_idx);
@@ -1,4 +1,5 @@
 use crate::commands::CommandDef;
+use crate::overlay::{copy_key_table, COPY_MODE_KEY_TABLE};
 use config::keyassignment::{
     ClipboardCopyDestination, ClipboardPasteSource, KeyAssignment, KeyTableEntry, KeyTables,
     MouseEventTrigger, SelectionMode,
@@ -49,6 +50,19 @@ impl InputMap {
             }
         }
 
+        // The user may override individual copy mode assignments by
+        // defining a copy_mode key table.  The defaults are always
+        // present, even with disable_default_key_bindings, because
+        // copy mode has no other way to receive input and could not
+        // otherwise be exited.
+        let copy_mode = keys
+            .by_name
+            .entry(COPY_MODE_KEY_TABLE.to_string())
+            .or_default();
+        for (key, entry) in copy_key_table() {
+            copy_mode.entry(key).or_insert(entry);
+        }
+
         if !config.disable_default_mouse_bindings {
             m!(
                 [
@@ -143,6 +157,30 @@ impl InputMap {
                     },
                     ExtendSelectionToMouseCursor(Some(SelectionMode::Line))
                 ],
+                [
+                    Modifiers::ALT,
+                    MouseEventTrigger::Down {
+                        streak: 1,
+                        button: MouseButton::Left
+                    },
+                    SelectTextAtMouseCursor(SelectionMode::Block)
+                ],
+                [
+                    Modifiers::ALT,
+                    MouseEventTrigger::Drag {
+                        streak: 1,
+                        button: MouseButton::Left
+                    },
+                    ExtendSelectionToMouseCursor(Some(SelectionMode::Block))
+                ],
+                [
+                    Modifiers::ALT,
+                    MouseEventTrigger::Up {
+                        streak: 1,
+                        button: MouseButton::Left
+                    },
+                    CompleteSelection(ClipboardCopyDestination::PrimarySelection)
+                ],
                 [
                     Modifiers::NONE,
                     MouseEventTrigger::Down {
@@ -172,6 +210,11 @@ impl InputMap {
 
         keys.default
             .retain(|_, v| v.action != KeyAssignment::DisableDefaultAssignment);
+        // Only the copy_mode table is seeded with defaults, so that is
+        // the only table in which an assignment can disable one
+        if let Some(table) = keys.by_name.get_mut(COPY_MODE_KEY_TABLE) {
+            table.retain(|_, v| v.action != KeyAssignment::DisableDefaultAssignment);
+        }
         mouse.retain(|_, v| *v != KeyAssignment::DisableDefaultAssignment);
 
         Self {
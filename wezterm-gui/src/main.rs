@@ -40,7 +40,6 @@ mod scripting;
 mod scrollbar;
 mod selection;
 mod shapecache;
-mod stats;
 mod tabbar;
 mod termwindow;
 mod update;
@@ -862,11 +861,12 @@ pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyho
                 format!(" pixel_sizes={:?}", font.pixel_sizes)
             };
             println!(
-                "{} -- {}{}{}",
+                "{} -- {}{}{}{}",
                 font.lua_name(),
                 font.aka(),
                 font.handle.diagnostic_string(),
-                pixel_sizes
+                pixel_sizes,
+                font.axes_summary()
             );
         }
 
@@ -884,11 +884,12 @@ pub fn run_ls_fonts(config: config::ConfigHandle, cmd: &LsFontsCommand) -> anyho
                         format!(" pixel_sizes={:?}", font.pixel_sizes)
                     };
                     println!(
-                        "{} -- {}{}{}",
+                        "{} -- {}{}{}{}",
                         font.lua_name(),
                         font.aka(),
                         font.handle.diagnostic_string(),
-                        pixel_sizes
+                        pixel_sizes,
+                        font.axes_summary()
                     );
                 }
             }
@@ -935,7 +936,7 @@ fn run() -> anyhow::Result<()> {
 
     env_bootstrap::bootstrap();
 
-    stats::Stats::init()?;
+    wezterm_mux_server_impl::stats::Stats::init()?;
     let _saver = umask::UmaskSaver::new();
 
     config::common_init(
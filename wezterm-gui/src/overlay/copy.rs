@@ -1,8 +1,10 @@
-use crate::selection::{SelectionCoordinate, SelectionRange};
+use crate::selection::{SelectionCoordinate, SelectionMode, SelectionRange};
 use crate::termwindow::{TermWindow, TermWindowNotif};
-use config::keyassignment::ScrollbackEraseMode;
+use config::keyassignment::{
+    CopyModeAssignment, KeyAssignment, KeyTable, KeyTableEntry, ScrollbackEraseMode,
+};
 use mux::domain::DomainId;
-use mux::pane::{Pane, PaneId};
+use mux::pane::{Pane, PaneId, Pattern, SearchResult};
 use mux::renderable::*;
 use portable_pty::PtySize;
 use rangeset::RangeSet;
@@ -10,7 +12,8 @@ use std::cell::{RefCell, RefMut};
 use std::ops::Range;
 use std::rc::Rc;
 use std::sync::Arc;
-use termwiz::surface::{CursorVisibility, SequenceNo};
+use termwiz::cell::{Cell, CellAttributes};
+use termwiz::surface::{CursorVisibility, SequenceNo, SEQ_ZERO};
 use unicode_segmentation::*;
 use url::Url;
 use wezterm_term::color::ColorPalette;
@@ -24,15 +27,113 @@ pub struct CopyOverlay {
     render: RefCell<CopyRenderable>,
 }
 
+/// The name of the key table that drives copy mode
+pub const COPY_MODE_KEY_TABLE: &str = "copy_mode";
+
 struct CopyRenderable {
     cursor: StableCursorPosition,
     delegate: Rc<dyn Pane>,
     start: Option<SelectionCoordinate>,
+    /// The style of selection that is made between `start` and the cursor
+    selection_mode: SelectionMode,
     viewport: Option<StableRowIndex>,
+    /// The search pattern, while the user is typing it in
+    search_prompt: Option<SearchPrompt>,
+    /// The row on which the search prompt was most recently rendered
+    prompt_row: Option<StableRowIndex>,
+    /// The most recent search; used to find the next/prior match
+    last_search: Option<(String, SearchDirection)>,
+    /// A jump that is waiting for the user to type the target character
+    pending_jump: Option<Jump>,
+    /// The most recent jump and its target character
+    last_jump: Option<(Jump, char)>,
     /// We use this to cancel ourselves later
     window: ::window::Window,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SearchDirection {
+    Forward,
+    Backward,
+}
+
+impl SearchDirection {
+    fn reverse(self) -> Self {
+        match self {
+            Self::Forward => Self::Backward,
+            Self::Backward => Self::Forward,
+        }
+    }
+}
+
+struct SearchPrompt {
+    pattern: String,
+    direction: SearchDirection,
+    /// Where the cursor was when the search began.
+    /// Matches are found relative to this position, and the cursor
+    /// is returned here if the search is cancelled.
+    origin: (usize, StableRowIndex),
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Jump {
+    forward: bool,
+    /// Stop on the cell before the target character rather than on it
+    till: bool,
+}
+
+/// Computes the column that a `jump` to `target` from column `x` of
+/// `line` lands on, or None if there is no such character.
+fn jump_target(line: &Line, x: usize, jump: Jump, target: char, repeat: bool) -> Option<usize> {
+    let mut buf = [0u8; 4];
+    let target: &str = target.encode_utf8(&mut buf);
+    // When repeating a `till` jump, the cursor is already adjacent
+    // to the previous match, so skip over it
+    let skip = if jump.till && repeat { 2 } else { 1 };
+
+    if jump.forward {
+        line.cells()
+            .iter()
+            .enumerate()
+            .skip(x + skip)
+            .find(|(_, cell)| cell.str() == target)
+            .map(|(idx, _)| if jump.till { idx - 1 } else { idx })
+    } else if x >= skip {
+        line.cells()
+            .iter()
+            .enumerate()
+            .take(x + 1 - skip)
+            .rev()
+            .find(|(_, cell)| cell.str() == target)
+            .map(|(idx, _)| if jump.till { idx + 1 } else { idx })
+    } else {
+        None
+    }
+}
+
+/// Returns the start of the first search result that follows `origin`
+/// in the specified direction, wrapping around at either end.
+fn find_match(
+    results: &mut [SearchResult],
+    direction: SearchDirection,
+    origin: (usize, StableRowIndex),
+) -> Option<(usize, StableRowIndex)> {
+    results.sort();
+    let (origin_x, origin_y) = origin;
+    let found = match direction {
+        SearchDirection::Forward => results
+            .iter()
+            .find(|res| (res.start_y, res.start_x) > (origin_y, origin_x))
+            .or_else(|| results.first()),
+        SearchDirection::Backward => results
+            .iter()
+            .rev()
+            .find(|res| (res.start_y, res.start_x) < (origin_y, origin_x))
+            .or_else(|| results.last()),
+    };
+    found.map(|res| (res.start_x, res.start_y))
+}
+
 struct Dimensions {
     vertical_gap: isize,
     dims: RenderableDimensions,
@@ -51,7 +152,13 @@ impl CopyOverlay {
             window,
             delegate: Rc::clone(pane),
             start: None,
+            selection_mode: SelectionMode::Cell,
             viewport: term_window.get_viewport(pane.pane_id()),
+            search_prompt: None,
+            prompt_row: None,
+            last_search: None,
+            pending_jump: None,
+            last_jump: None,
         };
         Rc::new(CopyOverlay {
             delegate: Rc::clone(pane),
@@ -63,6 +170,59 @@ impl CopyOverlay {
         let mut r = self.render.borrow_mut();
         r.viewport = viewport;
     }
+
+    /// Returns true while the overlay is collecting a search pattern or
+    /// the target character of a jump.  Keys should be delivered to the
+    /// overlay directly in that state, rather than being looked up in
+    /// the key tables.
+    pub fn is_capturing_input(&self) -> bool {
+        let r = self.render.borrow();
+        r.search_prompt.is_some() || r.pending_jump.is_some()
+    }
+
+    pub fn perform_assignment(&self, assignment: &CopyModeAssignment) {
+        use CopyModeAssignment::*;
+        let mut render = self.render.borrow_mut();
+        match assignment {
+            Close => render.close(),
+            MoveLeft => render.move_left_single_cell(),
+            MoveRight => render.move_right_single_cell(),
+            MoveUp => render.move_up_single_row(),
+            MoveDown => render.move_down_single_row(),
+            MoveForwardWord => render.move_forward_one_word(),
+            MoveBackwardWord => render.move_backward_one_word(),
+            MoveToStartOfLine => render.move_to_start_of_line(),
+            MoveToStartOfNextLine => render.move_to_start_of_next_line(),
+            MoveToStartOfLineContent => render.move_to_start_of_line_content(),
+            MoveToEndOfLineContent => render.move_to_end_of_line_content(),
+            MoveToScrollbackTop => render.move_to_top(),
+            MoveToScrollbackBottom => render.move_to_bottom(),
+            MoveToViewportTop => render.move_to_viewport_top(),
+            MoveToViewportMiddle => render.move_to_viewport_middle(),
+            MoveToViewportBottom => render.move_to_viewport_bottom(),
+            PageUp => render.page_up(),
+            PageDown => render.page_down(),
+            SetSelectionMode(mode) => render.set_selection_mode(*mode),
+            SearchForward => render.begin_search(SearchDirection::Forward),
+            SearchBackward => render.begin_search(SearchDirection::Backward),
+            NextMatch => render.next_match(false),
+            PriorMatch => render.next_match(true),
+            JumpForward { till } => {
+                render.pending_jump.replace(Jump {
+                    forward: true,
+                    till: *till,
+                });
+            }
+            JumpBackward { till } => {
+                render.pending_jump.replace(Jump {
+                    forward: false,
+                    till: *till,
+                });
+            }
+            JumpAgain => render.repeat_jump(false),
+            JumpReverse => render.repeat_jump(true),
+        }
+    }
 }
 
 impl CopyRenderable {
@@ -94,7 +254,18 @@ impl CopyRenderable {
                 y: self.cursor.y,
             };
 
-            self.adjust_selection(start, SelectionRange { start, end });
+            let pane = &*self.delegate;
+            let range = match self.selection_mode {
+                SelectionMode::Cell | SelectionMode::Block => SelectionRange { start, end },
+                SelectionMode::Word => SelectionRange::word_around(start, pane)
+                    .extend_with(SelectionRange::word_around(end, pane)),
+                SelectionMode::Line => SelectionRange::line_around(start, pane)
+                    .extend_with(SelectionRange::line_around(end, pane)),
+                SelectionMode::SemanticZone => SelectionRange::zone_around(start, pane)
+                    .extend_with(SelectionRange::zone_around(end, pane)),
+            };
+
+            self.adjust_selection(start, range);
         } else {
             self.adjust_viewport_for_cursor_position();
             self.window.invalidate();
@@ -104,11 +275,13 @@ impl CopyRenderable {
     fn adjust_selection(&self, start: SelectionCoordinate, range: SelectionRange) {
         let pane_id = self.delegate.pane_id();
         let window = self.window.clone();
+        let rectangular = self.selection_mode == SelectionMode::Block;
         self.window
             .notify(TermWindowNotif::Apply(Box::new(move |term_window| {
                 let mut selection = term_window.selection(pane_id);
                 selection.origin = Some(start);
                 selection.range = Some(range);
+                selection.rectangular = rectangular;
                 window.invalidate();
             })));
         self.adjust_viewport_for_cursor_position();
@@ -363,15 +536,164 @@ impl CopyRenderable {
         self.select_to_cursor_pos();
     }
 
-    fn toggle_selection_by_cell(&mut self) {
-        if self.start.take().is_none() {
-            let coord = SelectionCoordinate {
+    fn set_selection_mode(&mut self, mode: Option<SelectionMode>) {
+        let mode = match mode {
+            Some(mode) => mode,
+            None => {
+                self.start.take();
+                return;
+            }
+        };
+
+        if self.start.is_some() {
+            if self.selection_mode == mode {
+                self.start.take();
+                return;
+            }
+            // Switch the style of the current selection rather
+            // than starting a new one
+        } else {
+            self.start.replace(SelectionCoordinate {
                 x: self.cursor.x,
                 y: self.cursor.y,
-            };
-            self.start.replace(coord);
+            });
+        }
+        self.selection_mode = mode;
+        self.select_to_cursor_pos();
+    }
+
+    fn jump(&mut self, jump: Jump, target: char, repeat: bool) {
+        let y = self.cursor.y;
+        let (top, lines) = self.delegate.get_lines(y..y + 1);
+        let line = match lines.first() {
+            Some(line) => line,
+            None => return,
+        };
+        self.cursor.y = top;
+        if let Some(x) = jump_target(line, self.cursor.x, jump, target, repeat) {
+            self.cursor.x = x;
+        }
+        self.select_to_cursor_pos();
+    }
+
+    fn repeat_jump(&mut self, reverse: bool) {
+        if let Some((mut jump, target)) = self.last_jump {
+            if reverse {
+                jump.forward = !jump.forward;
+            }
+            self.jump(jump, target, true);
+        }
+    }
+
+    fn begin_search(&mut self, direction: SearchDirection) {
+        self.search_prompt.replace(SearchPrompt {
+            pattern: String::new(),
+            direction,
+            origin: (self.cursor.x, self.cursor.y),
+        });
+        self.window.invalidate();
+    }
+
+    fn update_search(&mut self) {
+        if let Some(prompt) = &self.search_prompt {
+            if prompt.pattern.is_empty() {
+                self.cursor.x = prompt.origin.0;
+                self.cursor.y = prompt.origin.1;
+                self.select_to_cursor_pos();
+            } else {
+                self.search(prompt.pattern.clone(), prompt.direction, prompt.origin);
+            }
+        }
+        self.window.invalidate();
+    }
+
+    fn cancel_search(&mut self) {
+        if let Some(prompt) = self.search_prompt.take() {
+            self.cursor.x = prompt.origin.0;
+            self.cursor.y = prompt.origin.1;
             self.select_to_cursor_pos();
         }
+        self.window.invalidate();
+    }
+
+    fn accept_search(&mut self) {
+        if let Some(prompt) = self.search_prompt.take() {
+            if prompt.pattern.is_empty() {
+                // Like vim, an empty pattern repeats the prior search,
+                // but in the newly requested direction
+                if let Some((pattern, _)) = self.last_search.take() {
+                    self.last_search.replace((pattern, prompt.direction));
+                    self.next_match(false);
+                }
+            } else {
+                self.last_search.replace((prompt.pattern, prompt.direction));
+            }
+        }
+        self.window.invalidate();
+    }
+
+    fn next_match(&mut self, reverse: bool) {
+        if let Some((pattern, direction)) = self.last_search.clone() {
+            let direction = if reverse {
+                direction.reverse()
+            } else {
+                direction
+            };
+            self.search(pattern, direction, (self.cursor.x, self.cursor.y));
+        }
+    }
+
+    /// Searches for pattern and moves the cursor to the first match
+    /// found in the specified direction from the origin.
+    /// The search is asynchronous; the cursor is moved once it completes.
+    fn search(&self, pattern: String, direction: SearchDirection, origin: (usize, StableRowIndex)) {
+        let pane: Rc<dyn Pane> = self.delegate.clone();
+        let window = self.window.clone();
+        promise::spawn::spawn(async move {
+            let results = pane
+                .search(Pattern::CaseSensitiveString(pattern.clone()))
+                .await?;
+
+            let pane_id = pane.pane_id();
+            let mut results = Some(results);
+            window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                let state = term_window.pane_state(pane_id);
+                if let Some(overlay) = state.overlay.as_ref() {
+                    if let Some(copy) = overlay.downcast_ref::<CopyOverlay>() {
+                        let mut r = copy.render.borrow_mut();
+                        // Ignore results for a pattern that has since been edited
+                        let stale = r
+                            .search_prompt
+                            .as_ref()
+                            .map(|prompt| prompt.pattern != pattern)
+                            .unwrap_or(false);
+                        if !stale {
+                            r.move_to_match(results.take().unwrap(), direction, origin);
+                        }
+                    }
+                }
+            })));
+            anyhow::Result::<()>::Ok(())
+        })
+        .detach();
+    }
+
+    fn move_to_match(
+        &mut self,
+        mut results: Vec<SearchResult>,
+        direction: SearchDirection,
+        origin: (usize, StableRowIndex),
+    ) {
+        let (x, y) = find_match(&mut results, direction, origin).unwrap_or(origin);
+        self.cursor.x = x;
+        self.cursor.y = y;
+        self.select_to_cursor_pos();
+    }
+
+    /// The search prompt is displayed on the bottom row of the viewport
+    fn compute_prompt_row(&self) -> StableRowIndex {
+        let dims = self.dimensions();
+        (dims.top + dims.dims.viewport_rows as StableRowIndex).saturating_sub(1)
     }
 }
 
@@ -384,8 +706,17 @@ impl Pane for CopyOverlay {
         format!("Copy mode: {}", self.delegate.get_title())
     }
 
-    fn send_paste(&self, _text: &str) -> anyhow::Result<()> {
-        anyhow::bail!("ignoring paste while copying");
+    fn send_paste(&self, text: &str) -> anyhow::Result<()> {
+        let mut r = self.render.borrow_mut();
+        match r.search_prompt.as_mut() {
+            Some(prompt) => {
+                // paste into the search prompt
+                prompt.pattern.push_str(text);
+                r.update_search();
+                Ok(())
+            }
+            None => anyhow::bail!("ignoring paste while copying"),
+        }
     }
 
     fn reader(&self) -> anyhow::Result<Option<Box<dyn std::io::Read + Send>>> {
@@ -405,78 +736,47 @@ impl Pane for CopyOverlay {
     }
 
     fn key_down(&self, key: KeyCode, mods: KeyModifiers) -> anyhow::Result<()> {
-        match (key, mods) {
-            (KeyCode::Char('c'), KeyModifiers::CTRL)
-            | (KeyCode::Char('g'), KeyModifiers::CTRL)
-            | (KeyCode::Char('q'), KeyModifiers::NONE)
-            | (KeyCode::Escape, KeyModifiers::NONE) => self.render.borrow().close(),
-            (KeyCode::Char('h'), KeyModifiers::NONE) | (KeyCode::LeftArrow, KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_left_single_cell();
-            }
-            (KeyCode::Char('j'), KeyModifiers::NONE) | (KeyCode::DownArrow, KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_down_single_row();
-            }
-            (KeyCode::Char('k'), KeyModifiers::NONE) | (KeyCode::UpArrow, KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_up_single_row();
-            }
-            (KeyCode::Char('l'), KeyModifiers::NONE)
-            | (KeyCode::RightArrow, KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_right_single_cell();
-            }
-
-            (KeyCode::RightArrow, KeyModifiers::ALT) |
-            (KeyCode::Char('f'), KeyModifiers::ALT)|
-            (KeyCode::Tab, KeyModifiers::NONE) |
-            (KeyCode::Char('w'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_forward_one_word();
+        // Most keys are handled via the copy_mode key table and arrive
+        // via perform_assignment; we only see keys here while collecting
+        // input for a jump or a search.
+        let mut r = self.render.borrow_mut();
+        if let Some(jump) = r.pending_jump.take() {
+            if let (KeyCode::Char(c), KeyModifiers::NONE)
+            | (KeyCode::Char(c), KeyModifiers::SHIFT) = (key, mods)
+            {
+                r.last_jump.replace((jump, c));
+                r.jump(jump, c, false);
             }
+            return Ok(());
+        }
 
-            (KeyCode::LeftArrow, KeyModifiers::ALT) |
-            (KeyCode::Char('b'), KeyModifiers::ALT) |
-            (KeyCode::Tab, KeyModifiers::SHIFT) |
-            (KeyCode::Char('b'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_backward_one_word();
-            }
-            (KeyCode::Char('0'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_start_of_line();
-            }
-            (KeyCode::Enter, KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_start_of_next_line();
-            }
-            (KeyCode::Char('$'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
-            (KeyCode::Char('$'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_end_of_line_content();
-            }
-            (KeyCode::Char('m'), KeyModifiers::ALT) |
-            (KeyCode::Char('^'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
-            (KeyCode::Char('^'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_start_of_line_content();
-            }
-            (KeyCode::Char(' '), KeyModifiers::NONE) | (KeyCode::Char('v'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().toggle_selection_by_cell();
-            }
-            (KeyCode::Char('G'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
-            (KeyCode::Char('G'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_bottom();
-            }
-            (KeyCode::Char('g'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_top();
-            }
-            (KeyCode::Char('H'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
-            (KeyCode::Char('H'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_viewport_top();
-            }
-            (KeyCode::Char('M'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
-            (KeyCode::Char('M'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_viewport_middle();
-            }
-            (KeyCode::Char('L'), KeyModifiers::SHIFT) | // FIXME: normalize the shift away!
-            (KeyCode::Char('L'), KeyModifiers::NONE) => {
-                self.render.borrow_mut().move_to_viewport_bottom();
+        if r.search_prompt.is_some() {
+            match (key, mods) {
+                (KeyCode::Escape, KeyModifiers::NONE)
+                | (KeyCode::Char('c'), KeyModifiers::CTRL)
+                | (KeyCode::Char('g'), KeyModifiers::CTRL) => r.cancel_search(),
+                (KeyCode::Enter, KeyModifiers::NONE) => r.accept_search(),
+                (KeyCode::Char(c), KeyModifiers::NONE)
+                | (KeyCode::Char(c), KeyModifiers::SHIFT) => {
+                    if let Some(prompt) = r.search_prompt.as_mut() {
+                        prompt.pattern.push(c);
+                    }
+                    r.update_search();
+                }
+                (KeyCode::Backspace, KeyModifiers::NONE) => {
+                    if let Some(prompt) = r.search_prompt.as_mut() {
+                        prompt.pattern.pop();
+                    }
+                    r.update_search();
+                }
+                (KeyCode::Char('u'), KeyModifiers::CTRL) => {
+                    if let Some(prompt) = r.search_prompt.as_mut() {
+                        prompt.pattern.clear();
+                    }
+                    r.update_search();
+                }
+                _ => {}
             }
-            (KeyCode::PageUp, KeyModifiers::NONE) | (KeyCode::Char('b'), KeyModifiers::CTRL) => self.render.borrow_mut().page_up(),
-            (KeyCode::PageDown, KeyModifiers::NONE) | (KeyCode::Char('f'), KeyModifiers::CTRL) => self.render.borrow_mut().page_down(),
-            _ => {}
         }
         Ok(())
     }
@@ -523,7 +823,16 @@ impl Pane for CopyOverlay {
     }
 
     fn get_cursor_position(&self) -> StableCursorPosition {
-        self.render.borrow().cursor
+        let r = self.render.borrow();
+        match &r.search_prompt {
+            // move to the search prompt
+            Some(prompt) => StableCursorPosition {
+                x: 1 + unicode_column_width(&prompt.pattern, None),
+                y: r.compute_prompt_row(),
+                ..r.cursor
+            },
+            None => r.cursor,
+        }
     }
 
     fn get_current_seqno(&self) -> SequenceNo {
@@ -535,11 +844,49 @@ impl Pane for CopyOverlay {
         lines: Range<StableRowIndex>,
         seqno: SequenceNo,
     ) -> RangeSet<StableRowIndex> {
-        self.delegate.get_changed_since(lines, seqno)
+        let mut dirty = self.delegate.get_changed_since(lines.clone(), seqno);
+        let r = self.render.borrow();
+        if let Some(row) = r.prompt_row {
+            dirty.add(row);
+        }
+        if r.search_prompt.is_some() {
+            dirty.add(r.compute_prompt_row());
+        }
+        dirty.intersection_with_range(lines)
     }
 
     fn get_lines(&self, lines: Range<StableRowIndex>) -> (StableRowIndex, Vec<Line>) {
-        self.delegate.get_lines(lines)
+        let (top, mut lines) = self.delegate.get_lines(lines);
+        let mut r = self.render.borrow_mut();
+        let in_range = |row: StableRowIndex| row >= top && ((row - top) as usize) < lines.len();
+
+        if r.prompt_row.map(in_range).unwrap_or(false) {
+            // The prior prompt row is being redrawn, so it no longer
+            // needs to be reported as changed
+            r.prompt_row = None;
+        }
+
+        let row = r.compute_prompt_row();
+        if let Some(prompt) = r.search_prompt.as_ref().filter(|_| in_range(row)) {
+            // Replace the bottom row with the search prompt
+            let line = &mut lines[(row - top) as usize];
+            let cols = r.dimensions().dims.cols;
+            let rev = CellAttributes::default().set_reverse(true).clone();
+            line.fill_range(0..cols, &Cell::new(' ', rev.clone()), SEQ_ZERO);
+            let leader = match prompt.direction {
+                SearchDirection::Forward => '/',
+                SearchDirection::Backward => '?',
+            };
+            line.overlay_text_with_attribute(
+                0,
+                &format!("{}{}", leader, prompt.pattern),
+                rev,
+                SEQ_ZERO,
+            );
+            r.prompt_row = Some(row);
+        }
+
+        (top, lines)
     }
 
     fn get_dimensions(&self) -> RenderableDimensions {
@@ -547,6 +894,104 @@ impl Pane for CopyOverlay {
     }
 }
 
+/// Returns the default key assignments for copy mode.
+/// These can be overridden via the `copy_mode` entry in `key_tables`.
+pub fn copy_key_table() -> KeyTable {
+    use window::{KeyCode as WKeyCode, Modifiers as WMods};
+    use CopyModeAssignment::*;
+
+    let mut table = KeyTable::default();
+    for (key, mods, action) in [
+        (WKeyCode::Char('\u{1b}'), WMods::NONE, Close),
+        (WKeyCode::Char('c'), WMods::CTRL, Close),
+        (WKeyCode::Char('g'), WMods::CTRL, Close),
+        (WKeyCode::Char('q'), WMods::NONE, Close),
+        (WKeyCode::Char('h'), WMods::NONE, MoveLeft),
+        (WKeyCode::LeftArrow, WMods::NONE, MoveLeft),
+        (WKeyCode::Char('j'), WMods::NONE, MoveDown),
+        (WKeyCode::DownArrow, WMods::NONE, MoveDown),
+        (WKeyCode::Char('k'), WMods::NONE, MoveUp),
+        (WKeyCode::UpArrow, WMods::NONE, MoveUp),
+        (WKeyCode::Char('l'), WMods::NONE, MoveRight),
+        (WKeyCode::RightArrow, WMods::NONE, MoveRight),
+        (WKeyCode::RightArrow, WMods::ALT, MoveForwardWord),
+        (WKeyCode::Char('f'), WMods::ALT, MoveForwardWord),
+        (WKeyCode::Char('\t'), WMods::NONE, MoveForwardWord),
+        (WKeyCode::Char('w'), WMods::NONE, MoveForwardWord),
+        (WKeyCode::LeftArrow, WMods::ALT, MoveBackwardWord),
+        (WKeyCode::Char('b'), WMods::ALT, MoveBackwardWord),
+        (WKeyCode::Char('\t'), WMods::SHIFT, MoveBackwardWord),
+        (WKeyCode::Char('b'), WMods::NONE, MoveBackwardWord),
+        (WKeyCode::Char('0'), WMods::NONE, MoveToStartOfLine),
+        (WKeyCode::Char('\r'), WMods::NONE, MoveToStartOfNextLine),
+        (WKeyCode::Char('$'), WMods::NONE, MoveToEndOfLineContent),
+        (WKeyCode::Char('$'), WMods::SHIFT, MoveToEndOfLineContent),
+        (WKeyCode::Char('m'), WMods::ALT, MoveToStartOfLineContent),
+        (WKeyCode::Char('^'), WMods::NONE, MoveToStartOfLineContent),
+        (WKeyCode::Char('^'), WMods::SHIFT, MoveToStartOfLineContent),
+        (
+            WKeyCode::Char(' '),
+            WMods::NONE,
+            SetSelectionMode(Some(SelectionMode::Cell)),
+        ),
+        (
+            WKeyCode::Char('v'),
+            WMods::NONE,
+            SetSelectionMode(Some(SelectionMode::Cell)),
+        ),
+        (
+            WKeyCode::Char('V'),
+            WMods::NONE,
+            SetSelectionMode(Some(SelectionMode::Line)),
+        ),
+        (
+            WKeyCode::Char('v'),
+            WMods::CTRL,
+            SetSelectionMode(Some(SelectionMode::Block)),
+        ),
+        (WKeyCode::Char('G'), WMods::NONE, MoveToScrollbackBottom),
+        (WKeyCode::Char('g'), WMods::NONE, MoveToScrollbackTop),
+        (WKeyCode::Char('H'), WMods::NONE, MoveToViewportTop),
+        (WKeyCode::Char('M'), WMods::NONE, MoveToViewportMiddle),
+        (WKeyCode::Char('L'), WMods::NONE, MoveToViewportBottom),
+        (WKeyCode::PageUp, WMods::NONE, PageUp),
+        (WKeyCode::Char('b'), WMods::CTRL, PageUp),
+        (WKeyCode::PageDown, WMods::NONE, PageDown),
+        (WKeyCode::Char('f'), WMods::CTRL, PageDown),
+        (WKeyCode::Char('/'), WMods::NONE, SearchForward),
+        (WKeyCode::Char('?'), WMods::NONE, SearchBackward),
+        (WKeyCode::Char('?'), WMods::SHIFT, SearchBackward),
+        (WKeyCode::Char('n'), WMods::NONE, NextMatch),
+        (WKeyCode::Char('N'), WMods::NONE, PriorMatch),
+        (
+            WKeyCode::Char('f'),
+            WMods::NONE,
+            JumpForward { till: false },
+        ),
+        (
+            WKeyCode::Char('F'),
+            WMods::NONE,
+            JumpBackward { till: false },
+        ),
+        (WKeyCode::Char('t'), WMods::NONE, JumpForward { till: true }),
+        (
+            WKeyCode::Char('T'),
+            WMods::NONE,
+            JumpBackward { till: true },
+        ),
+        (WKeyCode::Char(';'), WMods::NONE, JumpAgain),
+        (WKeyCode::Char(','), WMods::NONE, JumpReverse),
+    ] {
+        table.insert(
+            (key, mods),
+            KeyTableEntry {
+                action: KeyAssignment::CopyMode(action),
+            },
+        );
+    }
+    table
+}
+
 fn is_whitespace_word(word: &str) -> bool {
     if let Some(c) = word.chars().next() {
         c.is_whitespace()
@@ -554,3 +999,123 @@ fn is_whitespace_word(word: &str) -> bool {
         false
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FORWARD: Jump = Jump {
+        forward: true,
+        till: false,
+    };
+    const FORWARD_TILL: Jump = Jump {
+        forward: true,
+        till: true,
+    };
+    const BACKWARD: Jump = Jump {
+        forward: false,
+        till: false,
+    };
+    const BACKWARD_TILL: Jump = Jump {
+        forward: false,
+        till: true,
+    };
+
+    fn line(s: &str) -> Line {
+        Line::from_text(s, &CellAttributes::default(), SEQ_ZERO, None)
+    }
+
+    fn result(start_x: usize, start_y: StableRowIndex) -> SearchResult {
+        SearchResult {
+            start_y,
+            start_x,
+            end_y: start_y,
+            end_x: start_x + 1,
+            match_id: 0,
+        }
+    }
+
+    #[test]
+    fn jump_forward() {
+        let line = line("a.b.c.d");
+        assert_eq!(jump_target(&line, 0, FORWARD, '.', false), Some(1));
+        // The cell under the cursor is never a match
+        assert_eq!(jump_target(&line, 1, FORWARD, '.', false), Some(3));
+        assert_eq!(jump_target(&line, 0, FORWARD, 'd', false), Some(6));
+        assert_eq!(jump_target(&line, 0, FORWARD, 'z', false), None);
+        assert_eq!(jump_target(&line, 6, FORWARD, '.', false), None);
+    }
+
+    #[test]
+    fn jump_backward() {
+        let line = line("a.b.c.d");
+        assert_eq!(jump_target(&line, 6, BACKWARD, '.', false), Some(5));
+        assert_eq!(jump_target(&line, 5, BACKWARD, '.', false), Some(3));
+        assert_eq!(jump_target(&line, 6, BACKWARD, 'a', false), Some(0));
+        assert_eq!(jump_target(&line, 0, BACKWARD, 'a', false), None);
+    }
+
+    #[test]
+    fn jump_till() {
+        let line = line("a.b.c.d");
+        assert_eq!(jump_target(&line, 0, FORWARD_TILL, '.', false), Some(0));
+        assert_eq!(jump_target(&line, 0, FORWARD_TILL, 'c', false), Some(3));
+        assert_eq!(jump_target(&line, 6, BACKWARD_TILL, '.', false), Some(6));
+        assert_eq!(jump_target(&line, 6, BACKWARD_TILL, 'b', false), Some(3));
+
+        // Repeating a till jump must not get stuck next to the prior match
+        assert_eq!(jump_target(&line, 2, FORWARD_TILL, '.', true), Some(4));
+        assert_eq!(jump_target(&line, 4, BACKWARD_TILL, '.', true), Some(2));
+        assert_eq!(jump_target(&line, 0, BACKWARD_TILL, '.', true), None);
+    }
+
+    #[test]
+    fn match_forward() {
+        let mut results = vec![result(5, 2), result(1, 0), result(3, 1)];
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Forward, (0, 0)),
+            Some((1, 0))
+        );
+        // A match at the origin is skipped so that next_match advances
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Forward, (1, 0)),
+            Some((3, 1))
+        );
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Forward, (4, 1)),
+            Some((5, 2))
+        );
+        // Wraps around to the first match
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Forward, (5, 2)),
+            Some((1, 0))
+        );
+    }
+
+    #[test]
+    fn match_backward() {
+        let mut results = vec![result(5, 2), result(1, 0), result(3, 1)];
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Backward, (0, 3)),
+            Some((5, 2))
+        );
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Backward, (5, 2)),
+            Some((3, 1))
+        );
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Backward, (2, 1)),
+            Some((1, 0))
+        );
+        // Wraps around to the last match
+        assert_eq!(
+            find_match(&mut results, SearchDirection::Backward, (1, 0)),
+            Some((5, 2))
+        );
+    }
+
+    #[test]
+    fn match_none() {
+        assert_eq!(find_match(&mut [], SearchDirection::Forward, (1, 1)), None);
+    }
+}
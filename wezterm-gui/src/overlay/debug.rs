@@ -12,13 +12,13 @@ use termwiz::surface::Change;
 use termwiz::terminal::Terminal;
 
 struct LuaReplHost {
-    history: BasicHistory,
+    history: Box<dyn History>,
     lua: mlua::Lua,
 }
 
 impl LineEditorHost for LuaReplHost {
     fn history(&mut self) -> &mut dyn History {
-        &mut self.history
+        &mut *self.history
     }
 
     fn resolve_action(
@@ -75,10 +75,22 @@ pub fn show_debug_overlay(mut term: TermWizTerminal, gui_win: GuiWin) -> anyhow:
     lua.globals().set("window", gui_win)?;
 
     let mut latest_log_entry = None;
-    let mut host = LuaReplHost {
-        history: BasicHistory::default(),
-        lua,
+    let history_path = config::DATA_DIR.join("repl-history");
+    if let Err(err) = config::create_user_owned_dirs(&config::DATA_DIR) {
+        log::error!("unable to create {}: {:#}", config::DATA_DIR.display(), err);
+    }
+    let history: Box<dyn History> = match FileHistory::open(&history_path) {
+        Ok(history) => Box::new(history),
+        Err(err) => {
+            log::error!(
+                "unable to load repl history from {}: {:#}",
+                history_path.display(),
+                err
+            );
+            Box::new(BasicHistory::default())
+        }
     };
+    let mut host = LuaReplHost { history, lua };
 
     term.render(&[Change::Title("Debug".to_string())])?;
 
@@ -6,12 +6,10 @@
 //! time of writing our window layer doesn't provide an API for context
 //! menus.
 use crate::inputmap::InputMap;
+use crate::overlay::menu::{Menu, MenuEntry};
 use crate::termwindow::TermWindowNotif;
 use config::configuration;
 use config::keyassignment::{KeyAssignment, SpawnCommand, SpawnTabDomain};
-use config::lua::truncate_right;
-use fuzzy_matcher::skim::SkimMatcherV2;
-use fuzzy_matcher::FuzzyMatcher;
 use mux::domain::{DomainId, DomainState};
 use mux::pane::PaneId;
 use mux::tab::TabId;
@@ -19,21 +17,23 @@ use mux::termwiztermtab::TermWizTerminal;
 use mux::window::WindowId;
 use mux::Mux;
 use std::collections::BTreeMap;
-use termwiz::cell::{AttributeChange, CellAttributes};
-use termwiz::color::ColorAttribute;
-use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
-use termwiz::surface::{Change, Position};
+use termwiz::surface::Change;
 use termwiz::terminal::Terminal;
 use window::WindowOps;
 
 pub use config::keyassignment::LauncherFlags;
 
-#[derive(Clone)]
 struct Entry {
     pub label: String,
     pub action: KeyAssignment,
 }
 
+impl MenuEntry for Entry {
+    fn label(&self) -> &str {
+        &self.label
+    }
+}
+
 pub struct LauncherTabEntry {
     pub title: String,
     pub tab_id: TabId,
@@ -155,402 +155,139 @@ impl LauncherArgs {
     }
 }
 
-const ROW_OVERHEAD: usize = 3;
-
-struct LauncherState {
-    active_idx: usize,
-    max_items: usize,
-    top_row: usize,
-    entries: Vec<Entry>,
-    filter_term: String,
-    filtered_entries: Vec<Entry>,
-    pane_id: PaneId,
-    window: ::window::Window,
-    filtering: bool,
-    flags: LauncherFlags,
-}
-
-impl LauncherState {
-    fn update_filter(&mut self) {
-        if self.filter_term.is_empty() {
-            self.filtered_entries = self.entries.clone();
-            return;
-        }
-
-        self.filtered_entries.clear();
-
-        let matcher = SkimMatcherV2::default();
-
-        struct MatchResult {
-            row_idx: usize,
-            score: i64,
-        }
-
-        let mut scores: Vec<MatchResult> = self
-            .entries
-            .iter()
-            .enumerate()
-            .filter_map(|(row_idx, entry)| {
-                let score = matcher.fuzzy_match(&entry.label, &self.filter_term)?;
-                Some(MatchResult { row_idx, score })
-            })
-            .collect();
-
-        scores.sort_by(|a, b| a.score.cmp(&b.score).reverse());
-
-        for result in scores {
-            self.filtered_entries
-                .push(self.entries[result.row_idx].clone());
-        }
-
-        self.active_idx = 0;
-        self.top_row = 0;
-    }
-
-    fn build_entries(&mut self, args: LauncherArgs) {
-        let config = configuration();
-        // Pull in the user defined entries from the launch_menu
-        // section of the configuration.
-        if args.flags.contains(LauncherFlags::LAUNCH_MENU_ITEMS) {
-            for item in &config.launch_menu {
-                self.entries.push(Entry {
-                    label: match item.label.as_ref() {
-                        Some(label) => label.to_string(),
-                        None => match item.args.as_ref() {
-                            Some(args) => args.join(" "),
-                            None => "(default shell)".to_string(),
-                        },
+/// Returns the entries for the launcher, and the index of the entry
+/// that should be selected initially
+fn build_entries(args: &LauncherArgs) -> (Vec<Entry>, usize) {
+    let mut entries = vec![];
+    let mut active_idx = 0;
+    let config = configuration();
+    // Pull in the user defined entries from the launch_menu
+    // section of the configuration.
+    if args.flags.contains(LauncherFlags::LAUNCH_MENU_ITEMS) {
+        for item in &config.launch_menu {
+            entries.push(Entry {
+                label: match item.label.as_ref() {
+                    Some(label) => label.to_string(),
+                    None => match item.args.as_ref() {
+                        Some(args) => args.join(" "),
+                        None => "(default shell)".to_string(),
                     },
-                    action: KeyAssignment::SpawnCommandInNewTab(item.clone()),
-                });
-            }
-        }
-
-        for domain in &args.domains {
-            let entry = if domain.state == DomainState::Attached {
-                Entry {
-                    label: format!("New Tab ({})", domain.label),
-                    action: KeyAssignment::SpawnCommandInNewTab(SpawnCommand {
-                        domain: SpawnTabDomain::DomainName(domain.name.to_string()),
-                        ..SpawnCommand::default()
-                    }),
-                }
-            } else {
-                Entry {
-                    label: format!("Attach {}", domain.label),
-                    action: KeyAssignment::AttachDomain(domain.name.to_string()),
-                }
-            };
-
-            // Preselect the entry that corresponds to the active tab
-            // at the time that the launcher was set up, so that pressing
-            // Enter immediately afterwards spawns a tab in the same domain.
-            if domain.domain_id == args.domain_id_of_current_tab {
-                self.active_idx = self.entries.len();
-            }
-            self.entries.push(entry);
-        }
-
-        if args.flags.contains(LauncherFlags::WORKSPACES) {
-            for ws in &args.workspaces {
-                if *ws != args.active_workspace {
-                    self.entries.push(Entry {
-                        label: format!("Switch to workspace: `{}`", ws),
-                        action: KeyAssignment::SwitchToWorkspace {
-                            name: Some(ws.clone()),
-                            spawn: None,
-                        },
-                    });
-                }
-            }
-            self.entries.push(Entry {
-                label: format!(
-                    "Create new Workspace (current is `{}`)",
-                    args.active_workspace
-                ),
-                action: KeyAssignment::SwitchToWorkspace {
-                    name: None,
-                    spawn: None,
                 },
+                action: KeyAssignment::SpawnCommandInNewTab(item.clone()),
             });
         }
-
-        for tab in &args.tabs {
-            self.entries.push(Entry {
-                label: format!("{}. {} panes", tab.title, tab.pane_count),
-                action: KeyAssignment::ActivateTab(tab.tab_idx as isize),
-            });
-        }
-
-        if args.flags.contains(LauncherFlags::COMMANDS) {
-            let commands = crate::commands::CommandDef::expanded_commands(&config);
-            for cmd in commands {
-                if matches!(
-                    &cmd.action,
-                    KeyAssignment::ActivateTabRelative(_) | KeyAssignment::ActivateTab(_)
-                ) {
-                    // Filter out some noisy, repetitive entries
-                    continue;
-                }
-                self.entries.push(Entry {
-                    label: format!("{}. {}", cmd.brief, cmd.doc),
-                    action: cmd.action,
-                });
-            }
-        }
-
-        // Grab interesting key assignments and show those as a kind of command palette
-        if args.flags.contains(LauncherFlags::KEY_ASSIGNMENTS) {
-            let input_map = InputMap::new(&config);
-            let mut key_entries: Vec<Entry> = vec![];
-            // Give a consistent order to the entries
-            let keys: BTreeMap<_, _> = input_map.keys.default.into_iter().collect();
-            for ((keycode, mods), entry) in keys {
-                if matches!(
-                    &entry.action,
-                    KeyAssignment::ActivateTabRelative(_) | KeyAssignment::ActivateTab(_)
-                ) {
-                    // Filter out some noisy, repetitive entries
-                    continue;
-                }
-                if key_entries
-                    .iter()
-                    .find(|ent| ent.action == entry.action)
-                    .is_some()
-                {
-                    // Avoid duplicate entries
-                    continue;
-                }
-                key_entries.push(Entry {
-                    label: format!(
-                        "{:?} ({} {})",
-                        entry.action,
-                        mods.to_string(),
-                        keycode.to_string().escape_debug()
-                    ),
-                    action: entry.action,
-                });
-            }
-            key_entries.sort_by(|a, b| a.label.cmp(&b.label));
-            self.entries.append(&mut key_entries);
-        }
     }
 
-    fn render(&mut self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
-        let size = term.get_screen_size()?;
-        let max_width = size.cols.saturating_sub(6);
-
-        let mut changes = vec![
-            Change::ClearScreen(ColorAttribute::Default),
-            Change::CursorPosition {
-                x: Position::Absolute(0),
-                y: Position::Absolute(0),
-            },
-            Change::Text(format!(
-                "{}\r\n",
-                truncate_right(
-                    "Select an item and press Enter=launch  \
-                     Esc=cancel  /=filter",
-                    max_width
-                )
-            )),
-            Change::AllAttributes(CellAttributes::default()),
-        ];
-
-        let max_items = self.max_items;
-
-        for (row_num, (entry_idx, entry)) in self
-            .filtered_entries
-            .iter()
-            .enumerate()
-            .skip(self.top_row)
-            .enumerate()
-        {
-            if row_num > max_items {
-                break;
+    for domain in &args.domains {
+        let entry = if domain.state == DomainState::Attached {
+            Entry {
+                label: format!("New Tab ({})", domain.label),
+                action: KeyAssignment::SpawnCommandInNewTab(SpawnCommand {
+                    domain: SpawnTabDomain::DomainName(domain.name.to_string()),
+                    ..SpawnCommand::default()
+                }),
             }
-            if entry_idx == self.active_idx {
-                changes.push(AttributeChange::Reverse(true).into());
-            }
-
-            let label = truncate_right(&entry.label, max_width);
-            if row_num < 9 && !self.filtering {
-                changes.push(Change::Text(format!(" {}. {} \r\n", row_num + 1, label)));
-            } else {
-                changes.push(Change::Text(format!("    {} \r\n", label)));
+        } else {
+            Entry {
+                label: format!("Attach {}", domain.label),
+                action: KeyAssignment::AttachDomain(domain.name.to_string()),
             }
+        };
 
-            if entry_idx == self.active_idx {
-                changes.push(AttributeChange::Reverse(false).into());
-            }
+        // Preselect the entry that corresponds to the active tab
+        // at the time that the launcher was set up, so that pressing
+        // Enter immediately afterwards spawns a tab in the same domain.
+        if domain.domain_id == args.domain_id_of_current_tab {
+            active_idx = entries.len();
         }
-
-        if self.filtering || !self.filter_term.is_empty() {
-            changes.append(&mut vec![
-                Change::CursorPosition {
-                    x: Position::Absolute(0),
-                    y: Position::Absolute(0),
-                },
-                Change::ClearToEndOfLine(ColorAttribute::Default),
-                Change::Text(truncate_right(
-                    &format!("Fuzzy matching: {}", self.filter_term),
-                    max_width,
-                )),
-            ]);
-        }
-
-        term.render(&changes)
+        entries.push(entry);
     }
 
-    fn launch(&self, active_idx: usize) {
-        let assignment = self.filtered_entries[active_idx].action.clone();
-        self.window.notify(TermWindowNotif::PerformAssignment {
-            pane_id: self.pane_id,
-            assignment,
+    if args.flags.contains(LauncherFlags::WORKSPACES) {
+        for ws in &args.workspaces {
+            if *ws != args.active_workspace {
+                entries.push(Entry {
+                    label: format!("Switch to workspace: `{}`", ws),
+                    action: KeyAssignment::SwitchToWorkspace {
+                        name: Some(ws.clone()),
+                        spawn: None,
+                    },
+                });
+            }
+        }
+        entries.push(Entry {
+            label: format!(
+                "Create new Workspace (current is `{}`)",
+                args.active_workspace
+            ),
+            action: KeyAssignment::SwitchToWorkspace {
+                name: None,
+                spawn: None,
+            },
         });
     }
 
-    fn move_up(&mut self) {
-        self.active_idx = self.active_idx.saturating_sub(1);
-        if self.active_idx < self.top_row {
-            self.top_row = self.active_idx;
-        }
+    for tab in &args.tabs {
+        entries.push(Entry {
+            label: format!("{}. {} panes", tab.title, tab.pane_count),
+            action: KeyAssignment::ActivateTab(tab.tab_idx as isize),
+        });
     }
 
-    fn move_down(&mut self) {
-        self.active_idx = (self.active_idx + 1).min(self.filtered_entries.len() - 1);
-        if self.active_idx + self.top_row > self.max_items {
-            self.top_row = self.active_idx.saturating_sub(self.max_items);
+    if args.flags.contains(LauncherFlags::COMMANDS) {
+        let commands = crate::commands::CommandDef::expanded_commands(&config);
+        for cmd in commands {
+            if matches!(
+                &cmd.action,
+                KeyAssignment::ActivateTabRelative(_) | KeyAssignment::ActivateTab(_)
+            ) {
+                // Filter out some noisy, repetitive entries
+                continue;
+            }
+            entries.push(Entry {
+                label: format!("{}. {}", cmd.brief, cmd.doc),
+                action: cmd.action,
+            });
         }
     }
 
-    fn run_loop(&mut self, term: &mut TermWizTerminal) -> anyhow::Result<()> {
-        while let Ok(Some(event)) = term.poll_input(None) {
-            match event {
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char(c),
-                    ..
-                }) if !self.filtering && c >= '1' && c <= '9' => {
-                    self.launch(self.top_row + (c as u32 - '1' as u32) as usize);
-                    break;
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char('j'),
-                    ..
-                }) if !self.filtering => {
-                    self.move_down();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char('k'),
-                    ..
-                }) if !self.filtering => {
-                    self.move_up();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char('P'),
-                    modifiers: Modifiers::CTRL,
-                }) => {
-                    self.move_up();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char('N'),
-                    modifiers: Modifiers::CTRL,
-                }) => {
-                    self.move_down();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char('/'),
-                    ..
-                }) if !self.filtering => {
-                    self.filtering = true;
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Backspace,
-                    ..
-                }) => {
-                    if self.filter_term.pop().is_none()
-                        && !self.flags.contains(LauncherFlags::FUZZY)
-                    {
-                        self.filtering = false;
-                    }
-                    self.update_filter();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Char(c),
-                    ..
-                }) if self.filtering => {
-                    self.filter_term.push(c);
-                    self.update_filter();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::UpArrow,
-                    ..
-                }) => {
-                    self.move_up();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::DownArrow,
-                    ..
-                }) => {
-                    self.move_down();
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Escape,
-                    ..
-                }) => {
-                    break;
-                }
-                InputEvent::Mouse(MouseEvent {
-                    y, mouse_buttons, ..
-                }) if mouse_buttons.contains(MouseButtons::VERT_WHEEL) => {
-                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
-                        self.top_row = self.top_row.saturating_sub(1);
-                    } else {
-                        self.top_row += 1;
-                        self.top_row = self.top_row.min(
-                            self.filtered_entries
-                                .len()
-                                .saturating_sub(self.max_items)
-                                .saturating_sub(1),
-                        );
-                    }
-                    if y > 0 && y as usize <= self.filtered_entries.len() {
-                        self.active_idx = self.top_row + y as usize - 1;
-                    }
-                }
-                InputEvent::Mouse(MouseEvent {
-                    y, mouse_buttons, ..
-                }) => {
-                    if y > 0 && y as usize <= self.filtered_entries.len() {
-                        self.active_idx = self.top_row + y as usize - 1;
-
-                        if mouse_buttons == MouseButtons::LEFT {
-                            self.launch(self.active_idx);
-                            break;
-                        }
-                    }
-                    if mouse_buttons != MouseButtons::NONE {
-                        // Treat any other mouse button as cancel
-                        break;
-                    }
-                }
-                InputEvent::Key(KeyEvent {
-                    key: KeyCode::Enter,
-                    ..
-                }) => {
-                    self.launch(self.active_idx);
-                    break;
-                }
-                InputEvent::Resized { rows, .. } => {
-                    self.max_items = rows.saturating_sub(ROW_OVERHEAD);
-                }
-                _ => {}
+    // Grab interesting key assignments and show those as a kind of command palette
+    if args.flags.contains(LauncherFlags::KEY_ASSIGNMENTS) {
+        let input_map = InputMap::new(&config);
+        let mut key_entries: Vec<Entry> = vec![];
+        // Give a consistent order to the entries
+        let keys: BTreeMap<_, _> = input_map.keys.default.into_iter().collect();
+        for ((keycode, mods), entry) in keys {
+            if matches!(
+                &entry.action,
+                KeyAssignment::ActivateTabRelative(_) | KeyAssignment::ActivateTab(_)
+            ) {
+                // Filter out some noisy, repetitive entries
+                continue;
+            }
+            if key_entries
+                .iter()
+                .find(|ent| ent.action == entry.action)
+                .is_some()
+            {
+                // Avoid duplicate entries
+                continue;
             }
-            self.render(term)?;
+            key_entries.push(Entry {
+                label: format!(
+                    "{:?} ({} {})",
+                    entry.action,
+                    mods.to_string(),
+                    keycode.to_string().escape_debug()
+                ),
+                action: entry.action,
+            });
         }
-
-        Ok(())
+        key_entries.sort_by(|a, b| a.label.cmp(&b.label));
+        entries.append(&mut key_entries);
     }
+
+    (entries, active_idx)
 }
 
 pub fn launcher(
@@ -558,25 +295,18 @@ pub fn launcher(
     mut term: TermWizTerminal,
     window: ::window::Window,
 ) -> anyhow::Result<()> {
-    let size = term.get_screen_size()?;
-    let max_items = size.rows.saturating_sub(ROW_OVERHEAD);
-    let mut state = LauncherState {
-        active_idx: 0,
-        max_items,
-        pane_id: args.pane_id,
-        top_row: 0,
-        entries: vec![],
-        filter_term: String::new(),
-        filtered_entries: vec![],
-        window,
-        filtering: args.flags.contains(LauncherFlags::FUZZY),
-        flags: args.flags,
-    };
-
     term.set_raw_mode()?;
     term.render(&[Change::Title(args.title.to_string())])?;
-    state.build_entries(args);
-    state.update_filter();
-    state.render(&mut term)?;
-    state.run_loop(&mut term)
+
+    let (entries, active_idx) = build_entries(&args);
+    let mut menu = Menu::new(entries, "launch", args.flags.contains(LauncherFlags::FUZZY));
+    menu.set_active_idx(active_idx);
+
+    if let Some(entry) = menu.run(&mut term)? {
+        window.notify(TermWindowNotif::PerformAssignment {
+            pane_id: args.pane_id,
+            assignment: entry.action,
+        });
+    }
+    Ok(())
 }
@@ -0,0 +1,388 @@
+//! A menu presents a list of entries and allows the user to pick one
+//! of them using the keyboard or the mouse, optionally using fuzzy
+//! matching to filter the list.
+//! This is the common part of the launcher and the input selector.
+use config::lua::truncate_right;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::cell::{AttributeChange, CellAttributes, Intensity};
+use termwiz::color::ColorAttribute;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent, Modifiers, MouseButtons, MouseEvent};
+use termwiz::surface::{Change, Position};
+use termwiz::terminal::Terminal;
+
+/// The number of rows that are not available for displaying entries
+const ROW_OVERHEAD: usize = 3;
+
+pub trait MenuEntry {
+    fn label(&self) -> &str;
+
+    /// Additional text that is displayed, dimmed, after the label
+    fn description(&self) -> Option<&str> {
+        None
+    }
+}
+
+pub struct Menu<T> {
+    entries: Vec<T>,
+    /// Describes what happens when an entry is picked; displayed
+    /// in the hint at the top of the menu
+    accept_label: &'static str,
+    active_idx: usize,
+    max_items: usize,
+    top_row: usize,
+    filter_term: String,
+    /// Indices into `entries` of the entries that match the filter
+    filtered_entries: Vec<usize>,
+    filtering: bool,
+    always_fuzzy: bool,
+}
+
+impl<T: MenuEntry> Menu<T> {
+    /// If `fuzzy` is true, the menu starts out in filtering mode
+    /// and remains in it when the filter is cleared.
+    pub fn new(entries: Vec<T>, accept_label: &'static str, fuzzy: bool) -> Self {
+        let mut menu = Self {
+            entries,
+            accept_label,
+            active_idx: 0,
+            max_items: 0,
+            top_row: 0,
+            filter_term: String::new(),
+            filtered_entries: vec![],
+            filtering: fuzzy,
+            always_fuzzy: fuzzy,
+        };
+        menu.update_filter();
+        menu
+    }
+
+    /// Selects the entry at `idx` in the list of entries
+    pub fn set_active_idx(&mut self, idx: usize) {
+        self.active_idx = idx.min(self.filtered_entries.len().saturating_sub(1));
+    }
+
+    fn update_filter(&mut self) {
+        if self.filter_term.is_empty() {
+            self.filtered_entries = (0..self.entries.len()).collect();
+            return;
+        }
+
+        let matcher = SkimMatcherV2::default();
+
+        let mut scores: Vec<(usize, i64)> = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, entry)| {
+                let score = match entry.description() {
+                    Some(description) => matcher.fuzzy_match(
+                        &format!("{} {}", entry.label(), description),
+                        &self.filter_term,
+                    ),
+                    None => matcher.fuzzy_match(entry.label(), &self.filter_term),
+                }?;
+                Some((idx, score))
+            })
+            .collect();
+
+        scores.sort_by(|a, b| a.1.cmp(&b.1).reverse());
+
+        self.filtered_entries = scores.into_iter().map(|(idx, _)| idx).collect();
+        self.active_idx = 0;
+        self.top_row = 0;
+    }
+
+    fn render(&self, term: &mut TermWizTerminal) -> termwiz::Result<()> {
+        let size = term.get_screen_size()?;
+        let max_width = size.cols.saturating_sub(6);
+
+        let mut changes = vec![
+            Change::ClearScreen(ColorAttribute::Default),
+            Change::CursorPosition {
+                x: Position::Absolute(0),
+                y: Position::Absolute(0),
+            },
+            Change::Text(format!(
+                "{}\r\n",
+                truncate_right(
+                    &format!(
+                        "Select an item and press Enter={}  Esc=cancel  /=filter",
+                        self.accept_label
+                    ),
+                    max_width
+                )
+            )),
+            Change::AllAttributes(CellAttributes::default()),
+        ];
+
+        let max_items = self.max_items;
+
+        for (row_num, (entry_idx, idx)) in self
+            .filtered_entries
+            .iter()
+            .enumerate()
+            .skip(self.top_row)
+            .enumerate()
+        {
+            if row_num > max_items {
+                break;
+            }
+            let entry = &self.entries[*idx];
+            if entry_idx == self.active_idx {
+                changes.push(AttributeChange::Reverse(true).into());
+            }
+
+            let prefix = if row_num < 9 && !self.filtering {
+                format!(" {}. ", row_num + 1)
+            } else {
+                "    ".to_string()
+            };
+            let label = truncate_right(entry.label(), max_width);
+            changes.push(Change::Text(format!("{}{} ", prefix, label)));
+
+            if let Some(description) = entry.description() {
+                let remain = max_width.saturating_sub(label.chars().count() + 1);
+                if remain > 0 {
+                    changes.push(AttributeChange::Intensity(Intensity::Half).into());
+                    changes.push(Change::Text(truncate_right(description, remain)));
+                    changes.push(AttributeChange::Intensity(Intensity::Normal).into());
+                }
+            }
+            changes.push(Change::Text("\r\n".to_string()));
+
+            if entry_idx == self.active_idx {
+                changes.push(AttributeChange::Reverse(false).into());
+            }
+        }
+
+        if self.filtering || !self.filter_term.is_empty() {
+            changes.append(&mut vec![
+                Change::CursorPosition {
+                    x: Position::Absolute(0),
+                    y: Position::Absolute(0),
+                },
+                Change::ClearToEndOfLine(ColorAttribute::Default),
+                Change::Text(truncate_right(
+                    &format!("Fuzzy matching: {}", self.filter_term),
+                    max_width,
+                )),
+            ]);
+        }
+
+        term.render(&changes)
+    }
+
+    /// Removes and returns the entry at `active_idx` in the filtered list
+    fn take(mut self, active_idx: usize) -> Option<T> {
+        let idx = *self.filtered_entries.get(active_idx)?;
+        Some(self.entries.swap_remove(idx))
+    }
+
+    fn move_up(&mut self) {
+        self.active_idx = self.active_idx.saturating_sub(1);
+        if self.active_idx < self.top_row {
+            self.top_row = self.active_idx;
+        }
+    }
+
+    fn move_down(&mut self) {
+        self.active_idx = (self.active_idx + 1).min(self.filtered_entries.len().saturating_sub(1));
+        if self.active_idx + self.top_row > self.max_items {
+            self.top_row = self.active_idx.saturating_sub(self.max_items);
+        }
+    }
+
+    /// Displays the menu and processes input until the user picks
+    /// an entry, which is returned, or cancels the menu.
+    pub fn run(mut self, term: &mut TermWizTerminal) -> anyhow::Result<Option<T>> {
+        let size = term.get_screen_size()?;
+        self.max_items = size.rows.saturating_sub(ROW_OVERHEAD);
+        self.render(term)?;
+
+        while let Ok(Some(event)) = term.poll_input(None) {
+            match event {
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c),
+                    ..
+                }) if !self.filtering && ('1'..='9').contains(&c) => {
+                    let idx = self.top_row + (c as u32 - '1' as u32) as usize;
+                    if idx < self.filtered_entries.len() {
+                        return Ok(self.take(idx));
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('j'),
+                    ..
+                }) if !self.filtering => {
+                    self.move_down();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('k'),
+                    ..
+                }) if !self.filtering => {
+                    self.move_up();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('P'),
+                    modifiers: Modifiers::CTRL,
+                }) => {
+                    self.move_up();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('N'),
+                    modifiers: Modifiers::CTRL,
+                }) => {
+                    self.move_down();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char('/'),
+                    ..
+                }) if !self.filtering => {
+                    self.filtering = true;
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Backspace,
+                    ..
+                }) => {
+                    if self.filter_term.pop().is_none() && !self.always_fuzzy {
+                        self.filtering = false;
+                    }
+                    self.update_filter();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Char(c),
+                    ..
+                }) if self.filtering => {
+                    self.filter_term.push(c);
+                    self.update_filter();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::UpArrow,
+                    ..
+                }) => {
+                    self.move_up();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::DownArrow,
+                    ..
+                }) => {
+                    self.move_down();
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Escape,
+                    ..
+                }) => {
+                    break;
+                }
+                InputEvent::Mouse(MouseEvent {
+                    y, mouse_buttons, ..
+                }) if mouse_buttons.contains(MouseButtons::VERT_WHEEL) => {
+                    if mouse_buttons.contains(MouseButtons::WHEEL_POSITIVE) {
+                        self.top_row = self.top_row.saturating_sub(1);
+                    } else {
+                        self.top_row += 1;
+                        self.top_row = self.top_row.min(
+                            self.filtered_entries
+                                .len()
+                                .saturating_sub(self.max_items)
+                                .saturating_sub(1),
+                        );
+                    }
+                    if y > 0 && y as usize <= self.filtered_entries.len() {
+                        self.active_idx = self.top_row + y as usize - 1;
+                    }
+                }
+                InputEvent::Mouse(MouseEvent {
+                    y, mouse_buttons, ..
+                }) => {
+                    if y > 0 && y as usize <= self.filtered_entries.len() {
+                        self.active_idx = self.top_row + y as usize - 1;
+
+                        if mouse_buttons == MouseButtons::LEFT {
+                            let active_idx = self.active_idx;
+                            return Ok(self.take(active_idx));
+                        }
+                    }
+                    if mouse_buttons != MouseButtons::NONE {
+                        // Treat any other mouse button as cancel
+                        break;
+                    }
+                }
+                InputEvent::Key(KeyEvent {
+                    key: KeyCode::Enter,
+                    ..
+                }) => {
+                    let active_idx = self.active_idx;
+                    return Ok(self.take(active_idx));
+                }
+                InputEvent::Resized { rows, .. } => {
+                    self.max_items = rows.saturating_sub(ROW_OVERHEAD);
+                }
+                _ => {}
+            }
+            self.render(term)?;
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    impl MenuEntry for &str {
+        fn label(&self) -> &str {
+            self
+        }
+    }
+
+    fn filtered<'a>(menu: &Menu<&'a str>) -> Vec<&'a str> {
+        menu.filtered_entries
+            .iter()
+            .map(|&idx| menu.entries[idx])
+            .collect()
+    }
+
+    #[test]
+    fn filter() {
+        let mut menu = Menu::new(vec!["alpha", "beta", "gamma"], "accept", false);
+        assert_eq!(filtered(&menu), vec!["alpha", "beta", "gamma"]);
+
+        menu.set_active_idx(2);
+        menu.filter_term = "bt".to_string();
+        menu.update_filter();
+        assert_eq!(filtered(&menu), vec!["beta"]);
+        assert_eq!(menu.active_idx, 0);
+        assert_eq!(menu.take(0), Some("beta"));
+    }
+
+    #[test]
+    fn movement() {
+        let mut menu = Menu::new(vec!["a", "b", "c", "d", "e"], "accept", false);
+        menu.max_items = 2;
+
+        menu.move_up();
+        assert_eq!((menu.active_idx, menu.top_row), (0, 0));
+        menu.move_down();
+        menu.move_down();
+        assert_eq!((menu.active_idx, menu.top_row), (2, 0));
+        menu.move_down();
+        assert_eq!((menu.active_idx, menu.top_row), (3, 1));
+        menu.move_down();
+        menu.move_down();
+        assert_eq!(menu.active_idx, 4);
+        menu.move_up();
+        menu.move_up();
+        menu.move_up();
+        menu.move_up();
+        assert_eq!((menu.active_idx, menu.top_row), (0, 0));
+
+        let mut empty: Menu<&str> = Menu::new(vec![], "accept", true);
+        empty.move_down();
+        assert_eq!(empty.active_idx, 0);
+        assert_eq!(empty.take(0), None);
+    }
+}
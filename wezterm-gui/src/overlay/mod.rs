@@ -10,17 +10,22 @@ mod confirm_close_pane;
 mod copy;
 mod debug;
 mod launcher;
+mod menu;
+mod prompt;
 mod quickselect;
 mod search;
+mod selector;
 
 pub use confirm_close_pane::{
     confirm_close_pane, confirm_close_tab, confirm_close_window, confirm_quit_program,
 };
-pub use copy::CopyOverlay;
+pub use copy::{copy_key_table, CopyOverlay, COPY_MODE_KEY_TABLE};
 pub use debug::show_debug_overlay;
 pub use launcher::{launcher, LauncherArgs, LauncherFlags};
+pub use prompt::show_line_prompt_overlay;
 pub use quickselect::QuickSelectOverlay;
 pub use search::SearchOverlay;
+pub use selector::selector;
 
 pub fn start_overlay<T, F>(
     term_window: &TermWindow,
@@ -0,0 +1,51 @@
+//! Prompts for a single line of text on behalf of the
+//! `PromptInputLine` key assignment.
+use config::keyassignment::PromptInputLine;
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::input::{InputEvent, KeyCode, KeyEvent};
+use termwiz::lineedit::*;
+use termwiz::surface::Change;
+use termwiz::terminal::Terminal;
+
+struct PromptHost {
+    history: BasicHistory,
+}
+
+impl LineEditorHost for PromptHost {
+    fn history(&mut self) -> &mut dyn History {
+        &mut self.history
+    }
+
+    fn resolve_action(
+        &mut self,
+        event: &InputEvent,
+        _editor: &mut LineEditor<'_>,
+    ) -> Option<Action> {
+        match event {
+            InputEvent::Key(KeyEvent {
+                key: KeyCode::Escape,
+                ..
+            }) => Some(Action::Cancel),
+            _ => None,
+        }
+    }
+}
+
+/// Reads a line of text, returning None if the prompt was cancelled.
+pub fn show_line_prompt_overlay(
+    mut term: TermWizTerminal,
+    args: PromptInputLine,
+) -> anyhow::Result<Option<String>> {
+    term.no_grab_mouse_in_raw_mode();
+
+    let mut text = args.description.replace("\r\n", "\n").replace('\n', "\r\n");
+    text.push_str("\r\n");
+    term.render(&[Change::Text(text)])?;
+
+    let mut host = PromptHost {
+        history: BasicHistory::default(),
+    };
+    let mut editor = LineEditor::new(&mut term);
+    editor.set_prompt("> ");
+    Ok(editor.read_line(&mut host)?)
+}
@@ -0,0 +1,37 @@
+//! The input selector presents a list of choices provided by lua,
+//! and allows the user to pick one of them, optionally using fuzzy
+//! matching to filter the list.
+//! The selection is passed back to the lua callback that was
+//! specified by the `InputSelector` key assignment.
+use crate::overlay::menu::{Menu, MenuEntry};
+use config::keyassignment::{InputSelector, InputSelectorEntry};
+use mux::termwiztermtab::TermWizTerminal;
+use termwiz::surface::Change;
+use termwiz::terminal::Terminal;
+
+impl MenuEntry for InputSelectorEntry {
+    fn label(&self) -> &str {
+        &self.label
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.description.as_deref()
+    }
+}
+
+/// Runs the selector, returning the id and label of the chosen
+/// entry, or None if the selection was cancelled.
+pub fn selector(
+    mut term: TermWizTerminal,
+    args: InputSelector,
+) -> anyhow::Result<Option<(String, String)>> {
+    term.set_raw_mode()?;
+    term.render(&[Change::Title(args.title)])?;
+
+    let menu = Menu::new(args.choices, "accept", args.fuzzy);
+    Ok(menu.run(&mut term)?.map(|entry| {
+        let label = entry.label;
+        let id = entry.id.unwrap_or_else(|| label.clone());
+        (id, label)
+    }))
+}
@@ -1,10 +1,14 @@
 //! PaneObject represents a Mux Pane instance in lua code
 use super::luaerr;
 use anyhow::anyhow;
+use luahelper::impl_lua_conversion;
 use mlua::{UserData, UserDataMethods};
 use mux::pane::{Pane, PaneId};
 use mux::Mux;
+use serde::{Deserialize, Serialize};
 use std::rc::Rc;
+use std::time::UNIX_EPOCH;
+use wezterm_term::{CommandRecord, StableRowIndex};
 
 #[derive(Clone)]
 pub struct PaneObject {
@@ -28,6 +32,39 @@ impl PaneObject {
     }
 }
 
+/// The lua representation of a CommandRecord; times are
+/// expressed as seconds
+#[derive(Serialize, Deserialize)]
+struct CommandInfo {
+    command: String,
+    exit_status: Option<i32>,
+    start_time: Option<f64>,
+    duration: Option<f64>,
+    cwd: Option<String>,
+    prompt_y: StableRowIndex,
+    output_y: Option<StableRowIndex>,
+    end_y: Option<StableRowIndex>,
+}
+impl_lua_conversion!(CommandInfo);
+
+impl From<CommandRecord> for CommandInfo {
+    fn from(record: CommandRecord) -> Self {
+        Self {
+            command: record.command,
+            exit_status: record.exit_status,
+            start_time: record
+                .start_time
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|d| d.as_secs_f64()),
+            duration: record.duration.map(|d| d.as_secs_f64()),
+            cwd: record.cwd,
+            prompt_y: record.prompt_y,
+            output_y: record.output_y,
+            end_y: record.end_y,
+        }
+    }
+}
+
 impl UserData for PaneObject {
     fn add_methods<'lua, M: UserDataMethods<'lua, Self>>(methods: &mut M) {
         methods.add_method("pane_id", |_, this, _: ()| Ok(this.pane()?.pane_id()));
@@ -108,6 +145,14 @@ impl UserData for PaneObject {
             },
         );
 
+        methods.add_async_method("get_commands", |_, this, _: ()| async move {
+            let commands = this.pane()?.get_commands().await.map_err(luaerr)?;
+            Ok(commands
+                .into_iter()
+                .map(CommandInfo::from)
+                .collect::<Vec<_>>())
+        });
+
         methods.add_method("get_domain_name", |_, this, _: ()| {
             let pane = this.pane()?;
             let mut name = None;
@@ -17,6 +17,9 @@ pub struct Selection {
     pub range: Option<SelectionRange>,
     /// When the selection was made wrt. the pane content
     pub seqno: SequenceNo,
+    /// Whether the selection is a rectangular block rather than
+    /// a linear range of text.
+    pub rectangular: bool,
 }
 
 pub use config::keyassignment::SelectionMode;
@@ -25,11 +28,13 @@ impl Selection {
     pub fn clear(&mut self) {
         self.range = None;
         self.origin = None;
+        self.rectangular = false;
     }
 
     pub fn begin(&mut self, origin: SelectionCoordinate) {
         self.range = None;
         self.origin = Some(origin);
+        self.rectangular = false;
     }
 
     pub fn is_empty(&self) -> bool {
@@ -214,13 +219,16 @@ impl SelectionRange {
     /// indicates that the selection extends to the end of that row.
     /// Since this struct has no knowledge of line length, it cannot be
     /// more precise than that.
+    /// If `rectangular` is true, the selection is treated as a block
+    /// and the same span of columns is selected on every row.
     /// Must be called on a normalized range!
-    pub fn cols_for_row(&self, row: StableRowIndex) -> Range<usize> {
+    pub fn cols_for_row(&self, row: StableRowIndex, rectangular: bool) -> Range<usize> {
         let norm = self.normalize();
         if row < norm.start.y || row > norm.end.y {
             0..0
-        } else if norm.start.y == norm.end.y {
-            // A single line selection
+        } else if norm.start.y == norm.end.y || rectangular {
+            // A single line selection, or a block selection which
+            // spans the same columns on every row
             if norm.start.x <= norm.end.x {
                 norm.start.x..norm.end.x.saturating_add(1)
             } else {
@@ -238,3 +246,43 @@ impl SelectionRange {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use k9::assert_equal as assert_eq;
+
+    fn range(start: (usize, StableRowIndex), end: (usize, StableRowIndex)) -> SelectionRange {
+        SelectionRange {
+            start: SelectionCoordinate {
+                x: start.0,
+                y: start.1,
+            },
+            end: SelectionCoordinate { x: end.0, y: end.1 },
+        }
+    }
+
+    #[test]
+    fn linear_cols_for_row() {
+        let sel = range((4, 1), (2, 3));
+        assert_eq!(sel.cols_for_row(0, false), 0..0);
+        assert_eq!(sel.cols_for_row(1, false), 4..usize::MAX);
+        assert_eq!(sel.cols_for_row(2, false), 0..usize::MAX);
+        assert_eq!(sel.cols_for_row(3, false), 0..3);
+        assert_eq!(sel.cols_for_row(4, false), 0..0);
+    }
+
+    #[test]
+    fn rectangular_cols_for_row() {
+        let sel = range((4, 1), (2, 3));
+        assert_eq!(sel.cols_for_row(0, true), 0..0);
+        assert_eq!(sel.cols_for_row(1, true), 2..5);
+        assert_eq!(sel.cols_for_row(2, true), 2..5);
+        assert_eq!(sel.cols_for_row(3, true), 2..5);
+        assert_eq!(sel.cols_for_row(4, true), 0..0);
+
+        // Selecting upwards yields the same block
+        let sel = range((2, 3), (4, 1));
+        assert_eq!(sel.cols_for_row(2, true), 2..5);
+    }
+}
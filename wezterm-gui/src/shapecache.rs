@@ -11,7 +11,7 @@ pub struct ShapeCacheKey {
     pub text: String,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct GlyphPosition {
     pub glyph_idx: u32,
     pub num_cells: u8,
@@ -20,6 +20,16 @@ pub struct GlyphPosition {
     pub bitmap_pixel_width: u32,
 }
 
+/// A glyph from a COLR font, whose appearance depends upon the
+/// color of the text.  The shape cache is shared by text of all
+/// colors, so these glyphs are fetched from the glyph cache in
+/// the appropriate color each time that they are rendered.
+#[derive(Debug, Clone)]
+pub struct ColrGlyph {
+    pub info: GlyphInfo,
+    pub followed_by_space: bool,
+}
+
 #[derive(Debug)]
 pub struct ShapedInfo<T>
 where
@@ -28,6 +38,7 @@ where
 {
     pub glyph: Rc<CachedGlyph<T>>,
     pub pos: GlyphPosition,
+    pub colr: Option<ColrGlyph>,
 }
 
 impl<T> ShapedInfo<T>
@@ -35,27 +46,37 @@ where
     T: Texture2d,
     T: std::fmt::Debug,
 {
-    /// Process the results from the shaper, stitching together glyph
-    /// and positioning information
-    pub fn process(infos: &[GlyphInfo], glyphs: &[Rc<CachedGlyph<T>>]) -> Vec<ShapedInfo<T>> {
-        let mut pos: Vec<ShapedInfo<T>> = Vec::with_capacity(infos.len());
-
-        for (info, glyph) in infos.iter().zip(glyphs.iter()) {
-            pos.push(ShapedInfo {
-                pos: GlyphPosition {
-                    glyph_idx: info.glyph_pos,
-                    bitmap_pixel_width: glyph
-                        .texture
-                        .as_ref()
-                        .map_or(0, |t| t.coords.width() as u32),
-                    num_cells: info.num_cells,
-                    x_offset: info.x_offset,
-                    bearing_x: glyph.bearing_x.get() as f32,
-                },
-                glyph: Rc::clone(glyph),
-            });
+    /// Stitches together the glyph and positioning information
+    /// for a single glyph from the shaper
+    pub fn new(info: &GlyphInfo, glyph: &Rc<CachedGlyph<T>>) -> Self {
+        ShapedInfo {
+            pos: GlyphPosition {
+                glyph_idx: info.glyph_pos,
+                bitmap_pixel_width: glyph
+                    .texture
+                    .as_ref()
+                    .map_or(0, |t| t.coords.width() as u32),
+                num_cells: info.num_cells,
+                x_offset: info.x_offset,
+                bearing_x: glyph.bearing_x.get() as f32,
+            },
+            glyph: Rc::clone(glyph),
+            colr: None,
+        }
+    }
+}
+
+impl<T> Clone for ShapedInfo<T>
+where
+    T: Texture2d,
+    T: std::fmt::Debug,
+{
+    fn clone(&self) -> Self {
+        ShapedInfo {
+            glyph: Rc::clone(&self.glyph),
+            pos: self.pos.clone(),
+            colr: self.colr.clone(),
         }
-        pos
     }
 }
 
@@ -187,6 +208,7 @@ mod test {
                         font,
                         render_metrics,
                         num_cells,
+                        None,
                     )
                     .unwrap()
             })
@@ -194,9 +216,10 @@ mod test {
 
         eprintln!("infos: {:#?}", infos);
         eprintln!("glyphs: {:#?}", glyphs);
-        ShapedInfo::process(&infos, &glyphs)
-            .into_iter()
-            .map(|p| p.pos)
+        infos
+            .iter()
+            .zip(glyphs.iter())
+            .map(|(info, glyph)| ShapedInfo::new(info, glyph).pos)
             .collect()
     }
 
@@ -111,7 +111,14 @@ fn compute_tab_title(
         Some(title) => title,
         None => {
             let title = if let Some(pane) = &tab.active_pane {
-                let mut title = pane.title.clone();
+                // An explicitly assigned tab title takes precedence
+                // over the title of the active pane
+                let base_title = if tab.tab_title.is_empty() {
+                    pane.title.clone()
+                } else {
+                    tab.tab_title.clone()
+                };
+                let mut title = base_title.clone();
                 let classic_spacing = if config.use_fancy_tab_bar { "" } else { " " };
                 if config.show_tab_index_in_tab_bar {
                     title = format!(
@@ -123,7 +130,7 @@ fn compute_tab_title(
                             } else {
                                 1
                             },
-                        pane.title,
+                        base_title,
                         classic_spacing,
                     );
                 }
@@ -579,6 +579,7 @@ impl super::TermWindow {
                             &element.font,
                             context.metrics,
                             num_cells as u8,
+                            None,
                         )?;
 
                         min_y =
@@ -1,3 +1,4 @@
+use crate::overlay::{CopyOverlay, COPY_MODE_KEY_TABLE};
 use ::window::{DeadKeyStatus, KeyCode, KeyEvent, Modifiers, RawKeyEvent, WindowOps};
 use anyhow::Context;
 use mux::pane::Pane;
@@ -160,11 +161,33 @@ impl super::TermWindow {
         }
 
         if is_down {
-            let current_table = self.key_table_state.current_table();
-            if let Some(entry) =
-                self.input_map
-                    .lookup_key(&keycode, raw_modifiers | leader_mod, current_table)
-            {
+            let mut current_table = self.key_table_state.current_table();
+            let copy_mode = pane.downcast_ref::<CopyOverlay>();
+            let entry = match (current_table, copy_mode) {
+                // The copy overlay is collecting a search pattern or the
+                // target of a jump, so it needs to see the key itself
+                (None, Some(copy)) if copy.is_capturing_input() => None,
+                (None, Some(_)) => {
+                    match self.input_map.lookup_key(
+                        keycode,
+                        raw_modifiers | leader_mod,
+                        Some(COPY_MODE_KEY_TABLE),
+                    ) {
+                        Some(entry) => {
+                            current_table = Some(COPY_MODE_KEY_TABLE);
+                            Some(entry)
+                        }
+                        None => {
+                            self.input_map
+                                .lookup_key(keycode, raw_modifiers | leader_mod, None)
+                        }
+                    }
+                }
+                (table, _) => self
+                    .input_map
+                    .lookup_key(keycode, raw_modifiers | leader_mod, table),
+            };
+            if let Some(entry) = entry {
                 if self.config.debug_key_events {
                     log::info!(
                         "{}{:?} {:?} -> perform {:?}",
@@ -182,6 +182,8 @@ pub struct TabInformation {
     pub tab_index: usize,
     pub is_active: bool,
     pub active_pane: Option<PaneInformation>,
+    /// The title explicitly assigned to the tab, or empty
+    pub tab_title: String,
 }
 
 impl UserData for TabInformation {
@@ -189,6 +191,7 @@ impl UserData for TabInformation {
         fields.add_field_method_get("tab_id", |_, this| Ok(this.tab_id));
         fields.add_field_method_get("tab_index", |_, this| Ok(this.tab_index));
         fields.add_field_method_get("is_active", |_, this| Ok(this.is_active));
+        fields.add_field_method_get("tab_title", |_, this| Ok(this.tab_title.clone()));
         fields.add_field_method_get("active_pane", |_, this| {
             if let Some(pane) = &this.active_pane {
                 Ok(Some(pane.clone()))
@@ -1087,6 +1090,7 @@ impl TermWindow {
                 MuxNotification::PaneAdded(_)
                 | MuxNotification::PaneRemoved(_)
                 | MuxNotification::WindowWorkspaceChanged(_)
+                | MuxNotification::TabTitleChanged(_)
                 | MuxNotification::ActiveWorkspaceChanged(_)
                 | MuxNotification::Empty
                 | MuxNotification::WindowCreated(_) => {}
@@ -1231,7 +1235,8 @@ impl TermWindow {
             | MuxNotification::WindowCreated(_)
             | MuxNotification::ActiveWorkspaceChanged(_)
             | MuxNotification::Empty
-            | MuxNotification::WindowWorkspaceChanged(_) => return true,
+            | MuxNotification::WindowWorkspaceChanged(_)
+            | MuxNotification::TabTitleChanged(_) => return true,
         }
 
         window.notify(TermWindowNotif::MuxNotification(n));
@@ -1300,6 +1305,44 @@ impl TermWindow {
         .detach();
     }
 
+    /// Calls the lua callback registered as `name` with the window,
+    /// the pane and then `args`; a None in `args` is passed as nil.
+    /// Unlike emit_window_event, calls are not coalesced, as each
+    /// call carries its own arguments.
+    fn schedule_callback_event(&mut self, name: &str, pane_id: PaneId, args: Vec<Option<String>>) {
+        let window = GuiWin::new(self);
+        let pane = match Mux::get().expect("on main thread").get_pane(pane_id) {
+            Some(pane) => PaneObject::new(&pane),
+            None => return,
+        };
+        let name = name.to_string();
+
+        async fn do_event(
+            lua: Option<Rc<mlua::Lua>>,
+            name: String,
+            window: GuiWin,
+            pane: PaneObject,
+            args: Vec<Option<String>>,
+        ) -> anyhow::Result<()> {
+            if let Some(lua) = lua {
+                let args = lua.pack_multi((
+                    window,
+                    pane,
+                    args.into_iter().collect::<mlua::Variadic<_>>(),
+                ))?;
+                if let Err(err) = config::lua::emit_event(&lua, (name.clone(), args)).await {
+                    log::error!("while processing {} event: {:#}", name, err);
+                }
+            }
+            Ok(())
+        }
+
+        promise::spawn::spawn(config::with_lua_config_on_main_thread(move |lua| {
+            do_event(lua, name, window, pane, args)
+        }))
+        .detach();
+    }
+
     /// Called as part of finishing up a callout to lua.
     /// If again==false it means that there isn't a lua config
     /// to execute against, so we should just mark as done.
@@ -1885,6 +1928,79 @@ impl TermWindow {
         promise::spawn::spawn(future).detach();
     }
 
+    /// Resolves the name of the lua callback that should receive the
+    /// result of an InputSelector or PromptInputLine overlay
+    fn overlay_callback_name(action: &KeyAssignment) -> Option<String> {
+        match action {
+            KeyAssignment::EmitEvent(name) => Some(name.clone()),
+            other => {
+                log::error!(
+                    "the action for InputSelector and PromptInputLine must be \
+                     created by wezterm.action_callback, but got {:?}",
+                    other
+                );
+                None
+            }
+        }
+    }
+
+    fn show_input_selector(&mut self, args: &config::keyassignment::InputSelector) {
+        let name = match Self::overlay_callback_name(&args.action) {
+            Some(name) => name,
+            None => return,
+        };
+        let pane = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+        let pane_id = pane.pane_id();
+        let args = args.clone();
+        let window = self.window.as_ref().unwrap().clone();
+
+        let (overlay, future) = start_overlay_pane(self, &pane, move |_pane_id, term| {
+            crate::overlay::selector(term, args)
+        });
+        self.assign_overlay_for_pane(pane_id, overlay);
+        promise::spawn::spawn(async move {
+            let (id, label) = match future.await? {
+                Some((id, label)) => (Some(id), Some(label)),
+                None => (None, None),
+            };
+            window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                term_window.schedule_callback_event(&name, pane_id, vec![id, label]);
+            })));
+            anyhow::Result::<()>::Ok(())
+        })
+        .detach();
+    }
+
+    fn show_prompt_input_line(&mut self, args: &config::keyassignment::PromptInputLine) {
+        let name = match Self::overlay_callback_name(&args.action) {
+            Some(name) => name,
+            None => return,
+        };
+        let pane = match self.get_active_pane_no_overlay() {
+            Some(pane) => pane,
+            None => return,
+        };
+        let pane_id = pane.pane_id();
+        let args = args.clone();
+        let window = self.window.as_ref().unwrap().clone();
+
+        let (overlay, future) = start_overlay_pane(self, &pane, move |_pane_id, term| {
+            crate::overlay::show_line_prompt_overlay(term, args)
+        });
+        self.assign_overlay_for_pane(pane_id, overlay);
+        promise::spawn::spawn(async move {
+            let line = future.await?;
+            window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                term_window.schedule_callback_event(&name, pane_id, vec![line]);
+            })));
+            anyhow::Result::<()>::Ok(())
+        })
+        .detach();
+    }
+
     /// Returns the Prompt semantic zones
     fn get_semantic_prompt_zones(&mut self, pane: &Rc<dyn Pane>) -> &[StableRowIndex] {
         let mut cache = self
@@ -1943,6 +2059,57 @@ impl TermWindow {
         Ok(())
     }
 
+    /// Scrolls to the prompt of a command that reported a non-zero
+    /// exit status via OSC 133.  The command log is fetched
+    /// asynchronously, as the pane may be a remote one.
+    fn scroll_to_failed_command(&mut self, amount: isize) -> anyhow::Result<()> {
+        let pane = match self.get_active_pane_or_overlay() {
+            Some(pane) => pane,
+            None => return Ok(()),
+        };
+        let window = match self.window.clone() {
+            Some(window) => window,
+            None => return Ok(()),
+        };
+        let pane_id = pane.pane_id();
+
+        promise::spawn::spawn(async move {
+            let failed: Vec<StableRowIndex> = pane
+                .get_commands()
+                .await?
+                .into_iter()
+                .filter(|command| matches!(command.exit_status, Some(status) if status != 0))
+                .map(|command| command.prompt_y)
+                .collect();
+
+            window.notify(TermWindowNotif::Apply(Box::new(move |term_window| {
+                let pane = match term_window.get_active_pane_or_overlay() {
+                    Some(pane) if pane.pane_id() == pane_id => pane,
+                    _ => return,
+                };
+                let dims = pane.get_dimensions();
+                let position = term_window
+                    .get_viewport(pane_id)
+                    .unwrap_or(dims.physical_top);
+                let idx = match failed.binary_search(&position) {
+                    Ok(idx) => idx as isize + amount,
+                    // idx is the first command below the viewport
+                    Err(idx) if amount > 0 => idx as isize + amount - 1,
+                    Err(idx) => idx as isize + amount,
+                };
+                if let Some(row) = failed.get(idx.max(0) as usize) {
+                    term_window.set_viewport(pane_id, Some(*row), dims);
+                }
+                if let Some(win) = term_window.window.as_ref() {
+                    win.invalidate();
+                }
+            })));
+            anyhow::Result::<()>::Ok(())
+        })
+        .detach();
+        Ok(())
+    }
+
     fn scroll_by_page(&mut self, amount: f64) -> anyhow::Result<()> {
         let pane = match self.get_active_pane_or_overlay() {
             Some(pane) => pane,
@@ -2144,6 +2311,7 @@ impl TermWindow {
             ScrollByPage(n) => self.scroll_by_page(**n)?,
             ScrollByLine(n) => self.scroll_by_line(*n)?,
             ScrollToPrompt(n) => self.scroll_to_prompt(*n)?,
+            ScrollToFailedCommand(n) => self.scroll_to_failed_command(*n)?,
             ScrollToTop => self.scroll_to_top(pane),
             ScrollToBottom => self.scroll_to_bottom(pane),
             ShowTabNavigator => self.show_tab_navigator(),
@@ -2241,12 +2409,18 @@ impl TermWindow {
                     self.assign_overlay_for_pane(pane.pane_id(), qa);
                 }
             }
+            InputSelector(args) => self.show_input_selector(args),
+            PromptInputLine(args) => self.show_prompt_input_line(args),
             ActivateCopyMode => {
                 if let Some(pane) = self.get_active_pane_no_overlay() {
                     let copy = CopyOverlay::with_pane(self, &pane);
                     self.assign_overlay_for_pane(pane.pane_id(), copy);
                 }
             }
+            CopyMode(op) => match pane.downcast_ref::<CopyOverlay>() {
+                Some(copy) => copy.perform_assignment(op),
+                None => log::debug!("ignoring CopyMode({:?}) outside of copy mode", op),
+            },
             AdjustPaneSize(direction, amount) => {
                 let mux = Mux::get().unwrap();
                 let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
@@ -2289,6 +2463,87 @@ impl TermWindow {
                     tab.activate_pane_direction(*direction);
                 }
             }
+            SwapActivePaneDirection(direction) => {
+                let mux = Mux::get().unwrap();
+                let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+                    Some(tab) => tab,
+                    None => return Ok(()),
+                };
+
+                let tab_id = tab.tab_id();
+
+                if self.tab_state(tab_id).overlay.is_none() {
+                    let direction = *direction;
+                    promise::spawn::spawn(async move {
+                        let mux = Mux::get().unwrap();
+                        mux.swap_active_pane_direction(tab_id, direction).await
+                    })
+                    .detach();
+                }
+            }
+            RotatePanes(direction) => {
+                let mux = Mux::get().unwrap();
+                let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
+                    Some(tab) => tab,
+                    None => return Ok(()),
+                };
+
+                let tab_id = tab.tab_id();
+
+                if self.tab_state(tab_id).overlay.is_none() {
+                    let direction = *direction;
+                    promise::spawn::spawn(async move {
+                        let mux = Mux::get().unwrap();
+                        mux.rotate_panes(tab_id, direction).await
+                    })
+                    .detach();
+                }
+            }
+            MovePaneToNewTab => {
+                if let Some(pane) = self.get_active_pane_no_overlay() {
+                    let pane_id = pane.pane_id();
+                    let window_id = self.mux_window_id;
+                    promise::spawn::spawn(async move {
+                        let mux = Mux::get().unwrap();
+                        let (tab, window_id) = mux
+                            .move_pane_to_new_tab(pane_id, Some(window_id), None)
+                            .await?;
+                        if let Some(mut window) = mux.get_window_mut(window_id) {
+                            if let Some(idx) = window.idx_by_id(tab.tab_id()) {
+                                window.save_and_then_set_active(idx);
+                            }
+                        }
+                        anyhow::Result::<()>::Ok(())
+                    })
+                    .detach();
+                }
+            }
+            MovePaneToTab(tab_idx) => {
+                if let Some(pane) = self.get_active_pane_no_overlay() {
+                    let mux = Mux::get().unwrap();
+                    let tab_id = {
+                        let window = mux
+                            .get_window(self.mux_window_id)
+                            .ok_or_else(|| anyhow!("no such window"))?;
+                        let max = window.len();
+                        let tab_idx = if *tab_idx < 0 {
+                            max.saturating_sub(tab_idx.unsigned_abs())
+                        } else {
+                            *tab_idx as usize
+                        };
+                        match window.get_by_idx(tab_idx) {
+                            Some(tab) => tab.tab_id(),
+                            None => return Ok(()),
+                        }
+                    };
+                    let pane_id = pane.pane_id();
+                    promise::spawn::spawn(async move {
+                        let mux = Mux::get().unwrap();
+                        mux.move_pane_to_tab(pane_id, tab_id).await
+                    })
+                    .detach();
+                }
+            }
             TogglePaneZoomState => {
                 let mux = Mux::get().unwrap();
                 let tab = match mux.get_active_tab_for_window(self.mux_window_id) {
@@ -2672,6 +2927,7 @@ impl TermWindow {
                         .iter()
                         .find(|p| p.is_active)
                         .map(Self::pos_pane_to_pane_info),
+                    tab_title: tab.get_title(),
                 }
             })
             .collect()
@@ -41,7 +41,7 @@ use wezterm_font::{ClearShapeCache, GlyphInfo, LoadedFont};
 use wezterm_term::color::{ColorAttribute, ColorPalette, RgbColor};
 use wezterm_term::{CellAttributes, Line, StableRowIndex};
 use window::bitmaps::Texture2d;
-use window::color::LinearRgba;
+use window::color::{LinearRgba, SrgbaTuple};
 
 const TOP_LEFT_ROUNDED_CORNER: &[Poly] = &[Poly {
     path: &[
@@ -187,6 +187,9 @@ pub struct ClusterStyleCache<'a> {
     style: &'a TextStyle,
     underline_tex_rect: TextureRect,
     fg_color: LinearRgba,
+    /// The text color prior to applying blink; color fonts
+    /// use it to select a palette
+    text_fg: LinearRgba,
     bg_color: LinearRgba,
     underline_color: LinearRgba,
 }
@@ -1349,7 +1352,10 @@ impl super::TermWindow {
             )?;
         }
 
-        let selrange = self.selection(pos.pane.pane_id()).range.clone();
+        let (selrange, rectangular) = {
+            let sel = self.selection(pos.pane.pane_id());
+            (sel.range, sel.rectangular)
+        };
 
         let start = Instant::now();
         let selection_fg = palette.selection_fg.to_linear();
@@ -1362,7 +1368,7 @@ impl super::TermWindow {
         for (line_idx, line) in lines.iter().enumerate() {
             let stable_row = stable_top + line_idx as StableRowIndex;
 
-            let selrange = selrange.map_or(0..0, |sel| sel.cols_for_row(stable_row));
+            let selrange = selrange.map_or(0..0, |sel| sel.cols_for_row(stable_row, rectangular));
             // Constrain to the pane width!
             let selrange = selrange.start..selrange.end.min(dims.cols);
 
@@ -1684,7 +1690,7 @@ impl super::TermWindow {
                 let bg_color = params.palette.resolve_bg(attrs.background()).to_linear();
 
                 let fg_color = resolve_fg_color_attr(&attrs, attrs.foreground(), &params, style);
-                let (fg_color, bg_color, bg_is_default) = {
+                let (fg_color, text_fg, bg_color, bg_is_default) = {
                     let mut fg = fg_color;
                     let mut bg = bg_color;
                     let mut bg_default = bg_is_default;
@@ -1695,6 +1701,11 @@ impl super::TermWindow {
                         bg_default = false;
                     }
 
+                    if params.config.minimum_contrast > 1.0 {
+                        fg = fg.ensure_contrast_ratio(bg, params.config.minimum_contrast);
+                    }
+                    let text_fg = fg;
+
                     // Check for blink, and if this is the "not-visible"
                     // part of blinking then set fg = bg.  This is a cheap
                     // means of getting it done without impacting other
@@ -1726,7 +1737,7 @@ impl super::TermWindow {
                         }
                     }
 
-                    (fg, bg, bg_default)
+                    (fg, text_fg, bg, bg_default)
                 };
 
                 let glyph_color = fg_color;
@@ -1753,6 +1764,7 @@ impl super::TermWindow {
                     underline_tex_rect: underline_tex_rect.clone(),
                     bg_color,
                     fg_color: glyph_color,
+                    text_fg,
                     underline_color,
                 });
             }
@@ -1766,6 +1778,7 @@ impl super::TermWindow {
                 params.line,
                 params.font.as_ref(),
                 &params.render_metrics,
+                Some(style_params.text_fg.to_srgb()),
             )?;
             let pixel_width = glyph_info
                 .iter()
@@ -2669,7 +2682,9 @@ impl super::TermWindow {
         }
     }
 
-    fn glyph_infos_to_glyphs(
+    /// Resolves the glyphs for the results from the shaper,
+    /// stitching together glyph and positioning information
+    fn glyph_infos_to_shaped(
         &self,
         cluster: &CellCluster,
         line: &Line,
@@ -2678,8 +2693,9 @@ impl super::TermWindow {
         infos: &[GlyphInfo],
         font: &Rc<LoadedFont>,
         metrics: &RenderMetrics,
-    ) -> anyhow::Result<Vec<Rc<CachedGlyph<SrgbTexture2d>>>> {
-        let mut glyphs = Vec::with_capacity(infos.len());
+        foreground: Option<SrgbaTuple>,
+    ) -> anyhow::Result<Vec<ShapedInfo<SrgbTexture2d>>> {
+        let mut shaped = Vec::with_capacity(infos.len());
         for info in infos {
             let cell_idx = cluster.byte_to_cell_idx(info.cluster as usize);
 
@@ -2689,7 +2705,7 @@ impl super::TermWindow {
                         // Don't bother rendering the glyph from the font, as it can
                         // have incorrect advance metrics.
                         // Instead, just use our pixel-perfect cell metrics
-                        glyphs.push(Rc::new(CachedGlyph {
+                        let glyph = Rc::new(CachedGlyph {
                             brightness_adjust: 1.0,
                             has_color: false,
                             texture: None,
@@ -2699,7 +2715,8 @@ impl super::TermWindow {
                             bearing_x: PixelLength::zero(),
                             bearing_y: PixelLength::zero(),
                             scale: 1.0,
-                        }));
+                        });
+                        shaped.push(ShapedInfo::new(info, &glyph));
                         continue;
                     }
                 }
@@ -2710,16 +2727,25 @@ impl super::TermWindow {
                 None => false,
             };
 
-            glyphs.push(glyph_cache.cached_glyph(
+            let glyph = glyph_cache.cached_glyph(
                 info,
                 &style,
                 followed_by_space,
                 font,
                 metrics,
                 info.num_cells,
-            )?);
+                foreground,
+            )?;
+            let mut shaped_info = ShapedInfo::new(info, &glyph);
+            if font.has_colr(info.font_idx) {
+                shaped_info.colr.replace(ColrGlyph {
+                    info: info.clone(),
+                    followed_by_space,
+                });
+            }
+            shaped.push(shaped_info);
         }
-        Ok(glyphs)
+        Ok(shaped)
     }
 
     /// Shape the printable text from a cluster
@@ -2731,8 +2757,13 @@ impl super::TermWindow {
         line: &Line,
         font: Option<&Rc<LoadedFont>>,
         metrics: &RenderMetrics,
+        foreground: Option<SrgbaTuple>,
     ) -> anyhow::Result<Rc<Vec<ShapedInfo<SrgbTexture2d>>>> {
         let shape_resolve_start = Instant::now();
+        let font = match font {
+            Some(f) => Rc::clone(f),
+            None => self.fonts.resolve_font(style)?,
+        };
         let key = BorrowedShapeCacheKey {
             style,
             text: &cluster.text,
@@ -2741,10 +2772,6 @@ impl super::TermWindow {
             Some(Ok(info)) => info,
             Some(Err(err)) => return Err(err),
             None => {
-                let font = match font {
-                    Some(f) => Rc::clone(f),
-                    None => self.fonts.resolve_font(style)?,
-                };
                 let window = self.window.as_ref().unwrap().clone();
 
                 let presentation_width = PresentationWidth::with_cluster(&cluster);
@@ -2759,7 +2786,7 @@ impl super::TermWindow {
                     Some(&presentation_width),
                 ) {
                     Ok(info) => {
-                        let glyphs = self.glyph_infos_to_glyphs(
+                        let shaped = self.glyph_infos_to_shaped(
                             cluster,
                             line,
                             &style,
@@ -2767,8 +2794,9 @@ impl super::TermWindow {
                             &info,
                             &font,
                             metrics,
+                            foreground,
                         )?;
-                        let shaped = Rc::new(ShapedInfo::process(&info, &glyphs));
+                        let shaped = Rc::new(shaped);
 
                         self.shape_cache
                             .borrow_mut()
@@ -2787,6 +2815,35 @@ impl super::TermWindow {
                 }
             }
         };
+        // The cached shape is shared by text of every color, so fetch
+        // any COLR glyphs in the color of this text
+        let glyph_info = match foreground {
+            Some(foreground) if glyph_info.iter().any(|info| info.colr.is_some()) => {
+                let mut glyph_cache = gl_state.glyph_cache.borrow_mut();
+                let mut colored = Vec::with_capacity(glyph_info.len());
+                for shaped in glyph_info.iter() {
+                    match &shaped.colr {
+                        Some(colr) => {
+                            let glyph = glyph_cache.cached_glyph(
+                                &colr.info,
+                                style,
+                                colr.followed_by_space,
+                                &font,
+                                metrics,
+                                colr.info.num_cells,
+                                Some(foreground),
+                            )?;
+                            let mut shaped = ShapedInfo::new(&colr.info, &glyph);
+                            shaped.colr.replace(colr.clone());
+                            colored.push(shaped);
+                        }
+                        None => colored.push(shaped.clone()),
+                    }
+                }
+                Rc::new(colored)
+            }
+            _ => glyph_info,
+        };
         metrics::histogram!("cached_cluster_shape", shape_resolve_start.elapsed());
         log::trace!(
             "shape_resolve for cluster len {} -> elapsed {:?}",
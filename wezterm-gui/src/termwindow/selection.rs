@@ -12,12 +12,17 @@ impl super::TermWindow {
 
     pub fn selection_text(&self, pane: &Rc<dyn Pane>) -> String {
         let mut s = String::new();
+        let rectangular = self.selection(pane.pane_id()).rectangular;
         if let Some(sel) = self
             .selection(pane.pane_id())
             .range
             .as_ref()
             .map(|r| r.normalize())
         {
+            if rectangular {
+                return Self::rectangular_selection_text(pane, &sel);
+            }
+
             let mut last_was_wrapped = false;
             let first_row = sel.rows().start;
             let last_row = sel.rows().end;
@@ -31,7 +36,7 @@ impl super::TermWindow {
                     let this_row = line.first_row + idx as StableRowIndex;
                     if this_row >= first_row && this_row < last_row {
                         let last_phys_idx = phys.cells().len().saturating_sub(1);
-                        let cols = sel.cols_for_row(this_row);
+                        let cols = sel.cols_for_row(this_row, false);
                         let last_col_idx = cols.end.saturating_sub(1).min(last_phys_idx);
                         let col_span = phys.columns_as_str(cols);
                         // Only trim trailing whitespace if we are the last line
@@ -56,6 +61,20 @@ impl super::TermWindow {
         s
     }
 
+    /// Extracts the text of a block selection.  Each physical row is
+    /// trimmed to the columns of the block, and the rows are always
+    /// joined with newlines, regardless of whether they were wrapped.
+    fn rectangular_selection_text(pane: &Rc<dyn Pane>, sel: &SelectionRange) -> String {
+        let (first_row, lines) = pane.get_lines(sel.rows());
+        let mut rows = vec![];
+        for (idx, line) in lines.iter().enumerate() {
+            let this_row = first_row + idx as StableRowIndex;
+            let cols = sel.cols_for_row(this_row, true);
+            rows.push(line.columns_as_str(cols).trim_end().to_string());
+        }
+        rows.join("\n")
+    }
+
     pub fn clear_selection(&mut self, pane: &Rc<dyn Pane>) {
         let mut selection = self.selection(pane.pane_id());
         selection.clear();
@@ -69,14 +88,22 @@ impl super::TermWindow {
         pane: &Rc<dyn Pane>,
     ) {
         self.selection(pane.pane_id()).seqno = pane.get_current_seqno();
-        let mode = mode.unwrap_or(SelectionMode::Cell);
+        let mode = mode.unwrap_or_else(|| {
+            // Continue an existing block selection in the same style
+            if self.selection(pane.pane_id()).rectangular {
+                SelectionMode::Block
+            } else {
+                SelectionMode::Cell
+            }
+        });
+        self.selection(pane.pane_id()).rectangular = mode == SelectionMode::Block;
         let (position, y) = match self.pane_state(pane.pane_id()).mouse_terminal_coords {
             Some(coords) => coords,
             None => return,
         };
         let x = position.column;
         match mode {
-            SelectionMode::Cell => {
+            SelectionMode::Cell | SelectionMode::Block => {
                 // Origin is the cell in which the selection action started. E.g. the cell
                 // that had the mouse over it when the left mouse button was pressed
                 let origin = self
@@ -88,7 +115,14 @@ impl super::TermWindow {
                 // Compute the start and end horizontall cell of the selection.
                 // The selection extent depends on the mouse cursor position in relation
                 // to the origin.
-                let (start_x, end_x) = if (x >= origin.x && y == origin.y) || y > origin.y {
+                // A block selection spans the same columns on every row,
+                // so only the horizontal position matters in that case.
+                let forwards = if mode == SelectionMode::Block {
+                    x >= origin.x
+                } else {
+                    (x >= origin.x && y == origin.y) || y > origin.y
+                };
+                let (start_x, end_x) = if forwards {
                     // If the selection is extending forwards from the origin, it includes the
                     // origin and doesn't include the cell under the cursor. Note that the
                     // reported cell here is offset by -50% from the real cell you see on the
@@ -203,6 +237,11 @@ impl super::TermWindow {
                 self.selection(pane.pane_id())
                     .begin(SelectionCoordinate { x, y });
             }
+            SelectionMode::Block => {
+                self.selection(pane.pane_id())
+                    .begin(SelectionCoordinate { x, y });
+                self.selection(pane.pane_id()).rectangular = true;
+            }
         }
 
         self.selection(pane.pane_id()).seqno = pane.get_current_seqno();
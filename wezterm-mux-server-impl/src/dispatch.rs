@@ -158,6 +158,18 @@ where
                     stream.flush().await.context("flushing PDU to client")?;
                 }
             }
+            Ok(Item::Notif(MuxNotification::TabTitleChanged(tab_id))) => {
+                let title = {
+                    let mux = Mux::get().expect("to be running on gui thread");
+                    mux.get_tab(tab_id).map(|tab| tab.get_title())
+                };
+                if let Some(title) = title {
+                    Pdu::TabTitleChanged(codec::TabTitleChanged { tab_id, title })
+                        .encode_async(&mut stream, 0)
+                        .await?;
+                    stream.flush().await.context("flushing PDU to client")?;
+                }
+            }
             Ok(Item::Notif(MuxNotification::ActiveWorkspaceChanged(_))) => {}
             Ok(Item::Notif(MuxNotification::Empty)) => {}
             Err(err) => {
@@ -7,6 +7,7 @@ pub mod dispatch;
 pub mod local;
 pub mod pki;
 pub mod sessionhandler;
+pub mod stats;
 
 lazy_static::lazy_static! {
     pub static ref PKI: pki::Pki = pki::Pki::init().expect("failed to initialize PKI");
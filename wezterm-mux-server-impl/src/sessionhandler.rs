@@ -1,9 +1,10 @@
 use crate::PKI;
-use anyhow::{anyhow, Context};
+use anyhow::{anyhow, bail, Context};
 use codec::*;
 use mux::client::ClientId;
 use mux::pane::{Pane, PaneId};
 use mux::renderable::{RenderableDimensions, StableCursorPosition};
+use mux::session::SessionState;
 use mux::tab::TabId;
 use mux::Mux;
 use promise::spawn::spawn_into_main_thread;
@@ -302,6 +303,81 @@ impl SessionHandler {
                 .detach();
                 send_response(Ok(Pdu::UnitResponse(UnitResponse {})))
             }
+            Pdu::ActivatePane(ActivatePane { pane_id }) => {
+                let client_id = self.client_id.clone();
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let _identity = mux.with_identity(client_id);
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            let (_domain_id, window_id, tab_id) = mux
+                                .resolve_pane_id(pane_id)
+                                .ok_or_else(|| anyhow!("pane {} is not in any tab", pane_id))?;
+                            let tab = mux
+                                .get_tab(tab_id)
+                                .ok_or_else(|| anyhow!("tab {} is invalid", tab_id))?;
+                            tab.set_active_pane(&pane);
+
+                            let mut window = mux
+                                .get_window_mut(window_id)
+                                .ok_or_else(|| anyhow!("window {} is invalid", window_id))?;
+                            let tab_idx = window.idx_by_id(tab_id).ok_or_else(|| {
+                                anyhow!("tab {} isn't in window {}", tab_id, window_id)
+                            })?;
+                            window.save_and_then_set_active(tab_idx);
+                            drop(window);
+
+                            mux.record_focus_for_current_identity(pane_id);
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+            Pdu::SetTabTitle(SetTabTitle { tab_id, title }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let tab = mux
+                                .get_tab(tab_id)
+                                .ok_or_else(|| anyhow!("tab {} is invalid", tab_id))?;
+                            tab.set_title(&title);
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+            Pdu::SaveSession(SaveSession {
+                path,
+                include_scrollback,
+            }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let path = match path {
+                                // The client is responsible for resolving the
+                                // path, as our cwd is unrelated to theirs
+                                Some(path) if path.is_relative() => {
+                                    bail!("session path {} is not absolute", path.display())
+                                }
+                                Some(path) => path,
+                                None => config::configuration().mux_session_file(),
+                            };
+                            SessionState::capture(include_scrollback).save_to_file(&path)?;
+                            Ok(Pdu::UnitResponse(UnitResponse {}))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
             Pdu::GetClientList(GetClientList) => {
                 spawn_into_main_thread(async move {
                     catch(
@@ -317,20 +393,28 @@ impl SessionHandler {
                 })
                 .detach();
             }
+            Pdu::GetMetrics(GetMetrics {}) => {
+                send_response(Ok(Pdu::GetMetricsResponse(crate::stats::snapshot())))
+            }
             Pdu::ListPanes(ListPanes {}) => {
                 spawn_into_main_thread(async move {
                     catch(
                         move || {
                             let mux = Mux::get().unwrap();
                             let mut tabs = vec![];
+                            let mut tab_titles = vec![];
                             for window_id in mux.iter_windows().into_iter() {
                                 let window = mux.get_window(window_id).unwrap();
                                 for tab in window.iter() {
                                     tabs.push(tab.codec_pane_tree());
+                                    tab_titles.push(tab.get_title());
                                 }
                             }
-                            log::trace!("ListPanes {:#?}", tabs);
-                            Ok(Pdu::ListPanesResponse(ListPanesResponse { tabs }))
+                            log::trace!("ListPanes {:#?} {:?}", tabs, tab_titles);
+                            Ok(Pdu::ListPanesResponse(ListPanesResponse {
+                                tabs,
+                                tab_titles,
+                            }))
                         },
                         send_response,
                     )
@@ -421,6 +505,28 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::GetPaneCommands(GetPaneCommands { pane_id }) => {
+                async fn get_commands(pane_id: PaneId) -> anyhow::Result<Pdu> {
+                    let mux = Mux::get().unwrap();
+                    let pane = mux
+                        .get_pane(pane_id)
+                        .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+
+                    pane.get_commands().await.map(|commands| {
+                        Pdu::GetPaneCommandsResponse(GetPaneCommandsResponse { commands })
+                    })
+                }
+
+                spawn_into_main_thread(async move {
+                    promise::spawn::spawn(async move {
+                        let result = get_commands(pane_id).await;
+                        send_response(result);
+                    })
+                    .detach();
+                })
+                .detach();
+            }
+
             Pdu::SetPaneZoomed(SetPaneZoomed {
                 containing_tab_id,
                 pane_id,
@@ -541,6 +647,16 @@ impl SessionHandler {
                 .detach();
             }
 
+            pdu @ Pdu::SwapActivePaneDirection(_)
+            | pdu @ Pdu::RotatePanes(_)
+            | pdu @ Pdu::MovePaneToNewTab(_)
+            | pdu @ Pdu::MovePaneToTab(_) => {
+                spawn_into_main_thread(async move {
+                    schedule_rearrange_panes(pdu, send_response);
+                })
+                .detach();
+            }
+
             Pdu::GetPaneRenderChanges(GetPaneRenderChanges { pane_id, .. }) => {
                 let sender = self.to_write_tx.clone();
                 let per_pane = self.per_pane(pane_id);
@@ -594,6 +710,28 @@ impl SessionHandler {
                 .detach();
             }
 
+            Pdu::GetPaneRenderableDimensions(GetPaneRenderableDimensions { pane_id }) => {
+                spawn_into_main_thread(async move {
+                    catch(
+                        move || {
+                            let mux = Mux::get().unwrap();
+                            let pane = mux
+                                .get_pane(pane_id)
+                                .ok_or_else(|| anyhow!("no such pane {}", pane_id))?;
+                            Ok(Pdu::GetPaneRenderableDimensionsResponse(
+                                GetPaneRenderableDimensionsResponse {
+                                    pane_id,
+                                    cursor_position: pane.get_cursor_position(),
+                                    dimensions: pane.get_dimensions(),
+                                },
+                            ))
+                        },
+                        send_response,
+                    )
+                })
+                .detach();
+            }
+
             Pdu::GetImageCell(GetImageCell {
                 pane_id,
                 line_idx,
@@ -677,10 +815,15 @@ impl SessionHandler {
             | Pdu::GetLinesResponse { .. }
             | Pdu::GetCodecVersionResponse { .. }
             | Pdu::WindowWorkspaceChanged { .. }
+            | Pdu::TabTitleChanged { .. }
             | Pdu::GetTlsCredsResponse { .. }
             | Pdu::GetClientListResponse { .. }
             | Pdu::PaneRemoved { .. }
             | Pdu::GetImageCellResponse { .. }
+            | Pdu::GetPaneRenderableDimensionsResponse { .. }
+            | Pdu::MovePaneToNewTabResponse { .. }
+            | Pdu::GetMetricsResponse { .. }
+            | Pdu::GetPaneCommandsResponse { .. }
             | Pdu::ErrorResponse { .. } => {
                 send_response(Err(anyhow!("expected a request, got {:?}", decoded.pdu)))
             }
@@ -703,6 +846,73 @@ fn schedule_domain_spawn_v2<SND>(
         .detach();
 }
 
+fn schedule_rearrange_panes<SND>(pdu: Pdu, send_response: SND)
+where
+    SND: Fn(anyhow::Result<Pdu>) + 'static,
+{
+    promise::spawn::spawn(async move { send_response(rearrange_panes(pdu).await) }).detach();
+}
+
+/// Makes the pane that is active in the client's view of the tab
+/// active in our tab, so that the rearrangement is relative to it
+fn apply_client_active_pane(tab_id: TabId, active_pane_id: Option<PaneId>) -> anyhow::Result<()> {
+    let mux = Mux::get().unwrap();
+    let tab = mux
+        .get_tab(tab_id)
+        .ok_or_else(|| anyhow!("tab {} is invalid", tab_id))?;
+    if let Some(pane_id) = active_pane_id {
+        if !tab.set_active_pane_id_without_focus(pane_id) {
+            bail!("pane {} is not in tab {}", pane_id, tab_id);
+        }
+    }
+    Ok(())
+}
+
+async fn rearrange_panes(pdu: Pdu) -> anyhow::Result<Pdu> {
+    let mux = Mux::get().unwrap();
+    match pdu {
+        Pdu::SwapActivePaneDirection(SwapActivePaneDirection {
+            tab_id,
+            direction,
+            active_pane_id,
+        }) => {
+            apply_client_active_pane(tab_id, active_pane_id)?;
+            mux.swap_active_pane_direction(tab_id, direction).await?;
+        }
+        Pdu::RotatePanes(RotatePanes {
+            tab_id,
+            direction,
+            active_pane_id,
+        }) => {
+            apply_client_active_pane(tab_id, active_pane_id)?;
+            mux.rotate_panes(tab_id, direction).await?;
+        }
+        Pdu::MovePaneToNewTab(MovePaneToNewTab {
+            pane_id,
+            window_id,
+            workspace_for_new_window,
+        }) => {
+            let (tab, window_id) = mux
+                .move_pane_to_new_tab(pane_id, window_id, workspace_for_new_window)
+                .await?;
+            return Ok(Pdu::MovePaneToNewTabResponse(MovePaneToNewTabResponse {
+                tab_id: tab.tab_id(),
+                window_id,
+            }));
+        }
+        Pdu::MovePaneToTab(MovePaneToTab {
+            pane_id,
+            tab_id,
+            active_pane_id,
+        }) => {
+            apply_client_active_pane(tab_id, active_pane_id)?;
+            mux.move_pane_to_tab(pane_id, tab_id).await?;
+        }
+        _ => anyhow::bail!("unexpected pdu {:?}", pdu),
+    }
+    Ok(Pdu::UnitResponse(UnitResponse {}))
+}
+
 fn schedule_split_pane<SND>(split: SplitPane, send_response: SND, client_id: Option<Arc<ClientId>>)
 where
     SND: Fn(anyhow::Result<Pdu>) + 'static,
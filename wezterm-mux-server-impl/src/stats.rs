@@ -1,3 +1,7 @@
+use codec::{
+    CounterMetric, GaugeMetric, GetMetricsResponse, HistogramMetric, MetricKey, MetricUnit,
+    RateMetric,
+};
 use config::configuration;
 use hdrhistogram::Histogram;
 use metrics::{GaugeValue, Key, Recorder, Unit};
@@ -9,10 +13,20 @@ use tabout::{tabulate_output, Alignment, Column};
 
 static ENABLE_STAT_PRINT: AtomicBool = AtomicBool::new(true);
 
+lazy_static::lazy_static! {
+    static ref INNER: Arc<Mutex<Inner>> = Arc::new(Mutex::new(Inner {
+        histograms: HashMap::new(),
+        throughput: HashMap::new(),
+        counters: HashMap::new(),
+        gauges: HashMap::new(),
+    }));
+}
+
 struct Throughput {
     hist: Histogram<u64>,
     last: Option<Instant>,
     count: u64,
+    total: u64,
 }
 
 impl Throughput {
@@ -21,6 +35,7 @@ impl Throughput {
             hist: Histogram::new(2).expect("failed to create histogram"),
             last: None,
             count: 0,
+            total: 0,
         }
     }
 
@@ -37,6 +52,7 @@ impl Throughput {
             self.last = Some(Instant::now());
         };
         self.count += value;
+        self.total += value;
     }
 
     fn current(&mut self) -> u64 {
@@ -60,6 +76,7 @@ struct Inner {
     histograms: HashMap<Key, Histogram<u64>>,
     throughput: HashMap<Key, Throughput>,
     counters: HashMap<Key, u64>,
+    gauges: HashMap<Key, f64>,
 }
 
 impl Inner {
@@ -198,11 +215,7 @@ pub struct Stats {
 impl Stats {
     pub fn new() -> Self {
         Self {
-            inner: Arc::new(Mutex::new(Inner {
-                histograms: HashMap::new(),
-                throughput: HashMap::new(),
-                counters: HashMap::new(),
-            })),
+            inner: Arc::clone(&INNER),
         }
     }
 
@@ -243,6 +256,9 @@ impl Recorder for Stats {
 
     fn update_gauge(&self, key: &Key, value: GaugeValue) {
         log::trace!("gauge '{}' -> {:?}", key, value);
+        let mut inner = self.inner.lock().unwrap();
+        let gauge = inner.gauges.entry(key.clone()).or_insert(0.);
+        *gauge = value.update_value(*gauge);
     }
 
     fn record_histogram(&self, key: &Key, value: f64) {
@@ -268,3 +284,115 @@ impl Recorder for Stats {
         }
     }
 }
+
+fn metric_key(key: &Key) -> MetricKey {
+    MetricKey {
+        name: key.name().to_string(),
+        labels: key
+            .labels()
+            .map(|label| (label.key().to_string(), label.value().to_string()))
+            .collect(),
+    }
+}
+
+/// Returns the metrics that have been collected by this process
+pub fn snapshot() -> GetMetricsResponse {
+    let process = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "wezterm".to_string());
+
+    let mut inner = INNER.lock().unwrap();
+
+    let mut counters: Vec<CounterMetric> = inner
+        .counters
+        .iter()
+        .map(|(key, value)| CounterMetric {
+            key: metric_key(key),
+            value: *value,
+        })
+        .collect();
+    counters.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut histograms: Vec<HistogramMetric> = inner
+        .histograms
+        .iter()
+        .map(|(key, histogram)| {
+            let (unit, scale) = if key.name().ends_with(".size") {
+                (MetricUnit::Bytes, 1.)
+            } else {
+                // Latencies are recorded in nanoseconds
+                (MetricUnit::Seconds, 1_000_000_000.)
+            };
+            HistogramMetric {
+                key: metric_key(key),
+                unit,
+                count: histogram.len(),
+                sum: histogram.mean() * histogram.len() as f64 / scale,
+                p50: histogram.value_at_percentile(50.) as f64 / scale,
+                p75: histogram.value_at_percentile(75.) as f64 / scale,
+                p95: histogram.value_at_percentile(95.) as f64 / scale,
+                p99: histogram.value_at_percentile(99.) as f64 / scale,
+                max: histogram.max() as f64 / scale,
+            }
+        })
+        .collect();
+    histograms.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut rates: Vec<RateMetric> = inner
+        .throughput
+        .iter_mut()
+        .map(|(key, tput)| RateMetric {
+            key: metric_key(key),
+            current: tput.current(),
+            total: tput.total,
+            p50: tput.hist.value_at_percentile(50.),
+            p75: tput.hist.value_at_percentile(75.),
+            p95: tput.hist.value_at_percentile(95.),
+        })
+        .collect();
+    rates.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let mut gauges: Vec<GaugeMetric> = inner
+        .gauges
+        .iter()
+        .map(|(key, value)| GaugeMetric {
+            key: metric_key(key),
+            value: *value,
+        })
+        .collect();
+
+    // Caches record their hits and misses as `<cache>.hit.rate` and
+    // `<cache>.miss.rate`; derive the hit ratio from those totals
+    for hit in &rates {
+        let cache = match hit.key.name.strip_suffix(".hit.rate") {
+            Some(cache) => cache,
+            None => continue,
+        };
+        let miss_name = format!("{}.miss.rate", cache);
+        let misses = rates
+            .iter()
+            .find(|r| r.key.name == miss_name && r.key.labels == hit.key.labels)
+            .map(|r| r.total)
+            .unwrap_or(0);
+        let lookups = hit.total + misses;
+        if lookups > 0 {
+            gauges.push(GaugeMetric {
+                key: MetricKey {
+                    name: format!("{}.hit_ratio", cache),
+                    labels: hit.key.labels.clone(),
+                },
+                value: hit.total as f64 / lookups as f64,
+            });
+        }
+    }
+    gauges.sort_by(|a, b| a.key.cmp(&b.key));
+
+    GetMetricsResponse {
+        process,
+        counters,
+        gauges,
+        histograms,
+        rates,
+    }
+}
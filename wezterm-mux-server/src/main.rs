@@ -1,13 +1,16 @@
-use config::configuration;
+use config::{configuration, ConfigHandle};
 use mux::activity::Activity;
 use mux::domain::{Domain, LocalDomain};
+use mux::session::SessionState;
 use mux::Mux;
 use portable_pty::cmdbuilder::CommandBuilder;
 use std::ffi::OsString;
+use std::path::Path;
 use std::process::Command;
 use std::rc::Rc;
 use std::sync::Arc;
 use std::thread;
+use std::time::Duration;
 use structopt::*;
 use wezterm_gui_subcommands::*;
 
@@ -67,7 +70,7 @@ fn main() {
 fn run() -> anyhow::Result<()> {
     env_bootstrap::bootstrap();
 
-    //stats::Stats::init()?;
+    wezterm_mux_server_impl::stats::Stats::init()?;
     config::designate_this_as_the_main_thread();
     let _saver = umask::UmaskSaver::new();
 
@@ -202,12 +205,30 @@ fn run() -> anyhow::Result<()> {
 
 async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
     let mux = Mux::get().unwrap();
+    let config = config::configuration();
+
+    schedule_session_autosave(&config);
+
+    // An explicit program on the command line takes precedence
+    // over the saved session
+    if cmd.is_none() && config.mux_session_restore_on_startup {
+        let path = config.mux_session_file();
+        if path.exists() {
+            match restore_session(&path).await {
+                Ok(0) => {}
+                Ok(num_windows) => {
+                    log::info!("restored {} window(s) from {}", num_windows, path.display());
+                    return Ok(());
+                }
+                Err(err) => log::error!("failed to restore session: {:#}", err),
+            }
+        }
+    }
 
     let domain = mux.default_domain();
     let window_id = mux.new_empty_window(None);
     domain.attach(Some(*window_id)).await?;
 
-    let config = config::configuration();
     let _tab = mux
         .default_domain()
         .spawn(config.initial_size(), cmd, None, *window_id)
@@ -215,6 +236,35 @@ async fn async_run(cmd: Option<CommandBuilder>) -> anyhow::Result<()> {
     Ok(())
 }
 
+async fn restore_session(path: &Path) -> anyhow::Result<usize> {
+    let session = SessionState::load_from_file(path)?;
+    Mux::get().unwrap().default_domain().attach(None).await?;
+    session.restore().await
+}
+
+fn schedule_session_autosave(config: &ConfigHandle) {
+    let interval = config.mux_session_autosave_interval_seconds;
+    if interval == 0 {
+        return;
+    }
+    let include_scrollback = config.mux_session_autosave_scrollback;
+
+    promise::spawn::spawn(async move {
+        loop {
+            smol::Timer::after(Duration::from_secs(interval)).await;
+            if Mux::get().map(|mux| mux.is_empty()).unwrap_or(true) {
+                // Don't clobber the saved session with an empty one
+                continue;
+            }
+            let path = config::configuration().mux_session_file();
+            if let Err(err) = SessionState::capture(include_scrollback).save_to_file(&path) {
+                log::error!("failed to autosave session: {:#}", err);
+            }
+        }
+    })
+    .detach();
+}
+
 fn terminate_with_error(err: anyhow::Error) -> ! {
     log::error!("{:#}; terminating", err);
     std::process::exit(1);
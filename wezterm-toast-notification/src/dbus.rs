@@ -1,7 +1,7 @@
 #![cfg(all(not(target_os = "macos"), not(windows)))]
 //! See <https://developer.gnome.org/notification-spec/>
 
-use crate::ToastNotification;
+use crate::{ToastNotification, Urgency};
 use futures_util::stream::{abortable, StreamExt};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
@@ -91,7 +91,8 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
     let proxy = NotificationsProxy::new(&connection).await?;
     let caps = proxy.get_capabilities().await?;
 
-    if notif.url.is_some() && !caps.iter().any(|cap| cap == "actions") {
+    let supports_actions = caps.iter().any(|cap| cap == "actions");
+    if notif.url.is_some() && !supports_actions {
         // Server doesn't support actions, so skip showing this notification
         // because it might have text that says "click to see more"
         // and that just wouldn't work.
@@ -99,7 +100,14 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
     }
 
     let mut hints = HashMap::new();
-    hints.insert("urgency", Value::U8(2 /* Critical */));
+    hints.insert(
+        "urgency",
+        Value::U8(match notif.urgency {
+            Urgency::Low => 0,
+            Urgency::Normal => 1,
+            Urgency::Critical => 2,
+        }),
+    );
     let notification = proxy
         .notify(
             "wezterm",
@@ -109,6 +117,10 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
             &notif.message,
             if notif.url.is_some() {
                 &["show", "Show"]
+            } else if notif.on_click.is_some() {
+                // The "default" action is invoked by clicking on
+                // the notification itself
+                &["default", "Show"]
             } else {
                 &[]
             },
@@ -127,9 +139,12 @@ async fn show_notif_impl(notif: ToastNotification) -> Result<(), Box<dyn std::er
                 if args.nid == notification {
                     if let Some(url) = notif.url.as_ref() {
                         let _ = open::that_in_background(url);
-                        abort_closed.abort();
-                        break;
                     }
+                    if let Some(on_click) = notif.on_click.as_ref() {
+                        on_click();
+                    }
+                    abort_closed.abort();
+                    break;
                 }
             }
             Ok::<(), zbus::Error>(())
@@ -2,12 +2,53 @@ mod dbus;
 mod macos;
 mod windows;
 
-#[derive(Debug, Clone)]
+use std::sync::Arc;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Urgency {
+    Low,
+    Normal,
+    Critical,
+}
+
+/// Called when the user clicks on a notification.
+/// It is called from a background thread.
+pub type ClickHandler = Arc<dyn Fn() + Send + Sync>;
+
+#[derive(Clone)]
 pub struct ToastNotification {
     pub title: String,
     pub message: String,
     pub url: Option<String>,
     pub timeout: Option<std::time::Duration>,
+    pub urgency: Urgency,
+    pub on_click: Option<ClickHandler>,
+}
+
+impl Default for ToastNotification {
+    fn default() -> Self {
+        Self {
+            title: String::new(),
+            message: String::new(),
+            url: None,
+            timeout: None,
+            urgency: Urgency::Critical,
+            on_click: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for ToastNotification {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result {
+        fmt.debug_struct("ToastNotification")
+            .field("title", &self.title)
+            .field("message", &self.message)
+            .field("url", &self.url)
+            .field("timeout", &self.timeout)
+            .field("urgency", &self.urgency)
+            .field("on_click", &self.on_click.is_some())
+            .finish()
+    }
 }
 
 impl ToastNotification {
@@ -43,7 +84,7 @@ pub fn persistent_toast_notification_with_click_to_open_url(title: &str, message
         title: title.to_string(),
         message: message.to_string(),
         url: Some(url.to_string()),
-        timeout: None,
+        ..Default::default()
     });
 }
 
@@ -51,7 +92,6 @@ pub fn persistent_toast_notification(title: &str, message: &str) {
     show(ToastNotification {
         title: title.to_string(),
         message: message.to_string(),
-        url: None,
-        timeout: None,
+        ..Default::default()
     });
 }
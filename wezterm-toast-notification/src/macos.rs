@@ -1,6 +1,6 @@
 #![cfg(target_os = "macos")]
 
-use crate::ToastNotification;
+use crate::{ClickHandler, ToastNotification};
 use cocoa::base::*;
 use cocoa::foundation::{NSDictionary, NSString};
 use core_foundation::dictionary::CFMutableDictionary;
@@ -9,9 +9,43 @@ use objc::declare::ClassDecl;
 use objc::rc::StrongPtr;
 use objc::runtime::{Class, Object, Protocol, Sel};
 use objc::*;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
 
 const DELEGATE_CLS_NAME: &str = "WezTermNotifDelegate";
 
+lazy_static::lazy_static! {
+    /// The click handlers for the notifications that we have delivered.
+    /// NSUserNotification's userInfo can only hold plist types, so we
+    /// store an id there and look up the handler when it is activated.
+    /// Handlers are removed when their notification is activated,
+    /// dismissed or times out.
+    static ref CLICK_HANDLERS: Mutex<Vec<(u64, ClickHandler)>> = Mutex::new(vec![]);
+}
+static NEXT_CLICK_ID: AtomicU64 = AtomicU64::new(0);
+
+fn take_click_handler(click_id: u64) -> Option<ClickHandler> {
+    let mut handlers = CLICK_HANDLERS.lock().unwrap();
+    handlers
+        .iter()
+        .position(|(id, _)| *id == click_id)
+        .map(|idx| handlers.remove(idx).1)
+}
+
+/// Returns the click id stashed in the userInfo of notif, if any
+unsafe fn notif_click_id(notif: id) -> Option<u64> {
+    let info: *mut Object = msg_send![notif, userInfo];
+    if info.is_null() {
+        return None;
+    }
+    let click_id = info.valueForKey_(*nsstring("click_id"));
+    if click_id.is_null() {
+        return None;
+    }
+    let click_id = std::slice::from_raw_parts(click_id.UTF8String() as *const u8, click_id.len());
+    String::from_utf8_lossy(click_id).parse::<u64>().ok()
+}
+
 struct NotifDelegate {}
 
 impl NotifDelegate {
@@ -50,6 +84,9 @@ impl NotifDelegate {
 
     extern "C" fn did_dismiss_alert(_: &mut Object, _sel: Sel, center: id, notif: id) {
         unsafe {
+            if let Some(click_id) = notif_click_id(notif) {
+                take_click_handler(click_id);
+            }
             let () = msg_send![center, removeDeliveredNotification: notif];
         }
     }
@@ -67,6 +104,10 @@ impl NotifDelegate {
                 let url = String::from_utf8_lossy(url);
                 let _ = open::that(&*url);
             }
+
+            if let Some(handler) = notif_click_id(notif).and_then(take_click_handler) {
+                handler();
+            }
             let () = msg_send![center, removeDeliveredNotification: notif];
         }
     }
@@ -115,8 +156,21 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
         let () = msg_send![*notif, setInformativeText: nsstring(&toast.message)];
 
         let mut info = CFMutableDictionary::new();
+        let has_info = toast.url.is_some() || toast.on_click.is_some();
+        let mut click_id = None;
         if let Some(url) = toast.url {
             info.set(CFString::from_static_string("url"), CFString::new(&url));
+        }
+        if let Some(on_click) = toast.on_click {
+            let id = NEXT_CLICK_ID.fetch_add(1, Ordering::Relaxed);
+            CLICK_HANDLERS.lock().unwrap().push((id, on_click));
+            info.set(
+                CFString::from_static_string("click_id"),
+                CFString::new(&id.to_string()),
+            );
+            click_id.replace(id);
+        }
+        if has_info {
             let () = msg_send![*notif, setUserInfo: info];
         }
 
@@ -135,6 +189,9 @@ pub fn show_notif(toast: ToastNotification) -> Result<(), Box<dyn std::error::Er
             std::thread::spawn(move || {
                 std::thread::sleep(timeout);
                 let () = msg_send![center.0, removeDeliveredNotification: *notif.0];
+                if let Some(click_id) = click_id {
+                    take_click_handler(click_id);
+                }
             });
         }
     }
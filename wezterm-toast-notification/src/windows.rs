@@ -21,7 +21,7 @@ fn unwrap_arg<T>(a: &Option<T>) -> Result<&T, WinError> {
 fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
     let xml = XmlDocument::new()?;
 
-    let url_actions = if toast.url.is_some() {
+    let url_actions = if toast.url.is_some() || toast.on_click.is_some() {
         r#"
         <actions>
            <action content="Show" arguments="show" />
@@ -55,10 +55,15 @@ fn show_notif_impl(toast: TN) -> Result<(), Box<dyn std::error::Error>> {
 
             let args = result.Arguments()?;
 
-            if args == "show" {
+            // Clicking on the body of the toast activates it with
+            // empty arguments, while the button passes "show"
+            if args == "show" || args.is_empty() {
                 if let Some(url) = toast.url.as_ref() {
                     let _ = open::that(url);
                 }
+                if let Some(on_click) = toast.on_click.as_ref() {
+                    on_click();
+                }
             }
 
             Ok(())
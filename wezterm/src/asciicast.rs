@@ -8,15 +8,18 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::ffi::{OsStr, OsString};
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
-use std::path::PathBuf;
-use std::sync::mpsc::channel;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use structopt::StructOpt;
 use termwiz::escape::parser::Parser;
 use termwiz::escape::Action;
+use termwiz::input::{InputEvent, InputParser, KeyCode, KeyEvent, Modifiers};
 #[cfg(unix)]
 use unix::UnixTty as Tty;
-use wezterm_term::color::ColorPalette;
+use wezterm_term::color::{ColorPalette, SrgbaTuple};
+use wezterm_term::{Line, Terminal, TerminalConfiguration, TerminalSize, VisibleRowIndex};
 #[cfg(windows)]
 use win::WinTty as Tty;
 
@@ -112,6 +115,19 @@ impl Header {
     }
 }
 
+/// Terminal configuration used when replaying a recording without
+/// a real terminal; the colors come from the recording.
+#[derive(Debug)]
+struct ReplayTermConfig {
+    palette: ColorPalette,
+}
+
+impl TerminalConfiguration for ReplayTermConfig {
+    fn color_palette(&self) -> ColorPalette {
+        self.palette.clone()
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Theme {
     /// Normal text color
@@ -122,6 +138,44 @@ pub struct Theme {
     pub palette: String,
 }
 
+impl Theme {
+    /// Returns `palette` with the colors that are specified by
+    /// this theme replaced.  Colors that cannot be parsed are ignored.
+    pub fn apply_to_palette(&self, mut palette: ColorPalette) -> ColorPalette {
+        if let Ok(fg) = self.fg.parse() {
+            palette.foreground = fg;
+        }
+        if let Ok(bg) = self.bg.parse() {
+            palette.background = bg;
+        }
+        for (idx, color) in self.palette.split(':').take(16).enumerate() {
+            if let Ok(color) = color.parse() {
+                palette.colors.0[idx] = color;
+            }
+        }
+        palette
+    }
+
+    /// Returns the escape sequences that change the colors of the
+    /// terminal to those of this theme.  Colors that cannot be
+    /// parsed are skipped.
+    pub fn escape_sequences(&self) -> String {
+        let mut seq = String::new();
+        if let Ok(fg) = self.fg.parse::<SrgbaTuple>() {
+            seq.push_str(&format!("\x1b]10;{}\x1b\\", fg.to_rgb_string()));
+        }
+        if let Ok(bg) = self.bg.parse::<SrgbaTuple>() {
+            seq.push_str(&format!("\x1b]11;{}\x1b\\", bg.to_rgb_string()));
+        }
+        for (idx, color) in self.palette.split(':').take(16).enumerate() {
+            if let Ok(color) = color.parse::<SrgbaTuple>() {
+                seq.push_str(&format!("\x1b]4;{};{}\x1b\\", idx, color.to_rgb_string()));
+            }
+        }
+        seq
+    }
+}
+
 #[derive(Debug, Default, Serialize, Deserialize, Clone)]
 pub struct Event(pub f32, pub String, pub String);
 
@@ -231,9 +285,15 @@ mod win {
         pub fn reader(&self) -> anyhow::Result<FileDescriptor> {
             Ok(self.read.try_clone()?)
         }
+    }
 
-        pub fn write_all(&mut self, data: &[u8]) -> anyhow::Result<()> {
-            Ok(self.write.write_all(data)?)
+    impl Write for WinTty {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.write.write(data)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.write.flush()
         }
     }
 
@@ -312,9 +372,15 @@ mod unix {
         pub fn reader(&self) -> anyhow::Result<FileDescriptor> {
             Ok(self.tty.try_clone()?)
         }
+    }
+
+    impl Write for UnixTty {
+        fn write(&mut self, data: &[u8]) -> std::io::Result<usize> {
+            self.tty.write(data)
+        }
 
-        pub fn write_all(&mut self, data: &[u8]) -> anyhow::Result<()> {
-            Ok(self.tty.write_all(data)?)
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.tty.flush()
         }
     }
 
@@ -474,20 +540,44 @@ impl RecordCommand {
     }
 }
 
+/// How far the left and right arrow keys seek during playback
+const SEEK_SECONDS: f32 = 5.0;
+
 #[derive(Debug, StructOpt, Clone)]
 pub struct PlayCommand {
     /// Explain what is being sent/received
     #[structopt(long)]
     explain: bool,
 
+    /// Playback speed multiplier; 2 plays twice as fast
+    #[structopt(long, default_value = "1.0")]
+    speed: f32,
+
+    /// Limit the delay between events to at most this many seconds.
+    /// Defaults to the idle_time_limit recorded in the cast file.
+    #[structopt(long)]
+    idle_time_limit: Option<f32>,
+
+    /// Instead of playing the cast on this terminal, feed it into
+    /// an in-memory terminal and print the final screen contents
+    #[structopt(long, conflicts_with = "explain")]
+    headless: bool,
+
     cast_file: PathBuf,
 }
 
-impl PlayCommand {
-    pub fn run(&self) -> anyhow::Result<()> {
+/// The output events from a cast file.  The times have been
+/// adjusted to respect the idle time limit.
+pub struct Recording {
+    pub header: Header,
+    pub events: Vec<(f32, String)>,
+}
+
+impl Recording {
+    pub fn load(path: &Path, idle_time_limit: Option<f32>) -> anyhow::Result<Self> {
         let mut cast_file = BufReader::new(
-            std::fs::File::open(&self.cast_file)
-                .with_context(|| format!("reading cast file {}", self.cast_file.display()))?,
+            std::fs::File::open(path)
+                .with_context(|| format!("reading cast file {}", path.display()))?,
         );
         let mut header_line = String::new();
         cast_file
@@ -495,6 +585,339 @@ impl PlayCommand {
             .context("reading Header line")?;
 
         let header: Header = serde_json::from_str(&header_line).context("parsing Header")?;
+        let idle_time_limit = idle_time_limit.or(header.idle_time_limit);
+
+        let mut events = vec![];
+        let mut last_recorded = 0.;
+        let mut last_adjusted = 0.;
+        for line in cast_file.lines() {
+            let line = line?;
+            let event: Event = serde_json::from_str(&line)?;
+            if event.1 != "o" {
+                continue;
+            }
+            let mut delay = (event.0 - last_recorded).max(0.);
+            if let Some(limit) = idle_time_limit {
+                delay = delay.min(limit);
+            }
+            last_recorded = event.0;
+            last_adjusted += delay;
+            events.push((last_adjusted, event.2));
+        }
+
+        Ok(Self { header, events })
+    }
+
+    pub fn duration(&self) -> f32 {
+        self.events.last().map(|(t, _)| *t).unwrap_or(0.)
+    }
+
+    /// Returns the color palette for the recording: the palette
+    /// from `config`, overridden by the theme in the header.
+    pub fn palette(&self, config: &ConfigHandle) -> ColorPalette {
+        let palette: ColorPalette = config.resolved_palette.clone().into();
+        match &self.header.theme {
+            Some(theme) => theme.apply_to_palette(palette),
+            None => palette,
+        }
+    }
+
+    /// Creates a Terminal with the dimensions and colors of the
+    /// recording, into which its events can be fed
+    pub fn terminal(&self, config: &ConfigHandle) -> Terminal {
+        Terminal::new(
+            TerminalSize {
+                physical_rows: self.header.height as usize,
+                physical_cols: self.header.width as usize,
+                pixel_width: 0,
+                pixel_height: 0,
+            },
+            Arc::new(ReplayTermConfig {
+                palette: self.palette(config),
+            }),
+            "WezTerm",
+            config::wezterm_version(),
+            // Responses to queries have nowhere to go
+            Box::new(std::io::sink()),
+        )
+    }
+}
+
+/// Returns the lines that are currently visible in `term`
+pub fn visible_lines(term: &Terminal) -> Vec<Line> {
+    let screen = term.screen();
+    let rows = screen.phys_range(&(0..screen.physical_rows as VisibleRowIndex));
+    screen.lines_in_phys_range(rows)
+}
+
+/// Tracks the playback position and sends events to the tty
+struct Player<'a, W: Write> {
+    output: W,
+    recording: &'a Recording,
+    /// Index of the next event to be sent
+    next: usize,
+    /// The position in the recording at `resumed_at`
+    clock: f32,
+    resumed_at: Instant,
+    paused: bool,
+    speed: f32,
+    sent_parser: Parser,
+    sent_actions: Vec<Action>,
+}
+
+impl<'a, W: Write> Player<'a, W> {
+    fn new(output: W, recording: &'a Recording, speed: f32) -> Self {
+        Self {
+            output,
+            recording,
+            next: 0,
+            clock: 0.,
+            resumed_at: Instant::now(),
+            paused: false,
+            speed,
+            sent_parser: Parser::new(),
+            sent_actions: vec![],
+        }
+    }
+
+    /// Changes the colors of the terminal to match the theme of the
+    /// recording, if it has one
+    fn apply_theme(&mut self) -> anyhow::Result<()> {
+        if let Some(theme) = &self.recording.header.theme {
+            self.output.write_all(theme.escape_sequences().as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Restores the colors that were changed by `apply_theme`
+    fn restore_theme(&mut self) -> anyhow::Result<()> {
+        if self.recording.header.theme.is_some() {
+            self.output
+                .write_all(b"\x1b]104\x1b\\\x1b]110\x1b\\\x1b]111\x1b\\")?;
+        }
+        Ok(())
+    }
+
+    /// Returns the current position in the recording
+    fn position(&self) -> f32 {
+        if self.paused {
+            self.clock
+        } else {
+            self.clock + self.resumed_at.elapsed().as_secs_f32() * self.speed
+        }
+    }
+
+    fn set_position(&mut self, position: f32) {
+        self.clock = position;
+        self.resumed_at = Instant::now();
+    }
+
+    /// Returns how long to wait before the next event is due,
+    /// or None if playback is paused or complete.
+    fn next_delay(&self) -> Option<Duration> {
+        if self.paused {
+            return None;
+        }
+        let (target, _) = self.recording.events.get(self.next)?;
+        let delay = ((target - self.position()) / self.speed).max(0.);
+        Some(Duration::from_secs_f32(delay))
+    }
+
+    fn is_done(&self) -> bool {
+        self.next >= self.recording.events.len()
+    }
+
+    fn send(&mut self, text: &str) -> anyhow::Result<()> {
+        self.output.write_all(text.as_bytes())?;
+        let sent_actions = &mut self.sent_actions;
+        self.sent_parser
+            .parse(text.as_bytes(), |act| sent_actions.push(act));
+        Ok(())
+    }
+
+    /// Sends the next event
+    fn advance(&mut self) -> anyhow::Result<()> {
+        let recording = self.recording;
+        if let Some((_, text)) = recording.events.get(self.next) {
+            self.next += 1;
+            self.send(text)?;
+        }
+        Ok(())
+    }
+
+    /// Sends the next event and moves the position to its time
+    fn step(&mut self) -> anyhow::Result<()> {
+        let recording = self.recording;
+        if let Some((time, _)) = recording.events.get(self.next) {
+            self.advance()?;
+            self.set_position(*time);
+        }
+        Ok(())
+    }
+
+    fn toggle_pause(&mut self) {
+        let position = self.position();
+        self.paused = !self.paused;
+        self.set_position(position);
+    }
+
+    /// Moves the position to `target`.  Seeking backwards resets
+    /// the terminal and re-sends the events from the start of the
+    /// recording, as there is no way to undo their effects.
+    fn seek(&mut self, target: f32) -> anyhow::Result<()> {
+        let target = target.max(0.).min(self.recording.duration());
+        if target < self.position() {
+            self.output.write_all(b"\x1bc")?;
+            // The reset also discarded the colors of the theme
+            self.apply_theme()?;
+            self.sent_parser = Parser::new();
+            self.sent_actions.clear();
+            self.next = 0;
+        }
+
+        let recording = self.recording;
+        let mut text = String::new();
+        while let Some((time, output)) = recording.events.get(self.next) {
+            if *time > target {
+                break;
+            }
+            text.push_str(output);
+            self.next += 1;
+        }
+        self.send(&text)?;
+        self.set_position(target);
+        Ok(())
+    }
+
+    /// Processes a key press.  Returns false if playback should stop.
+    fn handle_key(&mut self, key: &KeyEvent) -> anyhow::Result<bool> {
+        match (key.key, key.modifiers) {
+            (KeyCode::Char(' '), _) => self.toggle_pause(),
+            (KeyCode::Char('.'), _) if self.paused => self.step()?,
+            (KeyCode::RightArrow, _) => self.seek(self.position() + SEEK_SECONDS)?,
+            (KeyCode::LeftArrow, _) => self.seek(self.position() - SEEK_SECONDS)?,
+            (KeyCode::Char('q'), _) => return Ok(false),
+            (KeyCode::Char('c'), Modifiers::CTRL) | (KeyCode::Char('C'), Modifiers::CTRL) => {
+                return Ok(false)
+            }
+            _ => {}
+        }
+        Ok(true)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResponseState {
+    Ground,
+    Escape,
+    Csi,
+    /// Inside a DCS, OSC, APC, PM or SOS string
+    String,
+    /// Saw ESC inside a string; a following `\\` is the terminator
+    StringEscape,
+}
+
+impl Default for ResponseState {
+    fn default() -> Self {
+        Self::Ground
+    }
+}
+
+/// Extracts the key presses from the data read from the tty during
+/// playback.  That data also contains the responses of the terminal
+/// to queries in the replayed output, such as DECRQSS or device
+/// attribute reports, which are discarded rather than being
+/// mistaken for the keys that control playback.
+#[derive(Default)]
+struct PlaybackInput {
+    parser: InputParser,
+    state: ResponseState,
+    csi: Vec<u8>,
+}
+
+impl PlaybackInput {
+    fn keys(&mut self, data: &[u8]) -> Vec<KeyEvent> {
+        let input = self.strip_responses(data);
+        let mut keys = vec![];
+        self.parser.parse(
+            &input,
+            |event| {
+                if let InputEvent::Key(key) = event {
+                    keys.push(key);
+                }
+            },
+            false,
+        );
+        keys
+    }
+
+    /// Removes string sequences and CSI reports from data.
+    /// Sequences may be split across calls.
+    fn strip_responses(&mut self, data: &[u8]) -> Vec<u8> {
+        let mut input = vec![];
+        for &b in data {
+            self.state = match (self.state, b) {
+                (ResponseState::Ground, 0x1b) => ResponseState::Escape,
+                (ResponseState::Ground, _) => {
+                    input.push(b);
+                    ResponseState::Ground
+                }
+                (ResponseState::Escape, b'[') => {
+                    self.csi.clear();
+                    ResponseState::Csi
+                }
+                (ResponseState::Escape, b'P' | b']' | b'_' | b'^' | b'X') => ResponseState::String,
+                (ResponseState::Escape, 0x1b) => {
+                    input.push(0x1b);
+                    ResponseState::Escape
+                }
+                (ResponseState::Escape, _) => {
+                    input.extend_from_slice(&[0x1b, b]);
+                    ResponseState::Ground
+                }
+                (ResponseState::Csi, 0x40..=0x7e) => {
+                    self.csi.push(b);
+                    if !is_csi_report(&self.csi) {
+                        input.extend_from_slice(b"\x1b[");
+                        input.extend_from_slice(&self.csi);
+                    }
+                    ResponseState::Ground
+                }
+                (ResponseState::Csi, _) => {
+                    self.csi.push(b);
+                    ResponseState::Csi
+                }
+                (ResponseState::String, 0x07) => ResponseState::Ground,
+                (ResponseState::String, 0x1b) => ResponseState::StringEscape,
+                (ResponseState::String, _) => ResponseState::String,
+                (ResponseState::StringEscape, b'\\') => ResponseState::Ground,
+                (ResponseState::StringEscape, 0x1b) => ResponseState::StringEscape,
+                (ResponseState::StringEscape, _) => ResponseState::String,
+            };
+        }
+        input
+    }
+}
+
+/// Returns true if `csi`, the bytes that follow `CSI`, is a report
+/// from the terminal rather than a key.  Keys never use a private
+/// parameter prefix other than `<` (SGR mouse reports) nor
+/// intermediate bytes, whereas reports such as DA and DECRPM do.
+fn is_csi_report(csi: &[u8]) -> bool {
+    matches!(csi.first(), Some(b'?' | b'>' | b'=')) || csi.iter().any(|b| (0x20..=0x2f).contains(b))
+}
+
+impl PlayCommand {
+    pub fn run(&self, config: ConfigHandle) -> anyhow::Result<()> {
+        if self.speed.is_nan() || self.speed <= 0. {
+            anyhow::bail!("--speed must be greater than zero");
+        }
+        let recording = Recording::load(&self.cast_file, self.idle_time_limit)?;
+
+        if self.headless {
+            return self.run_headless(config, &recording);
+        }
+        let header = &recording.header;
 
         let mut tty = Tty::new()?;
         let size = tty.get_size()?;
@@ -530,25 +953,56 @@ impl PlayCommand {
             });
         }
 
-        let start = Instant::now();
+        let mut player = Player::new(&mut tty, &recording, self.speed);
+        player.apply_theme()?;
+
+        // Data read from the tty during playback; this is a mixture
+        // of key presses that control playback and responses from
+        // the terminal to the replayed output
+        let mut received = vec![];
+        let mut input = PlaybackInput::default();
+
+        while !player.is_done() {
+            let data = match player.next_delay() {
+                Some(delay) => match rx.recv_timeout(delay) {
+                    Ok(Message::Stdin(data)) => data,
+                    Ok(_) => unreachable!(),
+                    Err(RecvTimeoutError::Timeout) => {
+                        player.advance()?;
+                        continue;
+                    }
+                    Err(RecvTimeoutError::Disconnected) => {
+                        std::thread::sleep(delay);
+                        player.advance()?;
+                        continue;
+                    }
+                },
+                None => match rx.recv() {
+                    Ok(Message::Stdin(data)) => data,
+                    Ok(_) => unreachable!(),
+                    Err(_) => break,
+                },
+            };
 
-        let mut sent_parser = Parser::new();
-        let mut sent_actions = vec![];
+            let keys = input.keys(&data);
+            received.push(data);
 
-        for line in cast_file.lines() {
-            let line = line?;
-            let event: Event = serde_json::from_str(&line)?;
-            if event.1 != "o" {
-                continue;
+            let mut keep_going = true;
+            for key in &keys {
+                if !player.handle_key(key)? {
+                    keep_going = false;
+                    break;
+                }
+            }
+            if !keep_going {
+                break;
             }
-            let target = start + Duration::from_secs_f32(event.0);
-            let duration = target.saturating_duration_since(Instant::now());
-            std::thread::sleep(duration);
-
-            tty.write_all(&event.2.as_bytes())?;
-            sent_parser.parse(&event.2.as_bytes(), |act| sent_actions.push(act));
         }
 
+        player.restore_theme()?;
+        let sent_actions = std::mem::take(&mut player.sent_actions);
+        drop(player);
+
         std::thread::sleep(Duration::from_millis(100));
 
         tty.set_cooked()?;
@@ -590,22 +1044,206 @@ impl PlayCommand {
         if self.explain {
             println!("< RECV");
         }
-        let mut parser = Parser::new();
         while let Ok(msg) = rx.try_recv() {
             match msg {
-                Message::Stdin(data) => {
-                    if self.explain {
-                        let answer_back = String::from_utf8_lossy(&data);
-                        println!("\t{:?}", answer_back);
-                        parser.parse(&data, |action| {
-                            println!("\t{:?}", action);
-                        });
-                    }
-                }
+                Message::Stdin(data) => received.push(data),
                 _ => unreachable!(),
             }
         }
+        let mut parser = Parser::new();
+        for data in received {
+            if self.explain {
+                let answer_back = String::from_utf8_lossy(&data);
+                println!("\t{:?}", answer_back);
+                parser.parse(&data, |action| {
+                    println!("\t{:?}", action);
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replays the recording into a Terminal, without regard to
+    /// timing, and prints the text of the final screen
+    fn run_headless(&self, config: ConfigHandle, recording: &Recording) -> anyhow::Result<()> {
+        let mut term = recording.terminal(&config);
+
+        for (_, text) in &recording.events {
+            term.advance_bytes(text);
+        }
+
+        let stdout = std::io::stdout();
+        let mut stdout = stdout.lock();
+        for line in visible_lines(&term) {
+            writeln!(stdout, "{}", line.as_str().trim_end())?;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn recording() -> Recording {
+        Recording {
+            header: Header {
+                theme: Some(Theme {
+                    fg: "#ffffff".to_string(),
+                    bg: "#000000".to_string(),
+                    palette: "#ff0000".to_string(),
+                }),
+                ..Header::default()
+            },
+            events: vec![
+                (1.0, "a".to_string()),
+                (2.0, "b".to_string()),
+                (3.0, "c".to_string()),
+            ],
+        }
+    }
+
+    const THEME: &str = "\x1b]10;#ffffff\x1b\\\x1b]11;#000000\x1b\\\x1b]4;0;#ff0000\x1b\\";
+
+    fn paused_player(recording: &Recording) -> Player<'_, Vec<u8>> {
+        let mut player = Player::new(vec![], recording, 1.0);
+        player.toggle_pause();
+        player
+    }
+
+    fn take_output(player: &mut Player<Vec<u8>>) -> String {
+        String::from_utf8(std::mem::take(&mut player.output)).unwrap()
+    }
+
+    fn key(key: KeyCode) -> KeyEvent {
+        KeyEvent {
+            key,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    #[test]
+    fn theme_escapes() {
+        assert_eq!(recording().header.theme.unwrap().escape_sequences(), THEME);
+
+        let theme = Theme {
+            fg: "bogus".to_string(),
+            bg: String::new(),
+            palette: "bogus:#00ff00".to_string(),
+        };
+        assert_eq!(theme.escape_sequences(), "\x1b]4;1;#00ff00\x1b\\");
+    }
+
+    #[test]
+    fn seek_forward() {
+        let recording = recording();
+        let mut player = paused_player(&recording);
+
+        player.seek(2.0).unwrap();
+        assert_eq!(take_output(&mut player), "ab");
+        assert_eq!(player.position(), 2.0);
+        assert_eq!(player.next, 2);
+
+        player.seek(100.).unwrap();
+        assert_eq!(take_output(&mut player), "c");
+        assert_eq!(player.position(), 3.0);
+        assert!(player.is_done());
+    }
+
+    #[test]
+    fn seek_backward() {
+        let recording = recording();
+        let mut player = paused_player(&recording);
+
+        player.seek(2.5).unwrap();
+        assert_eq!(take_output(&mut player), "ab");
+
+        // The terminal is reset, the theme applied again and the
+        // events replayed from the start
+        player.seek(1.5).unwrap();
+        assert_eq!(take_output(&mut player), format!("\x1bc{}a", THEME));
+        assert_eq!(player.position(), 1.5);
+        assert_eq!(player.next, 1);
+        assert_eq!(player.sent_actions, vec![Action::Print('a')]);
+
+        player.seek(-5.).unwrap();
+        assert_eq!(take_output(&mut player), format!("\x1bc{}", THEME));
+        assert_eq!(player.position(), 0.);
+        assert_eq!(player.next, 0);
+        assert!(player.sent_actions.is_empty());
+    }
+
+    #[test]
+    fn keys() {
+        let recording = recording();
+        let mut player = paused_player(&recording);
+
+        // Stepping moves to the time of the next event
+        assert!(player.handle_key(&key(KeyCode::Char('.'))).unwrap());
+        assert_eq!(take_output(&mut player), "a");
+        assert_eq!(player.position(), 1.0);
+
+        assert!(player.handle_key(&key(KeyCode::RightArrow)).unwrap());
+        assert_eq!(take_output(&mut player), "bc");
+        assert_eq!(player.position(), 3.0);
+
+        assert!(player.handle_key(&key(KeyCode::LeftArrow)).unwrap());
+        assert_eq!(take_output(&mut player), format!("\x1bc{}", THEME));
+        assert_eq!(player.position(), 0.);
+
+        assert!(player.handle_key(&key(KeyCode::Char(' '))).unwrap());
+        assert!(!player.paused);
+        // Stepping is only possible while paused
+        assert!(player.handle_key(&key(KeyCode::Char('.'))).unwrap());
+        assert_eq!(take_output(&mut player), "");
+        assert!(player.handle_key(&key(KeyCode::Char(' '))).unwrap());
+        assert!(player.paused);
+
+        assert!(!player.handle_key(&key(KeyCode::Char('q'))).unwrap());
+        assert!(!player
+            .handle_key(&KeyEvent {
+                key: KeyCode::Char('c'),
+                modifiers: Modifiers::CTRL,
+            })
+            .unwrap());
+    }
+
+    #[test]
+    fn terminal_responses_are_not_keys() {
+        let mut input = PlaybackInput::default();
+
+        // DECRQSS response for DECSCUSR
+        assert_eq!(input.keys(b"\x1bP1$r2 q\x1b\\"), vec![]);
+        // Primary and secondary device attributes, DECRPM
+        assert_eq!(
+            input.keys(b"\x1b[?62;22c\x1b[>1;10;0c\x1b[?2026;2$y"),
+            vec![]
+        );
+        // OSC color query responses, terminated by BEL or ST
+        assert_eq!(
+            input.keys(b"\x1b]11;rgb:0000/0000/0000\x07\x1b]10;rgb:ffff/ffff/ffff\x1b\\"),
+            vec![]
+        );
+
+        assert_eq!(
+            input.keys(b" \x1bP1$r0m\x1b\\q\x1b[C"),
+            vec![
+                key(KeyCode::Char(' ')),
+                key(KeyCode::Char('q')),
+                key(KeyCode::RightArrow),
+            ]
+        );
+    }
+
+    #[test]
+    fn split_terminal_responses() {
+        let mut input = PlaybackInput::default();
+        assert_eq!(input.keys(b".\x1bP1$r"), vec![key(KeyCode::Char('.'))]);
+        assert_eq!(input.keys(b"2 q\x1b"), vec![]);
+        assert_eq!(input.keys(b"\\q\x1b[?"), vec![key(KeyCode::Char('q'))]);
+        assert_eq!(input.keys(b"1u\x1b"), vec![]);
+        assert_eq!(input.keys(b"[D"), vec![key(KeyCode::LeftArrow)]);
+    }
+}
@@ -0,0 +1,91 @@
+//! Converts color schemes from other terminal emulators into
+//! wezterm's own TOML format
+use anyhow::{anyhow, bail, Context};
+use config::{color_scheme_to_toml, import_color_schemes, ColorSchemeFormat};
+use std::io::Write;
+use std::path::PathBuf;
+use structopt::StructOpt;
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct ConvertColorSchemeCommand {
+    /// The format of the input file.
+    /// If omitted, it is determined from the file extension.
+    #[structopt(
+        long,
+        possible_values = &["wezterm", "iterm2", "windows-terminal", "base16", "kitty"]
+    )]
+    format: Option<ColorSchemeFormat>,
+
+    /// When the input file holds several color schemes, such as
+    /// a Windows Terminal settings.json, selects the scheme to convert
+    #[structopt(long)]
+    scheme: Option<String>,
+
+    /// Where to write the output; defaults to stdout
+    #[structopt(long, short = "o", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    /// The color scheme file to convert
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+}
+
+impl ConvertColorSchemeCommand {
+    pub fn run(&self) -> anyhow::Result<()> {
+        let format = match self.format {
+            Some(format) => format,
+            None => ColorSchemeFormat::from_path(&self.input).ok_or_else(|| {
+                anyhow!(
+                    "cannot determine the format of {}; use --format to specify it",
+                    self.input.display()
+                )
+            })?,
+        };
+        let data = std::fs::read_to_string(&self.input)
+            .with_context(|| format!("reading {}", self.input.display()))?;
+        let stem = self
+            .input
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let mut schemes = import_color_schemes(&data, format)?;
+        let scheme = match &self.scheme {
+            Some(wanted) => {
+                let idx = schemes
+                    .iter()
+                    .position(|scheme| scheme.name.as_deref() == Some(wanted.as_str()))
+                    .ok_or_else(|| anyhow!("no color scheme named {} was found", wanted))?;
+                schemes.remove(idx)
+            }
+            None if schemes.len() == 1 => schemes.remove(0),
+            None if schemes.is_empty() => bail!("no color schemes were found"),
+            None => {
+                let names: Vec<&str> = schemes
+                    .iter()
+                    .filter_map(|scheme| scheme.name.as_deref())
+                    .collect();
+                bail!(
+                    "the file holds several color schemes; use --scheme to pick one of: {}",
+                    names.join(", ")
+                );
+            }
+        };
+
+        if !scheme.unmapped.is_empty() {
+            eprintln!(
+                "These colors cannot be represented in a wezterm color scheme \
+                 and were not converted: {}",
+                scheme.unmapped.join(", ")
+            );
+        }
+
+        let name = scheme.name.as_deref().unwrap_or(&stem);
+        let output = color_scheme_to_toml(name, &scheme.palette);
+        match &self.output {
+            Some(path) => std::fs::write(path, output)?,
+            None => std::io::stdout().lock().write_all(output.as_bytes())?,
+        }
+        Ok(())
+    }
+}
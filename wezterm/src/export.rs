@@ -0,0 +1,411 @@
+//! Renders asciicast recordings in formats suitable for publishing
+use crate::asciicast::{visible_lines, Recording};
+use config::ConfigHandle;
+use std::fmt::Write as _;
+use std::io::Write as _;
+use std::path::PathBuf;
+use std::str::FromStr;
+use strip_ansi_escapes::markup::{escape_html, Palette, Run};
+use structopt::StructOpt;
+use termwiz::cell::unicode_column_width;
+use wezterm_term::color::ColorPalette;
+use wezterm_term::Line;
+
+/// Metrics used to lay out the cells in an SVG
+const SVG_FONT_SIZE: f32 = 14.;
+const SVG_CELL_WIDTH: f32 = SVG_FONT_SIZE * 0.6;
+const SVG_LINE_HEIGHT: f32 = SVG_FONT_SIZE * 1.2;
+/// How long the final frame of an animation is held before it loops
+const SVG_FINAL_FRAME_SECONDS: f32 = 2.;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Text,
+    Html,
+    Svg,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "text" => Ok(Self::Text),
+            "html" => Ok(Self::Html),
+            "svg" => Ok(Self::Svg),
+            _ => anyhow::bail!("invalid format {}; expected text, html or svg", s),
+        }
+    }
+}
+
+#[derive(Debug, StructOpt, Clone)]
+pub struct ExportCommand {
+    /// The output format.  `text` and `html` render the final
+    /// screen, while `svg` produces an animation of the session.
+    #[structopt(
+        long,
+        default_value = "text",
+        possible_values = &["text", "html", "svg"]
+    )]
+    format: ExportFormat,
+
+    /// Limit the delay between frames of an animation to at most
+    /// this many seconds.
+    /// Defaults to the idle_time_limit recorded in the cast file.
+    #[structopt(long)]
+    idle_time_limit: Option<f32>,
+
+    /// Where to write the output; defaults to stdout
+    #[structopt(long, short = "o", parse(from_os_str))]
+    output: Option<PathBuf>,
+
+    #[structopt(parse(from_os_str))]
+    cast_file: PathBuf,
+}
+
+impl ExportCommand {
+    pub fn run(&self, config: ConfigHandle) -> anyhow::Result<()> {
+        let recording = Recording::load(&self.cast_file, self.idle_time_limit)?;
+        let palette = markup_palette(&recording.palette(&config));
+        let frames = render_frames(&recording, &config);
+        let (_, final_screen) = frames.last().expect("always have an initial frame");
+
+        let output = match self.format {
+            ExportFormat::Text => export_text(final_screen),
+            ExportFormat::Html => export_html(final_screen, &palette),
+            ExportFormat::Svg => export_svg(&recording, &frames, &palette),
+        };
+
+        match &self.output {
+            Some(path) => std::fs::write(path, output)?,
+            None => std::io::stdout().lock().write_all(output.as_bytes())?,
+        }
+        Ok(())
+    }
+}
+
+/// Feeds the recording through a terminal, returning the time and
+/// contents of the screen each time that it changes.
+/// The first frame is the initial blank screen.
+fn render_frames(recording: &Recording, config: &ConfigHandle) -> Vec<(f32, Vec<Line>)> {
+    let mut term = recording.terminal(config);
+    let mut frames = vec![(0., visible_lines(&term))];
+
+    for (time, text) in &recording.events {
+        term.advance_bytes(text);
+        let lines = visible_lines(&term);
+        let (prior_time, prior_lines) = frames.last_mut().expect("have a frame");
+        if lines == *prior_lines {
+            continue;
+        }
+        if *prior_time == *time {
+            // Several events at the same instant produce a single frame
+            *prior_lines = lines;
+        } else {
+            frames.push((*time, lines));
+        }
+    }
+
+    frames
+}
+
+fn export_text(lines: &[Line]) -> String {
+    let mut text = String::new();
+    for line in lines {
+        text.push_str(line.as_str().trim_end());
+        text.push('\n');
+    }
+    text
+}
+
+/// Returns the colors of `palette` in the form used to generate markup
+fn markup_palette(palette: &ColorPalette) -> Palette {
+    Palette {
+        colors: palette.colors.0.to_vec(),
+        foreground: palette.foreground,
+        background: palette.background,
+    }
+}
+
+fn export_html(lines: &[Line], palette: &Palette) -> String {
+    let mut html = vec![];
+    writeln!(
+        html,
+        "<pre style=\"color:{};background-color:{};\
+         font-family:monospace;padding:0.5em\">",
+        palette.foreground.to_rgb_string(),
+        palette.background.to_rgb_string()
+    )
+    .ok();
+
+    for line in lines {
+        let runs: Vec<Run> = line
+            .cluster(None)
+            .into_iter()
+            .map(|cluster| Run::new(cluster.text, &cluster.attrs, palette))
+            .collect();
+        // Don't emit the trailing blanks at the end of the line,
+        // unless they have a visible background
+        let last = runs
+            .iter()
+            .rposition(|run| !run.text.trim_end().is_empty() || run.bg.is_some());
+        let num_runs = last.map(|idx| idx + 1).unwrap_or(0);
+        for (idx, mut run) in runs.into_iter().take(num_runs).enumerate() {
+            if idx + 1 == num_runs && run.bg.is_none() {
+                run.text.truncate(run.text.trim_end().len());
+            }
+            run.write_html(&mut html).ok();
+        }
+        html.push(b'\n');
+    }
+
+    html.extend_from_slice(b"</pre>\n");
+    String::from_utf8(html).expect("html is utf8")
+}
+
+/// Returns the presentation attributes for an svg text element
+fn svg_text_attributes(run: &Run, palette: &Palette) -> String {
+    let fg = match &run.fg {
+        Some(fg) => fg.clone(),
+        None => palette.foreground.to_rgb_string(),
+    };
+    let mut attrs = format!(r#"fill="{}""#, fg);
+    if run.bold {
+        attrs.push_str(r#" font-weight="bold""#);
+    }
+    if run.dim {
+        attrs.push_str(r#" fill-opacity="0.5""#);
+    }
+    if run.italic {
+        attrs.push_str(r#" font-style="italic""#);
+    }
+    if let Some(decoration) = run.text_decoration_line() {
+        write!(attrs, r#" text-decoration="{}""#, decoration).ok();
+    }
+    attrs
+}
+
+/// Renders the cells of `lines` as svg elements
+fn svg_frame(lines: &[Line], palette: &Palette) -> String {
+    let mut backgrounds = String::new();
+    let mut text = String::new();
+
+    for (row, line) in lines.iter().enumerate() {
+        let y = row as f32 * SVG_LINE_HEIGHT;
+        for cluster in line.cluster(None) {
+            let x = cluster.first_cell_idx as f32 * SVG_CELL_WIDTH;
+            let width = cluster.width as f32 * SVG_CELL_WIDTH;
+            let run = Run::new(cluster.text, &cluster.attrs, palette);
+            if let Some(bg) = &run.bg {
+                writeln!(
+                    backgrounds,
+                    r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}"/>"#,
+                    x, y, width, SVG_LINE_HEIGHT, bg
+                )
+                .ok();
+            }
+            let run_text = run.text.trim_end();
+            if run.invisible || run_text.trim_start().is_empty() {
+                continue;
+            }
+            writeln!(
+                text,
+                r#"<text x="{:.2}" y="{:.2}" textLength="{:.2}" {}>{}</text>"#,
+                x,
+                // Position the baseline within the line
+                y + SVG_FONT_SIZE,
+                unicode_column_width(run_text, None) as f32 * SVG_CELL_WIDTH,
+                svg_text_attributes(&run, palette),
+                escape_html(run_text)
+            )
+            .ok();
+        }
+    }
+
+    backgrounds + &text
+}
+
+/// Renders the frames as an svg in which all of the frames are
+/// stacked vertically, and a css animation moves each of them into
+/// view at the appropriate time.
+fn export_svg(recording: &Recording, frames: &[(f32, Vec<Line>)], palette: &Palette) -> String {
+    let width = recording.header.width as f32 * SVG_CELL_WIDTH;
+    let height = recording.header.height as f32 * SVG_LINE_HEIGHT;
+    let (last_time, _) = frames.last().expect("always have an initial frame");
+    let duration = last_time + SVG_FINAL_FRAME_SECONDS;
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w:.2}" height="{h:.2}" viewBox="0 0 {w:.2} {h:.2}">"#,
+        w = width,
+        h = height
+    )
+    .ok();
+
+    svg.push_str("<style>\n");
+    writeln!(
+        svg,
+        ".term {{ font-family: Menlo, Consolas, 'DejaVu Sans Mono', monospace; \
+         font-size: {}px; white-space: pre; }}",
+        SVG_FONT_SIZE
+    )
+    .ok();
+    if frames.len() > 1 {
+        svg.push_str("@keyframes play {\n");
+        for (idx, (time, _)) in frames.iter().enumerate() {
+            writeln!(
+                svg,
+                "  {:.3}% {{ transform: translateY(-{:.2}px); }}",
+                100. * time / duration,
+                idx as f32 * height
+            )
+            .ok();
+        }
+        svg.push_str("}\n");
+        writeln!(
+            svg,
+            "#frames {{ animation: play {:.3}s step-end infinite; }}",
+            duration
+        )
+        .ok();
+    }
+    svg.push_str("</style>\n");
+
+    writeln!(
+        svg,
+        r#"<rect width="100%" height="100%" fill="{}"/>"#,
+        palette.background.to_rgb_string()
+    )
+    .ok();
+    svg.push_str("<g id=\"frames\" class=\"term\">\n");
+    for (idx, (_, lines)) in frames.iter().enumerate() {
+        writeln!(
+            svg,
+            r#"<g transform="translate(0 {:.2})">"#,
+            idx as f32 * height
+        )
+        .ok();
+        svg.push_str(&svg_frame(lines, palette));
+        svg.push_str("</g>\n");
+    }
+    svg.push_str("</g>\n</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::asciicast::{Header, Theme};
+
+    fn recording(width: u32, events: &[(f32, &str)]) -> Recording {
+        Recording {
+            header: Header {
+                width,
+                height: 2,
+                theme: Some(Theme {
+                    fg: "#ffffff".to_string(),
+                    bg: "#000000".to_string(),
+                    palette: "#000000:#ff0000:#00ff00".to_string(),
+                }),
+                ..Header::default()
+            },
+            events: events
+                .iter()
+                .map(|(time, text)| (*time, text.to_string()))
+                .collect(),
+        }
+    }
+
+    fn render(recording: &Recording) -> (Vec<(f32, Vec<Line>)>, Palette) {
+        config::use_test_configuration();
+        let config = config::configuration();
+        let palette = markup_palette(&recording.palette(&config));
+        (render_frames(recording, &config), palette)
+    }
+
+    /// Returns the screen after `text` has been output
+    fn screen(width: u32, text: &str) -> (Vec<Line>, Palette) {
+        let (mut frames, palette) = render(&recording(width, &[(1., text)]));
+        let (_, lines) = frames.pop().unwrap();
+        (lines, palette)
+    }
+
+    #[test]
+    fn frame_timing() {
+        let recording = recording(
+            8,
+            &[
+                (0.5, "a"),
+                (0.5, "b"),
+                (1., "\x1b[m"),
+                (2., "c"),
+                (3., "\r\nd"),
+            ],
+        );
+        let (frames, palette) = render(&recording);
+        let text: Vec<(f32, String)> = frames
+            .iter()
+            .map(|(time, lines)| (*time, export_text(lines)))
+            .collect();
+        // Events at the same instant are combined, and events that
+        // don't change the screen don't produce a frame
+        assert_eq!(
+            text,
+            vec![
+                (0., "\n\n".to_string()),
+                (0.5, "ab\n\n".to_string()),
+                (2., "abc\n\n".to_string()),
+                (3., "abc\nd\n".to_string()),
+            ]
+        );
+
+        let svg = export_svg(&recording, &frames, &palette);
+        assert!(svg.contains(
+            "@keyframes play {\n\
+             \x20 0.000% { transform: translateY(-0.00px); }\n\
+             \x20 10.000% { transform: translateY(-33.60px); }\n\
+             \x20 40.000% { transform: translateY(-67.20px); }\n\
+             \x20 60.000% { transform: translateY(-100.80px); }\n\
+             }\n\
+             #frames { animation: play 5.000s step-end infinite; }\n"
+        ));
+        assert_eq!(svg.matches("<g transform=").count(), 4);
+    }
+
+    #[test]
+    fn text() {
+        let (lines, _) = screen(10, "one  \r\n  <two>");
+        assert_eq!(export_text(&lines), "one\n  <two>\n");
+    }
+
+    #[test]
+    fn html() {
+        let (lines, palette) = screen(
+            20,
+            "<&>\"\x1b[1;31mred\x1b[0m \x1b[42mgreen  \x1b[0m  \r\n\x1b[3;4mx\x1b[0m",
+        );
+        assert_eq!(
+            export_html(&lines, &palette),
+            "<pre style=\"color:#ffffff;background-color:#000000;\
+             font-family:monospace;padding:0.5em\">\n\
+             &lt;&amp;&gt;&quot;\
+             <span style=\"color:#ff0000;font-weight:bold\">red</span> \
+             <span style=\"background-color:#00ff00\">green  </span>\n\
+             <span style=\"font-style:italic;text-decoration-line:underline;\
+             text-decoration-style:solid\">x</span>\n\
+             </pre>\n"
+        );
+    }
+
+    #[test]
+    fn svg() {
+        let (lines, palette) = screen(12, "\x1b[7mab\x1b[0m \x1b[8mhide\x1b[0m\r\n\x1b[2;9m<c>");
+        assert_eq!(
+            svg_frame(&lines, &palette),
+            "<rect x=\"0.00\" y=\"0.00\" width=\"16.80\" height=\"16.80\" fill=\"#ffffff\"/>\n\
+             <text x=\"0.00\" y=\"14.00\" textLength=\"16.80\" fill=\"#000000\">ab</text>\n\
+             <text x=\"0.00\" y=\"30.80\" textLength=\"25.20\" fill=\"#ffffff\" \
+             fill-opacity=\"0.5\" text-decoration=\"line-through\">&lt;c&gt;</text>\n"
+        );
+    }
+}
@@ -1,23 +1,34 @@
 use anyhow::{anyhow, Context};
+use chrono::serde::ts_seconds;
 use chrono::{DateTime, Utc};
 use config::keyassignment::SpawnTabDomain;
 use config::wezterm_version;
 use mux::activity::Activity;
 use mux::pane::PaneId;
-use mux::tab::SplitDirection;
+use mux::renderable::RenderableDimensions;
+use mux::tab::{SplitDirection, TabId};
 use mux::window::WindowId;
 use mux::Mux;
 use portable_pty::cmdbuilder::CommandBuilder;
+use serde::Serialize;
 use std::ffi::OsString;
 use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::PathBuf;
 use std::rc::Rc;
 use structopt::StructOpt;
 use tabout::{tabulate_output, Alignment, Column};
+use termwiz::cell::CellAttributes;
+use termwiz::surface::{Change, CursorShape, CursorVisibility};
 use umask::UmaskSaver;
 use wezterm_client::client::{unix_connect_with_retry, Client};
 use wezterm_gui_subcommands::*;
+use wezterm_term::StableRowIndex;
 
 mod asciicast;
+mod convert;
+mod export;
+mod stats;
 
 //    let message = "; ❤ 😍🤢\n\x1b[91;mw00t\n\x1b[37;104;m bleet\x1b[0;m.";
 
@@ -91,6 +102,18 @@ enum SubCommand {
 
     #[structopt(name = "replay", about = "Replay an asciicast terminal session")]
     Replay(asciicast::PlayCommand),
+
+    #[structopt(
+        name = "export",
+        about = "Render an asciicast terminal session as text, html or an animated svg"
+    )]
+    Export(export::ExportCommand),
+
+    #[structopt(
+        name = "convert-color-scheme",
+        about = "Convert an iTerm2, Windows Terminal, base16 or kitty color scheme into wezterm's format"
+    )]
+    ConvertColorScheme(convert::ConvertColorSchemeCommand),
 }
 
 #[derive(Debug, StructOpt, Clone)]
@@ -116,13 +139,158 @@ struct CliCommand {
     sub: CliSubCommand,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CliOutputFormat {
+    Table,
+    Json,
+}
+
+impl std::str::FromStr for CliOutputFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<CliOutputFormat> {
+        match s {
+            "table" => Ok(CliOutputFormat::Table),
+            "json" => Ok(CliOutputFormat::Json),
+            _ => anyhow::bail!("unknown format {}; expected one of table, json", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CliStatsFormat {
+    OpenMetrics,
+    Json,
+}
+
+impl std::str::FromStr for CliStatsFormat {
+    type Err = anyhow::Error;
+    fn from_str(s: &str) -> anyhow::Result<CliStatsFormat> {
+        match s {
+            "openmetrics" => Ok(CliStatsFormat::OpenMetrics),
+            "json" => Ok(CliStatsFormat::Json),
+            _ => anyhow::bail!("unknown format {}; expected one of openmetrics, json", s),
+        }
+    }
+}
+
+/// The schema for `wezterm cli list --format json`.
+/// This is consumed by scripts, so fields should only ever be added.
+#[derive(Serialize, Debug)]
+struct CliListResultItem {
+    window_id: WindowId,
+    tab_id: TabId,
+    pane_id: PaneId,
+    workspace: String,
+    size: CliListResultPtySize,
+    title: String,
+    cwd: String,
+    /// The cursor position, relative to the top of the viewport
+    cursor_x: usize,
+    cursor_y: StableRowIndex,
+    cursor_shape: CursorShape,
+    cursor_visibility: CursorVisibility,
+    is_active: bool,
+    is_zoomed: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct CliListResultPtySize {
+    rows: u16,
+    cols: u16,
+    pixel_width: u16,
+    pixel_height: u16,
+}
+
+/// Flattens the pane trees of `panes` into the items that are
+/// output by `wezterm cli list --format json`
+fn list_result_items(panes: codec::ListPanesResponse) -> Vec<CliListResultItem> {
+    let mut items = vec![];
+    for tabroot in panes.tabs {
+        let mut cursor = tabroot.into_tree().cursor();
+
+        loop {
+            if let Some(entry) = cursor.leaf_mut() {
+                items.push(CliListResultItem {
+                    window_id: entry.window_id,
+                    tab_id: entry.tab_id,
+                    pane_id: entry.pane_id,
+                    workspace: entry.workspace.clone(),
+                    size: CliListResultPtySize {
+                        rows: entry.size.rows,
+                        cols: entry.size.cols,
+                        pixel_width: entry.size.pixel_width,
+                        pixel_height: entry.size.pixel_height,
+                    },
+                    title: entry.title.clone(),
+                    cwd: entry
+                        .working_dir
+                        .as_ref()
+                        .map(|url| url.url.as_str())
+                        .unwrap_or("")
+                        .to_string(),
+                    cursor_x: entry.cursor_pos.x,
+                    cursor_y: entry.cursor_pos.y - entry.physical_top,
+                    cursor_shape: entry.cursor_pos.shape,
+                    cursor_visibility: entry.cursor_pos.visibility,
+                    is_active: entry.is_active_pane,
+                    is_zoomed: entry.is_zoomed_pane,
+                });
+            }
+            match cursor.preorder_next() {
+                Ok(c) => cursor = c,
+                Err(_) => break,
+            }
+        }
+    }
+    items
+}
+
+/// The schema for `wezterm cli list-clients --format json`.
+/// This is consumed by scripts, so fields should only ever be added.
+#[derive(Serialize, Debug)]
+struct CliListClientsResultItem {
+    username: String,
+    hostname: String,
+    pid: u32,
+    /// Unix timestamp, in seconds
+    #[serde(with = "ts_seconds")]
+    connected_at: DateTime<Utc>,
+    /// Seconds since `connected_at`
+    connection_elapsed: u64,
+    /// Unix timestamp, in seconds
+    #[serde(with = "ts_seconds")]
+    last_input: DateTime<Utc>,
+    /// Seconds since `last_input`
+    idle_time: u64,
+    workspace: String,
+    focused_pane_id: Option<PaneId>,
+}
+
+fn print_json<T: Serialize>(value: &T) -> anyhow::Result<()> {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+    serde_json::to_writer_pretty(&mut stdout, value)?;
+    writeln!(stdout)?;
+    Ok(())
+}
+
 #[derive(Debug, StructOpt, Clone)]
 enum CliSubCommand {
     #[structopt(name = "list", about = "list windows, tabs and panes")]
-    List,
+    List {
+        /// Controls the output format.
+        /// "table" and "json" are possible formats.
+        #[structopt(long = "format", default_value = "table")]
+        format: CliOutputFormat,
+    },
 
     #[structopt(name = "list-clients", about = "list clients")]
-    ListClients,
+    ListClients {
+        /// Controls the output format.
+        /// "table" and "json" are possible formats.
+        #[structopt(long = "format", default_value = "table")]
+        format: CliOutputFormat,
+    },
 
     #[structopt(name = "proxy", about = "start rpc proxy pipe")]
     Proxy,
@@ -216,6 +384,101 @@ Outputs the pane-id for the newly created pane on success"
         /// The text to send. If omitted, will read the text from stdin.
         text: Option<String>,
     },
+
+    /// Retrieves the textual content of a pane and outputs it to stdout
+    #[structopt(name = "get-text")]
+    GetText {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+
+        /// The starting line number.
+        /// 0 is the first line of the terminal screen.
+        /// Negative numbers proceed backwards into the scrollback.
+        /// The default is 0, the first line of the terminal screen.
+        #[structopt(long = "start-line", allow_hyphen_values = true)]
+        start_line: Option<isize>,
+
+        /// The ending line number.
+        /// 0 is the first line of the terminal screen.
+        /// Negative numbers proceed backwards into the scrollback.
+        /// The default is the bottom of the terminal screen.
+        #[structopt(long = "end-line", allow_hyphen_values = true)]
+        end_line: Option<isize>,
+
+        /// Include the escape sequences that color and style the text
+        #[structopt(long = "escapes")]
+        escapes: bool,
+    },
+
+    /// Activate (focus) a pane, making its tab the active tab
+    /// in its window
+    #[structopt(name = "activate-pane")]
+    ActivatePane {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+    },
+
+    /// Kill a pane
+    #[structopt(name = "kill-pane")]
+    KillPane {
+        /// Specify the target pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id")]
+        pane_id: Option<PaneId>,
+    },
+
+    /// Change the title of a tab
+    #[structopt(name = "set-tab-title")]
+    SetTabTitle {
+        /// Specify the target tab.
+        /// The default is to use the tab that contains the
+        /// pane specified by --pane-id.
+        #[structopt(long = "tab-id")]
+        tab_id: Option<TabId>,
+
+        /// Specify the current pane.
+        /// The default is to use the current pane based on the
+        /// environment variable WEZTERM_PANE.
+        #[structopt(long = "pane-id", conflicts_with = "tab_id")]
+        pane_id: Option<PaneId>,
+
+        /// The new title for the tab.
+        /// An empty title restores the default behavior of
+        /// showing the title of the active pane.
+        title: String,
+    },
+
+    /// Save the windows, tabs and panes of the mux server so that
+    /// they can be restored when the server is next started
+    #[structopt(name = "save-session")]
+    SaveSession {
+        /// Where to save the session.  The default is the
+        /// `mux_session_file` configured for the server.
+        #[structopt(long = "path", parse(from_os_str))]
+        path: Option<PathBuf>,
+
+        /// Include the text of the scrollback of each pane
+        #[structopt(long = "include-scrollback")]
+        include_scrollback: bool,
+    },
+
+    /// Report the metrics collected by the mux server, such as
+    /// PDU rates, parse throughput, render and frame times and
+    /// cache hit ratios
+    #[structopt(name = "stats")]
+    Stats {
+        /// Controls the output format.
+        /// "openmetrics" and "json" are possible formats.
+        #[structopt(long = "format", default_value = "openmetrics")]
+        format: CliStatsFormat,
+    },
 }
 
 use termwiz::escape::osc::{
@@ -369,7 +632,9 @@ fn run() -> anyhow::Result<()> {
         SubCommand::SetCwd(cmd) => cmd.run(),
         SubCommand::Cli(cli) => run_cli(config, cli),
         SubCommand::Record(cmd) => cmd.run(config),
-        SubCommand::Replay(cmd) => cmd.run(),
+        SubCommand::Replay(cmd) => cmd.run(config),
+        SubCommand::Export(cmd) => cmd.run(config),
+        SubCommand::ConvertColorScheme(cmd) => cmd.run(),
     }
 }
 
@@ -446,6 +711,65 @@ async fn resolve_pane_id(client: &Client, pane_id: Option<PaneId>) -> anyhow::Re
     Ok(pane_id)
 }
 
+/// Locates the tab that contains the specified pane
+async fn resolve_tab_id(client: &Client, pane_id: PaneId) -> anyhow::Result<TabId> {
+    let panes = client.list_panes().await?;
+    for tabroot in panes.tabs {
+        let mut cursor = tabroot.into_tree().cursor();
+
+        loop {
+            if let Some(entry) = cursor.leaf_mut() {
+                if entry.pane_id == pane_id {
+                    return Ok(entry.tab_id);
+                }
+            }
+            match cursor.preorder_next() {
+                Ok(c) => cursor = c,
+                Err(_) => break,
+            }
+        }
+    }
+    anyhow::bail!("unable to find the tab containing pane {}", pane_id);
+}
+
+/// Computes the stable rows to be output by `wezterm cli get-text`.
+/// `start_line` and `end_line` are relative to the top of the screen,
+/// and default to its first and last lines.  The start is clamped to
+/// the top of the scrollback; returns None if no rows are selected.
+fn get_text_range(
+    dims: &RenderableDimensions,
+    start_line: Option<isize>,
+    end_line: Option<isize>,
+) -> Option<Range<StableRowIndex>> {
+    let start_line: StableRowIndex = start_line.unwrap_or(0);
+    let end_line: StableRowIndex = end_line.unwrap_or(dims.viewport_rows as StableRowIndex - 1);
+    let first_row = (dims.physical_top + start_line).max(dims.scrollback_top);
+    let last_row = dims.physical_top + end_line;
+    if last_row < first_row {
+        return None;
+    }
+    Some(first_row..last_row + 1)
+}
+
+struct EscapesTarget {
+    target: Vec<u8>,
+}
+
+impl std::io::Write for EscapesTarget {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.target.write(buf)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl termwiz::render::RenderTty for EscapesTarget {
+    fn get_size_in_cells(&mut self) -> termwiz::Result<(usize, usize)> {
+        Ok((80, 24))
+    }
+}
+
 async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow::Result<()> {
     let mut ui = mux::connui::ConnectionUI::new_headless();
     let initial = true;
@@ -461,7 +785,29 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
     )?;
 
     match cli.sub {
-        CliSubCommand::ListClients => {
+        CliSubCommand::ListClients { format } => {
+            let clients = client.list_clients(codec::GetClientList).await?;
+            let now: DateTime<Utc> = Utc::now();
+
+            if format == CliOutputFormat::Json {
+                let items: Vec<CliListClientsResultItem> = clients
+                    .clients
+                    .into_iter()
+                    .map(|info| CliListClientsResultItem {
+                        username: info.client_id.username.clone(),
+                        hostname: info.client_id.hostname.clone(),
+                        pid: info.client_id.pid,
+                        connected_at: info.connected_at,
+                        connection_elapsed: (now - info.connected_at).num_seconds().max(0) as u64,
+                        last_input: info.last_input,
+                        idle_time: (now - info.last_input).num_seconds().max(0) as u64,
+                        workspace: info.active_workspace.unwrap_or_default(),
+                        focused_pane_id: info.focused_pane_id,
+                    })
+                    .collect();
+                return print_json(&items);
+            }
+
             let cols = vec![
                 Column {
                     name: "USER".to_string(),
@@ -493,8 +839,6 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
                 },
             ];
             let mut data = vec![];
-            let clients = client.list_clients(codec::GetClientList).await?;
-            let now: DateTime<Utc> = Utc::now();
 
             fn duration_string(d: chrono::Duration) -> String {
                 if let Ok(d) = d.to_std() {
@@ -522,7 +866,13 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
 
             tabulate_output(&cols, &data, &mut std::io::stdout().lock())?;
         }
-        CliSubCommand::List => {
+        CliSubCommand::List { format } => {
+            let panes = client.list_panes().await?;
+
+            if format == CliOutputFormat::Json {
+                return print_json(&list_result_items(panes));
+            }
+
             let cols = vec![
                 Column {
                     name: "WINID".to_string(),
@@ -554,7 +904,6 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
                 },
             ];
             let mut data = vec![];
-            let panes = client.list_panes().await?;
 
             for tabroot in panes.tabs {
                 let mut cursor = tabroot.into_tree().cursor();
@@ -633,6 +982,97 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
                 .send_paste(codec::SendPaste { pane_id, data })
                 .await?;
         }
+        CliSubCommand::GetText {
+            pane_id,
+            start_line,
+            end_line,
+            escapes,
+        } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            let dims = client
+                .get_dimensions(codec::GetPaneRenderableDimensions { pane_id })
+                .await?
+                .dimensions;
+
+            let range = match get_text_range(&dims, start_line, end_line) {
+                Some(range) => range,
+                None => return Ok(()),
+            };
+
+            let lines = client
+                .get_lines(codec::GetLines {
+                    pane_id,
+                    lines: vec![range],
+                })
+                .await?;
+            let (lines, _images) = lines.lines.extract_data();
+
+            let mut changes = vec![];
+            for (_, line) in lines {
+                if escapes {
+                    changes.extend(line.changes(&CellAttributes::default()));
+                    changes.push(Change::AllAttributes(CellAttributes::default()));
+                } else {
+                    let text = line.as_str();
+                    changes.push(Change::Text(if line.last_cell_was_wrapped() {
+                        text
+                    } else {
+                        text.trim_end().to_string()
+                    }));
+                }
+                if !line.last_cell_was_wrapped() {
+                    changes.push(Change::Text("\n".to_string()));
+                }
+            }
+
+            let mut renderer = config::lua::new_wezterm_terminfo_renderer();
+            let mut target = EscapesTarget { target: vec![] };
+            renderer.render_to(&changes, &mut target)?;
+            std::io::stdout().lock().write_all(&target.target)?;
+        }
+        CliSubCommand::ActivatePane { pane_id } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            client
+                .activate_pane(codec::ActivatePane { pane_id })
+                .await?;
+        }
+        CliSubCommand::KillPane { pane_id } => {
+            let pane_id = resolve_pane_id(&client, pane_id).await?;
+            client.kill_pane(codec::KillPane { pane_id }).await?;
+        }
+        CliSubCommand::SetTabTitle {
+            tab_id,
+            pane_id,
+            title,
+        } => {
+            let tab_id = match tab_id {
+                Some(tab_id) => tab_id,
+                None => {
+                    let pane_id = resolve_pane_id(&client, pane_id).await?;
+                    resolve_tab_id(&client, pane_id).await?
+                }
+            };
+            client
+                .set_tab_title(codec::SetTabTitle { tab_id, title })
+                .await?;
+        }
+        CliSubCommand::SaveSession {
+            path,
+            include_scrollback,
+        } => {
+            // The path is interpreted by the server, which may
+            // have a different cwd from ours
+            let path = match path {
+                Some(path) if path.is_relative() => Some(std::env::current_dir()?.join(path)),
+                path => path,
+            };
+            client
+                .save_session(codec::SaveSession {
+                    path,
+                    include_scrollback,
+                })
+                .await?;
+        }
         CliSubCommand::SpawnCommand {
             cwd,
             prog,
@@ -727,6 +1167,15 @@ async fn run_cli_async(config: config::ConfigHandle, cli: CliCommand) -> anyhow:
             // Wait forever; the stdio threads will terminate on EOF
             smol::future::pending().await
         }
+        CliSubCommand::Stats { format } => {
+            let metrics = client.get_metrics(codec::GetMetrics {}).await?;
+            match format {
+                CliStatsFormat::Json => return print_json(&metrics),
+                CliStatsFormat::OpenMetrics => {
+                    print!("{}", stats::format_openmetrics(&metrics));
+                }
+            }
+        }
         CliSubCommand::TlsCreds => {
             let creds = client.get_tls_creds().await?;
             codec::Pdu::GetTlsCredsResponse(creds).encode(std::io::stdout().lock(), 0)?;
@@ -767,3 +1216,126 @@ fn consume_stream_then_exit_process<F: Read, T: Write>(
     drop(activity);
     std::process::exit(0);
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use mux::renderable::StableCursorPosition;
+    use mux::tab::{PaneEntry, PaneNode, SplitDirectionAndSize};
+    use portable_pty::PtySize;
+
+    fn size(cols: u16, rows: u16) -> PtySize {
+        PtySize {
+            rows,
+            cols,
+            pixel_width: cols * 8,
+            pixel_height: rows * 16,
+        }
+    }
+
+    fn leaf(pane_id: PaneId, cwd: Option<&str>, cursor_pos: StableCursorPosition) -> PaneNode {
+        PaneNode::Leaf(PaneEntry {
+            window_id: 1,
+            tab_id: 2,
+            pane_id,
+            title: format!("pane {}", pane_id),
+            size: size(40, 24),
+            working_dir: cwd.map(|cwd| url::Url::parse(cwd).unwrap().into()),
+            is_active_pane: pane_id == 3,
+            is_zoomed_pane: false,
+            workspace: "default".to_string(),
+            cursor_pos,
+            physical_top: 100,
+        })
+    }
+
+    /// Scripts consume `wezterm cli list --format json`, so the names
+    /// and types of its fields must not change
+    #[test]
+    fn get_text_range_clamping() {
+        // 24 rows on screen, below 100 rows of scrollback of which
+        // the oldest 10 have been discarded
+        let dims = RenderableDimensions {
+            cols: 80,
+            viewport_rows: 24,
+            scrollback_rows: 114,
+            physical_top: 100,
+            scrollback_top: 10,
+        };
+        assert_eq!(get_text_range(&dims, None, None), Some(100..124));
+        assert_eq!(get_text_range(&dims, Some(-5), Some(0)), Some(95..101));
+        assert_eq!(get_text_range(&dims, Some(-1000), Some(-80)), Some(10..21));
+        assert_eq!(get_text_range(&dims, Some(-1000), None), Some(10..124));
+        assert_eq!(get_text_range(&dims, Some(3), Some(3)), Some(103..104));
+        assert_eq!(get_text_range(&dims, Some(5), Some(4)), None);
+        assert_eq!(get_text_range(&dims, None, Some(-95)), None);
+    }
+
+    #[test]
+    fn list_json_schema() {
+        let panes = codec::ListPanesResponse {
+            tabs: vec![PaneNode::Split {
+                left: Box::new(leaf(
+                    3,
+                    Some("file://host/home/user"),
+                    StableCursorPosition {
+                        x: 5,
+                        y: 110,
+                        shape: CursorShape::SteadyBar,
+                        visibility: CursorVisibility::Visible,
+                    },
+                )),
+                right: Box::new(leaf(
+                    4,
+                    None,
+                    StableCursorPosition {
+                        y: 100,
+                        ..Default::default()
+                    },
+                )),
+                node: SplitDirectionAndSize {
+                    direction: SplitDirection::Horizontal,
+                    first: size(40, 24),
+                    second: size(40, 24),
+                },
+            }],
+            tab_titles: vec!["tab".to_string()],
+        };
+
+        assert_eq!(
+            serde_json::to_value(list_result_items(panes)).unwrap(),
+            serde_json::json!([
+                {
+                    "window_id": 1,
+                    "tab_id": 2,
+                    "pane_id": 3,
+                    "workspace": "default",
+                    "size": {"rows": 24, "cols": 40, "pixel_width": 320, "pixel_height": 384},
+                    "title": "pane 3",
+                    "cwd": "file://host/home/user",
+                    "cursor_x": 5,
+                    "cursor_y": 10,
+                    "cursor_shape": "SteadyBar",
+                    "cursor_visibility": "Visible",
+                    "is_active": true,
+                    "is_zoomed": false,
+                },
+                {
+                    "window_id": 1,
+                    "tab_id": 2,
+                    "pane_id": 4,
+                    "workspace": "default",
+                    "size": {"rows": 24, "cols": 40, "pixel_width": 320, "pixel_height": 384},
+                    "title": "pane 4",
+                    "cwd": "",
+                    "cursor_x": 0,
+                    "cursor_y": 0,
+                    "cursor_shape": "Default",
+                    "cursor_visibility": "Visible",
+                    "is_active": false,
+                    "is_zoomed": false,
+                },
+            ])
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Formats the metrics reported by `wezterm cli stats`
+use codec::{GetMetricsResponse, MetricKey, MetricUnit};
+use std::fmt::Write;
+
+/// Produces a metric name that is acceptable to OpenMetrics;
+/// our dotted names are mapped to underscores and prefixed
+/// with `wezterm_`.
+fn metric_name(name: &str, suffix: &str) -> String {
+    let mut result = "wezterm_".to_string();
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            result.push(c);
+        } else {
+            result.push('_');
+        }
+    }
+    result.push_str(suffix);
+    result
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Formats the labels of `key` together with the process label,
+/// and any `extra` labels
+fn labels(process: &str, key: &MetricKey, extra: Option<(&str, &str)>) -> String {
+    let mut labels = vec![format!("process=\"{}\"", escape_label_value(process))];
+    for (name, value) in key.labels.iter() {
+        labels.push(format!("{}=\"{}\"", name, escape_label_value(value)));
+    }
+    if let Some((name, value)) = extra {
+        labels.push(format!("{}=\"{}\"", name, value));
+    }
+    format!("{{{}}}", labels.join(","))
+}
+
+/// Emits the TYPE and UNIT metadata for a metric family, unless it
+/// was already emitted for the immediately preceding metric.
+/// Metrics are sorted by key, so all of the label sets for a given
+/// family are adjacent to each other.
+fn family(
+    output: &mut String,
+    prior: &mut Option<String>,
+    name: &str,
+    kind: &str,
+    unit: Option<&str>,
+) {
+    if prior.as_deref() == Some(name) {
+        return;
+    }
+    writeln!(output, "# TYPE {} {}", name, kind).ok();
+    if let Some(unit) = unit {
+        writeln!(output, "# UNIT {} {}", name, unit).ok();
+    }
+    prior.replace(name.to_string());
+}
+
+/// Renders the metrics in the OpenMetrics text exposition format
+pub fn format_openmetrics(metrics: &GetMetricsResponse) -> String {
+    let mut output = String::new();
+    let process = metrics.process.as_str();
+    let mut prior = None;
+
+    for counter in &metrics.counters {
+        let name = metric_name(&counter.key.name, "");
+        family(&mut output, &mut prior, &name, "counter", None);
+        writeln!(
+            output,
+            "{}_total{} {}",
+            name,
+            labels(process, &counter.key, None),
+            counter.value
+        )
+        .ok();
+    }
+
+    for gauge in &metrics.gauges {
+        let name = metric_name(&gauge.key.name, "");
+        family(&mut output, &mut prior, &name, "gauge", None);
+        writeln!(
+            output,
+            "{}{} {}",
+            name,
+            labels(process, &gauge.key, None),
+            gauge.value
+        )
+        .ok();
+    }
+
+    for histogram in &metrics.histograms {
+        let (suffix, unit) = match histogram.unit {
+            MetricUnit::Seconds => ("_seconds", "seconds"),
+            MetricUnit::Bytes => ("_bytes", "bytes"),
+        };
+        let name = metric_name(&histogram.key.name, suffix);
+        family(&mut output, &mut prior, &name, "summary", Some(unit));
+        for (quantile, value) in [
+            ("0.5", histogram.p50),
+            ("0.75", histogram.p75),
+            ("0.95", histogram.p95),
+            ("0.99", histogram.p99),
+            ("1", histogram.max),
+        ] {
+            writeln!(
+                output,
+                "{}{} {}",
+                name,
+                labels(process, &histogram.key, Some(("quantile", quantile))),
+                value
+            )
+            .ok();
+        }
+        let key_labels = labels(process, &histogram.key, None);
+        writeln!(output, "{}_sum{} {}", name, key_labels, histogram.sum).ok();
+        writeln!(output, "{}_count{} {}", name, key_labels, histogram.count).ok();
+    }
+
+    // Rates are reported as a counter of the events, along with
+    // the distribution of the number of events per second
+    for rate in &metrics.rates {
+        let base = rate
+            .key
+            .name
+            .strip_suffix(".rate")
+            .unwrap_or(&rate.key.name);
+        let name = metric_name(base, "");
+        family(&mut output, &mut prior, &name, "counter", None);
+        writeln!(
+            output,
+            "{}_total{} {}",
+            name,
+            labels(process, &rate.key, None),
+            rate.total
+        )
+        .ok();
+    }
+    for rate in &metrics.rates {
+        let base = rate
+            .key
+            .name
+            .strip_suffix(".rate")
+            .unwrap_or(&rate.key.name);
+        let name = metric_name(base, "_per_second");
+        family(&mut output, &mut prior, &name, "summary", None);
+        for (quantile, value) in [("0.5", rate.p50), ("0.75", rate.p75), ("0.95", rate.p95)] {
+            writeln!(
+                output,
+                "{}{} {}",
+                name,
+                labels(process, &rate.key, Some(("quantile", quantile))),
+                value
+            )
+            .ok();
+        }
+    }
+
+    output.push_str("# EOF\n");
+    output
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use codec::{CounterMetric, GaugeMetric, HistogramMetric, RateMetric};
+
+    fn key(name: &str, labels: &[(&str, &str)]) -> MetricKey {
+        MetricKey {
+            name: name.to_string(),
+            labels: labels
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        }
+    }
+
+    fn metrics() -> GetMetricsResponse {
+        GetMetricsResponse {
+            process: "wezterm-gui".to_string(),
+            counters: vec![
+                CounterMetric {
+                    key: key("mux.pane.output", &[("domain", "local")]),
+                    value: 3,
+                },
+                CounterMetric {
+                    key: key("mux.pane.output", &[("domain", "a\\b\"c\nd")]),
+                    value: 4,
+                },
+            ],
+            gauges: vec![GaugeMetric {
+                key: key("mux.panes", &[]),
+                value: 2.5,
+            }],
+            histograms: vec![HistogramMetric {
+                key: key("font.shape", &[]),
+                unit: MetricUnit::Seconds,
+                count: 2,
+                sum: 0.5,
+                p50: 0.1,
+                p75: 0.2,
+                p95: 0.3,
+                p99: 0.4,
+                max: 0.45,
+            }],
+            rates: vec![RateMetric {
+                key: key("pty.read.rate", &[]),
+                total: 10,
+                current: 1,
+                p50: 1,
+                p75: 2,
+                p95: 5,
+            }],
+        }
+    }
+
+    #[test]
+    fn openmetrics() {
+        assert_eq!(
+            format_openmetrics(&metrics()),
+            r#"# TYPE wezterm_mux_pane_output counter
+wezterm_mux_pane_output_total{process="wezterm-gui",domain="local"} 3
+wezterm_mux_pane_output_total{process="wezterm-gui",domain="a\\b\"c\nd"} 4
+# TYPE wezterm_mux_panes gauge
+wezterm_mux_panes{process="wezterm-gui"} 2.5
+# TYPE wezterm_font_shape_seconds summary
+# UNIT wezterm_font_shape_seconds seconds
+wezterm_font_shape_seconds{process="wezterm-gui",quantile="0.5"} 0.1
+wezterm_font_shape_seconds{process="wezterm-gui",quantile="0.75"} 0.2
+wezterm_font_shape_seconds{process="wezterm-gui",quantile="0.95"} 0.3
+wezterm_font_shape_seconds{process="wezterm-gui",quantile="0.99"} 0.4
+wezterm_font_shape_seconds{process="wezterm-gui",quantile="1"} 0.45
+wezterm_font_shape_seconds_sum{process="wezterm-gui"} 0.5
+wezterm_font_shape_seconds_count{process="wezterm-gui"} 2
+# TYPE wezterm_pty_read counter
+wezterm_pty_read_total{process="wezterm-gui"} 10
+# TYPE wezterm_pty_read_per_second summary
+wezterm_pty_read_per_second{process="wezterm-gui",quantile="0.5"} 1
+wezterm_pty_read_per_second{process="wezterm-gui",quantile="0.75"} 2
+wezterm_pty_read_per_second{process="wezterm-gui",quantile="0.95"} 5
+# EOF
+"#
+        );
+    }
+
+    #[test]
+    fn openmetrics_empty() {
+        let metrics = GetMetricsResponse {
+            process: "wezterm-mux-server".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(format_openmetrics(&metrics), "# EOF\n");
+    }
+
+    #[test]
+    fn escape() {
+        assert_eq!(escape_label_value("plain"), "plain");
+        assert_eq!(
+            escape_label_value("back\\slash \"quoted\"\nline"),
+            "back\\\\slash \\\"quoted\\\"\\nline"
+        );
+        assert_eq!(
+            metric_name("mux.pane-count", "_bytes"),
+            "wezterm_mux_pane_count_bytes"
+        );
+    }
+
+    #[test]
+    fn json() {
+        assert_eq!(
+            serde_json::to_value(metrics()).unwrap(),
+            serde_json::json!({
+                "process": "wezterm-gui",
+                "counters": [
+                    {"key": {"name": "mux.pane.output", "labels": [["domain", "local"]]}, "value": 3},
+                    {"key": {"name": "mux.pane.output", "labels": [["domain", "a\\b\"c\nd"]]}, "value": 4},
+                ],
+                "gauges": [
+                    {"key": {"name": "mux.panes", "labels": []}, "value": 2.5},
+                ],
+                "histograms": [
+                    {
+                        "key": {"name": "font.shape", "labels": []},
+                        "unit": "Seconds",
+                        "count": 2,
+                        "sum": 0.5,
+                        "p50": 0.1,
+                        "p75": 0.2,
+                        "p95": 0.3,
+                        "p99": 0.4,
+                        "max": 0.45,
+                    },
+                ],
+                "rates": [
+                    {
+                        "key": {"name": "pty.read.rate", "labels": []},
+                        "total": 10,
+                        "current": 1,
+                        "p50": 1,
+                        "p75": 2,
+                        "p95": 5,
+                    },
+                ],
+            })
+        );
+    }
+}
@@ -478,7 +478,9 @@ impl ConceptFrame {
         .to_tuple_rgba();
 
         for info in infos {
-            if let Ok(mut glyph) = font.rasterize_glyph(info.glyph_pos, info.font_idx) {
+            if let Ok(mut glyph) =
+                font.rasterize_glyph(info.glyph_pos, info.font_idx, Some(title_color))
+            {
                 // fixup colors: they need to be switched to the appropriate
                 // pixel format, and for monochrome font data we need to tint
                 // it with their preferred title color